@@ -36,6 +36,8 @@ pub fn register_plugin() {
         image_formats: &[&IdfLoaderFactory],
         targets: &targets,
         probe_drivers: &[&EspUsbJtagFactory],
+        rtt_decoders: &[],
+        semihosting_handlers: &[],
     });
 }
 
@@ -21,6 +21,8 @@ pub fn register_plugin() {
                 &linuxgpiod::LinuxGpiodFactory,
                 &linuxspidevswd::LinuxSpidevSwdFactory,
             ],
+            rtt_decoders: &[],
+            semihosting_handlers: &[],
         });
     }
 }
@@ -34,12 +34,12 @@ use probe_rs_rpc::breakpoints::{
 use probe_rs_rpc::chip::{ChipData, ChipFamily, ChipInfoRequest, LoadChipFamilyRequest};
 use probe_rs_rpc::core_ops::{
     CoreAccessRequest, CoreBreakpointsRequest, CoreDumpRequest, CoreHaltRequest,
-    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWriteRegRequest,
+    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWatchpointsRequest, CoreWriteRegRequest,
     HandleSemihostingRequest, HandleSemihostingResult, StepRequest, StepResponse, WireCoreDump,
     WireCoreInformation, WireCoreMetadata, WireCoreStatus, WireRegisterId, WireRegisterReadResult,
-    WireRegisterValue, WireSteppingMode, WireVectorCatchCondition,
+    WireRegisterValue, WireSteppingMode, WireVectorCatchCondition, WireWatchpoint,
 };
-use probe_rs_rpc::cores::{CoresRequest, CoresStatusMap, HaltCoresRequest};
+use probe_rs_rpc::cores::{CoresRequest, CoresStatusMap, DumpCoresRequest, HaltCoresRequest};
 use probe_rs_rpc::debug_vars::{
     ClearCoreDebugStateRequest, EvaluateRequest, LoadSvdRequest, ScopesRequest, SetVariableRequest,
     VariablesRequest, WireEvaluateResponse, WireScope, WireSetVariableResponse, WireVariable,
@@ -48,8 +48,8 @@ use probe_rs_rpc::disassemble::{DisassembleRequest, WireDisassembledInstruction}
 use probe_rs_rpc::file::{AppendFileRequest, TempFile};
 use probe_rs_rpc::flash::{
     BootInfo, BootRequest, BuildRequest, BuildResult, DownloadOptions, EraseAllRequest,
-    EraseRangeRequest, FlashRequest, LoadRegionRequest, NewFlashLoaderRequest, ProgressEvent,
-    VerifyRequest, VerifyResult,
+    EraseRangeRequest, FlashRequest, InjectDataRequest, InjectedData, InjectionLocation,
+    LoadRegionRequest, NewFlashLoaderRequest, ProgressEvent, VerifyRequest, VerifyResult,
 };
 use probe_rs_rpc::format::FormatOptions;
 use probe_rs_rpc::info::{
@@ -60,13 +60,13 @@ use probe_rs_rpc::monitor::{
     MonitorExitReason, MonitorMode, MonitorOptions, MonitorRequest, RttEvent, SemihostingEvent,
 };
 use probe_rs_rpc::probe::{
-    AttachRequest, AttachResult, DebugProbeEntry, DebugProbeSelector, SelectProbeRequest,
-    SelectProbeResult,
+    AttachRequest, AttachResult, DebugProbeEntry, DebugProbeSelector, ProbeSpeedRequest,
+    SelectProbeRequest, SelectProbeResult,
 };
 use probe_rs_rpc::reset::{ResetCoreAndHaltRequest, ResetCoreRequest};
 use probe_rs_rpc::rtt_client::{
     CreateRttClientRequest, PollRttUpRequest, RttChannelRequest, RttChannels, RttClientData,
-    RttDownRequest, RttPollResult, ScanRegion,
+    RttDiscovery, RttDownRequest, RttPollResult,
 };
 use probe_rs_rpc::rtt_config::RttChannelConfig;
 use probe_rs_rpc::semihosting_options::SemihostingOptions;
@@ -77,26 +77,32 @@ use probe_rs_rpc::stack_trace::{
 use probe_rs_rpc::test::{
     ListTestsRequest, RunTestRequest, Test, TestKickoffRequest, TestResult, Tests,
 };
+use probe_rs_rpc::trace::{
+    Branch, BranchTraceReadRequest, SwoReadRequest, SwoStartRequest, SwoStopRequest,
+};
 use probe_rs_rpc::transport::memory::{PostcardReceiver, PostcardSender, WireRx, WireTx};
 use probe_rs_rpc::{
-    AttachEndpoint, BootEndpoint, BuildEndpoint, ChipInfoEndpoint, CleanUpRttEndpoint,
-    ClearCoreDebugStateEndpoint, ClearRttControlBlockEndpoint, CoreClearHwBpsEndpoint,
-    CoreDumpEndpoint, CoreEnableVcEndpoint, CoreHaltEndpoint, CoreMetadataEndpoint,
-    CoreReadRegistersEndpoint, CoreRunEndpoint, CoreSetHwBpsEndpoint, CoreStatusEndpoint,
-    CoreStepEndpoint, CoreWriteRegEndpoint, CoresStatusEndpoint, CreateRttClientEndpoint,
-    CreateTempFileEndpoint, DisassembleEndpoint, EraseAllEndpoint, EraseRangeEndpoint,
-    EvaluateEndpoint, FlashEndpoint, GetRttChannelsEndpoint, HaltCoresEndpoint,
-    HandleSemihostingEndpoint, ListChipFamiliesEndpoint, ListProbesEndpoint, ListTestsEndpoint,
-    LoadChipFamilyEndpoint, LoadDebugInfoEndpoint, LoadRegionEndpoint, LoadSvdEndpoint,
-    MonitorEndpoint, NewFlashLoaderEndpoint, PollRttUpEndpoint, ProgressEventTopic,
-    ReadBytesEndpoint, ReadMemory8Endpoint, ReadMemory16Endpoint, ReadMemory32Endpoint,
-    ReadMemory64Endpoint, ResetCoreAndHaltEndpoint, ResetCoreEndpoint,
-    ResolveSourceBreakpointsEndpoint, ResolveSourceLocationsEndpoint, ResumeCoresEndpoint,
-    RpcError, RpcResult, RttDownEndpoint, RttTopic, RunTestEndpoint, ScopesEndpoint,
-    SelectProbeEndpoint, SemihostingTopic, SetVariableEndpoint, TakeRichStackTraceEndpoint,
-    TakeStackTraceEndpoint, TargetInfoDataTopic, TargetInfoEndpoint, TargetMetadataEndpoint,
-    TempFileDataEndpoint, TestKickoffEndpoint, TokioSpawner, VariablesEndpoint, VerifyEndpoint,
-    WriteMemory8Endpoint, WriteMemory16Endpoint, WriteMemory32Endpoint, WriteMemory64Endpoint,
+    AttachEndpoint, BootEndpoint, BranchTraceReadEndpoint, BuildEndpoint, ChipInfoEndpoint,
+    CleanUpRttEndpoint, ClearCoreDebugStateEndpoint, ClearRttControlBlockEndpoint,
+    CoreClearHwBpsEndpoint, CoreClearSwBpsEndpoint, CoreClearWatchpointsEndpoint, CoreDumpEndpoint,
+    CoreEnableVcEndpoint, CoreHaltEndpoint, CoreMetadataEndpoint, CoreReadRegistersEndpoint,
+    CoreRunEndpoint, CoreSetHwBpsEndpoint, CoreSetSwBpsEndpoint, CoreSetWatchpointsEndpoint,
+    CoreStatusEndpoint, CoreStepEndpoint, CoreWriteRegEndpoint, CoresStatusEndpoint,
+    CreateRttClientEndpoint, CreateTempFileEndpoint, DisassembleEndpoint, DumpCoresEndpoint,
+    EraseAllEndpoint, EraseRangeEndpoint, EvaluateEndpoint, FlashEndpoint, GetRttChannelsEndpoint,
+    HaltCoresEndpoint, HandleSemihostingEndpoint, InjectDataEndpoint, ListChipFamiliesEndpoint,
+    ListProbesEndpoint, ListTestsEndpoint, LoadChipFamilyEndpoint, LoadDebugInfoEndpoint,
+    LoadRegionEndpoint, LoadSvdEndpoint, MonitorEndpoint, NewFlashLoaderEndpoint,
+    PollRttUpEndpoint, ProbeSpeedEndpoint, ProgressEventTopic, ReadBytesEndpoint,
+    ReadMemory8Endpoint, ReadMemory16Endpoint, ReadMemory32Endpoint, ReadMemory64Endpoint,
+    ResetCoreAndHaltEndpoint, ResetCoreEndpoint, ResolveSourceBreakpointsEndpoint,
+    ResolveSourceLocationsEndpoint, ResumeCoresEndpoint, RpcError, RpcResult, RttDownEndpoint,
+    RttTopic, RunTestEndpoint, ScopesEndpoint, SelectProbeEndpoint, SemihostingTopic,
+    SetHaltGroupEndpoint, SetVariableEndpoint, SwoReadEndpoint, SwoStartEndpoint, SwoStopEndpoint,
+    TakeRichStackTraceEndpoint, TakeStackTraceEndpoint, TargetInfoDataTopic, TargetInfoEndpoint,
+    TargetMetadataEndpoint, TempFileDataEndpoint, TestKickoffEndpoint, TokioSpawner,
+    VariablesEndpoint, VerifyEndpoint, WriteMemory8Endpoint, WriteMemory16Endpoint,
+    WriteMemory32Endpoint, WriteMemory64Endpoint,
 };
 use probe_rs_rpc::{FlashLoader, Key, RttClient, Session};
 
@@ -668,6 +674,19 @@ impl SessionInterface {
             .await
     }
 
+    /// Make the selected cores halt and resume together.
+    ///
+    /// When `cores` is `None`, every session core is part of the group. An empty list removes
+    /// the group.
+    pub async fn set_halt_group(&self, cores: Option<Vec<u32>>) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<SetHaltGroupEndpoint, _>(&CoresRequest {
+                sessid: self.sessid,
+                cores,
+            })
+            .await
+    }
+
     /// Read the status of selected cores.
     ///
     /// When `cores` is `None`, every session core is considered. Disabled cores
@@ -684,6 +703,30 @@ impl SessionInterface {
             .await
     }
 
+    /// Dump every core of the session server-side and return the serialized
+    /// `probe_rs::SessionDump`.
+    ///
+    /// The register blocks of the named `peripherals` are looked up in the
+    /// SVD file at `svd_path`, which is uploaded to the server first.
+    pub async fn dump_cores(
+        &self,
+        svd_path: Option<PathBuf>,
+        peripherals: Vec<String>,
+    ) -> Result<Vec<u8>, ClientError> {
+        let svd_path = match svd_path {
+            Some(path) => Some(self.client.upload_file(&path).await?.display().to_string()),
+            None => None,
+        };
+
+        self.client
+            .send_resp::<DumpCoresEndpoint, _>(&DumpCoresRequest {
+                sessid: self.sessid,
+                svd_path,
+                peripherals,
+            })
+            .await
+    }
+
     /// Prepares the core to execute the loaded image.
     ///
     /// When `resume` is true, all cores are started afterward. When false, the
@@ -748,6 +791,22 @@ impl SessionInterface {
             .await
     }
 
+    pub async fn inject_data(
+        &self,
+        loader: Key<FlashLoader>,
+        location: InjectionLocation,
+        data: InjectedData,
+    ) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<InjectDataEndpoint, _>(&InjectDataRequest {
+                sessid: self.sessid,
+                loader,
+                location,
+                data,
+            })
+            .await
+    }
+
     pub async fn build_flash_loader(
         &self,
         path: PathBuf,
@@ -787,6 +846,15 @@ impl SessionInterface {
                 .to_string();
         }
 
+        if let Some(ref mut mcuboot_key) = format.mcuboot_options.mcuboot_key {
+            *mcuboot_key = self
+                .client
+                .upload_file(mcuboot_key.as_ref())
+                .await?
+                .display()
+                .to_string();
+        }
+
         self.client
             .send_resp::<BuildEndpoint, _>(&BuildRequest {
                 sessid: self.sessid,
@@ -936,14 +1004,14 @@ impl SessionInterface {
 
     pub async fn create_rtt_client(
         &self,
-        scan_regions: ScanRegion,
+        discovery: RttDiscovery,
         config: Vec<RttChannelConfig>,
         default_config: RttChannelConfig,
     ) -> Result<RttClientData, ClientError> {
         self.client
             .send_resp::<CreateRttClientEndpoint, _>(&CreateRttClientRequest {
                 sessid: self.sessid,
-                scan_regions,
+                discovery,
                 config,
                 default_config,
             })
@@ -1258,6 +1326,61 @@ impl SessionInterface {
             )
             .await
     }
+
+    /// Change the speed of the probe in kHz, without detaching from the target.
+    ///
+    /// When `speed_khz` is `None`, the speed is left unchanged. Returns the speed the probe uses.
+    pub async fn probe_speed(&self, speed_khz: Option<u32>) -> Result<u32, ClientError> {
+        self.client
+            .send_resp::<ProbeSpeedEndpoint, _>(&ProbeSpeedRequest {
+                sessid: self.sessid,
+                speed_khz,
+            })
+            .await
+    }
+
+    /// Configure the target and probe to emit and capture ITM data over SWO.
+    pub async fn start_swo(&self, core: u32, clk: u32, baud: u32) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<SwoStartEndpoint, _>(&SwoStartRequest {
+                sessid: self.sessid,
+                core,
+                clk,
+                baud,
+            })
+            .await
+    }
+
+    /// Read the raw SWO data the probe captured since the last read.
+    pub async fn read_swo(&self) -> Result<Vec<u8>, ClientError> {
+        self.client
+            .send_resp::<SwoReadEndpoint, _>(&SwoReadRequest {
+                sessid: self.sessid,
+            })
+            .await
+    }
+
+    /// Stop emitting and capturing SWO data.
+    pub async fn stop_swo(&self, core: u32) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<SwoStopEndpoint, _>(&SwoStopRequest {
+                sessid: self.sessid,
+                core,
+            })
+            .await
+    }
+
+    /// Read the branches the MTB of a core recorded, oldest first.
+    ///
+    /// The MTB is set up by passing a branch trace configuration to [`SessionInterface::monitor`].
+    pub async fn read_branch_trace(&self, core: u32) -> Result<Vec<Branch>, ClientError> {
+        self.client
+            .send_resp::<BranchTraceReadEndpoint, _>(&BranchTraceReadRequest {
+                sessid: self.sessid,
+                core,
+            })
+            .await
+    }
 }
 
 #[derive(Clone)]
@@ -1507,6 +1630,54 @@ impl CoreInterface {
             .await
     }
 
+    /// Set a batch of software breakpoints, with one result per address.
+    pub async fn set_sw_breakpoints(
+        &self,
+        addresses: Vec<u64>,
+    ) -> Result<Vec<Result<(), RpcError>>, ClientError> {
+        self.client
+            .send_resp::<CoreSetSwBpsEndpoint, _>(&CoreBreakpointsRequest {
+                sessid: self.sessid,
+                core: self.core,
+                addresses,
+            })
+            .await
+    }
+
+    pub async fn clear_sw_breakpoints(&self, addresses: Vec<u64>) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<CoreClearSwBpsEndpoint, _>(&CoreBreakpointsRequest {
+                sessid: self.sessid,
+                core: self.core,
+                addresses,
+            })
+            .await
+    }
+
+    /// Set a batch of hardware watchpoints, with one result per watchpoint.
+    pub async fn set_watchpoints(
+        &self,
+        watchpoints: Vec<WireWatchpoint>,
+    ) -> Result<Vec<Result<(), RpcError>>, ClientError> {
+        self.client
+            .send_resp::<CoreSetWatchpointsEndpoint, _>(&CoreWatchpointsRequest {
+                sessid: self.sessid,
+                core: self.core,
+                watchpoints,
+            })
+            .await
+    }
+
+    pub async fn clear_watchpoints(&self, addresses: Vec<u64>) -> Result<(), ClientError> {
+        self.client
+            .send_resp::<CoreClearWatchpointsEndpoint, _>(&CoreBreakpointsRequest {
+                sessid: self.sessid,
+                core: self.core,
+                addresses,
+            })
+            .await
+    }
+
     pub async fn enable_vector_catch(
         &self,
         condition: WireVectorCatchCondition,
@@ -96,6 +96,8 @@ pub enum MemoryRegion {
     Generic(GenericRegion),
     /// Memory region describing flash, EEPROM or other non-volatile memory.
     Nvm(NvmRegion),
+    /// Memory region holding device configuration, like option bytes, OTP memory or fuses.
+    Config(ConfigRegion),
 }
 
 impl MemoryRegion {
@@ -105,6 +107,7 @@ impl MemoryRegion {
             MemoryRegion::Ram(rr) => rr.range,
             MemoryRegion::Generic(gr) => gr.range,
             MemoryRegion::Nvm(nr) => nr.range,
+            MemoryRegion::Config(cr) => cr.range,
         };
         start..end
     }
@@ -152,6 +155,30 @@ pub struct GenericRegion {
     pub access: Option<MemoryAccess>,
 }
 
+/// The kind of device configuration stored in a [`ConfigRegion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Schema)]
+pub enum ConfigRegionKind {
+    /// Option bytes, which can be programmed again.
+    OptionBytes,
+    /// One-time programmable memory.
+    Otp,
+    /// Fuses.
+    Fuses,
+}
+
+/// Represents a region that holds device configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Schema)]
+pub struct ConfigRegion {
+    /// A name to describe the region
+    pub name: Option<String>,
+    /// The kind of configuration stored in the region.
+    pub kind: ConfigRegionKind,
+    /// Address range of the region
+    pub range: (u64, u64),
+    /// List of cores that can access this region
+    pub cores: Vec<String>,
+}
+
 /// Represents access permissions of a region in RAM.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Schema)]
 pub struct MemoryAccess {
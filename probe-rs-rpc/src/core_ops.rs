@@ -62,6 +62,27 @@ pub struct CoreBreakpointsRequest {
     pub addresses: Vec<u64>,
 }
 
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct CoreWatchpointsRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+    pub watchpoints: Vec<WireWatchpoint>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Schema, Copy, Clone, PartialEq, Eq)]
+pub struct WireWatchpoint {
+    pub address: u64,
+    pub length: u64,
+    pub kind: WireWatchpointKind,
+}
+
+#[derive(Debug, Serialize, Deserialize, Schema, Copy, Clone, PartialEq, Eq)]
+pub enum WireWatchpointKind {
+    Read,
+    Write,
+    Access,
+}
+
 #[derive(Serialize, Deserialize, Schema, Clone)]
 pub struct CoreVectorCatchRequest {
     pub sessid: Key<Session>,
@@ -98,7 +119,7 @@ pub enum WireHaltReason {
     Multiple,
     Breakpoint(WireBreakpointCause),
     Exception,
-    Watchpoint,
+    Watchpoint(Option<WireWatchpoint>),
     Step,
     Request,
     External,
@@ -33,3 +33,16 @@ pub struct CoresStatusMap {
 }
 
 pub type CoresStatusResponse = RpcResult<CoresStatusMap>;
+
+/// Dump every core of the session into a single `probe_rs::SessionDump`.
+#[derive(Serialize, Deserialize, Schema, Clone)]
+pub struct DumpCoresRequest {
+    pub sessid: Key<Session>,
+    /// Server-side path to the CMSIS-SVD file the `peripherals` are looked up in.
+    pub svd_path: Option<String>,
+    /// Names of the SVD peripherals whose register blocks should be included in the dump.
+    pub peripherals: Vec<String>,
+}
+
+/// The serialized `probe_rs::SessionDump`, as written by `SessionDump::store`.
+pub type DumpCoresResponse = RpcResult<Vec<u8>>;
@@ -73,6 +73,8 @@ pub struct WireVariable {
     pub name: String,
     pub evaluate_name: Option<String>,
     pub memory_reference: Option<String>,
+    /// The size of the variable in memory, if it is known.
+    pub byte_size: Option<u64>,
     pub indexed_variables: Option<i64>,
     pub named_variables: Option<i64>,
     pub type_: Option<String>,
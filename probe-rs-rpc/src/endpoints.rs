@@ -7,11 +7,13 @@ use crate::breakpoints::{
 use crate::chip::{ChipInfoRequest, ChipInfoResponse, ListFamiliesResponse, LoadChipFamilyRequest};
 use crate::core_ops::{
     CoreAccessRequest, CoreBreakpointsRequest, CoreDumpRequest, CoreHaltRequest,
-    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWriteRegRequest,
+    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWatchpointsRequest, CoreWriteRegRequest,
     HandleSemihostingRequest, HandleSemihostingResponse, StepRequest, StepResult, WireCoreDump,
     WireCoreInformation, WireCoreMetadata, WireCoreStatus, WireRegisterReadResult,
 };
-use crate::cores::{CoresRequest, CoresStatusResponse, HaltCoresRequest};
+use crate::cores::{
+    CoresRequest, CoresStatusResponse, DumpCoresRequest, DumpCoresResponse, HaltCoresRequest,
+};
 use crate::debug_vars::{
     ClearCoreDebugStateRequest, EvaluateRequest, EvaluateResponse, LoadSvdRequest, LoadSvdResponse,
     ScopesRequest, ScopesResponse, SetVariableRequest, SetVariableResult, VariablesRequest,
@@ -21,14 +23,15 @@ use crate::disassemble::{DisassembleRequest, DisassembleResponse};
 use crate::file::{AppendFileRequest, CreateFileResponse};
 use crate::flash::{
     BootRequest, BuildRequest, BuildResponse, EraseAllRequest, EraseRangeRequest, FlashRequest,
-    LoadRegionRequest, NewFlashLoaderRequest, NewFlashLoaderResponse, ProgressEvent, VerifyRequest,
-    VerifyResponse,
+    InjectDataRequest, LoadRegionRequest, NewFlashLoaderRequest, NewFlashLoaderResponse,
+    ProgressEvent, VerifyRequest, VerifyResponse,
 };
 use crate::info::{InfoEvent, TargetInfoRequest, TargetMetadataRequest, TargetMetadataResponse};
 use crate::memory::{ReadBytesRequest, ReadMemoryRequest, WriteMemoryRequest};
 use crate::monitor::{MonitorRequest, MonitorResponse, RttEvent, SemihostingEvent};
 use crate::probe::{
-    AttachRequest, AttachResponse, ListProbesResponse, SelectProbeRequest, SelectProbeResponse,
+    AttachRequest, AttachResponse, ListProbesResponse, ProbeSpeedRequest, ProbeSpeedResponse,
+    SelectProbeRequest, SelectProbeResponse,
 };
 use crate::reset::{ResetCoreAndHaltRequest, ResetCoreRequest};
 use crate::rtt_client::{
@@ -43,6 +46,10 @@ use crate::test::{
     ListTestsRequest, ListTestsResponse, RunTestRequest, RunTestResponse, TestKickoffRequest,
     TestKickoffResponse,
 };
+use crate::trace::{
+    BranchTraceReadRequest, BranchTraceReadResponse, SwoReadRequest, SwoReadResponse,
+    SwoStartRequest, SwoStopRequest,
+};
 use crate::{NoResponse, RpcError, RpcResult};
 
 type ReadMemory8Response = RpcResult<Vec<u8>>;
@@ -65,6 +72,8 @@ type CoreMetadataResponse = RpcResult<WireCoreMetadata>;
 type CoreReadRegistersResponse = RpcResult<Vec<WireRegisterReadResult>>;
 type CoreDumpResponse = RpcResult<WireCoreDump>;
 type CoreSetHwBpsResponse = RpcResult<Vec<Result<(), RpcError>>>;
+type CoreSetSwBpsResponse = RpcResult<Vec<Result<(), RpcError>>>;
+type CoreSetWatchpointsResponse = RpcResult<Vec<Result<(), RpcError>>>;
 
 endpoints! {
     list = ENDPOINT_LIST;
@@ -73,19 +82,23 @@ endpoints! {
     | ListProbesEndpoint        | ()                      | ListProbesResponse      | "probe/list"       |
     | SelectProbeEndpoint       | SelectProbeRequest      | SelectProbeResponse     | "probe/select"     |
     | AttachEndpoint            | AttachRequest           | AttachResponse          | "probe/attach"     |
-
-    | HaltCoresEndpoint         | HaltCoresRequest        | CoresStatusResponse     | "cores/halt"       |
-    | ResumeCoresEndpoint       | CoresRequest            | CoresStatusResponse     | "cores/resume"     |
-    | CoresStatusEndpoint       | CoresRequest            | CoresStatusResponse     | "cores/status"     |
-    | NewFlashLoaderEndpoint    | NewFlashLoaderRequest   | NewFlashLoaderResponse  | "flash/new"        |
-    | BuildEndpoint             | BuildRequest            | BuildResponse           | "flash/build"      |
-    | LoadRegionEndpoint        | LoadRegionRequest       | NoResponse              | "flash/load_region"|
-    | FlashEndpoint             | FlashRequest            | NoResponse              | "flash/flash"      |
-    | EraseAllEndpoint          | EraseAllRequest         | NoResponse              | "flash/erase_all"  |
-    | EraseRangeEndpoint        | EraseRangeRequest       | NoResponse              | "flash/erase_range"|
-    | VerifyEndpoint            | VerifyRequest           | VerifyResponse          | "flash/verify"     |
-    | BootEndpoint              | BootRequest             | NoResponse              | "flash/boot"       |
-    | MonitorEndpoint           | MonitorRequest          | MonitorResponse         | "monitor"          |
+    | ProbeSpeedEndpoint        | ProbeSpeedRequest       | ProbeSpeedResponse      | "probe/speed"      |
+
+    | HaltCoresEndpoint         | HaltCoresRequest        | CoresStatusResponse     | "cores/halt"           |
+    | ResumeCoresEndpoint       | CoresRequest            | CoresStatusResponse     | "cores/resume"         |
+    | CoresStatusEndpoint       | CoresRequest            | CoresStatusResponse     | "cores/status"         |
+    | DumpCoresEndpoint         | DumpCoresRequest        | DumpCoresResponse       | "cores/dump"           |
+    | SetHaltGroupEndpoint      | CoresRequest            | NoResponse              | "cores/set_halt_group" |
+    | NewFlashLoaderEndpoint    | NewFlashLoaderRequest   | NewFlashLoaderResponse  | "flash/new"            |
+    | BuildEndpoint             | BuildRequest            | BuildResponse           | "flash/build"          |
+    | LoadRegionEndpoint        | LoadRegionRequest       | NoResponse              | "flash/load_region"    |
+    | InjectDataEndpoint        | InjectDataRequest       | NoResponse              | "flash/inject"         |
+    | FlashEndpoint             | FlashRequest            | NoResponse              | "flash/flash"          |
+    | EraseAllEndpoint          | EraseAllRequest         | NoResponse              | "flash/erase_all"      |
+    | EraseRangeEndpoint        | EraseRangeRequest       | NoResponse              | "flash/erase_range"    |
+    | VerifyEndpoint            | VerifyRequest           | VerifyResponse          | "flash/verify"         |
+    | BootEndpoint              | BootRequest             | NoResponse              | "flash/boot"           |
+    | MonitorEndpoint           | MonitorRequest          | MonitorResponse         | "monitor"              |
 
     | TakeStackTraceEndpoint     | TakeStackTraceRequest     | TakeStackTraceResponse     | "stack_trace"              |
     | TakeRichStackTraceEndpoint | TakeRichStackTraceRequest | TakeRichStackTraceResponse | "stack_trace/rich"         |
@@ -111,6 +124,11 @@ endpoints! {
     | RunTestEndpoint           | RunTestRequest          | RunTestResponse         | "tests/run"        |
     | TestKickoffEndpoint       | TestKickoffRequest      | TestKickoffResponse     | "tests/kickoff"    |
 
+    | SwoStartEndpoint        | SwoStartRequest        | NoResponse              | "trace/swo/start"     |
+    | SwoReadEndpoint         | SwoReadRequest         | SwoReadResponse         | "trace/swo/read"      |
+    | SwoStopEndpoint         | SwoStopRequest         | NoResponse              | "trace/swo/stop"      |
+    | BranchTraceReadEndpoint | BranchTraceReadRequest | BranchTraceReadResponse | "trace/branches/read" |
+
     | CreateTempFileEndpoint    | ()                      | CreateFileResponse      | "temp_file/new"    |
     | TempFileDataEndpoint      | AppendFileRequest       | NoResponse              | "temp_file/append" |
 
@@ -130,6 +148,10 @@ endpoints! {
     | CoreWriteRegEndpoint         | CoreWriteRegRequest      | NoResponse                 | "core/write_reg"          |
     | CoreSetHwBpsEndpoint         | CoreBreakpointsRequest   | CoreSetHwBpsResponse       | "core/set_hw_bps"         |
     | CoreClearHwBpsEndpoint       | CoreBreakpointsRequest   | NoResponse                 | "core/clear_hw_bps"       |
+    | CoreSetSwBpsEndpoint         | CoreBreakpointsRequest   | CoreSetSwBpsResponse       | "core/set_sw_bps"         |
+    | CoreClearSwBpsEndpoint       | CoreBreakpointsRequest   | NoResponse                 | "core/clear_sw_bps"       |
+    | CoreSetWatchpointsEndpoint   | CoreWatchpointsRequest   | CoreSetWatchpointsResponse | "core/set_watchpoints"    |
+    | CoreClearWatchpointsEndpoint | CoreBreakpointsRequest   | NoResponse                 | "core/clear_watchpoints"  |
     | CoreEnableVcEndpoint         | CoreVectorCatchRequest   | NoResponse                 | "core/enable_vc"          |
     | CoreMetadataEndpoint         | CoreAccessRequest        | CoreMetadataResponse       | "core/metadata"           |
     | CoreReadRegistersEndpoint    | CoreReadRegistersRequest | CoreReadRegistersResponse  | "core/read_registers"     |
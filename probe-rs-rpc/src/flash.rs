@@ -9,6 +9,7 @@ pub struct DownloadOptions {
     pub keep_unwritten_bytes: bool,
     pub do_chip_erase: bool,
     pub skip_erase: bool,
+    pub preverify_checksum: bool,
     pub verify: bool,
     pub disable_double_buffering: bool,
     pub preferred_algos: Vec<String>,
@@ -47,6 +48,26 @@ pub struct LoadRegionRequest {
     pub data: Vec<u8>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize, Schema)]
+pub enum InjectedData {
+    Bytes(Vec<u8>),
+    Crc32 { start: u64, end: u64 },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Schema)]
+pub enum InjectionLocation {
+    Address(u64),
+    Placeholder { start: u64, end: u64 },
+}
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct InjectDataRequest {
+    pub sessid: Key<Session>,
+    pub loader: Key<FlashLoader>,
+    pub location: InjectionLocation,
+    pub data: InjectedData,
+}
+
 #[derive(Serialize, Deserialize, Schema)]
 pub struct BuildRequest {
     pub sessid: Key<Session>,
@@ -121,6 +121,168 @@ pub struct ElfCliOptions {
     pub skip_section: Vec<String>,
 }
 
+#[cfg(feature = "clap")]
+fn parse_manifest_var(input: &str) -> Result<String, String> {
+    match input.split_once('=') {
+        Some((name, _)) if !name.is_empty() => Ok(input.to_string()),
+        _ => Err(format!("expected `NAME=VALUE`, got `{input}`")),
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, Schema)]
+#[cfg_attr(feature = "clap", derive(clap::Parser))]
+#[serde(default)]
+pub struct ManifestCliOptions {
+    /// Sets a variable used by the patches of the manifest, as `NAME=VALUE`.
+    #[cfg_attr(
+        feature = "clap",
+        clap(
+            long,
+            value_parser = parse_manifest_var,
+            help_heading = "DOWNLOAD CONFIGURATION / MANIFEST IMAGE"
+        )
+    )]
+    pub manifest_var: Vec<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, Schema)]
+#[cfg_attr(feature = "clap", derive(clap::Parser))]
+#[serde(default)]
+pub struct ExternalFlashCliOptions {
+    /// Programs the external SPI NOR flash of the target, through the SPI controller that maps it
+    /// into memory. The erase sizes and opcodes are read from the SFDP tables of the flash.
+    ///
+    /// Supported are the QUADSPI of STM32F7, STM32H7 and STM32L4, the FlexSPI of i.MX RT10xx and
+    /// i.MX RT116x/117x, and the QSPI interface of the RP2040.
+    #[cfg_attr(
+        feature = "clap",
+        clap(long, help_heading = "DOWNLOAD CONFIGURATION / EXTERNAL FLASH")
+    )]
+    pub external_flash: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Schema)]
+#[cfg_attr(feature = "clap", derive(clap::Parser))]
+#[serde(default)]
+pub struct McubootCliOptions {
+    /// Wraps the image in an MCUboot header and TLV trailer, so it can be booted by MCUboot.
+    /// Encrypted images are not supported; create them with `imgtool` instead.
+    #[cfg_attr(
+        feature = "clap",
+        clap(long, help_heading = "DOWNLOAD CONFIGURATION / MCUBOOT IMAGE")
+    )]
+    pub mcuboot: bool,
+
+    /// The key to sign the image with, a PEM file with an ECDSA P-256, Ed25519 or RSA key.
+    /// Without a key, the image only contains its SHA-256 hash.
+    #[cfg_attr(
+        feature = "clap",
+        clap(
+            long,
+            requires = "mcuboot",
+            help_heading = "DOWNLOAD CONFIGURATION / MCUBOOT IMAGE"
+        )
+    )]
+    pub mcuboot_key: Option<String>,
+
+    /// The address of the slot. Defaults to the start of the image minus the header size.
+    #[cfg_attr(
+        feature = "clap",
+        clap(
+            long,
+            value_parser = parse_u64,
+            requires = "mcuboot",
+            help_heading = "DOWNLOAD CONFIGURATION / MCUBOOT IMAGE"
+        )
+    )]
+    pub mcuboot_slot: Option<u64>,
+
+    /// The size of the slot. Required to mark the image for a test swap.
+    #[cfg_attr(
+        feature = "clap",
+        clap(
+            long,
+            value_parser = parse_u64,
+            requires = "mcuboot",
+            help_heading = "DOWNLOAD CONFIGURATION / MCUBOOT IMAGE"
+        )
+    )]
+    pub mcuboot_slot_size: Option<u64>,
+
+    /// The size of the header. The image has to be linked to start this far into the slot.
+    #[cfg_attr(
+        feature = "clap",
+        clap(
+            long,
+            value_parser = parse_u32,
+            default_value = "0x200",
+            help_heading = "DOWNLOAD CONFIGURATION / MCUBOOT IMAGE"
+        )
+    )]
+    pub mcuboot_header_size: u32,
+
+    /// The version of the image, as `MAJOR.MINOR.REVISION+BUILD`.
+    #[cfg_attr(
+        feature = "clap",
+        clap(
+            long,
+            default_value = "0.0.0",
+            help_heading = "DOWNLOAD CONFIGURATION / MCUBOOT IMAGE"
+        )
+    )]
+    pub mcuboot_version: String,
+
+    /// Marks the image for a test swap, by writing the trailer magic at the end of the slot.
+    #[cfg_attr(
+        feature = "clap",
+        clap(
+            long,
+            requires = "mcuboot_slot_size",
+            help_heading = "DOWNLOAD CONFIGURATION / MCUBOOT IMAGE"
+        )
+    )]
+    pub mcuboot_test_swap: bool,
+
+    /// Marks the image as confirmed, so a swap is permanent.
+    #[cfg_attr(
+        feature = "clap",
+        clap(
+            long,
+            requires = "mcuboot_slot_size",
+            help_heading = "DOWNLOAD CONFIGURATION / MCUBOOT IMAGE"
+        )
+    )]
+    pub mcuboot_confirm: bool,
+
+    /// The largest write alignment of the flash, which determines the layout of the trailer.
+    #[cfg_attr(
+        feature = "clap",
+        clap(
+            long,
+            value_parser = parse_u32,
+            default_value = "8",
+            help_heading = "DOWNLOAD CONFIGURATION / MCUBOOT IMAGE"
+        )
+    )]
+    pub mcuboot_align: u32,
+}
+
+impl Default for McubootCliOptions {
+    fn default() -> Self {
+        Self {
+            mcuboot: false,
+            mcuboot_key: None,
+            mcuboot_slot: None,
+            mcuboot_slot_size: None,
+            mcuboot_header_size: 0x200,
+            mcuboot_version: "0.0.0".to_string(),
+            mcuboot_test_swap: false,
+            mcuboot_confirm: false,
+            mcuboot_align: 8,
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, Default, Schema)]
 #[cfg_attr(feature = "clap", derive(clap::Parser))]
 #[serde(default)]
@@ -145,6 +307,15 @@ pub struct FormatOptions {
 
     #[cfg_attr(feature = "clap", clap(flatten))]
     pub elf_options: ElfCliOptions,
+
+    #[cfg_attr(feature = "clap", clap(flatten))]
+    pub manifest_options: ManifestCliOptions,
+
+    #[cfg_attr(feature = "clap", clap(flatten))]
+    pub external_flash_options: ExternalFlashCliOptions,
+
+    #[cfg_attr(feature = "clap", clap(flatten))]
+    pub mcuboot_options: McubootCliOptions,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Schema)]
@@ -165,6 +336,8 @@ pub enum FormatKind {
     Idf,
 
     Uf2,
+
+    Manifest,
 }
 
 impl FormatKind {
@@ -177,6 +350,7 @@ impl FormatKind {
                 "elf" => Ok(Self::Elf),
                 "idf" | "esp-idf" | "espidf" => Ok(Self::Idf),
                 "uf2" => Ok(Self::Uf2),
+                "manifest" => Ok(Self::Manifest),
                 _ => Err(format!("invalid variant: {format}")),
             },
             None => Ok(Self::Elf),
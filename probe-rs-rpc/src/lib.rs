@@ -172,4 +172,5 @@ pub mod rtt_config;
 pub mod semihosting_options;
 pub mod stack_trace;
 pub mod test;
+pub mod trace;
 pub mod transport;
@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::flash::BootInfo;
 use crate::semihosting_options::SemihostingOptions;
+use crate::trace::BranchTraceConfig;
 use crate::{Key, RpcResult, RttClient, Session};
 
 #[derive(Serialize, Deserialize, Schema)]
@@ -29,6 +30,8 @@ pub struct MonitorOptions {
     pub catch_hlt: bool,
     pub rtt_client: Option<Key<RttClient>>,
     pub semihosting_options: SemihostingOptions,
+    /// Record the branches of the monitored core with the MTB.
+    pub branch_trace: Option<BranchTraceConfig>,
 }
 
 #[derive(Serialize, Deserialize, Schema)]
@@ -114,8 +114,19 @@ pub struct AttachRequest {
     pub connect_under_reset: bool,
     pub dry_run: bool,
     pub allow_erase_all: bool,
+    pub allow_flash_breakpoints: bool,
     pub resume_target: bool,
     pub wait_for_probe: Option<Duration>,
 }
 
 pub type AttachResponse = RpcResult<AttachResult>;
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct ProbeSpeedRequest {
+    pub sessid: Key<Session>,
+    /// The new speed in kHz, or `None` to only read the current speed.
+    pub speed_khz: Option<u32>,
+}
+
+/// The speed the probe uses, in kHz.
+pub type ProbeSpeedResponse = RpcResult<u32>;
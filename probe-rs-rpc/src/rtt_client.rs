@@ -12,10 +12,63 @@ pub enum ScanRegion {
     Exact(u64),
 }
 
+/// How to locate the RTT control block. The strategies are tried in field order.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Schema)]
+pub struct RttDiscovery {
+    /// The address of the `_SEGGER_RTT` symbol.
+    pub symbol: Option<u64>,
+    /// Addresses given by the user.
+    pub pinned: Vec<u64>,
+    /// The region to scan, if any.
+    pub scan: Option<ScanRegion>,
+}
+
+impl RttDiscovery {
+    /// Creates a pipeline that only uses the given region. An exact address is pinned, an empty
+    /// list of ranges disables scanning.
+    pub fn from_scan_region(region: ScanRegion) -> Self {
+        match region {
+            ScanRegion::Exact(address) => Self {
+                pinned: vec![address],
+                ..Self::default()
+            },
+            ScanRegion::Ranges(ranges) if ranges.is_empty() => Self::default(),
+            region => Self {
+                scan: Some(region),
+                ..Self::default()
+            },
+        }
+    }
+}
+
+/// The strategy that located the RTT control block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Schema)]
+pub enum RttDiscoveryStrategy {
+    Symbol,
+    Pinned,
+    Scan,
+}
+
+impl std::fmt::Display for RttDiscoveryStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Symbol => "the _SEGGER_RTT symbol",
+            Self::Pinned => "a pinned address",
+            Self::Scan => "a memory scan",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Schema)]
+pub struct RttLocation {
+    pub address: u64,
+    pub strategy: RttDiscoveryStrategy,
+}
+
 #[derive(Serialize, Deserialize, Schema)]
 pub struct CreateRttClientRequest {
     pub sessid: Key<Session>,
-    pub scan_regions: ScanRegion,
+    pub discovery: RttDiscovery,
     pub config: Vec<RttChannelConfig>,
     pub default_config: RttChannelConfig,
 }
@@ -54,6 +107,8 @@ pub struct RttChannelMeta {
 pub struct RttChannels {
     pub up: Vec<RttChannelMeta>,
     pub down: Vec<RttChannelMeta>,
+    /// Where the control block was found, if the client is attached.
+    pub location: Option<RttLocation>,
 }
 
 pub type RttChannelsResponse = RpcResult<RttChannels>;
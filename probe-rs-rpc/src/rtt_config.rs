@@ -45,6 +45,10 @@ pub struct RttChannelConfig {
     #[serde(default)]
     pub data_format: DataFormat,
 
+    /// The name of an RTT decoder registered by a plugin. Takes precedence over `data_format`.
+    #[serde(default)]
+    pub decoder: Option<String>,
+
     /// RTT channel operating mode. Defaults to the target's configuration.
     #[serde(default)]
     pub mode: Option<ChannelMode>,
@@ -67,6 +71,7 @@ impl Default for RttChannelConfig {
         RttChannelConfig {
             channel_number: Default::default(),
             data_format: Default::default(),
+            decoder: Default::default(),
             mode: Default::default(),
             show_timestamps: default_show_timestamps(),
             show_location: Default::default(),
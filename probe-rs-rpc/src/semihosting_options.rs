@@ -14,6 +14,8 @@ pub enum Mapping {
 #[derive(Serialize, Deserialize, Schema, Clone, Default)]
 pub struct SemihostingOptions {
     mappings: Vec<Mapping>,
+    sandbox: Option<String>,
+    coverage_dir: Option<String>,
 }
 
 impl SemihostingOptions {
@@ -25,6 +27,24 @@ impl SemihostingOptions {
         &self.mappings
     }
 
+    /// The host directory used for `SYS_SYSTEM` and `SYS_TMPNAM`.
+    pub fn sandbox(&self) -> Option<&str> {
+        self.sandbox.as_deref()
+    }
+
+    pub fn set_sandbox(&mut self, dir: String) {
+        self.sandbox = Some(dir);
+    }
+
+    /// The host directory that receives `.gcda` and `.profraw` files written by the target.
+    pub fn coverage_dir(&self) -> Option<&str> {
+        self.coverage_dir.as_deref()
+    }
+
+    pub fn set_coverage_dir(&mut self, dir: String) {
+        self.coverage_dir = Some(dir);
+    }
+
     pub fn add_file(&mut self, from: String, to: String) -> Result<(), Infallible> {
         self.mappings.push(Mapping::Exact(from, to));
         Ok(())
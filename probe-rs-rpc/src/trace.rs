@@ -0,0 +1,57 @@
+use postcard_schema::Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Key, RpcResult, Session};
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct SwoStartRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+    /// The speed of the clock feeding the TPIU/SWO module in Hz.
+    pub clk: u32,
+    /// The desired baud rate of the SWO output.
+    pub baud: u32,
+}
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct SwoReadRequest {
+    pub sessid: Key<Session>,
+}
+
+/// The raw SWO data received since the last read.
+pub type SwoReadResponse = RpcResult<Vec<u8>>;
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct SwoStopRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+}
+
+/// The SRAM window the MTB records branches into.
+#[derive(Clone, Copy, Serialize, Deserialize, Schema)]
+pub struct BranchTraceConfig {
+    /// The offset of the window from the SRAM base address of the MTB.
+    pub offset: u32,
+    /// The size of the window in bytes. Must be a power of two.
+    pub size: u32,
+}
+
+#[derive(Serialize, Deserialize, Schema)]
+pub struct BranchTraceReadRequest {
+    pub sessid: Key<Session>,
+    pub core: u32,
+}
+
+/// A branch recorded by the MTB.
+#[derive(Clone, Serialize, Deserialize, Schema)]
+pub struct Branch {
+    pub source: u32,
+    pub destination: u32,
+    /// The branch was an exception entry or an exception return.
+    pub exception: bool,
+    /// This is the first branch recorded after the trace was started.
+    pub start: bool,
+}
+
+/// The recorded branches, oldest first.
+pub type BranchTraceReadResponse = RpcResult<Vec<Branch>>;
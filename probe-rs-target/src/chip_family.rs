@@ -1,7 +1,7 @@
 use crate::memory::RegionMergeIterator as _;
 use crate::serialize::hex_jep106_option;
+use crate::{ConfigRegion, MemoryRange, MemoryRegion};
 use crate::{CoreAccessOptions, chip_detection::ChipDetectionMethod};
-use crate::{MemoryRange, MemoryRegion};
 
 use super::chip::Chip;
 use super::flash_algorithm::RawFlashAlgorithm;
@@ -401,6 +401,43 @@ impl ChipFamily {
                         variant.name, memory
                     ));
                 }
+
+                if let MemoryRegion::Config(region) = memory {
+                    Self::validate_config_fields(&variant.name, region)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ensures that the fields of a configuration region fit into the region.
+    fn validate_config_fields(variant: &str, region: &ConfigRegion) -> Result<(), String> {
+        let size = region.range.end - region.range.start;
+        for field in &region.fields {
+            if field.bit_width == 0 || field.bit_width > 64 {
+                return Err(format!(
+                    "Variant {variant}, field {} has an invalid width of {} bits",
+                    field.name, field.bit_width
+                ));
+            }
+
+            if field.byte_range().end as u64 > size {
+                return Err(format!(
+                    "Variant {variant}, field {} does not fit into the region {:#010x?}",
+                    field.name, region.range
+                ));
+            }
+
+            if let Some(value) = field
+                .values
+                .iter()
+                .find(|value| value.value > field.max_value())
+            {
+                return Err(format!(
+                    "Variant {variant}, value {} of field {} does not fit into {} bits",
+                    value.name, field.name, field.bit_width
+                ));
             }
         }
 
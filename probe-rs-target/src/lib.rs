@@ -28,8 +28,9 @@ pub use chip_family::{
 pub use flash_algorithm::{RawFlashAlgorithm, TransferEncoding};
 pub use flash_properties::FlashProperties;
 pub use memory::{
-    GenericRegion, MemoryAccess, MemoryRange, MemoryRegion, NvmRegion, PageInfo, RamRegion,
-    RegionMergeIterator, SectorDescription, SectorInfo,
+    ConfigField, ConfigFieldValue, ConfigRegion, ConfigRegionKind, GenericRegion, MemoryAccess,
+    MemoryRange, MemoryRegion, NvmRegion, PageInfo, RamRegion, RegionMergeIterator,
+    SectorDescription, SectorInfo,
 };
 
 #[cfg(feature = "bincode")]
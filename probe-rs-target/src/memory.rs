@@ -277,6 +277,182 @@ impl GenericRegion {
     }
 }
 
+/// The kind of device configuration stored in a [`ConfigRegion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConfigRegionKind {
+    /// Option bytes, which can be erased and programmed again, e.g. the STM32 option bytes or
+    /// the nRF UICR.
+    OptionBytes,
+    /// One-time programmable memory.
+    Otp,
+    /// Fuses, e.g. the ESP32 eFuses. Bits can only be set, never cleared.
+    Fuses,
+}
+
+impl ConfigRegionKind {
+    /// Returns `true` if the region can only be programmed once, so every change is
+    /// irreversible.
+    pub fn is_one_time_programmable(&self) -> bool {
+        matches!(self, ConfigRegionKind::Otp | ConfigRegionKind::Fuses)
+    }
+}
+
+/// Represents a region that holds device configuration, like option bytes, OTP memory or fuses.
+///
+/// The region is read like memory, and programmed with the flash algorithm that covers its
+/// range. Unlike an [`NvmRegion`], it is never written when flashing an image.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigRegion {
+    /// A name to describe the region
+    pub name: Option<String>,
+    /// The kind of configuration stored in the region.
+    pub kind: ConfigRegionKind,
+    /// Address range of the region
+    #[serde(serialize_with = "hex_range")]
+    pub range: Range<u64>,
+    /// List of cores that can access this region
+    pub cores: Vec<String>,
+    /// The fields stored in the region.
+    #[serde(default)]
+    pub fields: Vec<ConfigField>,
+}
+
+impl ConfigRegion {
+    /// Returns whether the region is accessible by the given core.
+    pub fn accessible_by(&self, core_name: &str) -> bool {
+        self.cores.iter().any(|c| c == core_name)
+    }
+
+    /// Returns the field with the given name.
+    pub fn field(&self, name: &str) -> Option<&ConfigField> {
+        self.fields
+            .iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns `true` if changing `field` from `old` to `new` can't be undone.
+    pub fn is_irreversible_change(&self, field: &ConfigField, old: u64, new: u64) -> bool {
+        old != new
+            && (self.kind.is_one_time_programmable()
+                || field.irreversible
+                || field.value(new).is_some_and(|value| value.irreversible))
+    }
+
+    /// Returns the region as an [`NvmRegion`], which is used to program it with a flash
+    /// algorithm.
+    pub fn to_nvm_region(&self) -> NvmRegion {
+        NvmRegion {
+            name: self.name.clone(),
+            range: self.range.clone(),
+            cores: self.cores.clone(),
+            is_alias: true,
+            access: Some(MemoryAccess {
+                execute: false,
+                ..Default::default()
+            }),
+        }
+    }
+}
+
+/// A field of a [`ConfigRegion`].
+///
+/// The field occupies `bit_width` bits, starting at bit `bit_offset` of the little endian value
+/// stored at `offset`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigField {
+    /// The name of the field, e.g. `RDP`.
+    pub name: String,
+    /// A description of the field.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// The offset of the field in bytes, from the start of the region.
+    #[serde(serialize_with = "hex_u_int")]
+    pub offset: u64,
+    /// The position of the least significant bit of the field.
+    #[serde(default)]
+    pub bit_offset: u32,
+    /// The width of the field in bits, at most 64.
+    pub bit_width: u32,
+    /// Named values of the field.
+    #[serde(default)]
+    pub values: Vec<ConfigFieldValue>,
+    /// True if any change of the field can't be undone, e.g. a lock bit.
+    #[serde(default)]
+    pub irreversible: bool,
+}
+
+impl ConfigField {
+    /// Returns the bytes of the region that hold the field.
+    pub fn byte_range(&self) -> Range<usize> {
+        let start = self.offset as usize + self.bit_offset as usize / 8;
+        let end = self.offset as usize + (self.bit_offset + self.bit_width).div_ceil(8) as usize;
+        start..end
+    }
+
+    /// Returns the largest value the field can hold.
+    pub fn max_value(&self) -> u64 {
+        u64::MAX >> (64 - self.bit_width.clamp(1, 64))
+    }
+
+    /// Extracts the value of the field from the contents of the region.
+    ///
+    /// Panics if the contents don't cover the field.
+    pub fn extract(&self, data: &[u8]) -> u64 {
+        let shift = self.bit_offset % 8;
+        let mut raw = 0u128;
+        for (i, byte) in data[self.byte_range()].iter().enumerate() {
+            raw |= (*byte as u128) << (8 * i);
+        }
+        (raw >> shift) as u64 & self.max_value()
+    }
+
+    /// Stores `value` into the contents of the region, leaving all other bits untouched.
+    ///
+    /// Panics if the contents don't cover the field. Bits of `value` that don't fit into the field
+    /// are ignored.
+    pub fn insert(&self, data: &mut [u8], value: u64) {
+        let shift = self.bit_offset % 8;
+        let mask = (self.max_value() as u128) << shift;
+        let value = ((value & self.max_value()) as u128) << shift;
+        for (i, byte) in data[self.byte_range()].iter_mut().enumerate() {
+            let byte_mask = (mask >> (8 * i)) as u8;
+            *byte = (*byte & !byte_mask) | ((value >> (8 * i)) as u8 & byte_mask);
+        }
+    }
+
+    /// Returns the named value with the given value.
+    pub fn value(&self, value: u64) -> Option<&ConfigFieldValue> {
+        self.values.iter().find(|named| named.value == value)
+    }
+
+    /// Returns the named value with the given name.
+    pub fn value_by_name(&self, name: &str) -> Option<&ConfigFieldValue> {
+        self.values
+            .iter()
+            .find(|named| named.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A named value of a [`ConfigField`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFieldValue {
+    /// The name of the value, e.g. `Level1`.
+    pub name: String,
+    /// The value of the field.
+    #[serde(serialize_with = "hex_u_int")]
+    pub value: u64,
+    /// A description of the value.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// True if setting the field to this value can't be undone, e.g. a permanent readout
+    /// protection level.
+    #[serde(default)]
+    pub irreversible: bool,
+}
+
 /// Holds information about a specific, individual flash
 /// sector.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -394,6 +570,8 @@ pub enum MemoryRegion {
     /// Memory region describing flash, EEPROM or other non-volatile memory.
     #[serde(alias = "Flash")] // Keeping the "Flash" name this for backwards compatibility
     Nvm(NvmRegion),
+    /// Memory region holding device configuration, like option bytes, OTP memory or fuses.
+    Config(ConfigRegion),
 }
 
 impl MemoryRegion {
@@ -413,12 +591,21 @@ impl MemoryRegion {
         }
     }
 
+    /// Returns the configuration region if this is a configuration region, otherwise None.
+    pub fn as_config_region(&self) -> Option<&ConfigRegion> {
+        match self {
+            MemoryRegion::Config(region) => Some(region),
+            _ => None,
+        }
+    }
+
     /// Returns the address range of the memory region.
     pub fn address_range(&self) -> Range<u64> {
         match self {
             MemoryRegion::Ram(rr) => rr.range.clone(),
             MemoryRegion::Generic(gr) => gr.range.clone(),
             MemoryRegion::Nvm(nr) => nr.range.clone(),
+            MemoryRegion::Config(cr) => cr.range.clone(),
         }
     }
 
@@ -433,6 +620,7 @@ impl MemoryRegion {
             MemoryRegion::Ram(region) => &region.cores,
             MemoryRegion::Generic(region) => &region.cores,
             MemoryRegion::Nvm(region) => &region.cores,
+            MemoryRegion::Config(region) => &region.cores,
         }
     }
 
@@ -684,4 +872,68 @@ mod test {
             ]
         );
     }
+
+    fn rdp_field() -> ConfigField {
+        ConfigField {
+            name: "RDP".to_string(),
+            description: None,
+            offset: 0,
+            bit_offset: 0,
+            bit_width: 8,
+            values: vec![
+                ConfigFieldValue {
+                    name: "Level0".to_string(),
+                    value: 0xAA,
+                    description: None,
+                    irreversible: false,
+                },
+                ConfigFieldValue {
+                    name: "Level2".to_string(),
+                    value: 0xCC,
+                    description: None,
+                    irreversible: true,
+                },
+            ],
+            irreversible: false,
+        }
+    }
+
+    #[test]
+    fn config_field_spanning_bytes() {
+        let field = ConfigField {
+            name: "BOR_LEV".to_string(),
+            description: None,
+            offset: 4,
+            bit_offset: 6,
+            bit_width: 4,
+            values: vec![],
+            irreversible: false,
+        };
+        assert_eq!(field.byte_range(), 4..6);
+
+        let mut data = [0xFF; 8];
+        field.insert(&mut data, 0b0101);
+        assert_eq!(data, [0xFF, 0xFF, 0xFF, 0xFF, 0x7F, 0xFD, 0xFF, 0xFF]);
+        assert_eq!(field.extract(&data), 0b0101);
+    }
+
+    #[test]
+    fn irreversible_config_changes() {
+        let field = rdp_field();
+        let mut region = ConfigRegion {
+            name: Some("OPT".to_string()),
+            kind: ConfigRegionKind::OptionBytes,
+            range: 0x1FFF_7800..0x1FFF_7810,
+            cores: vec!["main".to_string()],
+            fields: vec![field.clone()],
+        };
+
+        assert!(!region.is_irreversible_change(&field, 0xAA, 0xBB));
+        assert!(region.is_irreversible_change(&field, 0xAA, 0xCC));
+        assert!(!region.is_irreversible_change(&field, 0xCC, 0xCC));
+
+        region.kind = ConfigRegionKind::Fuses;
+        assert!(region.is_irreversible_change(&field, 0xAA, 0xBB));
+        assert_eq!(region.field("rdp"), Some(&field));
+    }
 }
@@ -5,10 +5,13 @@ pub mod cargo_flash;
 pub mod chip;
 pub mod common;
 pub mod complete;
+pub mod config_region;
 pub mod dap_server;
 pub mod debug;
 pub mod download;
+pub mod dump_replay;
 pub mod erase;
+pub mod etm;
 pub mod gdb_server;
 pub mod info;
 pub mod itm;
@@ -17,6 +20,7 @@ pub mod mi;
 pub mod profile;
 pub mod read;
 pub mod reset;
+pub mod rtt_replay;
 pub mod run;
 #[cfg(feature = "remote")]
 pub mod serve;
@@ -9,6 +9,8 @@ use probe_rs::{MemoryInterface, config::Registry, probe::list::Lister};
 use crate::util::common_options::LoadedProbeOptions;
 use crate::util::common_options::ProbeOptions;
 
+mod flash;
+
 const PROBE_SPEEDS: [u32; 10] = [320, 640, 960, 3200, 6400, 9600, 32000, 64000, 96000, 320000];
 const TEST_SIZES: [usize; 5] = [1, 8, 32, 512, 8192];
 
@@ -19,9 +21,10 @@ pub struct Cmd {
 
     /// Start address for the benchmark test.
     ///
-    /// Should be located in RAM.
-    #[clap(long = "address", value_parser= parse_hex)]
-    address: u64,
+    /// Should be located in RAM. For the flash benchmark, this defaults to the start of the
+    /// first flash region of the target.
+    #[clap(long = "address", value_parser= parse_hex, required_unless_present = "flash")]
+    address: Option<u64>,
 
     /// Minimum speed for the debug probe.
     ///
@@ -52,6 +55,9 @@ pub struct Cmd {
     /// both reduce the amount of jitter, and also quantify it (via standard deviation calcs)
     #[clap(long = "iterations", value_parser= parse_usize, default_value="5")]
     iterations: usize,
+
+    #[clap(flatten)]
+    flash: flash::FlashOptions,
 }
 
 fn parse_usize(src: &str) -> Result<usize, ParseIntError> {
@@ -86,6 +92,19 @@ impl Cmd {
     pub fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
         let speed = self.common.speed;
         let common_options = self.common.load(registry)?;
+
+        if self.flash.flash {
+            Cmd::print_info(&common_options, lister)?;
+            return self.flash.run(
+                &common_options,
+                lister,
+                self.address,
+                speed.unwrap_or(3000),
+                self.iterations,
+            );
+        }
+
+        let address = self.address.context("No address given")?;
         let mut max_speed = self.max_speed;
         let mut speeds = vec![];
         // if no max-speed specified, assume the user just wants to use a single speed (as per other cli cmds)
@@ -108,7 +127,7 @@ impl Cmd {
                     lister,
                     *speed,
                     size,
-                    self.address,
+                    address,
                     self.word_size,
                     self.iterations,
                 );
@@ -0,0 +1,455 @@
+//! Benchmark of the complete flash programming path.
+//!
+//! Synthetic images are flashed with [`FlashLoader::commit`], and the duration of every phase
+//! (erase, fill, program, verify) is taken from the [`ProgressEvent`]s emitted while flashing.
+//!
+//! With `--simulated-latency`, the images are flashed to a [`FakeProbe`] with a simulated core,
+//! so the benchmark runs without hardware, e.g. in CI.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use probe_rs::Session;
+use probe_rs::flashing::{DownloadOptions, FlashProgress, ProgressEvent, ProgressOperation};
+use probe_rs::integration::FakeProbe;
+use probe_rs::probe::list::Lister;
+use serde::Serialize;
+
+use super::{mean, parse_int, std_deviation};
+use crate::util::common_options::LoadedProbeOptions;
+
+#[derive(clap::Args)]
+pub struct FlashOptions {
+    /// Benchmark flash programming instead of memory accesses.
+    #[clap(long, help_heading = "FLASH BENCHMARK")]
+    pub flash: bool,
+
+    /// Sizes of the synthetic images in bytes.
+    #[clap(
+        long,
+        value_parser = parse_int::parse::<u64>,
+        default_value = "16384",
+        value_delimiter = ',',
+        help_heading = "FLASH BENCHMARK"
+    )]
+    flash_sizes: Vec<u64>,
+
+    /// The content of the synthetic images.
+    #[clap(long, value_enum, default_value_t = FillPattern::Random, help_heading = "FLASH BENCHMARK")]
+    fill_pattern: FillPattern,
+
+    /// Seed for the `random` fill pattern, to flash the same image in every run.
+    #[clap(long, value_parser = parse_int, default_value = "0", help_heading = "FLASH BENCHMARK")]
+    seed: u32,
+
+    /// Verify the flashed data.
+    #[clap(long, help_heading = "FLASH BENCHMARK")]
+    verify: bool,
+
+    /// Flash to a simulated probe and core instead of the hardware, with this latency in
+    /// microseconds for every memory access. The simulated flash keeps its contents, so this
+    /// can't be combined with `--verify`.
+    #[clap(
+        long,
+        value_parser = parse_int::parse::<u64>,
+        conflicts_with = "verify",
+        help_heading = "FLASH BENCHMARK"
+    )]
+    simulated_latency: Option<u64>,
+
+    /// Disable double buffering when programming.
+    #[clap(long, help_heading = "FLASH BENCHMARK")]
+    disable_double_buffering: bool,
+
+    /// Use the chip erase instead of erasing single sectors.
+    #[clap(long, help_heading = "FLASH BENCHMARK")]
+    chip_erase: bool,
+
+    /// Write the results as JSON to this file.
+    #[clap(long, help_heading = "FLASH BENCHMARK")]
+    json: Option<PathBuf>,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum FillPattern {
+    /// All bytes are 0x00.
+    Zeros,
+    /// All bytes are 0xFF, which is the erased state of most flash memories.
+    Ones,
+    /// The low byte of the offset into the image.
+    Increment,
+    /// Pseudo-random data, generated from `--seed`.
+    Random,
+}
+
+impl FillPattern {
+    fn image(self, size: u64, seed: u32) -> Vec<u8> {
+        let size = size as usize;
+        match self {
+            FillPattern::Zeros => vec![0x00; size],
+            FillPattern::Ones => vec![0xFF; size],
+            FillPattern::Increment => (0..size).map(|i| i as u8).collect(),
+            FillPattern::Random => {
+                let mut rng = fastrand::Rng::with_seed(seed.into());
+                let mut data = vec![0; size];
+                rng.fill(&mut data);
+                data
+            }
+        }
+    }
+}
+
+/// The results of all benchmark runs, in the format written by `--json`.
+#[derive(Serialize)]
+struct FlashBenchmarkReport {
+    probe_rs_version: &'static str,
+    probe: String,
+    target: String,
+    speed_khz: u32,
+    address: u64,
+    fill_pattern: FillPattern,
+    verify: bool,
+    double_buffering: bool,
+    chip_erase: bool,
+    dry_run: bool,
+    simulated_latency_us: Option<u64>,
+    results: Vec<SizeResult>,
+}
+
+/// The runs of one image size.
+#[derive(Serialize)]
+struct SizeResult {
+    size: u64,
+    mean_total_s: Option<f64>,
+    std_dev_total_s: Option<f64>,
+    runs: Vec<PhaseTimings>,
+}
+
+/// The timings of a single run.
+#[derive(Serialize, Default, Debug)]
+struct PhaseTimings {
+    /// Duration of the complete `FlashLoader::commit` call.
+    total_s: f64,
+    phases: BTreeMap<&'static str, PhaseTiming>,
+}
+
+#[derive(Serialize, Default, Debug, PartialEq)]
+struct PhaseTiming {
+    /// Time between the start and the end of the phase.
+    duration_s: f64,
+    /// Sum of the durations reported for the individual sectors or pages.
+    operation_s: f64,
+    /// Number of bytes processed.
+    bytes: u64,
+    failed: bool,
+}
+
+/// Collects the phase timings from the progress events of a single run.
+#[derive(Default)]
+struct PhaseRecorder {
+    started: BTreeMap<&'static str, Instant>,
+    timings: PhaseTimings,
+}
+
+impl PhaseRecorder {
+    fn handle(&mut self, event: ProgressEvent, now: Instant) {
+        match event {
+            ProgressEvent::Started(operation) => {
+                self.started.insert(phase_name(operation), now);
+            }
+            ProgressEvent::Progress {
+                operation,
+                size,
+                time,
+            } => {
+                let timing = self.phase(operation);
+                timing.bytes += size;
+                timing.operation_s += time.as_secs_f64();
+            }
+            ProgressEvent::Finished(operation) => self.finish(operation, now, false),
+            ProgressEvent::Failed(operation) => self.finish(operation, now, true),
+            _ => {}
+        }
+    }
+
+    fn phase(&mut self, operation: ProgressOperation) -> &mut PhaseTiming {
+        self.timings
+            .phases
+            .entry(phase_name(operation))
+            .or_default()
+    }
+
+    fn finish(&mut self, operation: ProgressOperation, now: Instant, failed: bool) {
+        let started = self.started.remove(phase_name(operation));
+        let timing = self.phase(operation);
+        if let Some(started) = started {
+            timing.duration_s += now.duration_since(started).as_secs_f64();
+        }
+        timing.failed |= failed;
+    }
+
+    fn finish_run(mut self, total: Duration) -> PhaseTimings {
+        self.timings.total_s = total.as_secs_f64();
+        self.timings
+    }
+}
+
+fn phase_name(operation: ProgressOperation) -> &'static str {
+    match operation {
+        ProgressOperation::Fill => "fill",
+        ProgressOperation::Erase => "erase",
+        ProgressOperation::Program => "program",
+        ProgressOperation::Verify => "verify",
+        ProgressOperation::Ram => "ram",
+    }
+}
+
+impl FlashOptions {
+    pub fn run(
+        &self,
+        common_options: &LoadedProbeOptions,
+        lister: &Lister,
+        address: Option<u64>,
+        speed: u32,
+        iterations: usize,
+    ) -> anyhow::Result<()> {
+        let mut probe = match self.simulated_latency {
+            Some(latency) => {
+                FakeProbe::with_simulated_core(Duration::from_micros(latency)).into_probe()
+            }
+            None => common_options.attach_probe(lister)?,
+        };
+        let probe_name = probe.get_name();
+        probe
+            .set_speed(speed)
+            .with_context(|| format!("Failed to set speed {speed}"))?;
+        let target = common_options.get_target_selector()?;
+        let mut session = common_options.attach_session(probe, target)?;
+
+        let report = self.benchmark(
+            &mut session,
+            probe_name,
+            address,
+            speed,
+            iterations,
+            common_options.dry_run(),
+        )?;
+
+        if let Some(path) = &self.json {
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            serde_json::to_writer_pretty(file, &report)?;
+        }
+
+        Ok(())
+    }
+
+    fn benchmark(
+        &self,
+        session: &mut Session,
+        probe: String,
+        address: Option<u64>,
+        speed: u32,
+        iterations: usize,
+        dry_run: bool,
+    ) -> anyhow::Result<FlashBenchmarkReport> {
+        let address = match address {
+            Some(address) => address,
+            None => session
+                .target()
+                .memory_map
+                .iter()
+                .filter_map(|region| region.as_nvm_region())
+                .find(|region| !region.is_alias)
+                .map(|region| region.range.start)
+                .context("The target has no flash memory")?,
+        };
+
+        let mut report = FlashBenchmarkReport {
+            probe_rs_version: env!("CARGO_PKG_VERSION"),
+            probe,
+            target: session.target().name.clone(),
+            speed_khz: speed,
+            address,
+            fill_pattern: self.fill_pattern,
+            verify: self.verify,
+            double_buffering: !self.disable_double_buffering,
+            chip_erase: self.chip_erase,
+            dry_run,
+            simulated_latency_us: self.simulated_latency,
+            results: vec![],
+        };
+
+        for &size in &self.flash_sizes {
+            println!(
+                "Test: Flash {size} bytes at {address:#010x}, pattern {:?}, Number of iterations {iterations}",
+                self.fill_pattern
+            );
+
+            let image = self.fill_pattern.image(size, self.seed);
+            let mut runs = Vec::with_capacity(iterations);
+            for _ in 0..iterations {
+                let mut loader = session.target().flash_loader();
+                loader.add_data(address, &image)?;
+
+                let mut recorder = PhaseRecorder::default();
+                let start = Instant::now();
+                {
+                    let mut options = DownloadOptions::new();
+                    options.progress = FlashProgress::new(|event| {
+                        recorder.handle(event, Instant::now());
+                    });
+                    options.verify = self.verify;
+                    options.disable_double_buffering = self.disable_double_buffering;
+                    options.do_chip_erase = self.chip_erase;
+                    options.dry_run = dry_run;
+
+                    loader.commit(session, options)?;
+                }
+                runs.push(recorder.finish_run(start.elapsed()));
+            }
+
+            let totals = runs.iter().map(|run| run.total_s).collect::<Vec<_>>();
+            let result = SizeResult {
+                size,
+                mean_total_s: mean(&totals),
+                std_dev_total_s: std_deviation(&totals),
+                runs,
+            };
+
+            print_result(&result);
+            report.results.push(result);
+        }
+
+        Ok(report)
+    }
+}
+
+fn print_result(result: &SizeResult) {
+    if let (Some(mean), Some(std_dev)) = (result.mean_total_s, result.std_dev_total_s) {
+        println!(
+            "Results: Total: {:.3} s Std Dev {:.3}, {:.2} bytes/s",
+            mean,
+            std_dev,
+            result.size as f64 / mean
+        );
+    }
+
+    let mut phases = BTreeMap::<&str, Vec<f64>>::new();
+    for run in &result.runs {
+        for (name, timing) in &run.phases {
+            phases.entry(name).or_default().push(timing.duration_s);
+        }
+    }
+    for (name, durations) in phases {
+        if let Some(mean) = mean(&durations) {
+            println!("  {name:<8} {mean:.3} s");
+        }
+    }
+
+    // Insert another blank line to visually separate results
+    println!();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn phases_are_timed_from_progress_events() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        let mut recorder = PhaseRecorder::default();
+        recorder.handle(ProgressEvent::Started(ProgressOperation::Erase), at(0));
+        for _ in 0..2 {
+            recorder.handle(
+                ProgressEvent::Progress {
+                    operation: ProgressOperation::Erase,
+                    size: 4096,
+                    time: Duration::from_millis(40),
+                },
+                at(50),
+            );
+        }
+        recorder.handle(ProgressEvent::Finished(ProgressOperation::Erase), at(100));
+        recorder.handle(ProgressEvent::Started(ProgressOperation::Program), at(100));
+        recorder.handle(ProgressEvent::Failed(ProgressOperation::Program), at(150));
+
+        let timings = recorder.finish_run(Duration::from_millis(200));
+        assert_eq!(timings.total_s, 0.2);
+        assert_eq!(
+            timings.phases["erase"],
+            PhaseTiming {
+                duration_s: 0.1,
+                operation_s: 0.08,
+                bytes: 8192,
+                failed: false,
+            }
+        );
+        assert_eq!(timings.phases["program"].duration_s, 0.05);
+        assert!(timings.phases["program"].failed);
+    }
+
+    #[test]
+    fn simulated_flash_reports_phase_timings() {
+        use clap::Parser as _;
+
+        #[derive(clap::Parser)]
+        struct Cli {
+            #[clap(flatten)]
+            flash: FlashOptions,
+        }
+
+        let options = Cli::parse_from([
+            "benchmark",
+            "--flash",
+            "--flash-sizes",
+            "2048,4096",
+            "--simulated-latency",
+            "5",
+        ])
+        .flash;
+
+        let probe = FakeProbe::with_simulated_core(Duration::from_micros(5)).into_probe();
+        let mut session = probe
+            .attach("nrf51822_xxAC", probe_rs::Permissions::default())
+            .unwrap();
+
+        let report = options
+            .benchmark(&mut session, "simulated".to_string(), None, 4000, 2, false)
+            .unwrap();
+        let json = serde_json::to_value(&report).unwrap();
+
+        assert_eq!(json["simulated_latency_us"], 5);
+        assert_eq!(json["results"].as_array().unwrap().len(), 2);
+        for (result, size) in json["results"].as_array().unwrap().iter().zip([2048, 4096]) {
+            assert_eq!(result["size"], size);
+            assert!(result["mean_total_s"].as_f64().unwrap() > 0.0);
+
+            let runs = result["runs"].as_array().unwrap();
+            assert_eq!(runs.len(), 2);
+            for run in runs {
+                let phases = &run["phases"];
+                for phase in ["erase", "program"] {
+                    assert!(phases[phase]["duration_s"].as_f64().unwrap() > 0.0);
+                    assert!(phases[phase]["duration_s"].as_f64() <= run["total_s"].as_f64());
+                    assert_eq!(phases[phase]["failed"], false);
+                }
+                assert_eq!(phases["program"]["bytes"], size);
+            }
+        }
+    }
+
+    #[test]
+    fn random_images_are_reproducible() {
+        assert_eq!(
+            FillPattern::Random.image(64, 7),
+            FillPattern::Random.image(64, 7)
+        );
+        assert_eq!(FillPattern::Increment.image(258, 0)[257], 1);
+    }
+}
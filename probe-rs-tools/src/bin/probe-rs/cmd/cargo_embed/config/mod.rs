@@ -63,6 +63,8 @@ pub struct Flashing {
     pub disable_double_buffering: bool,
     /// Whether to verify flash contents before downloading.
     pub preverify: bool,
+    /// Whether to skip up-to-date sectors using checksums computed on the target.
+    pub preverify_checksum: bool,
     /// Whether to verify flash contents after downloading.
     pub verify: bool,
     /// Whether to read RTT output from the flash loader.
@@ -96,6 +98,9 @@ pub struct UpChannelConfig {
     pub mode: Option<ChannelMode>,
     #[serde(default)]
     pub format: Option<DataFormat>,
+    /// The name of an RTT decoder registered by a plugin. Takes precedence over `format`.
+    #[serde(default)]
+    pub decoder: Option<String>,
     #[serde(default)]
     pub show_location: Option<bool>,
     #[serde(default)]
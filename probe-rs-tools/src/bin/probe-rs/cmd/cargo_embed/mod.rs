@@ -10,7 +10,7 @@ use probe_rs::rtt::find_rtt_control_block_in_raw_file;
 use probe_rs_rpc::core_ops::WireCoreStatus;
 use probe_rs_rpc::flash::BootInfo;
 use probe_rs_rpc::format::{FormatKind, FormatOptions};
-use probe_rs_rpc::rtt_client::ScanRegion;
+use probe_rs_rpc::rtt_client::{RttDiscovery, ScanRegion};
 use probe_rs_rpc::rtt_config::RttChannelConfig;
 use probe_rs_rpc::{Key, RttClient};
 use probe_rs_rpc_client::{RpcClient, SessionInterface};
@@ -80,6 +80,10 @@ struct CliOptions {
     /// take precedence over environment variables.
     #[arg(long, global = true, env = "PROBE_RS_CONFIG_PRESET")]
     preset: Option<String>,
+
+    /// Load a plugin from a shared library, or all shared libraries in a directory.
+    #[arg(long = "plugin", global = true, value_name = "PATH")]
+    plugins: Vec<PathBuf>,
 }
 
 pub async fn main(args: Vec<OsString>, config: Config, offset: UtcOffset) {
@@ -152,6 +156,8 @@ async fn main_try(args: Vec<OsString>, config: Config, offset: UtcOffset) -> Res
 
     let _log_guard = setup_logging(None, embed_config.general.log_level);
 
+    crate::load_plugins(&config, &opt.plugins)?;
+
     #[cfg(feature = "remote")]
     let connection_params = embed_config
         .remote
@@ -261,6 +267,7 @@ async fn run_embed(
         connect_under_reset: config.general.connect_under_reset,
         dry_run: false,
         allow_erase_all: config.flashing.enabled || config.gdb.enabled,
+        allow_flash_breakpoints: false,
         attach_timeout: None,
     };
 
@@ -302,18 +309,17 @@ async fn run_embed(
         None
     };
 
-    let scan = if let Some(ref elf) = elf {
-        if let Ok(Some(addr)) = find_rtt_control_block_in_raw_file(elf) {
-            ScanRegion::Exact(addr)
-        } else {
-            // Do not scan the memory for the control block.
-            ScanRegion::Ranges(vec![])
+    let discovery = if let Some(ref elf) = elf {
+        // Do not scan the memory for the control block.
+        RttDiscovery {
+            symbol: find_rtt_control_block_in_raw_file(elf).ok().flatten(),
+            ..RttDiscovery::default()
         }
     } else {
-        ScanRegion::Ram
+        RttDiscovery::from_scan_region(ScanRegion::Ram)
     };
 
-    if config.rtt.enabled && matches!(scan, ScanRegion::Ranges(ref ranges) if ranges.is_empty()) {
+    if config.rtt.enabled && discovery.symbol.is_none() && discovery.scan.is_none() {
         return Err(anyhow!(
             "RTT is enabled, but no RTT control block was found in the ELF file"
         ));
@@ -322,7 +328,7 @@ async fn run_embed(
     let rtt_config = create_rtt_config(&config);
     let rtt_client = session
         .create_rtt_client(
-            scan,
+            discovery,
             rtt_config.channels.clone(),
             rtt_config.default_config.clone(),
         )
@@ -339,11 +345,13 @@ async fn run_embed(
             restore_unwritten: config.flashing.restore_unwritten_bytes,
             flash_layout_output_path: config.flashing.flash_layout_output_path.clone(),
             preverify: config.flashing.preverify,
+            preverify_checksum: config.flashing.preverify_checksum,
             verify: config.flashing.verify,
             chip_erase: config.flashing.do_chip_erase,
             read_flasher_rtt: config.flashing.read_flasher_rtt,
             prefer_flash_algorithm: Vec::new(),
             ram_chunk_size: None,
+            inject_options: Default::default(),
         };
 
         boot_info = cli::flash(
@@ -384,7 +392,10 @@ async fn run_embed(
             gdb_connection_string,
         ));
 
-        let context = GdbSessionContext::from_session(&session, &registry).await?;
+        let mut context = GdbSessionContext::from_session(&session, &registry).await?;
+        if elf.is_some() {
+            context.elf = Some(path.clone());
+        }
         let instances =
             GdbInstanceConfiguration::from_context(&context, Some(gdb_connection_string));
         let session_gdb = session.clone();
@@ -492,7 +503,14 @@ async fn run_rttui_app(
         tokio::time::sleep(Duration::from_millis(10)).await;
     };
 
-    tracing::info!("RTT initialized.");
+    match channels.location {
+        Some(location) => tracing::info!(
+            "RTT initialized at {:#010x}, found by {}.",
+            location.address,
+            location.strategy
+        ),
+        None => tracing::info!("RTT initialized."),
+    }
 
     // Check if the terminal supports x
 
@@ -564,6 +582,7 @@ fn create_rtt_config(config: &config::Config) -> RttConfig {
             data_format: channel_config
                 .format
                 .unwrap_or(default_channel_config.data_format),
+            decoder: channel_config.decoder.clone(),
             show_timestamps: channel_config
                 .show_timestamps
                 .unwrap_or(default_channel_config.show_timestamps),
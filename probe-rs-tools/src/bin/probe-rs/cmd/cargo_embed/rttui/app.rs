@@ -144,31 +144,47 @@ impl App {
                     .unwrap_or(default_channel_config.data_format)
             };
 
-            let data_format = match channel_format {
-                DataFormat::String => RttDecoder::String {
-                    timestamp_offset: Some(timestamp_offset),
-                    last_line_done: false,
-                    show_timestamps: channel_config
-                        .show_timestamps
-                        .unwrap_or(default_channel_config.show_timestamps),
-                },
-                DataFormat::BinaryLE => RttDecoder::BinaryLE,
-                DataFormat::Defmt if defmt_data.is_none() => {
-                    tracing::warn!("Defmt data not found in ELF file");
-                    continue;
+            let plugin_decoder = match channel_config.decoder.as_deref() {
+                Some(name) => {
+                    let decoder = RttDecoder::plugin(name, &up.name);
+                    if decoder.is_none() {
+                        tracing::warn!("RTT decoder {name} is not registered by any plugin");
+                    }
+                    decoder
                 }
-                DataFormat::Defmt => RttDecoder::Defmt {
-                    processor: DefmtProcessor::new(
-                        defmt_data.clone().unwrap(),
-                        channel_config
+                None if channel_config.format.is_none() => RttDecoder::plugin_for_channel(&up.name),
+                None => None,
+            };
+
+            let data_format = if let Some(decoder) = plugin_decoder {
+                decoder
+            } else {
+                match channel_format {
+                    DataFormat::String => RttDecoder::String {
+                        timestamp_offset: Some(timestamp_offset),
+                        last_line_done: false,
+                        show_timestamps: channel_config
                             .show_timestamps
                             .unwrap_or(default_channel_config.show_timestamps),
-                        channel_config
-                            .show_location
-                            .unwrap_or(default_channel_config.show_location),
-                        channel_config.log_format.as_deref(),
-                    ),
-                },
+                    },
+                    DataFormat::BinaryLE => RttDecoder::BinaryLE,
+                    DataFormat::Defmt if defmt_data.is_none() => {
+                        tracing::warn!("Defmt data not found in ELF file");
+                        continue;
+                    }
+                    DataFormat::Defmt => RttDecoder::Defmt {
+                        processor: DefmtProcessor::new(
+                            defmt_data.clone().unwrap(),
+                            channel_config
+                                .show_timestamps
+                                .unwrap_or(default_channel_config.show_timestamps),
+                            channel_config
+                                .show_location
+                                .unwrap_or(default_channel_config.show_location),
+                            channel_config.log_format.as_deref(),
+                        ),
+                    },
+                }
             };
 
             up_channels.push(Rc::new(RefCell::new(UpChannel::new(
@@ -90,6 +90,10 @@ struct CliOptions {
     /// take precedence over environment variables.
     #[arg(long, global = true, env = "PROBE_RS_CONFIG_PRESET")]
     preset: Option<String>,
+
+    /// Load a plugin from a shared library, or all shared libraries in a directory.
+    #[arg(long = "plugin", global = true, value_name = "PATH")]
+    plugins: Vec<PathBuf>,
 }
 
 /// Creates a registry that holds the builtin targets and the targets of the
@@ -113,6 +117,8 @@ pub async fn main(args: Vec<OsString>, config: Config) -> anyhow::Result<()> {
     // Initialize the logger with the loglevel given on the commandline.
     let _log_guard = setup_logging(None, opt.log);
 
+    crate::load_plugins(&config, &opt.plugins)?;
+
     #[cfg(feature = "remote")]
     let connection_params = opt
         .host
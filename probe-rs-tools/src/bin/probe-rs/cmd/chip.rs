@@ -81,6 +81,7 @@ pub async fn print_chip_info(
             MemoryRegion::Ram(_) => "RAM",
             MemoryRegion::Generic(_) => "Generic",
             MemoryRegion::Nvm(_) => "NVM",
+            MemoryRegion::Config(_) => "Config",
         };
         writeln!(output, "{kind}: {range:#010x?} ({size})")?
     }
@@ -0,0 +1,268 @@
+use std::io::Write as _;
+
+use anyhow::Context as _;
+use probe_rs::Session;
+use probe_rs::config::{ConfigField, ConfigRegion, Registry, TargetSelector};
+use probe_rs::flashing::{self, ConfigFieldChange, ConfigRegionData, FlashProgress};
+use probe_rs::probe::list::Lister;
+
+use crate::util::common_options::ProbeOptions;
+use crate::util::parse_u64;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    #[clap(subcommand)]
+    subcommand: Subcommand,
+}
+
+#[derive(clap::Subcommand)]
+enum Subcommand {
+    /// List the configuration regions of the target and their fields
+    List,
+    /// Read the fields of a configuration region
+    Read {
+        /// The name of the region. Can be omitted if the target has a single configuration region.
+        #[clap(long)]
+        region: Option<String>,
+    },
+    /// Show which fields would change, without programming anything
+    Diff {
+        /// The name of the region. Can be omitted if the target has a single configuration region.
+        #[clap(long)]
+        region: Option<String>,
+
+        /// The new field values, as `FIELD=VALUE`. The value is a number or the name of a value.
+        #[clap(required = true, value_parser = parse_assignment)]
+        values: Vec<(String, String)>,
+    },
+    /// Program fields of a configuration region
+    Write {
+        /// The name of the region. Can be omitted if the target has a single configuration region.
+        #[clap(long)]
+        region: Option<String>,
+
+        /// The new field values, as `FIELD=VALUE`. The value is a number or the name of a value.
+        #[clap(required = true, value_parser = parse_assignment)]
+        values: Vec<(String, String)>,
+
+        /// Program changes that can't be undone, like OTP memory, fuses or permanent lock bits,
+        /// without asking for confirmation.
+        #[clap(long)]
+        allow_irreversible: bool,
+    },
+}
+
+impl Cmd {
+    pub fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        let non_interactive = self.common.non_interactive;
+
+        if let Subcommand::List = self.subcommand {
+            let target = match self.common.load(registry)?.get_target_selector()? {
+                TargetSelector::Specified(target) => target,
+                _ => anyhow::bail!("Listing configuration regions requires `--chip`."),
+            };
+            let regions = config_regions(&target.memory_map);
+            if regions.is_empty() {
+                println!("{} has no configuration regions.", target.name);
+            }
+            for region in regions {
+                print_region(region);
+            }
+            return Ok(());
+        }
+
+        let (mut session, _probe_options) = self.common.simple_attach(registry, lister)?;
+
+        match self.subcommand {
+            Subcommand::List => unreachable!(),
+            Subcommand::Read { region } => {
+                let region = select_region(&session, region.as_deref())?;
+                let data = ConfigRegionData::read(&mut session, &region)?;
+
+                println!("{}:", region_name(&region));
+                for (field, value) in data.fields() {
+                    println!("  {:<24} {}", field.name, format_value(field, value));
+                }
+            }
+            Subcommand::Diff { region, values } => {
+                let region = select_region(&session, region.as_deref())?;
+                let (_, changes) = apply_values(&mut session, &region, &values)?;
+                print_changes(&region, &changes);
+            }
+            Subcommand::Write {
+                region,
+                values,
+                allow_irreversible,
+            } => {
+                let region = select_region(&session, region.as_deref())?;
+                let (new, changes) = apply_values(&mut session, &region, &values)?;
+                print_changes(&region, &changes);
+                if changes.is_empty() {
+                    return Ok(());
+                }
+
+                let irreversible = changes.iter().any(|change| change.irreversible);
+                if irreversible && !allow_irreversible {
+                    anyhow::ensure!(
+                        !non_interactive,
+                        "Refusing to program irreversible changes without `--allow-irreversible`."
+                    );
+                    confirm_irreversible()?;
+                }
+
+                flashing::program_config_region(&mut session, &new, true, FlashProgress::empty())?;
+                println!("Programmed {} field(s).", changes.len());
+                println!("The device may need a reset to apply the new configuration.");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_assignment(input: &str) -> Result<(String, String), String> {
+    let (field, value) = input
+        .split_once('=')
+        .ok_or_else(|| format!("expected `FIELD=VALUE`, got `{input}`"))?;
+    Ok((field.trim().to_string(), value.trim().to_string()))
+}
+
+fn config_regions(memory_map: &[probe_rs::config::MemoryRegion]) -> Vec<&ConfigRegion> {
+    memory_map
+        .iter()
+        .filter_map(|region| region.as_config_region())
+        .collect()
+}
+
+fn region_name(region: &ConfigRegion) -> String {
+    match &region.name {
+        Some(name) => format!("{name} ({:?})", region.kind),
+        None => format!("{:#010x} ({:?})", region.range.start, region.kind),
+    }
+}
+
+fn select_region(session: &Session, name: Option<&str>) -> anyhow::Result<ConfigRegion> {
+    let regions = config_regions(&session.target().memory_map);
+
+    let region = match name {
+        Some(name) => regions.into_iter().find(|region| {
+            region
+                .name
+                .as_deref()
+                .is_some_and(|region_name| region_name.eq_ignore_ascii_case(name))
+        }),
+        None if regions.len() > 1 => {
+            anyhow::bail!(
+                "The target has several configuration regions, select one with `--region`."
+            )
+        }
+        None => regions.into_iter().next(),
+    };
+
+    region.cloned().with_context(|| match name {
+        Some(name) => format!("The target has no configuration region named `{name}`."),
+        None => "The target has no configuration regions.".to_string(),
+    })
+}
+
+fn apply_values(
+    session: &mut Session,
+    region: &ConfigRegion,
+    values: &[(String, String)],
+) -> anyhow::Result<(ConfigRegionData, Vec<ConfigFieldChange>)> {
+    let current = ConfigRegionData::read(session, region)?;
+    let mut new = current.clone();
+
+    for (name, value) in values {
+        let field = region
+            .field(name)
+            .with_context(|| format!("The region has no field named `{name}`."))?;
+        let value = match field.value_by_name(value) {
+            Some(named) => named.value,
+            None => parse_u64(value)
+                .with_context(|| format!("`{value}` is not a value of the field `{name}`."))?,
+        };
+        new.set(name, value)?;
+    }
+
+    let changes = current.diff(&new);
+    Ok((new, changes))
+}
+
+fn format_value(field: &ConfigField, value: u64) -> String {
+    match field.value(value) {
+        Some(named) => format!("{value:#x} ({})", named.name),
+        None => format!("{value:#x}"),
+    }
+}
+
+fn print_region(region: &ConfigRegion) {
+    println!(
+        "{}: {:#010x}..{:#010x}",
+        region_name(region),
+        region.range.start,
+        region.range.end
+    );
+    for field in &region.fields {
+        let irreversible = if field.irreversible {
+            " [irreversible]"
+        } else {
+            ""
+        };
+        println!(
+            "  {:<24} offset {:#x}, bits {}..{}{irreversible}",
+            field.name,
+            field.offset,
+            field.bit_offset,
+            field.bit_offset + field.bit_width,
+        );
+        if let Some(description) = &field.description {
+            println!("    {description}");
+        }
+        for value in &field.values {
+            let irreversible = if value.irreversible {
+                " [irreversible]"
+            } else {
+                ""
+            };
+            println!("    {:#x} = {}{irreversible}", value.value, value.name);
+        }
+    }
+}
+
+fn print_changes(region: &ConfigRegion, changes: &[ConfigFieldChange]) {
+    if changes.is_empty() {
+        println!("{} is up to date.", region_name(region));
+        return;
+    }
+
+    println!("{}:", region_name(region));
+    for change in changes {
+        let field = region.field(&change.name).expect("changed fields exist");
+        let irreversible = if change.irreversible {
+            "  [irreversible]"
+        } else {
+            ""
+        };
+        println!(
+            "  {:<24} {} -> {}{irreversible}",
+            change.name,
+            format_value(field, change.old),
+            format_value(field, change.new),
+        );
+    }
+}
+
+fn confirm_irreversible() -> anyhow::Result<()> {
+    eprint!("Some of these changes can't be undone. Type `yes` to program them: ");
+    std::io::stderr().flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+
+    anyhow::ensure!(input.trim() == "yes", "Aborted, nothing was programmed.");
+    Ok(())
+}
@@ -22,12 +22,13 @@ use crate::rpc::{
             from_wire_core_information, from_wire_core_status, from_wire_core_type,
             from_wire_instruction_set, from_wire_register_id, from_wire_register_value,
             to_wire_register_id, to_wire_register_value, to_wire_vector_catch_condition,
+            to_wire_watchpoint,
         },
     },
 };
 use probe_rs::{
     Architecture, CoreInformation, CoreRegisters, CoreStatus, CoreType, Error, RegisterId,
-    RegisterValue, VectorCatchCondition,
+    RegisterValue, VectorCatchCondition, Watchpoint,
 };
 use probe_rs_debug::{
     ColumnType, DebugRegisters, ObjectRef, SourceLocation as DebugSourceLocation, StackFrame,
@@ -120,6 +121,8 @@ pub struct RpcBackend {
 pub(crate) struct CoreMetadata {
     pub(crate) architecture: Architecture,
     pub(crate) registers: &'static CoreRegisters,
+    /// The SVD file last loaded for this core, if any.
+    pub(crate) svd_file: Option<PathBuf>,
 }
 
 /// Per-core information the [`RpcBackend`] caller has to gather at attach
@@ -208,6 +211,7 @@ impl RpcBackend {
                 CoreMetadata {
                     architecture: info.architecture,
                     registers,
+                    svd_file: None,
                 }
             })
             .collect();
@@ -329,29 +333,91 @@ impl RpcBackend {
         Ok(from_wire_core_status(wire))
     }
 
-    /// Set a batch of hardware breakpoints, reporting per-address failures in
-    /// place rather than failing the whole batch.
-    pub(crate) async fn set_hw_breakpoints(
+    /// Set a batch of breakpoints, reporting per-address failures in place
+    /// rather than failing the whole batch.
+    ///
+    /// Hardware breakpoints are preferred. Addresses that do not get one, e.g.
+    /// because all breakpoint units are in use, get a software breakpoint.
+    pub(crate) async fn set_breakpoints(
         &mut self,
         core_index: usize,
         addresses: Vec<u64>,
     ) -> Result<Vec<Result<(), RpcError>>, Error> {
         let client = self.core(core_index);
-        client.set_hw_breakpoints(addresses).await.map_err(rpc_err)
+        let mut results = client
+            .set_hw_breakpoints(addresses.clone())
+            .await
+            .map_err(rpc_err)?;
+
+        let fallback = addresses
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| result.is_err())
+            .map(|(address, _)| *address)
+            .collect::<Vec<_>>();
+        if fallback.is_empty() {
+            return Ok(results);
+        }
+
+        let sw_results = client.set_sw_breakpoints(fallback).await.map_err(rpc_err)?;
+        for (result, sw_result) in results
+            .iter_mut()
+            .filter(|result| result.is_err())
+            .zip(sw_results)
+        {
+            if let Err(hw_error) = result {
+                let sw_result = sw_result.map_err(|sw_error| {
+                    RpcError::from(format!(
+                        "{hw_error}, and no software breakpoint could be set: {sw_error}"
+                    ))
+                });
+                *result = sw_result;
+            }
+        }
+
+        Ok(results)
     }
 
-    pub(crate) async fn clear_hw_breakpoints(
+    /// Clear a batch of breakpoints set by [`Self::set_breakpoints`].
+    pub(crate) async fn clear_breakpoints(
         &mut self,
         core_index: usize,
         addresses: Vec<u64>,
     ) -> Result<(), Error> {
         let client = self.core(core_index);
         client
-            .clear_hw_breakpoints(addresses)
+            .clear_hw_breakpoints(addresses.clone())
+            .await
+            .map_err(rpc_err)?;
+        client
+            .clear_sw_breakpoints(addresses)
+            .await
+            .map_err(rpc_err)
+    }
+
+    /// Set a batch of hardware watchpoints, reporting per-watchpoint failures
+    /// in place rather than failing the whole batch.
+    pub(crate) async fn set_watchpoints(
+        &mut self,
+        core_index: usize,
+        watchpoints: Vec<Watchpoint>,
+    ) -> Result<Vec<Result<(), RpcError>>, Error> {
+        let client = self.core(core_index);
+        client
+            .set_watchpoints(watchpoints.into_iter().map(to_wire_watchpoint).collect())
             .await
             .map_err(rpc_err)
     }
 
+    pub(crate) async fn clear_watchpoints(
+        &mut self,
+        core_index: usize,
+        addresses: Vec<u64>,
+    ) -> Result<(), Error> {
+        let client = self.core(core_index);
+        client.clear_watchpoints(addresses).await.map_err(rpc_err)
+    }
+
     pub(crate) async fn halt(
         &mut self,
         core_index: usize,
@@ -684,13 +750,31 @@ impl RpcBackend {
         core_index: usize,
         path: Option<PathBuf>,
     ) -> Result<(), Error> {
+        self.core_metadata[core_index].svd_file = None;
         self.session_interface()
-            .load_svd(core_index as u32, path)
+            .load_svd(core_index as u32, path.clone())
             .await
             .map_err(rpc_err)?;
+        self.core_metadata[core_index].svd_file = path;
         Ok(())
     }
 
+    /// Dump every core of the session, including the register blocks of the
+    /// named `peripherals` from the SVD file loaded for `core_index`.
+    pub(crate) async fn dump_cores(
+        &mut self,
+        core_index: usize,
+        peripherals: Vec<String>,
+    ) -> Result<probe_rs::SessionDump, Error> {
+        let svd_file = self.core_metadata[core_index].svd_file.clone();
+        let data = self
+            .session_interface()
+            .dump_cores(svd_file, peripherals)
+            .await
+            .map_err(rpc_err)?;
+        probe_rs::SessionDump::load_raw(&data).map_err(rpc_err)
+    }
+
     pub(crate) async fn program_counter(&mut self, core_index: usize) -> Option<u64> {
         match self.program_counter_id(core_index).await {
             Ok(id) => self
@@ -759,6 +843,25 @@ impl RpcBackend {
             .collect())
     }
 
+    /// Returns the memory reference and size of the child `name` of
+    /// `variables_reference`, if it is stored in memory.
+    pub(crate) async fn variable_location(
+        &mut self,
+        core_index: usize,
+        variables_reference: u32,
+        name: &str,
+    ) -> Result<Option<(String, Option<u64>)>, Error> {
+        let session = self.session_interface();
+        let wire = session
+            .variables(core_index as u32, variables_reference, None)
+            .await
+            .map_err(rpc_err)?;
+        Ok(wire
+            .into_iter()
+            .find(|variable| variable.name == name)
+            .and_then(|variable| Some((variable.memory_reference?, variable.byte_size))))
+    }
+
     fn evaluate_response_body(
         wire: probe_rs_rpc::debug_vars::WireEvaluateResponse,
     ) -> EvaluateResponseBody {
@@ -841,6 +944,7 @@ impl RpcBackend {
                 keep_unwritten_bytes: config.restore_unwritten_bytes,
                 do_chip_erase: config.full_chip_erase,
                 skip_erase: false,
+                preverify_checksum: false,
                 verify: config.verify_after_flashing,
                 disable_double_buffering: false,
                 preferred_algos: Vec::new(),
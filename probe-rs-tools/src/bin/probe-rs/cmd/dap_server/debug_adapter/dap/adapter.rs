@@ -23,7 +23,7 @@ use dap_types::*;
 use parse_int::parse;
 use probe_rs::{
     Architecture, CoreInformation, CoreRegister, CoreStatus, HaltReason, RegisterDataType,
-    RegisterRole, RegisterValue, UnwindRule,
+    RegisterRole, RegisterValue, UnwindRule, Watchpoint, WatchpointKind,
 };
 use probe_rs_debug::{
     ColumnType, ObjectRef, SourceLocation, SteppingMode, VerifiedBreakpoint,
@@ -40,6 +40,9 @@ use std::{fmt::Display, str, time::Duration};
 /// Progress ID used for progress reporting when the debug adapter protocol is used.
 pub(crate) type ProgressId = i64;
 
+/// The number of bytes a data breakpoint watches if the size of the data is unknown.
+const DEFAULT_DATA_BREAKPOINT_SIZE: u64 = 4;
+
 #[derive(Debug, PartialEq, Eq)]
 enum EvaluateDispatch {
     Server,
@@ -863,7 +866,7 @@ impl DebugAdapter {
         // One round trip to clear the old set, one to set the new set.
         if let Err(error) = session_data
             .backend
-            .clear_hw_breakpoints(core_index, clear_addrs)
+            .clear_breakpoints(core_index, clear_addrs)
             .await
         {
             return self.send_response::<()>(
@@ -879,7 +882,7 @@ impl DebugAdapter {
             .collect();
         let set_results = session_data
             .backend
-            .set_hw_breakpoints(core_index, set_addrs)
+            .set_breakpoints(core_index, set_addrs)
             .await
             .map_err(|e| DebuggerError::Other(anyhow!("Failed to set breakpoints: {e}")))?;
 
@@ -1009,7 +1012,7 @@ impl DebugAdapter {
 
         if let Err(error) = session_data
             .backend
-            .clear_hw_breakpoints(core_index, clear_addrs)
+            .clear_breakpoints(core_index, clear_addrs)
             .await
         {
             tracing::warn!("Failed to clear instruction breakpoints. {}", error);
@@ -1017,7 +1020,7 @@ impl DebugAdapter {
         let set_addrs: Vec<u64> = parsed.iter().copied().flatten().collect();
         let set_results = session_data
             .backend
-            .set_hw_breakpoints(core_index, set_addrs.clone())
+            .set_breakpoints(core_index, set_addrs.clone())
             .await
             .map_err(|e| {
                 DebuggerError::Other(anyhow!("Failed to set instruction breakpoints: {e}"))
@@ -1100,6 +1103,197 @@ impl DebugAdapter {
         )
     }
 
+    /// Describes the memory a data breakpoint can watch: a child of a
+    /// variables container, or an address from the memory view.
+    pub(crate) async fn data_breakpoint_info(
+        &mut self,
+        session_data: &mut SessionData,
+        core_index: usize,
+        request: &Request,
+    ) -> Result<()> {
+        let arguments: DataBreakpointInfoArguments = get_arguments(self, request)?;
+
+        let data_id = match (arguments.as_address, arguments.variables_reference) {
+            (Some(true), _) => MemoryAddress::try_from(arguments.name.as_str())
+                .map(|MemoryAddress(address)| {
+                    Some(DataBreakpointId {
+                        address,
+                        length: arguments
+                            .bytes
+                            .unwrap_or(DEFAULT_DATA_BREAKPOINT_SIZE as i64)
+                            as u64,
+                    })
+                })
+                .map_err(|error| error.to_string()),
+            (_, Some(variables_reference)) => session_data
+                .backend
+                .variable_location(core_index, variables_reference as u32, &arguments.name)
+                .await
+                .map(|location| {
+                    let (memory_reference, byte_size) = location?;
+                    let MemoryAddress(address) =
+                        MemoryAddress::try_from(memory_reference.as_str()).ok()?;
+                    Some(DataBreakpointId {
+                        address,
+                        length: arguments
+                            .bytes
+                            .map(|bytes| bytes as u64)
+                            .or(byte_size)
+                            .unwrap_or(DEFAULT_DATA_BREAKPOINT_SIZE),
+                    })
+                })
+                .map_err(|error| error.to_string()),
+            _ => Ok(None),
+        };
+
+        let body = match data_id {
+            Ok(Some(data_id)) => DataBreakpointInfoResponseBody {
+                access_types: Some(vec![
+                    DataBreakpointAccessType::Write,
+                    DataBreakpointAccessType::Read,
+                    DataBreakpointAccessType::ReadWrite,
+                ]),
+                can_persist: Some(false),
+                description: format!(
+                    "{} ({} bytes @{:#010x})",
+                    arguments.name, data_id.length, data_id.address
+                ),
+                data_id: Some(data_id.to_string()),
+            },
+            Ok(None) => DataBreakpointInfoResponseBody {
+                access_types: None,
+                can_persist: None,
+                data_id: None,
+                description: format!("{} is not stored in memory", arguments.name),
+            },
+            Err(error) => DataBreakpointInfoResponseBody {
+                access_types: None,
+                can_persist: None,
+                data_id: None,
+                description: error,
+            },
+        };
+
+        self.send_response(request, Ok(Some(body)))
+    }
+
+    /// Replaces all data breakpoints of the core with hardware watchpoints.
+    pub(crate) async fn set_data_breakpoints(
+        &mut self,
+        session_data: &mut SessionData,
+        core_index: usize,
+        request: &Request,
+    ) -> Result<()> {
+        let arguments: SetDataBreakpointsArguments = get_arguments(self, request)?;
+
+        let clear_addrs = match session_data.core_data_mut(core_index) {
+            Err(error) => return self.send_response::<()>(request, Err(&error)),
+            Ok(core_data) => core_data
+                .data_breakpoints
+                .drain(..)
+                .map(|watchpoint| watchpoint.address)
+                .collect::<Vec<_>>(),
+        };
+        if let Err(error) = session_data
+            .backend
+            .clear_watchpoints(core_index, clear_addrs)
+            .await
+        {
+            tracing::warn!("Failed to clear data breakpoints. {}", error);
+        }
+
+        let parsed: Vec<Result<Watchpoint, String>> = arguments
+            .breakpoints
+            .iter()
+            .map(|breakpoint| {
+                if breakpoint.condition.is_some() || breakpoint.hit_condition.is_some() {
+                    return Err("Conditional data breakpoints are not supported".to_string());
+                }
+                let data_id = DataBreakpointId::try_from(breakpoint.data_id.as_str())
+                    .map_err(|error| error.to_string())?;
+                Ok(Watchpoint {
+                    address: data_id.address,
+                    length: data_id.length,
+                    kind: match breakpoint.access_type {
+                        Some(DataBreakpointAccessType::Read) => WatchpointKind::Read,
+                        Some(DataBreakpointAccessType::ReadWrite) => WatchpointKind::Access,
+                        Some(DataBreakpointAccessType::Write) | None => WatchpointKind::Write,
+                    },
+                })
+            })
+            .collect();
+
+        let watchpoints: Vec<Watchpoint> = parsed.iter().flatten().copied().collect();
+        let mut set_results = session_data
+            .backend
+            .set_watchpoints(core_index, watchpoints)
+            .await
+            .map_err(|e| DebuggerError::Other(anyhow!("Failed to set data breakpoints: {e}")))?
+            .into_iter();
+
+        let mut breakpoints: Vec<Breakpoint> = Vec::with_capacity(parsed.len());
+        let mut to_cache: Vec<Watchpoint> = Vec::new();
+        for watchpoint in parsed {
+            let result = watchpoint.and_then(|watchpoint| match set_results.next() {
+                Some(Ok(())) => Ok(watchpoint),
+                Some(Err(error)) => Err(error.to_string()),
+                None => Err("The server returned no result".to_string()),
+            });
+            breakpoints.push(match result {
+                Ok(watchpoint) => {
+                    to_cache.push(watchpoint);
+                    Breakpoint {
+                        column: None,
+                        end_column: None,
+                        end_line: None,
+                        id: Some(watchpoint.address as i64),
+                        instruction_reference: None,
+                        line: None,
+                        message: Some(format!(
+                            "Data breakpoint set on {} bytes @{:#010x}",
+                            watchpoint.length, watchpoint.address
+                        )),
+                        offset: None,
+                        source: None,
+                        verified: true,
+                        reason: None,
+                    }
+                }
+                Err(error) => Breakpoint {
+                    column: None,
+                    end_column: None,
+                    end_line: None,
+                    id: None,
+                    instruction_reference: None,
+                    line: None,
+                    message: Some(format!("Failed to set data breakpoint: {error}")),
+                    offset: None,
+                    source: None,
+                    verified: false,
+                    reason: Some("failed".to_string()),
+                },
+            });
+        }
+
+        if let Ok(core_data) = session_data.core_data_mut(core_index) {
+            core_data.data_breakpoints = to_cache;
+        }
+
+        for breakpoint_response in &breakpoints {
+            if !breakpoint_response.verified
+                && let Some(message) = &breakpoint_response.message
+            {
+                self.log_to_console(format!("Warning: {message}"));
+                self.show_message(MessageSeverity::Warning, message.clone());
+            }
+        }
+
+        self.send_response(
+            request,
+            Ok(Some(SetDataBreakpointsResponseBody { breakpoints })),
+        )
+    }
+
     pub(crate) async fn threads(
         &mut self,
         session_data: &mut SessionData,
@@ -1752,7 +1946,7 @@ impl DebugAdapter {
     }
 
     /// Reset and halt the core (REPL `reset` / DAP `restart`), re-applying
-    /// hardware breakpoints on RISC-V / Xtensa.
+    /// hardware breakpoints and data breakpoints on RISC-V / Xtensa.
     pub(crate) async fn reset_and_halt_core_async(
         &mut self,
         backend: &mut RpcBackend,
@@ -1777,7 +1971,7 @@ impl DebugAdapter {
             let addrs: Vec<u64> = core_data.breakpoints.iter().map(|bp| bp.address).collect();
             if !addrs.is_empty() {
                 backend
-                    .set_hw_breakpoints(core_index, addrs)
+                    .set_breakpoints(core_index, addrs)
                     .await
                     .map_err(|e| {
                         DebuggerError::Other(anyhow!(
@@ -1785,6 +1979,16 @@ impl DebugAdapter {
                         ))
                     })?;
             }
+            if !core_data.data_breakpoints.is_empty() {
+                backend
+                    .set_watchpoints(core_index, core_data.data_breakpoints.clone())
+                    .await
+                    .map_err(|e| {
+                        DebuggerError::Other(anyhow!(
+                            "Failed to re-apply data breakpoints after reset: {e}"
+                        ))
+                    })?;
+            }
         }
 
         core_data.last_known_status = CoreStatus::Unknown;
@@ -30,7 +30,14 @@ impl DapStatus for CoreStatus {
                     "exception",
                     "Core halted due to an exception, e.g. interrupt handler".to_string(),
                 ),
-                HaltReason::Watchpoint => (
+                HaltReason::Watchpoint(Some(watchpoint)) => (
+                    "data breakpoint",
+                    format!(
+                        "Core halted due to a data breakpoint on {} byte(s) @{:#010x}",
+                        watchpoint.length, watchpoint.address
+                    ),
+                ),
+                HaltReason::Watchpoint(None) => (
                     "data breakpoint",
                     "Core halted due to a watchpoint or data breakpoint".to_string(),
                 ),
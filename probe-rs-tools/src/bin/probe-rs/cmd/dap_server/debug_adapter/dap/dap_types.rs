@@ -28,6 +28,35 @@ impl TryFrom<&str> for MemoryAddress {
     }
 }
 
+/// The `dataId` of a data breakpoint: the address and length of the watched memory,
+/// formatted as `<address>/<length>`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DataBreakpointId {
+    pub address: u64,
+    pub length: u64,
+}
+
+impl TryFrom<&str> for DataBreakpointId {
+    type Error = DebuggerError;
+
+    fn try_from(data_id: &str) -> Result<Self, Self::Error> {
+        let invalid =
+            || DebuggerError::UserMessage(format!("Invalid data breakpoint: {data_id:?}"));
+        let (address, length) = data_id.split_once('/').ok_or_else(invalid)?;
+
+        Ok(DataBreakpointId {
+            address: MemoryAddress::try_from(address)?.0,
+            length: parse(length).map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl Display for DataBreakpointId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#010x}/{}", self.address, self.length)
+    }
+}
+
 /// Arguments for custom [`RttWindowOpenedArguments`] request, so that VSCode can confirm once a specific RTT channel's window has opened.
 /// `probe-rs-debugger` will delay polling RTT channels until the data window has opened. This ensure no RTT data is lost on the client.
 #[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
@@ -228,7 +257,7 @@ impl Display for Message {
 #[cfg(test)]
 #[allow(clippy::unwrap_used, reason = "Test code")]
 mod test {
-    use crate::cmd::dap_server::debug_adapter::dap::dap_types::MemoryAddress;
+    use crate::cmd::dap_server::debug_adapter::dap::dap_types::{DataBreakpointId, MemoryAddress};
 
     #[test]
     fn memory_address() {
@@ -240,4 +269,16 @@ mod test {
         assert_eq!(MemoryAddress::try_from("0X40001000").unwrap().0, 0x40001000);
         assert_eq!(MemoryAddress::try_from("1073745920").unwrap().0, 0x40001000);
     }
+
+    #[test]
+    fn data_breakpoint_id() {
+        let id = DataBreakpointId {
+            address: 0x2000_0010,
+            length: 4,
+        };
+        assert_eq!(id.to_string(), "0x20000010/4");
+        assert_eq!(DataBreakpointId::try_from("0x20000010/4").unwrap(), id);
+        assert!(DataBreakpointId::try_from("0x20000010").is_err());
+        assert!(DataBreakpointId::try_from("0x20000010/four").is_err());
+    }
 }
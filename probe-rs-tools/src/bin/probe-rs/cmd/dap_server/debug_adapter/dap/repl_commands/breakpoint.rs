@@ -258,9 +258,7 @@ async fn clear_breakpoint<'a>(
         }
     };
 
-    backend
-        .clear_hw_breakpoints(core_index, vec![address])
-        .await?;
+    backend.clear_breakpoints(core_index, vec![address]).await?;
     let before = core_data.breakpoints.len();
     core_data.breakpoints.retain(|ab| ab.address != address);
     let removed = before != core_data.breakpoints.len();
@@ -1,6 +1,7 @@
 use std::{fmt::Write as _, ops::Range, path::Path, str::FromStr};
 
 use linkme::distributed_slice;
+use probe_rs::{CoreDump, DumpCompression};
 use probe_rs_debug::{ObjectRef, VariableName};
 
 use crate::cmd::dap_server::{
@@ -46,10 +47,11 @@ static EXAMINE: ReplCommand = ReplCommand {
 #[distributed_slice(REPL_COMMANDS)]
 static DUMP: ReplCommand = ReplCommand {
     command: "dump",
-    help_text: "Create a core dump at a target location. Specify memory ranges to dump, or leave blank to dump in-scope memory regions.",
+    help_text: "Create a core dump at a target location. Specify memory ranges to dump, or leave blank to dump in-scope memory regions. Paths ending in `.elf` are stored as an ELF core file that GDB and LLDB can open, paths ending in `.zst` or `.deflate` are compressed. With `--reference=<path>`, only the memory that differs from an earlier dump is stored.",
     requires_target_halted: true,
     sub_commands: &[],
     args: &[
+        ReplCommandArgs::Optional("--reference=<path of an earlier dump>"),
         ReplCommandArgs::Optional("memory start address"),
         ReplCommandArgs::Optional("memory size in bytes"),
         ReplCommandArgs::Optional("path (default: ./coredump)"),
@@ -57,6 +59,19 @@ static DUMP: ReplCommand = ReplCommand {
     handler: async_fn!(dump_core),
 };
 
+#[distributed_slice(REPL_COMMANDS)]
+static DUMP_ALL: ReplCommand = ReplCommand {
+    command: "dumpall",
+    help_text: "Create a dump of all cores, including every RAM region of the target. Peripheral register blocks can be included by name, using the SVD file of the current core. Paths ending in `.zst` or `.deflate` are compressed.",
+    requires_target_halted: false,
+    sub_commands: &[],
+    args: &[
+        ReplCommandArgs::Optional("path (default: ./sessiondump)"),
+        ReplCommandArgs::Optional("peripheral names"),
+    ],
+    handler: async_fn!(dump_all_cores),
+};
+
 async fn print_variables<'a>(
     backend: &'a mut RpcBackend,
     core_data: &'a mut CoreData,
@@ -209,6 +224,14 @@ async fn dump_core<'a>(
     let core_index = core_data.core_index;
     let mut args = command_arguments.split_whitespace().collect::<Vec<_>>();
 
+    let reference = match args.iter().position(|arg| arg.starts_with("--reference=")) {
+        Some(index) => {
+            let path = args.remove(index).trim_start_matches("--reference=");
+            Some(CoreDump::load(Path::new(path))?)
+        }
+        None => None,
+    };
+
     // If we get an odd number of arguments, treat all n * 2 args at the start as memory blocks
     // and the last argument as the path tho store the coredump at.
     let location = Path::new(
@@ -259,9 +282,53 @@ async fn dump_core<'a>(
         .dump_core(core_index, ranges)
         .await
         .map_err(DebuggerError::from)?;
-    dump.store(location)?;
+    if let Some(reference) = reference {
+        dump.store_delta(location, &reference, dump_compression(location))?;
+    } else if location
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("elf"))
+    {
+        dump.store_elf(location)?;
+    } else {
+        dump.store_compressed(location, dump_compression(location))?;
+    }
 
     Ok(EvalResponse::Message(format!(
         "Core dump {range_string} successfully stored at {location:?}.",
     )))
 }
+
+/// Selects the compression of a dump from the extension of its path.
+fn dump_compression(location: &Path) -> DumpCompression {
+    match location
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some("zst") => DumpCompression::Zstd,
+        Some("deflate") => DumpCompression::Deflate,
+        _ => DumpCompression::None,
+    }
+}
+
+async fn dump_all_cores<'a>(
+    backend: &'a mut RpcBackend,
+    core_data: &'a mut CoreData,
+    command_arguments: &'a str,
+    _evaluate_arguments: &'a EvaluateArguments,
+    _adapter: &'a mut DebugAdapter,
+) -> EvalResult {
+    let mut args = command_arguments.split_whitespace();
+    let location = Path::new(args.next().unwrap_or("./sessiondump"));
+    let peripherals = args.map(str::to_string).collect::<Vec<_>>();
+
+    let dump = backend
+        .dump_cores(core_data.core_index, peripherals)
+        .await
+        .map_err(DebuggerError::from)?;
+    dump.store_compressed(location, dump_compression(location))?;
+
+    Ok(EvalResponse::Message(format!(
+        "Dump of {} core(s) successfully stored at {location:?}.",
+        dump.cores.len()
+    )))
+}
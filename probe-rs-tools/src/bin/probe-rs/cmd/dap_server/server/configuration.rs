@@ -79,6 +79,10 @@ pub struct SessionConfig {
     #[serde(default)]
     pub(crate) allow_erase_all: bool,
 
+    /// Allow software breakpoints in flash, which reprogram the flash sector containing the breakpoint.
+    #[serde(default)]
+    pub(crate) allow_flash_breakpoints: bool,
+
     /// Flashing configuration
     #[serde(default)]
     pub(crate) flashing_config: FlashingConfig,
@@ -268,6 +272,7 @@ impl SessionConfig {
             cycle_power: false,
             dry_run: false,
             allow_erase_all: self.allow_erase_all,
+            allow_flash_breakpoints: self.allow_flash_breakpoints,
             attach_timeout: self
                 .attach_timeout
                 .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()),
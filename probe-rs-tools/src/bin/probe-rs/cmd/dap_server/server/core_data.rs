@@ -3,13 +3,13 @@ use std::any::Any;
 use super::session_data;
 use crate::cmd::dap_server::debug_adapter::dap::repl_commands::ReplCommand;
 use crate::rpc::{Key, RttClient};
-use probe_rs_rpc::rtt_client::ScanRegion as WireScanRegion;
+use probe_rs_rpc::rtt_client::RttDiscovery;
 
 /// `(channel number, channel name)` pairs returned while attaching to RTT.
 pub(crate) type ChannelNames = Vec<(u32, String)>;
 use crate::cmd::dap_server::server::debug_rtt;
 use crate::util::rtt::DefmtState;
-use probe_rs::CoreStatus;
+use probe_rs::{CoreStatus, Watchpoint};
 
 /// [CoreData] is used to cache data needed by the debugger, on a per-core basis.
 pub struct CoreData {
@@ -30,7 +30,9 @@ pub struct CoreData {
     /// complete server unwind succeeds.
     pub stack_frames: Vec<probe_rs_debug::stack_frame::StackFrame>,
     pub breakpoints: Vec<session_data::ActiveBreakpoint>,
-    pub rtt_scan_ranges: WireScanRegion,
+    /// The data breakpoints set with `setDataBreakpoints`, as hardware watchpoints.
+    pub data_breakpoints: Vec<Watchpoint>,
+    pub rtt_discovery: RttDiscovery,
     pub rtt_connection: Option<debug_rtt::RttConnection>,
     /// defmt data of the program binary, parsed on the first RTT attach.
     ///
@@ -85,7 +87,8 @@ fn stack_frame_display_cache_is_invalidated_before_replacement() {
         target_name: String::new(),
         stack_frames: vec![frame(1)],
         breakpoints: vec![],
-        rtt_scan_ranges: WireScanRegion::Ranges(vec![]),
+        data_breakpoints: vec![],
+        rtt_discovery: RttDiscovery::default(),
         rtt_connection: None,
         defmt_state: None,
         rtt_remote_handle: None,
@@ -39,6 +39,8 @@ fn dap_capabilities() -> Capabilities {
         supports_set_variable: Some(true),
         supports_disassemble_request: Some(true),
         supports_instruction_breakpoints: Some(true),
+        supports_data_breakpoints: Some(true),
+        supports_data_breakpoint_bytes: Some(true),
         supports_stepping_granularity: Some(true),
         supports_completions_request: Some(true),
         // ANSI output is emitted only when the client also opts in.
@@ -252,6 +254,8 @@ impl Debugger {
                 "configurationDone"
                     | "setBreakpoints"
                     | "setInstructionBreakpoints"
+                    | "dataBreakpointInfo"
+                    | "setDataBreakpoints"
                     | "clearBreakpoint"
                     | "stackTrace"
                     | "threads"
@@ -283,6 +287,16 @@ impl Debugger {
                     .set_instruction_breakpoints(session_data, core_index, request)
                     .await?;
             }
+            "dataBreakpointInfo" => {
+                debug_adapter
+                    .data_breakpoint_info(session_data, core_index, request)
+                    .await?;
+            }
+            "setDataBreakpoints" => {
+                debug_adapter
+                    .set_data_breakpoints(session_data, core_index, request)
+                    .await?;
+            }
             "readMemory" => {
                 debug_adapter
                     .read_memory(session_data, core_index, request)
@@ -1167,6 +1181,8 @@ mod test {
         assert_eq!(capabilities.supports_set_variable, Some(true));
         assert_eq!(capabilities.supports_disassemble_request, Some(true));
         assert_eq!(capabilities.supports_instruction_breakpoints, Some(true));
+        assert_eq!(capabilities.supports_data_breakpoints, Some(true));
+        assert_eq!(capabilities.supports_data_breakpoint_bytes, Some(true));
         assert_eq!(capabilities.supports_completions_request, Some(true));
 
         // Behavior capabilities implemented by existing request handlers.
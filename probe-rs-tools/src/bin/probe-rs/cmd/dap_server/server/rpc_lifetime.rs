@@ -145,6 +145,7 @@ mod tests {
             connect_under_reset: false,
             dry_run: false,
             allow_erase_all: false,
+            allow_flash_breakpoints: false,
             attach_timeout: None,
         };
 
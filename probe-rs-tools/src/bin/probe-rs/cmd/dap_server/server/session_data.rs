@@ -25,7 +25,7 @@ use probe_rs::{BreakpointCause, CoreStatus, HaltReason, rtt::find_rtt_control_bl
 use probe_rs_debug::SourceLocation;
 use probe_rs_rpc::breakpoints::SourceBreakpointLocation;
 use probe_rs_rpc::format::FormatKind;
-use probe_rs_rpc::rtt_client::ScanRegion as WireScanRegion;
+use probe_rs_rpc::rtt_client::RttDiscovery;
 use probe_rs_rpc_client::{ResolvedUpload, RpcClient};
 use std::{any::Any, env::set_current_dir, path::Path};
 use time::UtcOffset;
@@ -222,21 +222,20 @@ impl SessionData {
             };
 
             core_data.defmt_state = None;
-            core_data.rtt_scan_ranges = match core_configuration.program_binary.as_ref() {
+            // Do not scan the memory for the control block.
+            core_data.rtt_discovery = match core_configuration.program_binary.as_ref() {
                 Some(program_binary)
                     if matches!(image_format, FormatKind::Elf | FormatKind::Idf) =>
                 {
                     let elf = std::fs::read(program_binary)
                         .map_err(|error| anyhow!("Error attempting to attach to RTT: {error}"))?;
 
-                    if let Ok(Some(addr)) = find_rtt_control_block_in_raw_file(&elf) {
-                        WireScanRegion::Exact(addr)
-                    } else {
-                        // Do not scan the memory for the control block.
-                        WireScanRegion::Ranges(vec![])
+                    RttDiscovery {
+                        symbol: find_rtt_control_block_in_raw_file(&elf).ok().flatten(),
+                        ..RttDiscovery::default()
                     }
                 }
-                _ => WireScanRegion::Ranges(vec![]),
+                _ => RttDiscovery::default(),
             };
         }
 
@@ -257,13 +256,13 @@ impl SessionData {
             return Ok(handle);
         }
 
-        let wire_scan = self.core_data[cd_idx].rtt_scan_ranges.clone();
+        let discovery = self.core_data[cd_idx].rtt_discovery.clone();
 
         let data = self
             .backend
             .session_interface()
             .create_rtt_client(
-                wire_scan,
+                discovery,
                 rtt_config.channels.clone(),
                 rtt_config.default_config.clone(),
             )
@@ -370,7 +369,7 @@ impl SessionData {
             }
         }
         self.backend
-            .clear_hw_breakpoints(core_index, old_addrs)
+            .clear_breakpoints(core_index, old_addrs)
             .await
             .map_err(DebuggerError::ProbeRs)?;
         if let Ok(core_data) = self.core_data_mut(core_index) {
@@ -381,7 +380,7 @@ impl SessionData {
         let set_addrs: Vec<u64> = to_set.iter().map(|(a, _, _)| *a).collect();
         let set_results = self
             .backend
-            .set_hw_breakpoints(core_index, set_addrs)
+            .set_breakpoints(core_index, set_addrs)
             .await
             .map_err(DebuggerError::ProbeRs)?;
         if let Ok(core_data) = self.core_data_mut(core_index) {
@@ -533,35 +532,47 @@ impl SessionData {
             let show_location = channel_config.show_location;
             let log_format = channel_config.log_format.clone();
 
-            let channel_data_format = match channel_config.data_format {
-                DataFormat::String => RttDecoder::String {
-                    timestamp_offset: Some(timestamp_offset),
-                    last_line_done: false,
-                    show_timestamps,
-                },
-                DataFormat::BinaryLE => RttDecoder::BinaryLE,
-                DataFormat::Defmt => {
-                    let defmt_state = if let Some(data) = defmt_data.as_ref() {
-                        data
-                    } else if let Some(program_binary) = program_binary {
-                        let elf = std::fs::read(program_binary).map_err(|error| {
-                            anyhow!("Error attempting to attach to RTT: {error}")
-                        })?;
-                        defmt_data.insert(DefmtState::try_from_bytes(&elf)?)
-                    } else {
-                        defmt_data.insert(None)
-                    };
-
-                    match defmt_state {
-                        Some(defmt_state) => RttDecoder::Defmt {
-                            processor: DefmtProcessor::new(
-                                defmt_state.clone(),
-                                show_timestamps,
-                                show_location,
-                                log_format.as_deref(),
-                            ),
-                        },
-                        None => RttDecoder::BinaryLE,
+            let plugin_decoder = match channel_config.decoder.as_deref() {
+                Some(name) => Some(RttDecoder::plugin(name, channel_name).ok_or_else(|| {
+                    anyhow!("RTT decoder {name} is not registered by any plugin")
+                })?),
+                None if use_auto_formats => RttDecoder::plugin_for_channel(channel_name),
+                None => None,
+            };
+
+            let channel_data_format = if let Some(decoder) = plugin_decoder {
+                decoder
+            } else {
+                match channel_config.data_format {
+                    DataFormat::String => RttDecoder::String {
+                        timestamp_offset: Some(timestamp_offset),
+                        last_line_done: false,
+                        show_timestamps,
+                    },
+                    DataFormat::BinaryLE => RttDecoder::BinaryLE,
+                    DataFormat::Defmt => {
+                        let defmt_state = if let Some(data) = defmt_data.as_ref() {
+                            data
+                        } else if let Some(program_binary) = program_binary {
+                            let elf = std::fs::read(program_binary).map_err(|error| {
+                                anyhow!("Error attempting to attach to RTT: {error}")
+                            })?;
+                            defmt_data.insert(DefmtState::try_from_bytes(&elf)?)
+                        } else {
+                            defmt_data.insert(None)
+                        };
+
+                        match defmt_state {
+                            Some(defmt_state) => RttDecoder::Defmt {
+                                processor: DefmtProcessor::new(
+                                    defmt_state.clone(),
+                                    show_timestamps,
+                                    show_location,
+                                    log_format.as_deref(),
+                                ),
+                            },
+                            None => RttDecoder::BinaryLE,
+                        }
                     }
                 }
             };
@@ -594,6 +605,14 @@ impl SessionData {
                 return Ok(());
             }
 
+            if let Some(location) = channels.location {
+                tracing::info!(
+                    "RTT control block at {:#010x}, found by {}",
+                    location.address,
+                    location.strategy
+                );
+            }
+
             let up = channels
                 .up
                 .into_iter()
@@ -912,7 +931,8 @@ fn build_core_data(
         target_name: format!("{}-{}", core_configuration.core_index, target_name),
         stack_frames: vec![],
         breakpoints: vec![],
-        rtt_scan_ranges: WireScanRegion::Ranges(vec![]),
+        data_breakpoints: vec![],
+        rtt_discovery: RttDiscovery::default(),
         rtt_connection: None,
         defmt_state: None,
         rtt_remote_handle: None,
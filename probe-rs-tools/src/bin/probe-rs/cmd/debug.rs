@@ -284,6 +284,7 @@ impl Cmd {
                     wire_protocol: self.common.protocol,
                     attach_timeout: self.common.attach_timeout.map(|t| t.as_secs_f64()),
                     allow_erase_all: false,
+                    allow_flash_breakpoints: false,
                     flashing_config: FlashingConfig {
                         flashing_enabled: self.launch && self.binary.is_some(),
                         verify_before_flashing: self.preverify,
@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use probe_rs::{CoreDump, CoreDumpError, SessionDump};
+use probe_rs_debug::{DebugInfo, DebugRegisters, exception_handler_for_core};
+
+use crate::rpc::functions::stack_trace::convert::to_wire_stack_trace_frame_ref;
+use crate::util::cli::format_stack_frame;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    /// The dump, created with the `dump` or `dumpall` debugger commands.
+    dump: PathBuf,
+
+    /// The ELF file of the firmware that was running when the dump was taken.
+    elf: PathBuf,
+
+    /// The core dump a delta dump was created from.
+    #[clap(long)]
+    reference: Option<PathBuf>,
+
+    /// Only replay the dump of this core. By default, all cores in the dump are replayed.
+    #[clap(long)]
+    core: Option<usize>,
+
+    /// The maximum number of stack frames to print per core.
+    #[clap(long, default_value = "500")]
+    stack_frame_limit: u32,
+}
+
+impl Cmd {
+    pub fn run(self) -> anyhow::Result<()> {
+        let debug_info = DebugInfo::from_file(&self.elf)
+            .with_context(|| format!("Failed to read the debug info of {}", self.elf.display()))?;
+
+        let cores = match SessionDump::load(&self.dump) {
+            Ok(dump) => {
+                println!("Dump of {}", dump.target);
+                dump.cores
+                    .iter()
+                    .filter_map(|core| {
+                        Some((core.index, core.name.clone(), dump.core(core.index)?))
+                    })
+                    .collect::<Vec<_>>()
+            }
+            // Dumps of a single core are replayed as core 0. They fail to load as a session dump
+            // because of their header or their layout, any other error is reported as is.
+            Err(CoreDumpError::InvalidHeader(_) | CoreDumpError::DecodingCoreDump(_)) => {
+                let dump = match &self.reference {
+                    Some(reference) => {
+                        CoreDump::load_delta(&self.dump, &CoreDump::load(reference)?)?
+                    }
+                    None => CoreDump::load(&self.dump)?,
+                };
+                vec![(0, "core".to_string(), dump)]
+            }
+            Err(error) => return Err(error).context("Failed to load the dump"),
+        };
+
+        for (index, name, mut dump) in cores {
+            if self.core.is_some_and(|core| core != index) {
+                continue;
+            }
+
+            let initial_registers = DebugRegisters::from_coredump(&dump);
+            let exception_handler = exception_handler_for_core(dump.core_type());
+            let instruction_set = dump.instruction_set();
+            let frames = debug_info.unwind(
+                &mut dump,
+                initial_registers,
+                exception_handler.as_ref(),
+                Some(instruction_set),
+                self.stack_frame_limit as usize,
+            )?;
+
+            println!("Core {index} ({name})");
+            for (i, frame) in frames.iter().enumerate() {
+                let frame = to_wire_stack_trace_frame_ref(frame);
+                println!("    Frame {i}: {}", format_stack_frame(&frame, None));
+            }
+            if frames.len() >= self.stack_frame_limit as usize {
+                println!("Use `--stack-frame-limit` to increase the number of frames displayed.");
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+//! Provides ETM instruction trace capabilities.
+
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use probe_rs::architecture::arm::{
+    component::find_component, dp::DpAddress, etm, memory::PeripheralType,
+};
+use probe_rs::config::Registry;
+use probe_rs::probe::list::Lister;
+
+use crate::CoreOptions;
+use crate::cmd::profile::flat::Symbols;
+use crate::util::common_options::ProbeOptions;
+use crate::util::instruction_trace::{HistoryEntry, ProgramImage, reconstruct};
+use crate::util::parse_duration_secs;
+
+/// Record the instructions executed by a core into the Embedded Trace Buffer/FIFO (ETB/ETF), and
+/// print the execution history.
+///
+/// The ETM of the core is configured to trace all instructions, and the trace is read out from
+/// the device via the debug probe until the core halts, for example on a breakpoint or
+/// a fault, or the duration elapses. The trace is then decoded against the ELF file to answer
+/// "how did we get here?". With an ETB, only the most recent trace that fits into its buffer is
+/// available.
+///
+/// Note: Not all targets have an ETM and an ETB/ETF.
+#[derive(clap::Parser)]
+pub struct Cmd {
+    #[clap(flatten)]
+    shared: CoreOptions,
+
+    #[clap(flatten)]
+    common: ProbeOptions,
+
+    /// The ELF file of the program running on the target.
+    elf: PathBuf,
+
+    /// The maximum time to trace, in seconds.
+    #[clap(long, default_value = "1", value_parser = parse_duration_secs)]
+    duration: Duration,
+
+    /// Only print the last N entries of the execution history.
+    #[clap(long)]
+    last: Option<usize>,
+}
+
+impl Cmd {
+    pub fn run(self, registry: &mut Registry, lister: &Lister) -> anyhow::Result<()> {
+        let image = ProgramImage::from_elf(&std::fs::read(&self.elf)?)?;
+        let symbols = Symbols::try_from(&self.elf).map_err(|e| {
+            anyhow!(
+                "Failed to read symbol data from {}: {}",
+                self.elf.display(),
+                e
+            )
+        })?;
+
+        let (mut session, _probe_options) = self.common.simple_attach(registry, lister)?;
+        let core_index = self.shared.core;
+
+        let protocol = session.setup_instruction_trace(core_index)?;
+        let core_type = {
+            let mut core = session.core(core_index)?;
+            if core.core_halted()? {
+                core.run()?;
+            }
+            core.core_type()
+        };
+
+        // The FIFO of a TMC has to be read continuously, as it stalls the ETM when it is full.
+        // Reading an ETB restarts the capture and loses the trace in between, so it is only
+        // read once at the end, and holds the most recent trace then.
+        let components = session.get_arm_components(DpAddress::Default)?;
+        let read_continuously = find_component(&components, PeripheralType::Tmc).is_ok();
+
+        let mut trace = vec![];
+        let start = Instant::now();
+        while start.elapsed() < self.duration {
+            if read_continuously {
+                trace.extend(session.read_trace_data()?);
+            }
+            if session.core(core_index)?.core_halted()? {
+                break;
+            }
+            sleep(Duration::from_millis(10));
+        }
+        trace.extend(session.read_trace_data()?);
+
+        let elements = etm::decode(protocol, &trace);
+        let history = reconstruct(protocol, core_type, &elements, &image)?;
+        let skip = self
+            .last
+            .map_or(0, |last| history.len().saturating_sub(last));
+
+        let mut function = None;
+        for entry in &history[skip..] {
+            match entry {
+                HistoryEntry::Instruction {
+                    address,
+                    text,
+                    executed,
+                } => {
+                    let name = symbols.get_name(*address);
+                    if name != function {
+                        println!("{}:", name.as_deref().unwrap_or("<unknown>"));
+                        function = name;
+                    }
+                    let condition = if *executed { "" } else { " (condition failed)" };
+                    println!("    {address:#010x}: {text}{condition}");
+                }
+                HistoryEntry::Exception { number } => {
+                    println!("-- exception {number} --");
+                    function = None;
+                }
+                HistoryEntry::ExceptionReturn => {
+                    println!("-- exception return --");
+                    function = None;
+                }
+                HistoryEntry::Gap => {
+                    println!("-- trace data lost --");
+                    function = None;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -5,6 +5,7 @@
 //! clients (for example an RTT UI) without a local `FairMutex<Session>`.
 
 mod arch;
+mod rtos;
 mod stub;
 mod target;
 
@@ -12,6 +13,7 @@ pub(crate) use stub::{GdbInstanceConfiguration, run};
 
 use std::path::PathBuf;
 use std::process::Command;
+use std::sync::Arc;
 use std::time::Duration;
 
 use probe_rs::CoreRegisters;
@@ -24,6 +26,7 @@ use tokio::runtime::Handle;
 
 use crate::rpc::functions::core_ops::convert::{from_wire_core_type, from_wire_instruction_set};
 use crate::util::{cli, common_options::ProbeOptions};
+use rtos::{Rtos, RtosKind};
 
 #[derive(clap::Parser)]
 pub struct Cmd {
@@ -43,9 +46,13 @@ pub struct Cmd {
     #[clap(long, help = "Spawn gdb after starting the gdbserver.")]
     gdb: Option<String>,
 
+    /// Show the tasks of an RTOS as GDB threads, using the symbols of the ELF file.
+    #[clap(long, value_enum, requires = "path")]
+    rtos: Option<RtosKind>,
+
     /// The path to the ELF file to debug.
     ///
-    /// This only needs to be specified when using `--gdb`.
+    /// This only needs to be specified when using `--gdb` or `--rtos`.
     #[clap(index = 1)]
     path: Option<PathBuf>,
 
@@ -78,7 +85,15 @@ impl Cmd {
             .gdb_connection_string
             .unwrap_or_else(|| "localhost:1337".to_string());
 
-        let context = GdbSessionContext::from_session(&session, &registry).await?;
+        let mut context = GdbSessionContext::from_session(&session, &registry).await?;
+        context.elf = self.path.clone();
+        if let (Some(kind), Some(path)) = (self.rtos, &self.path) {
+            context.rtos = rtos::load(kind, path)?;
+            if context.rtos.is_none() {
+                println!("No RTOS was detected in {}", path.display());
+            }
+        }
+
         let instances =
             GdbInstanceConfiguration::from_context(&context, Some(gdb_connection_string));
 
@@ -134,6 +149,10 @@ pub(crate) struct GdbSessionContext {
     pub memory_map: Vec<MemoryRegion>,
     /// Absolute flash sectors from RPC `target/metadata`.
     pub flash_sectors: Vec<WireFlashSector>,
+    /// Thread awareness for the RTOS of the debugged application.
+    pub rtos: Option<Arc<dyn Rtos>>,
+    /// The ELF file of the debugged application.
+    pub elf: Option<PathBuf>,
 }
 
 #[derive(Clone)]
@@ -185,6 +204,8 @@ impl GdbSessionContext {
             cores,
             memory_map: metadata.memory_map,
             flash_sectors: metadata.flash_sectors,
+            rtos: None,
+            elf: None,
         })
     }
 }
@@ -0,0 +1,101 @@
+//! Thread awareness for the Embassy executor.
+//!
+//! Embassy tasks are futures polled on the stack of their executor, so they have no saved
+//! registers. Each `#[embassy_executor::task]` creates a `POOL` static holding the task's
+//! storage, which starts with the task header and its state word.
+
+use super::{Rtos, RtosCore, RtosThread, Symbols, TargetMemory, TaskCode};
+
+/// The task was spawned and has not finished yet.
+const STATE_SPAWNED: u32 = 1 << 0;
+/// The task is in the run queue of its executor.
+const STATE_RUN_QUEUED: u32 = 1 << 1;
+
+struct Task {
+    name: String,
+    pool: u64,
+    code: TaskCode,
+}
+
+pub(crate) struct Embassy {
+    tasks: Vec<Task>,
+}
+
+impl Embassy {
+    pub fn new(symbols: &Symbols) -> anyhow::Result<Self> {
+        let mut tasks = symbols
+            .paths()
+            .filter_map(|(path, symbol)| {
+                let [.., function, last] = path else {
+                    return None;
+                };
+                if last != "POOL" {
+                    return None;
+                }
+                // Older versions of the task macro put the pool into `__{name}_task`.
+                let name = function
+                    .strip_prefix("__")
+                    .and_then(|name| name.strip_suffix("_task"))
+                    .unwrap_or(function)
+                    .to_string();
+
+                let inner = format!("__{name}_task");
+                let code = TaskCode::find(symbols, |path| {
+                    path.iter().any(|component| component == "{{closure}}")
+                        && path
+                            .iter()
+                            .any(|component| *component == name || *component == inner)
+                });
+
+                Some(Task {
+                    name,
+                    pool: symbol.address,
+                    code,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        anyhow::ensure!(!tasks.is_empty(), "The ELF file has no Embassy tasks");
+        tasks.sort_by_key(|task| task.pool);
+
+        Ok(Self { tasks })
+    }
+}
+
+impl Rtos for Embassy {
+    fn name(&self) -> &'static str {
+        "Embassy"
+    }
+
+    fn threads(
+        &self,
+        memory: &mut dyn TargetMemory,
+        core: RtosCore,
+    ) -> anyhow::Result<Vec<RtosThread>> {
+        let mut threads = Vec::with_capacity(self.tasks.len());
+        for task in &self.tasks {
+            let running = task.code.contains(core.pc);
+            let state_bits = memory.read_u32(task.pool)?;
+
+            let state = if running {
+                "Running"
+            } else if state_bits & STATE_RUN_QUEUED != 0 {
+                "Ready"
+            } else if state_bits & STATE_SPAWNED != 0 {
+                "Waiting"
+            } else {
+                "Not spawned"
+            };
+
+            threads.push(RtosThread {
+                id: task.pool,
+                name: task.name.clone(),
+                state: state.to_string(),
+                running,
+                registers: None,
+            });
+        }
+
+        Ok(threads)
+    }
+}
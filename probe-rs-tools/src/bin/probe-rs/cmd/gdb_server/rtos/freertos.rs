@@ -0,0 +1,335 @@
+//! Thread awareness for FreeRTOS on 32-bit targets.
+
+use probe_rs::CoreType;
+
+use super::{
+    Rtos, RtosCore, RtosThread, StackedWord, Symbols, TargetMemory, cortex_m, unstack_cortex_m,
+};
+
+/// The size of `List_t`.
+const LIST_SIZE: u64 = 20;
+/// The offset of `xListEnd` in `List_t`.
+const LIST_END_OFFSET: u64 = 8;
+/// The offset of `pxNext` in `ListItem_t` and `MiniListItem_t`.
+const ITEM_NEXT_OFFSET: u64 = 4;
+/// The offset of `pvOwner` in `ListItem_t`.
+const ITEM_OWNER_OFFSET: u64 = 12;
+
+/// The offset of `uxPriority` in `TCB_t`, without MPU wrappers.
+const TCB_PRIORITY_OFFSET: u64 = 44;
+/// The offset of `pcTaskName` in `TCB_t`, without MPU wrappers.
+const TCB_NAME_OFFSET: u64 = 52;
+/// The default `configMAX_TASK_NAME_LEN`.
+const MAX_TASK_NAME_LENGTH: usize = 16;
+
+/// Guards against corrupted lists.
+const MAX_LIST_ITEMS: usize = 1024;
+
+/// The offsets into the kernel structures.
+///
+/// They depend on the configuration of the kernel, like `configUSE_16_BIT_TICKS` and MPU
+/// wrappers, so they are read from the debug information of the ELF file. The default values are
+/// those of the default configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    list_size: u64,
+    list_end: u64,
+    /// The offset of `pxNext` in `MiniListItem_t`, for the end marker of a list.
+    end_next: u64,
+    item_next: u64,
+    item_owner: u64,
+    tcb_priority: u64,
+    tcb_name: u64,
+    /// `configMAX_TASK_NAME_LEN`
+    max_name_length: usize,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            list_size: LIST_SIZE,
+            list_end: LIST_END_OFFSET,
+            end_next: ITEM_NEXT_OFFSET,
+            item_next: ITEM_NEXT_OFFSET,
+            item_owner: ITEM_OWNER_OFFSET,
+            tcb_priority: TCB_PRIORITY_OFFSET,
+            tcb_name: TCB_NAME_OFFSET,
+            max_name_length: MAX_TASK_NAME_LENGTH,
+        }
+    }
+}
+
+impl Layout {
+    fn from_symbols(symbols: &Symbols) -> Self {
+        let default = Self::default();
+        let offset = |structure: &str, member: &str, default: u64| {
+            symbols
+                .layout(structure)
+                .and_then(|layout| layout.offset(member))
+                .unwrap_or(default)
+        };
+
+        Self {
+            list_size: symbols
+                .layout("xLIST")
+                .map_or(default.list_size, |list| list.size),
+            list_end: offset("xLIST", "xListEnd", default.list_end),
+            end_next: offset("xMINI_LIST_ITEM", "pxNext", default.end_next),
+            item_next: offset("xLIST_ITEM", "pxNext", default.item_next),
+            item_owner: offset("xLIST_ITEM", "pvOwner", default.item_owner),
+            tcb_priority: offset("tskTCB", "uxPriority", default.tcb_priority),
+            tcb_name: offset("tskTCB", "pcTaskName", default.tcb_name),
+            // The name is NUL terminated, so the padding after it can be read as well.
+            max_name_length: symbols
+                .layout("tskTCB")
+                .and_then(|tcb| tcb.space("pcTaskName"))
+                .map_or(default.max_name_length, |space| space as usize),
+        }
+    }
+}
+
+pub(crate) struct FreeRtos {
+    layout: Layout,
+    current_tcb: u64,
+    /// `pxReadyTasksLists`, and the number of priorities.
+    ready_lists: (u64, u64),
+    /// The other lists a task can be in, with the state of their tasks.
+    lists: Vec<(u64, &'static str)>,
+}
+
+impl FreeRtos {
+    pub fn new(symbols: &Symbols) -> anyhow::Result<Self> {
+        let ready_lists = symbols
+            .get("pxReadyTasksLists")
+            .ok_or_else(|| anyhow::anyhow!("The ELF file has no symbol `pxReadyTasksLists`"))?;
+
+        let mut lists = vec![
+            (symbols.address("xDelayedTaskList1")?, "Blocked"),
+            (symbols.address("xDelayedTaskList2")?, "Blocked"),
+            (symbols.address("xPendingReadyList")?, "Ready"),
+        ];
+        // These lists only exist if the kernel is configured to suspend and delete tasks.
+        for (name, state) in [
+            ("xSuspendedTaskList", "Suspended"),
+            ("xTasksWaitingTermination", "Deleted"),
+        ] {
+            if let Some(symbol) = symbols.get(name) {
+                lists.push((symbol.address, state));
+            }
+        }
+
+        let layout = Layout::from_symbols(symbols);
+        tracing::debug!("FreeRTOS structure layout: {layout:?}");
+
+        Ok(Self {
+            layout,
+            current_tcb: symbols.address("pxCurrentTCB")?,
+            ready_lists: (
+                ready_lists.address,
+                (ready_lists.size / layout.list_size).max(1),
+            ),
+            lists,
+        })
+    }
+
+    /// Returns the owners of the items of the list at `list`.
+    fn list_owners(&self, memory: &mut dyn TargetMemory, list: u64) -> anyhow::Result<Vec<u64>> {
+        let end = list + self.layout.list_end;
+        let mut owners = Vec::new();
+
+        let mut item = memory.read_u32(end + self.layout.end_next)? as u64;
+        while item != end && item != 0 {
+            anyhow::ensure!(
+                owners.len() < MAX_LIST_ITEMS,
+                "The FreeRTOS task list at {list:#010x} is corrupted"
+            );
+            owners.push(memory.read_u32(item + self.layout.item_owner)? as u64);
+            item = memory.read_u32(item + self.layout.item_next)? as u64;
+        }
+
+        Ok(owners)
+    }
+}
+
+impl Rtos for FreeRtos {
+    fn name(&self) -> &'static str {
+        "FreeRTOS"
+    }
+
+    fn threads(
+        &self,
+        memory: &mut dyn TargetMemory,
+        core: RtosCore,
+    ) -> anyhow::Result<Vec<RtosThread>> {
+        let current = memory.read_u32(self.current_tcb)? as u64;
+        // The scheduler has not been started yet.
+        if current == 0 {
+            return Ok(Vec::new());
+        }
+
+        let (ready_lists, priorities) = self.ready_lists;
+        let mut tasks = Vec::new();
+        for priority in 0..priorities {
+            for tcb in self.list_owners(memory, ready_lists + priority * self.layout.list_size)? {
+                tasks.push((tcb, "Ready"));
+            }
+        }
+        for &(list, state) in &self.lists {
+            for tcb in self.list_owners(memory, list)? {
+                tasks.push((tcb, state));
+            }
+        }
+        tasks.sort_by_key(|(tcb, _)| *tcb);
+        tasks.dedup_by_key(|(tcb, _)| *tcb);
+
+        let software_frame = software_frame(core);
+        let mut threads = Vec::with_capacity(tasks.len());
+        for (tcb, state) in tasks {
+            let running = tcb == current;
+            let name =
+                memory.read_string(tcb + self.layout.tcb_name, self.layout.max_name_length)?;
+            let priority = memory.read_u32(tcb + self.layout.tcb_priority)?;
+
+            let registers = match &software_frame {
+                Some(frame) if !running => {
+                    let top_of_stack = memory.read_u32(tcb)? as u64;
+                    Some(unstack_cortex_m(memory, top_of_stack, frame)?)
+                }
+                _ => None,
+            };
+
+            threads.push(RtosThread {
+                id: tcb,
+                name,
+                state: format!(
+                    "{}, priority {priority}",
+                    if running { "Running" } else { state }
+                ),
+                running,
+                registers,
+            });
+        }
+
+        Ok(threads)
+    }
+}
+
+/// The registers the FreeRTOS port of the core saves below the exception frame.
+///
+/// For ARMv8-M this is the layout of the ports without TrustZone support.
+fn software_frame(core: RtosCore) -> Option<Vec<StackedWord>> {
+    let callee_saved = (4..12).map(|index| StackedWord::Register(cortex_m::r(index)));
+
+    let frame = match core.core_type {
+        CoreType::Armv6m => callee_saved.collect(),
+        CoreType::Armv7m | CoreType::Armv7em if !core.has_fpu => callee_saved.collect(),
+        CoreType::Armv7m | CoreType::Armv7em => {
+            callee_saved.chain([StackedWord::ExcReturn]).collect()
+        }
+        CoreType::Armv8m => [StackedWord::Ignored, StackedWord::ExcReturn]
+            .into_iter()
+            .chain(callee_saved)
+            .collect(),
+        _ => return None,
+    };
+
+    Some(frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::StructLayout;
+    use super::super::tests::TestMemory;
+    use super::*;
+
+    #[test]
+    fn read_task_lists() {
+        let mut symbols = Symbols::default();
+        symbols.insert("pxCurrentTCB", 0x2000_0000, 4);
+        symbols.insert("pxReadyTasksLists", 0x2000_0010, 2 * LIST_SIZE);
+        symbols.insert("xDelayedTaskList1", 0x2000_0040, LIST_SIZE);
+        symbols.insert("xDelayedTaskList2", 0x2000_0060, LIST_SIZE);
+        symbols.insert("xPendingReadyList", 0x2000_0080, LIST_SIZE);
+        let rtos = FreeRtos::new(&symbols).unwrap();
+
+        let mut memory = TestMemory::new(0x2000_0000, 0x1000);
+        // Empty lists point back to their end marker.
+        for list in [
+            0x2000_0010,
+            0x2000_0024,
+            0x2000_0040,
+            0x2000_0060,
+            0x2000_0080,
+        ] {
+            memory.write_u32(list + LIST_END_OFFSET + ITEM_NEXT_OFFSET, list as u32 + 8);
+        }
+
+        let idle = 0x2000_0200;
+        let blinky = 0x2000_0300;
+        memory.write_u32(0x2000_0000, idle as u32);
+        memory.write_bytes(idle + TCB_NAME_OFFSET, b"IDLE\0");
+        memory.write_bytes(blinky + TCB_NAME_OFFSET, b"blinky\0");
+        memory.write_u32(blinky + TCB_PRIORITY_OFFSET, 1);
+
+        // Each task is in one list, through its state list item at offset 4.
+        let (ready_idle, delayed_blinky) = (idle + 4, blinky + 4);
+        memory.write_u32(0x2000_0010 + 12, ready_idle as u32);
+        memory.write_u32(ready_idle + ITEM_NEXT_OFFSET, 0x2000_0018);
+        memory.write_u32(ready_idle + ITEM_OWNER_OFFSET, idle as u32);
+        memory.write_u32(0x2000_0040 + 12, delayed_blinky as u32);
+        memory.write_u32(delayed_blinky + ITEM_NEXT_OFFSET, 0x2000_0048);
+        memory.write_u32(delayed_blinky + ITEM_OWNER_OFFSET, blinky as u32);
+
+        // The stack of the blinky task, with r4-r11 and the exception frame.
+        let stack = 0x2000_0800;
+        memory.write_u32(blinky, stack as u32);
+        memory.write_u32(stack + 32 + 24, 0x0800_0420);
+
+        let core = RtosCore {
+            core_type: CoreType::Armv7m,
+            has_fpu: false,
+            pc: 0x0800_0100,
+        };
+        let threads = rtos.threads(&mut memory, core).unwrap();
+
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].description(), "IDLE (Running, priority 0)");
+        assert!(threads[0].running && threads[0].registers.is_none());
+
+        assert_eq!(threads[1].description(), "blinky (Blocked, priority 1)");
+        let registers = threads[1].registers.as_ref().unwrap();
+        assert_eq!(registers[&cortex_m::PC], 0x0800_0420);
+        assert_eq!(registers[&cortex_m::SP], stack + 64);
+    }
+
+    #[test]
+    fn layout_from_debug_information() {
+        let mut symbols = Symbols::default();
+        // A kernel with MPU wrappers, whose TCB holds `xMPUSettings` after `pxTopOfStack`.
+        symbols.structs.insert(
+            "tskTCB".to_string(),
+            StructLayout {
+                size: 120,
+                members: [
+                    ("pxTopOfStack", 0),
+                    ("xMPUSettings", 4),
+                    ("uxPriority", 84),
+                    ("pxStack", 88),
+                    ("pcTaskName", 92),
+                    ("uxBasePriority", 116),
+                ]
+                .into_iter()
+                .map(|(name, offset)| (name.to_string(), offset))
+                .collect(),
+            },
+        );
+
+        let layout = Layout::from_symbols(&symbols);
+        assert_eq!(layout.tcb_priority, 84);
+        assert_eq!(layout.tcb_name, 92);
+        assert_eq!(layout.max_name_length, 24);
+        // The lists keep their default layout.
+        assert_eq!(layout.list_size, LIST_SIZE);
+        assert_eq!(layout.item_owner, ITEM_OWNER_OFFSET);
+    }
+}
@@ -0,0 +1,640 @@
+//! RTOS thread awareness for the GDB stub.
+//!
+//! An [`Rtos`] reads the task lists of a kernel from target memory, using the addresses of the
+//! kernel's symbols in the ELF file. Each task is exposed to GDB as a thread. Tasks that are not
+//! running have their registers saved on their stack, which is unwound to show their backtrace.
+
+mod embassy;
+mod freertos;
+mod rtic;
+mod zephyr;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Context as _;
+use object::{Object, ObjectSection, ObjectSymbol};
+use probe_rs::{CoreType, RegisterId};
+
+/// The RTOS to provide thread awareness for.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RtosKind {
+    /// Detect the RTOS from the symbols of the ELF file.
+    Auto,
+    #[value(name = "freertos")]
+    FreeRtos,
+    Zephyr,
+    Rtic,
+    Embassy,
+}
+
+/// A task of the RTOS, exposed to GDB as a thread.
+#[derive(Debug, Clone)]
+pub(crate) struct RtosThread {
+    /// The address of the task's control block, which identifies it across updates.
+    pub id: u64,
+    pub name: String,
+    pub state: String,
+    /// True for the task that runs on the core.
+    pub running: bool,
+    /// The registers saved when the task was switched out. `None` for the running task, and
+    /// for tasks that share the stack of the core, whose registers are those of the core.
+    pub registers: Option<SavedRegisters>,
+}
+
+impl RtosThread {
+    /// The text GDB shows for the thread in `info threads`.
+    pub fn description(&self) -> String {
+        format!("{} ({})", self.name, self.state)
+    }
+}
+
+/// Registers of a task, read from its stack.
+pub(crate) type SavedRegisters = HashMap<RegisterId, u64>;
+
+/// Target memory, read from the core the RTOS runs on.
+pub(crate) trait TargetMemory {
+    fn read(&mut self, address: u64, length: usize) -> anyhow::Result<Vec<u8>>;
+
+    fn read_u32(&mut self, address: u64) -> anyhow::Result<u32> {
+        let bytes = self.read(address, 4)?;
+        Ok(u32::from_le_bytes(bytes[..4].try_into()?))
+    }
+
+    fn read_u8(&mut self, address: u64) -> anyhow::Result<u8> {
+        Ok(self.read(address, 1)?[0])
+    }
+
+    /// Reads a NUL terminated string of at most `max_length` bytes.
+    fn read_string(&mut self, address: u64, max_length: usize) -> anyhow::Result<String> {
+        let bytes = self.read(address, max_length)?;
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+}
+
+/// Facts about the core an RTOS runs on, needed to unwind task stacks.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RtosCore {
+    pub core_type: CoreType,
+    pub has_fpu: bool,
+    /// The program counter of the core, used to find the running task of stackless schedulers.
+    pub pc: u64,
+}
+
+/// Thread awareness for one RTOS.
+pub(crate) trait Rtos: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Reads the current tasks from target memory. The core must be halted.
+    fn threads(
+        &self,
+        memory: &mut dyn TargetMemory,
+        core: RtosCore,
+    ) -> anyhow::Result<Vec<RtosThread>>;
+}
+
+/// A symbol of the ELF file.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Symbol {
+    pub address: u64,
+    pub size: u64,
+}
+
+/// The layout of a C structure, from the debug information of the ELF file.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct StructLayout {
+    pub size: u64,
+    /// The members by their name, with their offset.
+    members: HashMap<String, u64>,
+}
+
+impl StructLayout {
+    pub fn offset(&self, member: &str) -> Option<u64> {
+        self.members.get(member).copied()
+    }
+
+    /// The bytes from `member` to the next member or the end of the structure, which includes
+    /// any padding after the member.
+    pub fn space(&self, member: &str) -> Option<u64> {
+        let offset = self.offset(member)?;
+        let next = self
+            .members
+            .values()
+            .copied()
+            .filter(|&other| other > offset)
+            .min()
+            .unwrap_or(self.size);
+        Some(next.saturating_sub(offset))
+    }
+}
+
+/// The symbols of the ELF file, by their raw and their demangled name.
+#[derive(Debug, Default)]
+pub(crate) struct Symbols {
+    symbols: HashMap<String, Symbol>,
+    /// Demangled Rust paths of the symbols, split into their components.
+    paths: Vec<(Vec<String>, Symbol)>,
+    /// The C structures of the debug information, by their tag and their typedef names.
+    structs: HashMap<String, StructLayout>,
+}
+
+impl Symbols {
+    pub fn from_elf(elf: &[u8]) -> anyhow::Result<Self> {
+        let file = object::File::parse(elf)?;
+        let mut symbols = Self::default();
+        for symbol in file.symbols() {
+            let Ok(name) = symbol.name() else {
+                continue;
+            };
+            symbols.insert(name, symbol.address(), symbol.size());
+        }
+        // Without debug information, the RTOS falls back to the default layout of its structures.
+        match struct_layouts(&file) {
+            Ok(structs) => symbols.structs = structs,
+            Err(error) => tracing::debug!("Failed to read the structures of the ELF file: {error}"),
+        }
+        Ok(symbols)
+    }
+
+    fn insert(&mut self, name: &str, address: u64, size: u64) {
+        let symbol = Symbol { address, size };
+        if let Some(path) = demangle_path(name) {
+            self.paths.push((path, symbol));
+        }
+        self.symbols.insert(name.to_string(), symbol);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).copied()
+    }
+
+    pub fn address(&self, name: &str) -> anyhow::Result<u64> {
+        self.get(name)
+            .map(|symbol| symbol.address)
+            .with_context(|| format!("The ELF file has no symbol `{name}`"))
+    }
+
+    /// Returns the layout of the C structure with the tag or typedef name `name`.
+    pub fn layout(&self, name: &str) -> Option<&StructLayout> {
+        self.structs.get(name)
+    }
+
+    /// Iterates the demangled Rust paths of the symbols.
+    pub fn paths(&self) -> impl Iterator<Item = (&[String], Symbol)> {
+        self.paths
+            .iter()
+            .map(|(path, symbol)| (path.as_slice(), *symbol))
+    }
+}
+
+/// Reads the layouts of the C structures from the DWARF information of `file`.
+fn struct_layouts(file: &object::File) -> anyhow::Result<HashMap<String, StructLayout>> {
+    use addr2line::gimli;
+
+    let endian = if file.is_little_endian() {
+        gimli::RunTimeEndian::Little
+    } else {
+        gimli::RunTimeEndian::Big
+    };
+    let sections = gimli::DwarfSections::load(|id| -> anyhow::Result<Cow<'_, [u8]>> {
+        Ok(file
+            .section_by_name(id.name())
+            .and_then(|section| section.uncompressed_data().ok())
+            .unwrap_or(Cow::Borrowed(&[])))
+    })?;
+    let dwarf = sections.borrow(|section| gimli::EndianSlice::new(section, endian));
+
+    let mut layouts = HashMap::new();
+    let mut units = dwarf.units();
+    while let Some(header) = units.next()? {
+        let unit = dwarf.unit(header)?;
+        let name = |value| -> anyhow::Result<String> {
+            Ok(dwarf
+                .attr_string(&unit, value)?
+                .to_string_lossy()
+                .into_owned())
+        };
+
+        // Structures by their offset in the unit, and typedefs with the offset of their type.
+        let mut structs = HashMap::new();
+        let mut typedefs = HashMap::new();
+        // The structure whose members are read, with its depth.
+        let mut current = None;
+
+        let mut entries = unit.entries();
+        while let Some(entry) = entries.next_dfs()? {
+            if entry.depth() == 0 {
+                // Other languages, like Rust, have many more types but no RTOS structures.
+                let is_c = matches!(
+                    entry.attr_value(gimli::DW_AT_language),
+                    Some(gimli::AttributeValue::Language(
+                        gimli::DW_LANG_C89
+                            | gimli::DW_LANG_C
+                            | gimli::DW_LANG_C99
+                            | gimli::DW_LANG_C11
+                            | gimli::DW_LANG_C17
+                    ))
+                );
+                if !is_c {
+                    break;
+                }
+                continue;
+            }
+
+            if let Some((depth, offset)) = current {
+                if entry.depth() <= depth {
+                    current = None;
+                } else if entry.depth() == depth + 1
+                    && entry.tag() == gimli::DW_TAG_member
+                    && let Some(member) = entry.attr_value(gimli::DW_AT_name)
+                    && let Some(location) = entry
+                        .attr_value(gimli::DW_AT_data_member_location)
+                        .and_then(|location| location.udata_value())
+                {
+                    let layout: &mut (Option<String>, StructLayout) =
+                        structs.get_mut(&offset).unwrap();
+                    layout.1.members.insert(name(member)?, location);
+                }
+            }
+
+            match entry.tag() {
+                gimli::DW_TAG_structure_type if current.is_none() => {
+                    // Declarations of structures have no size.
+                    let Some(size) = entry
+                        .attr_value(gimli::DW_AT_byte_size)
+                        .and_then(|size| size.udata_value())
+                    else {
+                        continue;
+                    };
+                    let tag = entry.attr_value(gimli::DW_AT_name).map(name).transpose()?;
+                    structs.insert(
+                        entry.offset(),
+                        (
+                            tag,
+                            StructLayout {
+                                size,
+                                members: HashMap::new(),
+                            },
+                        ),
+                    );
+                    current = Some((entry.depth(), entry.offset()));
+                }
+                gimli::DW_TAG_typedef => {
+                    if let Some(typedef) = entry.attr_value(gimli::DW_AT_name)
+                        && let Some(gimli::AttributeValue::UnitRef(target)) =
+                            entry.attr_value(gimli::DW_AT_type)
+                    {
+                        typedefs.insert(entry.offset(), (name(typedef)?, target));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (tag, layout) in structs.values() {
+            if let Some(tag) = tag {
+                layouts.insert(tag.clone(), layout.clone());
+            }
+        }
+        for (typedef, target) in typedefs.values() {
+            let mut target = *target;
+            // Typedefs can name other typedefs, like `TCB_t` names `tskTCB`.
+            for _ in 0..8 {
+                match typedefs.get(&target) {
+                    Some((_, next)) => target = *next,
+                    None => break,
+                }
+            }
+            if let Some((_, layout)) = structs.get(&target) {
+                layouts.insert(typedef.clone(), layout.clone());
+            }
+        }
+    }
+
+    Ok(layouts)
+}
+
+/// The code of a task of a stackless scheduler, which is used to find the running task.
+#[derive(Debug, Default)]
+pub(crate) struct TaskCode(Vec<Range<u64>>);
+
+impl TaskCode {
+    /// Collects the functions whose demangled path matches `matches`.
+    pub fn find(symbols: &Symbols, matches: impl Fn(&[String]) -> bool) -> Self {
+        Self(
+            symbols
+                .paths()
+                .filter(|(path, symbol)| symbol.size > 0 && matches(path))
+                .map(|(_, symbol)| {
+                    // The lowest bit of Thumb function addresses is set.
+                    let start = symbol.address & !1;
+                    start..start + symbol.size
+                })
+                .collect(),
+        )
+    }
+
+    pub fn contains(&self, pc: u64) -> bool {
+        self.0.iter().any(|range| range.contains(&pc))
+    }
+}
+
+/// Loads the symbols of `elf`, and creates the thread awareness for `kind`.
+///
+/// Returns `None` if `kind` is [`RtosKind::Auto`] and no RTOS was detected.
+pub(crate) fn load(kind: RtosKind, elf: &Path) -> anyhow::Result<Option<Arc<dyn Rtos>>> {
+    let data = std::fs::read(elf).with_context(|| format!("Failed to read {}", elf.display()))?;
+    let symbols = Symbols::from_elf(&data)
+        .with_context(|| format!("Failed to read the symbols of {}", elf.display()))?;
+
+    let rtos: Option<Arc<dyn Rtos>> = match kind {
+        RtosKind::FreeRtos => Some(Arc::new(freertos::FreeRtos::new(&symbols)?)),
+        RtosKind::Zephyr => Some(Arc::new(zephyr::Zephyr::new(&symbols)?)),
+        RtosKind::Rtic => Some(Arc::new(rtic::Rtic::new(&symbols)?)),
+        RtosKind::Embassy => Some(Arc::new(embassy::Embassy::new(&symbols)?)),
+        RtosKind::Auto => {
+            if let Ok(rtos) = freertos::FreeRtos::new(&symbols) {
+                Some(Arc::new(rtos))
+            } else if let Ok(rtos) = zephyr::Zephyr::new(&symbols) {
+                Some(Arc::new(rtos))
+            } else if let Ok(rtos) = rtic::Rtic::new(&symbols) {
+                Some(Arc::new(rtos))
+            } else if let Ok(rtos) = embassy::Embassy::new(&symbols) {
+                Some(Arc::new(rtos))
+            } else {
+                None
+            }
+        }
+    };
+
+    if let Some(rtos) = &rtos {
+        tracing::info!("Enabled thread awareness for {}", rtos.name());
+    }
+
+    Ok(rtos)
+}
+
+/// Demangles the path of a legacy mangled Rust symbol, like `_ZN3app4main17h0123456789abcdefE`.
+///
+/// The hash component at the end is removed.
+fn demangle_path(name: &str) -> Option<Vec<String>> {
+    let mut rest = name.strip_prefix("_ZN")?;
+    let mut path = Vec::new();
+
+    while !rest.starts_with('E') {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let length = rest[..digits].parse::<usize>().ok()?;
+        let component = rest.get(digits..digits + length)?;
+        path.push(decode_component(component));
+        rest = &rest[digits + length..];
+    }
+
+    if path
+        .last()
+        .is_some_and(|last| last.len() == 17 && last.starts_with('h'))
+    {
+        path.pop();
+    }
+
+    Some(path)
+}
+
+/// Replaces the escapes of a legacy mangled path component, like `$u7b$`.
+fn decode_component(component: &str) -> String {
+    // A leading `$` is escaped with an underscore.
+    let component = component
+        .strip_prefix('_')
+        .filter(|c| c.starts_with('$'))
+        .unwrap_or(component);
+
+    let mut decoded = String::new();
+    let mut rest = component;
+    while let Some(start) = rest.find('$') {
+        decoded.push_str(&rest[..start]);
+        let Some(end) = rest[start + 1..].find('$') else {
+            break;
+        };
+        let escape = &rest[start + 1..start + 1 + end];
+        match escape {
+            "LT" => decoded.push('<'),
+            "GT" => decoded.push('>'),
+            "RF" => decoded.push('&'),
+            "BP" => decoded.push('*'),
+            "LP" => decoded.push('('),
+            "RP" => decoded.push(')'),
+            "C" => decoded.push(','),
+            "SP" => decoded.push('@'),
+            _ => match escape
+                .strip_prefix('u')
+                .and_then(|code| u32::from_str_radix(code, 16).ok())
+                .and_then(char::from_u32)
+            {
+                Some(c) => decoded.push(c),
+                None => decoded.push_str(escape),
+            },
+        }
+        rest = &rest[start + end + 2..];
+    }
+    decoded.push_str(rest);
+
+    decoded.replace("..", "::")
+}
+
+/// The Cortex-M register numbers of probe-rs.
+pub(crate) mod cortex_m {
+    use probe_rs::RegisterId;
+
+    pub const SP: RegisterId = RegisterId(13);
+    pub const LR: RegisterId = RegisterId(14);
+    pub const PC: RegisterId = RegisterId(15);
+    pub const XPSR: RegisterId = RegisterId(0b1_0000);
+
+    pub fn r(index: u16) -> RegisterId {
+        RegisterId(index)
+    }
+}
+
+/// A word that a context switch saved on the stack, below the exception frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StackedWord {
+    Register(RegisterId),
+    /// The `EXC_RETURN` value of the switch, which tells whether floating point registers were
+    /// saved.
+    ExcReturn,
+    Ignored,
+}
+
+/// Unwinds a task stack of a Cortex-M core.
+///
+/// `software_frame` lists the words the context switch pushed, from `sp` upwards. When the
+/// task used the FPU, `s16`-`s31` follow them. Then comes the frame the core stacked on exception
+/// entry.
+pub(crate) fn unstack_cortex_m(
+    memory: &mut dyn TargetMemory,
+    mut sp: u64,
+    software_frame: &[StackedWord],
+) -> anyhow::Result<SavedRegisters> {
+    let mut registers = SavedRegisters::new();
+    // Without an EXC_RETURN value, the basic frame without FPU state is used.
+    let mut extended_frame = false;
+
+    let software = memory.read(sp, software_frame.len() * 4)?;
+    for (word, value) in software_frame.iter().zip(software.chunks_exact(4)) {
+        let value = u32::from_le_bytes(value.try_into().unwrap());
+        match word {
+            StackedWord::Register(id) => {
+                registers.insert(*id, value as u64);
+            }
+            StackedWord::ExcReturn => extended_frame = value & (1 << 4) == 0,
+            StackedWord::Ignored => {}
+        }
+    }
+    sp += software.len() as u64;
+
+    if extended_frame {
+        // s16-s31
+        sp += 16 * 4;
+    }
+
+    unstack_exception_frame(memory, &mut registers, sp, extended_frame)?;
+
+    Ok(registers)
+}
+
+/// Reads the frame a Cortex-M core stacked at `sp` on exception entry into `registers`, and
+/// sets the stack pointer from before the exception.
+///
+/// An `extended_frame` also holds `s0`-`s15` and `FPSCR`, which is the case if bit 4 of the
+/// `EXC_RETURN` value is clear.
+pub(crate) fn unstack_exception_frame(
+    memory: &mut dyn TargetMemory,
+    registers: &mut SavedRegisters,
+    mut sp: u64,
+    extended_frame: bool,
+) -> anyhow::Result<()> {
+    let exception_frame = memory.read(sp, 8 * 4)?;
+    let words = exception_frame
+        .chunks_exact(4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()) as u64)
+        .collect::<Vec<_>>();
+    for (index, id) in [
+        cortex_m::r(0),
+        cortex_m::r(1),
+        cortex_m::r(2),
+        cortex_m::r(3),
+        cortex_m::r(12),
+        cortex_m::LR,
+        cortex_m::PC,
+        cortex_m::XPSR,
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        registers.insert(id, words[index]);
+    }
+    sp += 8 * 4;
+
+    if extended_frame {
+        // s0-s15, FPSCR and a reserved word
+        sp += 18 * 4;
+    }
+    // The core aligned the stack to 8 bytes on exception entry.
+    if words[7] & (1 << 9) != 0 {
+        sp += 4;
+    }
+    registers.insert(cortex_m::SP, sp);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Target memory backed by a buffer at `base`.
+    pub(super) struct TestMemory {
+        pub base: u64,
+        pub data: Vec<u8>,
+    }
+
+    impl TestMemory {
+        pub fn new(base: u64, size: usize) -> Self {
+            Self {
+                base,
+                data: vec![0; size],
+            }
+        }
+
+        pub fn write_u32(&mut self, address: u64, value: u32) {
+            let offset = (address - self.base) as usize;
+            self.data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+
+        pub fn write_bytes(&mut self, address: u64, bytes: &[u8]) {
+            let offset = (address - self.base) as usize;
+            self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+        }
+    }
+
+    impl TargetMemory for TestMemory {
+        fn read(&mut self, address: u64, length: usize) -> anyhow::Result<Vec<u8>> {
+            let offset = address
+                .checked_sub(self.base)
+                .context("Address below the test memory")? as usize;
+            self.data
+                .get(offset..offset + length)
+                .map(<[u8]>::to_vec)
+                .context("Address above the test memory")
+        }
+    }
+
+    #[test]
+    fn demangle_legacy_paths() {
+        assert_eq!(
+            demangle_path("_ZN3app13__blinky_task4POOL17h0123456789abcdefE").unwrap(),
+            ["app", "__blinky_task", "POOL"]
+        );
+        assert_eq!(
+            demangle_path("_ZN3app4main28_$u7b$$u7b$closure$u7d$$u7d$17h0123456789abcdefE")
+                .unwrap(),
+            ["app", "main", "{{closure}}"]
+        );
+        assert!(demangle_path("pxCurrentTCB").is_none());
+    }
+
+    #[test]
+    fn unstack_extended_frame() {
+        let mut memory = TestMemory::new(0x2000_0000, 0x200);
+        let sp = 0x2000_0100;
+
+        // r4-r11 and EXC_RETURN of a thread that used the FPU.
+        for index in 0..8 {
+            memory.write_u32(sp + index * 4, 0x40 + index as u32);
+        }
+        memory.write_u32(sp + 32, 0xFFFF_FFED);
+        let frame = sp + 36 + 64;
+        for (index, value) in [0, 1, 2, 3, 12, 0x0800_1235, 0x0800_1000, 0x0100_0200]
+            .into_iter()
+            .enumerate()
+        {
+            memory.write_u32(frame + index as u64 * 4, value);
+        }
+
+        let mut software_frame = (4..12)
+            .map(|index| StackedWord::Register(cortex_m::r(index)))
+            .collect::<Vec<_>>();
+        software_frame.push(StackedWord::ExcReturn);
+
+        let registers = unstack_cortex_m(&mut memory, sp, &software_frame).unwrap();
+        assert_eq!(registers[&cortex_m::r(4)], 0x40);
+        assert_eq!(registers[&cortex_m::r(11)], 0x47);
+        assert_eq!(registers[&cortex_m::r(12)], 12);
+        assert_eq!(registers[&cortex_m::PC], 0x0800_1000);
+        // Exception frame, FPU state and the alignment word.
+        assert_eq!(registers[&cortex_m::SP], frame + 32 + 72 + 4);
+    }
+}
@@ -0,0 +1,78 @@
+//! Thread awareness for RTIC applications.
+//!
+//! RTIC tasks run on the stack of the interrupt that dispatches them, so they have no saved
+//! registers. The software tasks are found through the `__rtic_internal_{name}_EXEC` executors
+//! that RTIC generates for them, and a task is shown as running while the core executes its code.
+
+use super::{Rtos, RtosCore, RtosThread, Symbols, TargetMemory, TaskCode};
+
+const EXECUTOR_PREFIX: &str = "__rtic_internal_";
+const EXECUTOR_SUFFIX: &str = "_EXEC";
+
+struct Task {
+    name: String,
+    executor: u64,
+    code: TaskCode,
+}
+
+pub(crate) struct Rtic {
+    tasks: Vec<Task>,
+}
+
+impl Rtic {
+    pub fn new(symbols: &Symbols) -> anyhow::Result<Self> {
+        let mut tasks = Vec::new();
+        for (path, symbol) in symbols.paths() {
+            let Some(name) = path.last().and_then(|last| {
+                last.strip_prefix(EXECUTOR_PREFIX)?
+                    .strip_suffix(EXECUTOR_SUFFIX)
+            }) else {
+                continue;
+            };
+
+            // The task functions live in the module of the application.
+            let module = &path[..path.len() - 1];
+            let code = TaskCode::find(symbols, |path| {
+                path.starts_with(module) && path.iter().any(|component| component == name)
+            });
+
+            tasks.push(Task {
+                name: name.to_string(),
+                executor: symbol.address,
+                code,
+            });
+        }
+
+        anyhow::ensure!(!tasks.is_empty(), "The ELF file has no RTIC tasks");
+        tasks.sort_by_key(|task| task.executor);
+
+        Ok(Self { tasks })
+    }
+}
+
+impl Rtos for Rtic {
+    fn name(&self) -> &'static str {
+        "RTIC"
+    }
+
+    fn threads(
+        &self,
+        _memory: &mut dyn TargetMemory,
+        core: RtosCore,
+    ) -> anyhow::Result<Vec<RtosThread>> {
+        Ok(self
+            .tasks
+            .iter()
+            .map(|task| {
+                let running = task.code.contains(core.pc);
+                RtosThread {
+                    id: task.executor,
+                    name: task.name.clone(),
+                    state: if running { "Running" } else { "Idle" }.to_string(),
+                    running,
+                    registers: None,
+                }
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,224 @@
+//! Thread awareness for Zephyr, which needs `CONFIG_DEBUG_THREAD_INFO`.
+//!
+//! With this option Zephyr describes the layout of its thread structure in the
+//! `_kernel_thread_info_offsets` array.
+
+use super::{
+    Rtos, RtosCore, RtosThread, SavedRegisters, Symbols, TargetMemory, cortex_m,
+    unstack_exception_frame,
+};
+
+/// Indices into `_kernel_thread_info_offsets`.
+const OFFSET_K_CURR_THREAD: u64 = 1;
+const OFFSET_K_THREADS: u64 = 2;
+const OFFSET_T_NEXT_THREAD: u64 = 4;
+const OFFSET_T_STATE: u64 = 5;
+const OFFSET_T_PRIO: u64 = 7;
+const OFFSET_T_STACK_PTR: u64 = 8;
+const OFFSET_T_NAME: u64 = 9;
+const OFFSET_T_ARM_EXC_RETURN: u64 = 13;
+
+/// Marks offsets of fields that the kernel was built without.
+const UNIMPLEMENTED: u32 = u32::MAX;
+
+/// The maximum `CONFIG_THREAD_MAX_NAME_LEN`.
+const MAX_THREAD_NAME_LENGTH: usize = 32;
+
+/// Guards against corrupted lists.
+const MAX_THREADS: usize = 1024;
+
+/// The thread state bits of `thread_state`.
+const STATES: [(u8, &str); 5] = [
+    (1 << 1, "Pending"),
+    (1 << 2, "Prestart"),
+    (1 << 3, "Dead"),
+    (1 << 4, "Suspended"),
+    (1 << 5, "Aborting"),
+];
+
+pub(crate) struct Zephyr {
+    kernel: u64,
+    offsets: u64,
+    num_offsets: u64,
+}
+
+impl Zephyr {
+    pub fn new(symbols: &Symbols) -> anyhow::Result<Self> {
+        let offsets = symbols
+            .get("_kernel_thread_info_offsets")
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "The ELF file has no symbol `_kernel_thread_info_offsets`, enable CONFIG_DEBUG_THREAD_INFO"
+                )
+            })?;
+
+        Ok(Self {
+            kernel: symbols.address("_kernel")?,
+            offsets: offsets.address,
+            num_offsets: offsets.size / 4,
+        })
+    }
+
+    /// Reads the offset of a field, or `None` if the kernel has no such field.
+    fn offset(&self, memory: &mut dyn TargetMemory, index: u64) -> anyhow::Result<Option<u64>> {
+        if index >= self.num_offsets {
+            return Ok(None);
+        }
+        let offset = memory.read_u32(self.offsets + index * 4)?;
+        Ok((offset != UNIMPLEMENTED).then_some(offset as u64))
+    }
+
+    fn required_offset(&self, memory: &mut dyn TargetMemory, index: u64) -> anyhow::Result<u64> {
+        self.offset(memory, index)?
+            .ok_or_else(|| anyhow::anyhow!("Zephyr does not describe thread field {index}"))
+    }
+}
+
+impl Rtos for Zephyr {
+    fn name(&self) -> &'static str {
+        "Zephyr"
+    }
+
+    fn threads(
+        &self,
+        memory: &mut dyn TargetMemory,
+        core: RtosCore,
+    ) -> anyhow::Result<Vec<RtosThread>> {
+        let current_offset = self.required_offset(memory, OFFSET_K_CURR_THREAD)?;
+        let threads_offset = self.required_offset(memory, OFFSET_K_THREADS)?;
+        let next_offset = self.required_offset(memory, OFFSET_T_NEXT_THREAD)?;
+        let state_offset = self.required_offset(memory, OFFSET_T_STATE)?;
+        let priority_offset = self.offset(memory, OFFSET_T_PRIO)?;
+        let stack_pointer_offset = self.offset(memory, OFFSET_T_STACK_PTR)?;
+        let name_offset = self.offset(memory, OFFSET_T_NAME)?;
+        let exc_return_offset = self.offset(memory, OFFSET_T_ARM_EXC_RETURN)?;
+
+        let current = memory.read_u32(self.kernel + current_offset)? as u64;
+
+        let mut threads = Vec::new();
+        let mut thread = memory.read_u32(self.kernel + threads_offset)? as u64;
+        while thread != 0 {
+            anyhow::ensure!(
+                threads.len() < MAX_THREADS,
+                "The Zephyr thread list is corrupted"
+            );
+
+            let running = thread == current;
+            let name = match name_offset {
+                Some(offset) => memory.read_string(thread + offset, MAX_THREAD_NAME_LENGTH)?,
+                None => String::new(),
+            };
+            let name = if name.is_empty() {
+                format!("{thread:#010x}")
+            } else {
+                name
+            };
+
+            let state_bits = memory.read_u8(thread + state_offset)?;
+            let mut state = STATES
+                .iter()
+                .filter(|(bit, _)| state_bits & bit != 0)
+                .map(|(_, name)| *name)
+                .collect::<Vec<_>>()
+                .join(", ");
+            if running {
+                state = "Running".to_string();
+            } else if state.is_empty() {
+                state = "Ready".to_string();
+            }
+            if let Some(offset) = priority_offset {
+                // Priorities are signed, cooperative threads have negative priorities.
+                let priority = memory.read_u8(thread + offset)? as i8;
+                state.push_str(&format!(", priority {priority}"));
+            }
+
+            let registers = match stack_pointer_offset {
+                Some(offset) if !running && core.core_type.is_cortex_m() => {
+                    let exc_return_address = exc_return_offset.map(|offset| thread + offset);
+                    Some(unstack(memory, thread + offset, exc_return_address)?)
+                }
+                _ => None,
+            };
+
+            threads.push(RtosThread {
+                id: thread,
+                name,
+                state,
+                running,
+                registers,
+            });
+
+            thread = memory.read_u32(thread + next_offset)? as u64;
+        }
+
+        Ok(threads)
+    }
+}
+
+/// Unwinds a thread on Cortex-M, whose `callee_saved` structure holds `r4`-`r11` followed by
+/// the stack pointer `psp`, which points to the exception frame.
+///
+/// Kernels with `CONFIG_ARM_STORE_EXC_RETURN` save the lowest byte of `EXC_RETURN` at
+/// `exc_return_address`, which tells whether the exception frame holds the FPU state. `s16`-`s31`
+/// are saved in the thread structure, not on the stack.
+fn unstack(
+    memory: &mut dyn TargetMemory,
+    psp_address: u64,
+    exc_return_address: Option<u64>,
+) -> anyhow::Result<SavedRegisters> {
+    let psp = memory.read_u32(psp_address)? as u64;
+    let extended_frame = match exc_return_address {
+        Some(address) => memory.read_u8(address)? & (1 << 4) == 0,
+        None => false,
+    };
+
+    let mut registers = SavedRegisters::new();
+    unstack_exception_frame(memory, &mut registers, psp, extended_frame)?;
+
+    let callee_saved = psp_address - 8 * 4;
+    let words = memory.read(callee_saved, 8 * 4)?;
+    for (index, word) in words.chunks_exact(4).enumerate() {
+        let value = u32::from_le_bytes(word.try_into().unwrap());
+        registers.insert(cortex_m::r(4 + index as u16), value as u64);
+    }
+
+    Ok(registers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::TestMemory;
+    use super::*;
+
+    #[test]
+    fn unstack_fpu_frame() {
+        let mut memory = TestMemory::new(0x2000_0000, 0x400);
+        let thread = 0x2000_0000;
+        let psp_address = thread + 0x60;
+        let exc_return_address = thread + 0x80;
+        let psp = 0x2000_0200;
+
+        for index in 0..8 {
+            memory.write_u32(psp_address - 32 + index * 4, 0x40 + index as u32);
+        }
+        memory.write_u32(psp_address, psp as u32);
+        // The lowest byte of an EXC_RETURN with FPU state.
+        memory.write_bytes(exc_return_address, &[0xED]);
+        for (index, value) in [0, 1, 2, 3, 12, 0x0800_1235, 0x0800_1000, 0x0100_0000]
+            .into_iter()
+            .enumerate()
+        {
+            memory.write_u32(psp + index as u64 * 4, value);
+        }
+
+        let registers = unstack(&mut memory, psp_address, Some(exc_return_address)).unwrap();
+        assert_eq!(registers[&cortex_m::r(4)], 0x40);
+        assert_eq!(registers[&cortex_m::r(11)], 0x47);
+        assert_eq!(registers[&cortex_m::PC], 0x0800_1000);
+        // The exception frame, `s0`-`s15`, FPSCR and a reserved word.
+        assert_eq!(registers[&cortex_m::SP], psp + 32 + 72);
+
+        let registers = unstack(&mut memory, psp_address, None).unwrap();
+        assert_eq!(registers[&cortex_m::SP], psp + 32);
+    }
+}
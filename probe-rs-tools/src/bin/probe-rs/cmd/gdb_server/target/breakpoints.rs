@@ -0,0 +1,146 @@
+use super::{GdbErrorExt, RuntimeTarget};
+
+use gdbstub::{
+    arch::Arch,
+    target::ext::breakpoints::{
+        Breakpoints, HwBreakpoint, HwBreakpointOps, HwWatchpoint, HwWatchpointOps, SwBreakpoint,
+        SwBreakpointOps, WatchKind,
+    },
+};
+use probe_rs_rpc::core_ops::{WireWatchpoint, WireWatchpointKind};
+
+impl Breakpoints for RuntimeTarget {
+    fn support_sw_breakpoint(&mut self) -> Option<SwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_hw_breakpoint(&mut self) -> Option<HwBreakpointOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_hw_watchpoint(&mut self) -> Option<HwWatchpointOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl SwBreakpoint for RuntimeTarget {
+    fn add_sw_breakpoint(
+        &mut self,
+        addr: u64,
+        _kind: <Self::Arch as Arch>::BreakpointKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        for core_info in &self.cores {
+            let core = self.session.core(core_info.index);
+            let results = self
+                .block_on(core.set_sw_breakpoints(vec![addr]))
+                .into_target_result()?;
+
+            if let Some(Err(e)) = results.into_iter().next() {
+                tracing::warn!("Failed to set software breakpoint at {addr:#010x}: {e}");
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn remove_sw_breakpoint(
+        &mut self,
+        addr: u64,
+        _kind: <Self::Arch as Arch>::BreakpointKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        for core_info in &self.cores {
+            let core = self.session.core(core_info.index);
+            self.block_on(core.clear_sw_breakpoints(vec![addr]))
+                .into_target_result()?;
+        }
+
+        Ok(true)
+    }
+}
+
+impl HwBreakpoint for RuntimeTarget {
+    fn add_hw_breakpoint(
+        &mut self,
+        addr: u64,
+        _kind: <Self::Arch as Arch>::BreakpointKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        for core_info in &self.cores {
+            let core = self.session.core(core_info.index);
+            self.block_on(core.set_hw_breakpoint(addr))
+                .into_target_result()?;
+        }
+
+        Ok(true)
+    }
+
+    fn remove_hw_breakpoint(
+        &mut self,
+        addr: u64,
+        _kind: <Self::Arch as Arch>::BreakpointKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        for core_info in &self.cores {
+            let core = self.session.core(core_info.index);
+            self.block_on(core.clear_hw_breakpoints(vec![addr]))
+                .into_target_result()?;
+        }
+
+        Ok(true)
+    }
+}
+
+impl HwWatchpoint for RuntimeTarget {
+    fn add_hw_watchpoint(
+        &mut self,
+        addr: u64,
+        len: u64,
+        kind: WatchKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        let watchpoint = WireWatchpoint {
+            address: addr,
+            length: len,
+            kind: match kind {
+                WatchKind::Read => WireWatchpointKind::Read,
+                WatchKind::Write => WireWatchpointKind::Write,
+                WatchKind::ReadWrite => WireWatchpointKind::Access,
+            },
+        };
+
+        for (set_on, core_info) in self.cores.iter().enumerate() {
+            let core = self.session.core(core_info.index);
+            let results = self
+                .block_on(core.set_watchpoints(vec![watchpoint]))
+                .into_target_result()?;
+
+            // GDB falls back to software watchpoints if the hardware cannot watch the range.
+            if let Some(Err(e)) = results.into_iter().next() {
+                tracing::warn!("Failed to set watchpoint at {addr:#010x}: {e}");
+
+                // Remove the watchpoint from the cores it was already set on.
+                for core_info in &self.cores[..set_on] {
+                    let core = self.session.core(core_info.index);
+                    self.block_on(core.clear_watchpoints(vec![addr]))
+                        .into_target_result()?;
+                }
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn remove_hw_watchpoint(
+        &mut self,
+        addr: u64,
+        _len: u64,
+        _kind: WatchKind,
+    ) -> gdbstub::target::TargetResult<bool, Self> {
+        for core_info in &self.cores {
+            let core = self.session.core(core_info.index);
+            self.block_on(core.clear_watchpoints(vec![addr]))
+                .into_target_result()?;
+        }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use super::RuntimeTarget;
+use crate::cmd::gdb_server::target::utils::copy_range_to_buf;
+
+use gdbstub::common::Pid;
+use gdbstub::target::TargetResult;
+use gdbstub::target::ext::exec_file::ExecFile;
+use gdbstub::target::ext::host_io::{
+    FsKind, HostIo, HostIoClose, HostIoCloseOps, HostIoErrno, HostIoError, HostIoFstat,
+    HostIoFstatOps, HostIoOpen, HostIoOpenFlags, HostIoOpenMode, HostIoOpenOps, HostIoPread,
+    HostIoPreadOps, HostIoResult, HostIoSetfs, HostIoSetfsOps, HostIoStat,
+};
+
+// GDB reads the executable through `qXfer:exec-file` and `vFile` when it was started without
+// one, so the ELF file does not have to be copied to the machine GDB runs on. The stub is reachable
+// over the network, so only the ELF file can be opened, and only for reading.
+impl ExecFile for RuntimeTarget {
+    fn get_exec_file(
+        &self,
+        _pid: Option<Pid>,
+        offset: u64,
+        length: usize,
+        buf: &mut [u8],
+    ) -> TargetResult<usize, Self> {
+        let Some(elf) = &self.elf else {
+            return Ok(0);
+        };
+
+        let path = elf.to_string_lossy();
+        Ok(copy_range_to_buf(path.as_bytes(), offset, length, buf))
+    }
+}
+
+impl HostIo for RuntimeTarget {
+    fn support_open(&mut self) -> Option<HostIoOpenOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_close(&mut self) -> Option<HostIoCloseOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_pread(&mut self) -> Option<HostIoPreadOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_fstat(&mut self) -> Option<HostIoFstatOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_setfs(&mut self) -> Option<HostIoSetfsOps<'_, Self>> {
+        Some(self)
+    }
+}
+
+impl HostIoOpen for RuntimeTarget {
+    fn open(
+        &mut self,
+        filename: &[u8],
+        flags: HostIoOpenFlags,
+        _mode: HostIoOpenMode,
+    ) -> HostIoResult<u32, Self> {
+        let filename =
+            std::str::from_utf8(filename).map_err(|_| HostIoError::Errno(HostIoErrno::EINVAL))?;
+
+        let write_flags = HostIoOpenFlags::O_WRONLY
+            | HostIoOpenFlags::O_RDWR
+            | HostIoOpenFlags::O_APPEND
+            | HostIoOpenFlags::O_CREAT
+            | HostIoOpenFlags::O_TRUNC;
+        if flags.intersects(write_flags) || !self.is_exec_file(Path::new(filename)) {
+            tracing::warn!("GDB tried to open {filename}, which is not the ELF file");
+            return Err(HostIoError::Errno(HostIoErrno::EACCES));
+        }
+
+        let file = File::open(filename)?;
+        let fd = self.next_host_fd;
+        self.next_host_fd += 1;
+        self.host_files.insert(fd, file);
+
+        Ok(fd)
+    }
+}
+
+impl HostIoClose for RuntimeTarget {
+    fn close(&mut self, fd: u32) -> HostIoResult<(), Self> {
+        self.host_files
+            .remove(&fd)
+            .map(drop)
+            .ok_or(HostIoError::Errno(HostIoErrno::EBADF))
+    }
+}
+
+impl HostIoPread for RuntimeTarget {
+    fn pread(
+        &mut self,
+        fd: u32,
+        count: usize,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> HostIoResult<usize, Self> {
+        let file = self
+            .host_files
+            .get_mut(&fd)
+            .ok_or(HostIoError::Errno(HostIoErrno::EBADF))?;
+
+        let count = count.min(buf.len());
+        file.seek(SeekFrom::Start(offset))?;
+        let mut read = 0;
+        while read < count {
+            match file.read(&mut buf[read..count])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+
+        Ok(read)
+    }
+}
+
+impl HostIoFstat for RuntimeTarget {
+    fn fstat(&mut self, fd: u32) -> HostIoResult<HostIoStat, Self> {
+        let file = self
+            .host_files
+            .get(&fd)
+            .ok_or(HostIoError::Errno(HostIoErrno::EBADF))?;
+        let metadata = file.metadata()?;
+
+        let time = |time: std::io::Result<std::time::SystemTime>| {
+            time.ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs() as u32)
+                .unwrap_or(0)
+        };
+
+        Ok(HostIoStat {
+            st_dev: 0,
+            st_ino: 0,
+            st_mode: HostIoOpenMode::S_IFREG
+                | HostIoOpenMode::S_IRUSR
+                | HostIoOpenMode::S_IRGRP
+                | HostIoOpenMode::S_IROTH,
+            st_nlink: 1,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            st_size: metadata.len(),
+            st_blksize: 4096,
+            st_blocks: metadata.len().div_ceil(512),
+            st_atime: time(metadata.accessed()),
+            st_mtime: time(metadata.modified()),
+            st_ctime: time(metadata.modified()),
+        })
+    }
+}
+
+impl HostIoSetfs for RuntimeTarget {
+    fn setfs(&mut self, _fs: FsKind) -> HostIoResult<(), Self> {
+        // There is only the file system of the host.
+        Ok(())
+    }
+}
+
+impl RuntimeTarget {
+    fn is_exec_file(&self, path: &Path) -> bool {
+        let Some(elf) = &self.elf else {
+            return false;
+        };
+
+        match (dunce::canonicalize(path), dunce::canonicalize(elf)) {
+            (Ok(path), Ok(elf)) => path == elf,
+            _ => false,
+        }
+    }
+}
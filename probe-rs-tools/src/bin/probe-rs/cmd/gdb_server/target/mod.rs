@@ -0,0 +1,432 @@
+mod base;
+mod breakpoints;
+mod desc;
+mod flash;
+mod host_io;
+mod monitor;
+mod resume;
+mod semihosting;
+mod thread;
+mod traits;
+mod utils;
+
+use crate::cmd::gdb_server::arch::RuntimeArch;
+use crate::cmd::gdb_server::rtos::{Rtos, RtosThread};
+use crate::cmd::gdb_server::target::desc::TargetDescription;
+use probe_rs::CoreRegisters;
+use probe_rs::InstructionSet;
+use probe_rs_rpc::chip::MemoryRegion;
+use probe_rs_rpc::core_ops::WireBreakpointCause;
+use probe_rs_rpc::core_ops::WireCoreStatus;
+use probe_rs_rpc::core_ops::WireHaltReason;
+use probe_rs_rpc::core_ops::WireWatchpointKind;
+use probe_rs_rpc::info::WireFlashSector;
+use probe_rs_rpc::{FlashLoader, Key, RttClient};
+use probe_rs_rpc_client::{ClientError, CoreInterface, SessionInterface};
+use tokio::runtime::Handle;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::future::Future;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use gdbstub::common::{Signal, Tid};
+use gdbstub::conn::ConnectionExt;
+use gdbstub::stub::state_machine::{GdbStubStateMachine, GdbStubStateMachineInner, state};
+use gdbstub::stub::{GdbStub, MultiThreadStopReason};
+use gdbstub::target::Target;
+use gdbstub::target::ext::base::BaseOps;
+use gdbstub::target::ext::breakpoints::{BreakpointsOps, WatchKind};
+use gdbstub::target::ext::exec_file::ExecFileOps;
+use gdbstub::target::ext::flash::FlashOps;
+use gdbstub::target::ext::host_io::HostIoOps;
+use gdbstub::target::ext::memory_map::MemoryMapOps;
+use gdbstub::target::ext::monitor_cmd::MonitorCmdOps;
+use gdbstub::target::ext::target_description_xml_override::TargetDescriptionXmlOverrideOps;
+
+pub(crate) use traits::GdbErrorExt;
+
+use semihosting::SemihostingOutcome;
+
+use super::GdbSessionContext;
+
+/// Actions for resuming a core
+#[derive(Debug, Copy, Clone)]
+pub(crate) enum ResumeAction {
+    Unchanged,
+    Resume,
+    Step,
+}
+
+/// Cached facts for one core exposed by this stub.
+#[derive(Clone)]
+pub(crate) struct CoreCache {
+    pub index: usize,
+    pub name: String,
+    pub core_type: probe_rs::CoreType,
+    pub registers: &'static CoreRegisters,
+    pub instruction_set: InstructionSet,
+}
+
+/// The top level gdbstub target for a probe-rs RPC debug session
+pub(crate) struct RuntimeTarget {
+    session: SessionInterface,
+    handle: Handle,
+    cores: Vec<CoreCache>,
+    target_name: String,
+    memory_map: Vec<MemoryRegion>,
+    flash_sectors: Vec<WireFlashSector>,
+
+    listener: TcpListener,
+    gdb: Option<GdbStubStateMachine<'static, RuntimeTarget, TcpStream>>,
+    resume_action: (usize, ResumeAction),
+
+    target_desc: TargetDescription,
+    /// Server-side flash loader created for an in-progress GDB `load`.
+    flash_loader: Option<Key<FlashLoader>>,
+    /// True when GDB already erased sectors via `flash_erase` for this load.
+    flash_erased: bool,
+    memory_map_xml: Option<String>,
+
+    /// Thread awareness for the RTOS running on the single core of this stub.
+    rtos: Option<Arc<dyn Rtos>>,
+    /// The tasks of the RTOS, read when the core halted. Empty if they are not known.
+    threads: Vec<RtosThread>,
+
+    /// The ELF file of the application, which GDB can read with `vFile` requests.
+    elf: Option<PathBuf>,
+    /// Files GDB opened with `vFile:open`.
+    host_files: HashMap<u32, File>,
+    next_host_fd: u32,
+
+    /// Server-side RTT client started with `monitor rtt start`.
+    rtt_client: Option<Key<RttClient>>,
+}
+
+impl RuntimeTarget {
+    pub fn new(
+        session: SessionInterface,
+        handle: Handle,
+        context: &GdbSessionContext,
+        core_indices: Vec<usize>,
+        addrs: &[SocketAddr],
+    ) -> Result<Self, anyhow::Error> {
+        let listener = TcpListener::bind(addrs)?;
+        listener.set_nonblocking(true)?;
+
+        let cores = core_indices
+            .into_iter()
+            .map(|index| {
+                context
+                    .cores
+                    .iter()
+                    .find(|c| c.index == index)
+                    .cloned()
+                    .map(|c| CoreCache {
+                        index: c.index,
+                        name: c.name,
+                        core_type: c.core_type,
+                        registers: c.registers,
+                        instruction_set: c.instruction_set,
+                    })
+                    .ok_or_else(|| anyhow::anyhow!("Missing core metadata for core {index}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // GDB stops all threads when one of them stops, so the cores should halt together.
+        if cores.len() > 1 {
+            let indices = cores.iter().map(|core| core.index as u32).collect();
+            handle.block_on(session.set_halt_group(Some(indices)))?;
+        }
+
+        let rtos = match &context.rtos {
+            Some(_) if cores.len() > 1 => {
+                tracing::warn!(
+                    "RTOS thread awareness is only supported for single core GDB stubs, it is disabled for {} cores",
+                    cores.len()
+                );
+                None
+            }
+            rtos => rtos.clone(),
+        };
+
+        Ok(Self {
+            session,
+            handle,
+            cores,
+            target_name: context.target_name.clone(),
+            memory_map: context.memory_map.clone(),
+            flash_sectors: context.flash_sectors.clone(),
+            listener,
+            gdb: None,
+            resume_action: (0, ResumeAction::Unchanged),
+            target_desc: TargetDescription::default(),
+            flash_loader: None,
+            flash_erased: false,
+            memory_map_xml: None,
+            rtos,
+            threads: Vec::new(),
+            elf: context
+                .elf
+                .as_ref()
+                .map(|elf| dunce::canonicalize(elf).unwrap_or_else(|_| elf.clone())),
+            host_files: HashMap::new(),
+            next_host_fd: 0,
+            rtt_client: None,
+        })
+    }
+
+    pub(crate) fn block_on<F: Future>(&self, fut: F) -> F::Output {
+        self.handle.block_on(fut)
+    }
+
+    pub(crate) fn core(&self, index: usize) -> CoreInterface {
+        self.session.core(index)
+    }
+
+    pub fn process(&mut self) -> Result<Duration, anyhow::Error> {
+        if self.gdb.is_none() {
+            let stream = match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    tracing::info!("New connection from {addr:#?}");
+                    stream
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    return Ok(Duration::from_millis(10));
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            self.halt_all_cores()?;
+            self.load_target_desc()?;
+            self.memory_map_xml = Some(self.build_memory_map_xml()?);
+
+            let state_machine = GdbStub::new(stream)
+                .run_state_machine(self)
+                .map_err(|e| anyhow::anyhow!(e))?;
+
+            self.gdb = Some(state_machine);
+        }
+
+        let Some(gdb) = self.gdb.take() else {
+            return Ok(Duration::ZERO);
+        };
+
+        let mut wait_time = Duration::ZERO;
+
+        self.gdb = match gdb {
+            GdbStubStateMachine::Idle(state) => self.handle_idle(state, &mut wait_time)?,
+            GdbStubStateMachine::Running(state) => self.handle_running(state, &mut wait_time)?,
+            GdbStubStateMachine::CtrlCInterrupt(state) => self.handle_ctrl_c(state)?,
+            GdbStubStateMachine::Disconnected(state) => {
+                tracing::info!("GDB client disconnected: {:?}", state.get_reason());
+                None
+            }
+        };
+
+        Ok(wait_time)
+    }
+
+    fn halt_all_cores(&mut self) -> Result<(), ClientError> {
+        let cores = self.cores.iter().map(|core| core.index as u32).collect();
+        self.block_on(
+            self.session
+                .halt_cores(Some(cores), Duration::from_millis(100)),
+        )?;
+        self.update_threads();
+        Ok(())
+    }
+
+    /// Returns the core a GDB thread runs on.
+    pub(crate) fn thread_core(&self, tid: Tid) -> usize {
+        if self.threads.is_empty() {
+            tid.get() - 1
+        } else {
+            self.cores[0].index
+        }
+    }
+
+    /// Returns the RTOS task of a GDB thread.
+    pub(crate) fn rtos_thread(&self, tid: Tid) -> Option<&RtosThread> {
+        self.threads.get(tid.get() - 1)
+    }
+
+    /// Returns the GDB thread that runs on a core.
+    fn core_thread(&self, core_index: usize) -> Tid {
+        let index = self
+            .threads
+            .iter()
+            .position(|thread| thread.running)
+            .unwrap_or(core_index);
+        Tid::new(index + 1).unwrap()
+    }
+
+    fn handle_idle<'a>(
+        &mut self,
+        mut state: GdbStubStateMachineInner<'a, state::Idle<Self>, Self, TcpStream>,
+        wait_time: &mut Duration,
+    ) -> Result<Option<GdbStubStateMachine<'a, Self, TcpStream>>, anyhow::Error> {
+        let next_byte = {
+            let conn = state.borrow_conn();
+            read_if_available(conn)?
+        };
+
+        let next_state = if let Some(b) = next_byte {
+            state.incoming_data(self, b)?
+        } else {
+            *wait_time = Duration::from_millis(10);
+            state.into()
+        };
+
+        Ok(Some(next_state))
+    }
+
+    fn handle_running<'a>(
+        &mut self,
+        mut state: GdbStubStateMachineInner<'a, state::Running, Self, TcpStream>,
+        wait_time: &mut Duration,
+    ) -> Result<Option<GdbStubStateMachine<'a, Self, TcpStream>>, anyhow::Error> {
+        let next_byte = {
+            let conn = state.borrow_conn();
+            read_if_available(conn)?
+        };
+
+        if let Some(b) = next_byte {
+            return Ok(Some(state.incoming_data(self, b)?));
+        }
+
+        let cores = self.cores.iter().map(|core| core.index as u32).collect();
+        let statuses = self.block_on(self.session.cores_status(Some(cores)))?;
+
+        let halted = statuses
+            .statuses
+            .into_iter()
+            .find_map(|(index, status)| match status {
+                WireCoreStatus::Halted(reason) => Some((index as usize, reason)),
+                _ => None,
+            });
+
+        if let Some((index, WireHaltReason::Breakpoint(WireBreakpointCause::Semihosting(_)))) =
+            halted
+        {
+            match self.handle_semihosting(index, state.borrow_conn())? {
+                SemihostingOutcome::Resumed => return Ok(Some(state.into())),
+                SemihostingOutcome::Exited(code) => {
+                    self.halt_all_cores()?;
+                    let reason = MultiThreadStopReason::Exited(code);
+                    return Ok(Some(state.report_stop(self, reason)?));
+                }
+                SemihostingOutcome::Halted => {}
+            }
+        }
+
+        let next_state = if let Some((index, reason)) = halted {
+            self.halt_all_cores()?;
+
+            // The running thread is only known once the RTOS tasks were read.
+            let tid = self.core_thread(index);
+            let reason = match reason {
+                WireHaltReason::Breakpoint(
+                    WireBreakpointCause::Hardware | WireBreakpointCause::Unknown,
+                ) => MultiThreadStopReason::HwBreak(tid),
+                WireHaltReason::Step => MultiThreadStopReason::DoneStep,
+                WireHaltReason::Watchpoint(Some(watchpoint)) => MultiThreadStopReason::Watch {
+                    tid,
+                    kind: match watchpoint.kind {
+                        WireWatchpointKind::Read => WatchKind::Read,
+                        WireWatchpointKind::Write => WatchKind::Write,
+                        WireWatchpointKind::Access => WatchKind::ReadWrite,
+                    },
+                    addr: watchpoint.address,
+                },
+                WireHaltReason::Watchpoint(None) => MultiThreadStopReason::SignalWithThread {
+                    tid,
+                    signal: Signal::SIGTRAP,
+                },
+                WireHaltReason::Breakpoint(WireBreakpointCause::Semihosting(_)) => {
+                    MultiThreadStopReason::SignalWithThread {
+                        tid,
+                        signal: Signal::SIGTRAP,
+                    }
+                }
+                _ => MultiThreadStopReason::SignalWithThread {
+                    tid,
+                    signal: Signal::SIGINT,
+                },
+            };
+            state.report_stop(self, reason)?
+        } else {
+            *wait_time = Duration::from_millis(10);
+            state.into()
+        };
+
+        Ok(Some(next_state))
+    }
+
+    fn handle_ctrl_c<'a>(
+        &mut self,
+        state: GdbStubStateMachineInner<'a, state::CtrlCInterrupt, Self, TcpStream>,
+    ) -> Result<Option<GdbStubStateMachine<'a, Self, TcpStream>>, anyhow::Error> {
+        self.halt_all_cores()?;
+        let next_state =
+            state.interrupt_handled(self, Some(MultiThreadStopReason::Signal(Signal::SIGINT)))?;
+
+        Ok(Some(next_state))
+    }
+}
+
+impl Target for RuntimeTarget {
+    type Arch = RuntimeArch;
+    type Error = anyhow::Error;
+
+    fn base_ops(&mut self) -> BaseOps<'_, Self::Arch, Self::Error> {
+        BaseOps::MultiThread(self)
+    }
+
+    fn support_target_description_xml_override(
+        &mut self,
+    ) -> Option<TargetDescriptionXmlOverrideOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_breakpoints(&mut self) -> Option<BreakpointsOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_memory_map(&mut self) -> Option<MemoryMapOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_flash_operations(&mut self) -> Option<FlashOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_monitor_cmd(&mut self) -> Option<MonitorCmdOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_host_io(&mut self) -> Option<HostIoOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn support_exec_file(&mut self) -> Option<ExecFileOps<'_, Self>> {
+        Some(self)
+    }
+
+    fn guard_rail_implicit_sw_breakpoints(&self) -> bool {
+        true
+    }
+}
+
+fn read_if_available(conn: &mut TcpStream) -> Result<Option<u8>, anyhow::Error> {
+    match conn.peek() {
+        Ok(p) => match p {
+            Some(_) => conn.read().map(Some).map_err(|e| e.into()),
+            None => Ok(None),
+        },
+        Err(e) => Err(anyhow::Error::from(e)),
+    }
+}
@@ -0,0 +1,328 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::RuntimeTarget;
+use crate::util::cli;
+
+use gdbstub::target::ext::monitor_cmd::ConsoleOutput;
+use gdbstub::target::ext::monitor_cmd::MonitorCmd;
+use gdbstub::target::ext::monitor_cmd::outputln;
+use probe_rs_rpc::core_ops::WireVectorCatchCondition;
+use probe_rs_rpc::flash::{DownloadOptions, VerifyResult};
+use probe_rs_rpc::format::FormatOptions;
+use probe_rs_rpc::rtt_client::{RttDiscovery, ScanRegion};
+use probe_rs_rpc::rtt_config::RttChannelConfig;
+
+const HELP_TEXT: &str = r#"Supported Commands:
+
+    info - print session information
+    reset - reset target
+    reset halt - reset target and halt afterwards
+    halt [core] - halt a core, or all cores of this GDB stub
+    resume [core] - resume a core, or all cores of this GDB stub, without notifying GDB
+    erase - erase the whole flash memory
+    flash [file] - download a file, or the ELF file, to flash memory
+    verify [file] - compare the flash memory to a file, or the ELF file
+    vector_catch <hardfault|corereset|securefault|svc|hlt|all> - halt on a vector
+    rtt start - attach to the RTT control block of the target
+    rtt [channel] - print the data of an RTT up channel, or of all up channels
+    rtt stop - detach from the RTT control block
+    speed [kHz] - print or change the speed of the probe
+    swo start <clk> <baud> - capture ITM data over SWO, with the TPIU clock in Hz
+    swo - print the captured ITM packets
+    swo stop - stop capturing SWO data
+"#;
+
+impl MonitorCmd for RuntimeTarget {
+    fn handle_monitor_cmd(
+        &mut self,
+        cmd: &[u8],
+        mut out: ConsoleOutput<'_>,
+    ) -> Result<(), Self::Error> {
+        let cmd = String::from_utf8_lossy(cmd);
+        let args = cmd.split_whitespace().collect::<Vec<_>>();
+
+        let result = match args.as_slice() {
+            ["info"] => {
+                outputln!(out, "Target: {}", self.target_name);
+                for core in &self.cores {
+                    outputln!(
+                        out,
+                        "  core {}: {} ({:?})",
+                        core.index,
+                        core.name,
+                        core.core_type
+                    );
+                }
+                Ok(())
+            }
+            ["reset"] => {
+                outputln!(out, "Resetting target");
+                match self.block_on(self.session.core(0).reset()) {
+                    Ok(_) => outputln!(out, "Done"),
+                    Err(e) => outputln!(out, "Error while resetting target:\n\t{}", e),
+                }
+                Ok(())
+            }
+            ["reset", "halt"] => {
+                let timeout = Duration::from_secs(1);
+                outputln!(out, "Resetting and halting target");
+                match self.block_on(self.session.core(0).reset_and_halt(timeout)) {
+                    Ok(_) => outputln!(out, "Target halted"),
+                    Err(e) => outputln!(out, "Error while halting target:\n\t{}", e),
+                }
+                Ok(())
+            }
+            ["halt", core @ ..] => self.monitor_halt(core, &mut out),
+            ["resume", core @ ..] => self.monitor_resume(core, &mut out),
+            ["erase"] => self.monitor_erase(&mut out),
+            ["flash", file @ ..] => self.monitor_flash(file, &mut out),
+            ["verify", file @ ..] => self.monitor_verify(file, &mut out),
+            ["vector_catch", condition] => self.monitor_vector_catch(condition, &mut out),
+            ["rtt", "start"] => self.monitor_rtt_start(&mut out),
+            ["rtt", "stop"] => self.monitor_rtt_stop(&mut out),
+            ["rtt", channel @ ..] => self.monitor_rtt(channel, &mut out),
+            ["speed", speed @ ..] => self.monitor_speed(speed, &mut out),
+            ["swo", "start", clk, baud] => self.monitor_swo_start(clk, baud, &mut out),
+            ["swo", "stop"] => self.monitor_swo_stop(&mut out),
+            ["swo"] => self.monitor_swo(&mut out),
+            _ => {
+                outputln!(out, "{}", HELP_TEXT);
+                Ok(())
+            }
+        };
+
+        if let Err(e) = result {
+            outputln!(out, "Error: {:#}", e);
+        }
+
+        Ok(())
+    }
+}
+
+impl RuntimeTarget {
+    /// Returns the cores a command applies to: the given core, or all cores of this stub.
+    fn monitor_cores(&self, core: &[&str]) -> anyhow::Result<Vec<u32>> {
+        match core {
+            [] => Ok(self.cores.iter().map(|core| core.index as u32).collect()),
+            [core] => Ok(vec![core.parse()?]),
+            _ => anyhow::bail!("Expected at most one core"),
+        }
+    }
+
+    /// Returns the file a flash command applies to: the given file, or the ELF file.
+    fn monitor_file(&self, file: &[&str]) -> anyhow::Result<PathBuf> {
+        match file {
+            [] => self
+                .elf
+                .clone()
+                .ok_or_else(|| anyhow::anyhow!("No file was given, and the ELF file is unknown")),
+            [file] => Ok(PathBuf::from(file)),
+            _ => anyhow::bail!("Expected at most one file"),
+        }
+    }
+
+    fn monitor_halt(&mut self, core: &[&str], out: &mut ConsoleOutput<'_>) -> anyhow::Result<()> {
+        let cores = self.monitor_cores(core)?;
+        let status = self.block_on(
+            self.session
+                .halt_cores(Some(cores), Duration::from_millis(500)),
+        )?;
+        for (core, status) in status.statuses {
+            outputln!(out, "Core {}: {:?}", core, status);
+        }
+        self.update_threads();
+        Ok(())
+    }
+
+    fn monitor_resume(&mut self, core: &[&str], out: &mut ConsoleOutput<'_>) -> anyhow::Result<()> {
+        let cores = self.monitor_cores(core)?;
+        let status = self.block_on(self.session.resume_cores(Some(cores)))?;
+        for (core, status) in status.statuses {
+            outputln!(out, "Core {}: {:?}", core, status);
+        }
+        Ok(())
+    }
+
+    fn monitor_erase(&mut self, out: &mut ConsoleOutput<'_>) -> anyhow::Result<()> {
+        outputln!(out, "Erasing all flash memory");
+        self.block_on(self.session.erase_all(false, async |_| {}))?;
+        outputln!(out, "Done");
+        Ok(())
+    }
+
+    fn monitor_flash(&mut self, file: &[&str], out: &mut ConsoleOutput<'_>) -> anyhow::Result<()> {
+        let path = self.monitor_file(file)?;
+        outputln!(out, "Flashing {}", path.display());
+
+        let mut options = DownloadOptions::default();
+        options.sanitize();
+        self.block_on(async {
+            let loader = self
+                .session
+                .build_flash_loader(path, FormatOptions::default(), None, false)
+                .await?;
+            self.session
+                .flash(options, loader.loader, None, async |_| {})
+                .await
+        })?;
+
+        outputln!(out, "Done, use `monitor reset halt` to start the new image");
+        Ok(())
+    }
+
+    fn monitor_verify(&mut self, file: &[&str], out: &mut ConsoleOutput<'_>) -> anyhow::Result<()> {
+        let path = self.monitor_file(file)?;
+        let result = self.block_on(async {
+            let loader = self
+                .session
+                .build_flash_loader(path.clone(), FormatOptions::default(), None, false)
+                .await?;
+            self.session.verify(loader.loader, async |_| {}).await
+        })?;
+
+        match result {
+            VerifyResult::Ok => outputln!(out, "The flash memory matches {}", path.display()),
+            VerifyResult::Mismatch => {
+                outputln!(out, "The flash memory does not match {}", path.display())
+            }
+        }
+        Ok(())
+    }
+
+    fn monitor_vector_catch(
+        &mut self,
+        condition: &str,
+        out: &mut ConsoleOutput<'_>,
+    ) -> anyhow::Result<()> {
+        let condition = match condition {
+            "hardfault" => WireVectorCatchCondition::HardFault,
+            "corereset" => WireVectorCatchCondition::CoreReset,
+            "securefault" => WireVectorCatchCondition::SecureFault,
+            "svc" => WireVectorCatchCondition::Svc,
+            "hlt" => WireVectorCatchCondition::Hlt,
+            "all" => WireVectorCatchCondition::All,
+            other => anyhow::bail!("Unknown vector catch condition `{other}`"),
+        };
+
+        for core in &self.cores {
+            self.block_on(self.core(core.index).enable_vector_catch(condition))?;
+        }
+        outputln!(out, "Vector catch enabled");
+        Ok(())
+    }
+
+    fn monitor_rtt_start(&mut self, out: &mut ConsoleOutput<'_>) -> anyhow::Result<()> {
+        if let Some(rtt_client) = self.rtt_client.take() {
+            self.block_on(self.session.clean_up_rtt(rtt_client))?;
+        }
+
+        let symbol = match &self.elf {
+            Some(elf) => self.block_on(cli::parse_metadata(elf))?.0.rtt_symbol,
+            None => None,
+        };
+        let discovery = RttDiscovery {
+            symbol,
+            ..RttDiscovery::from_scan_region(ScanRegion::Ram)
+        };
+
+        let rtt_client = self
+            .block_on(self.session.create_rtt_client(
+                discovery,
+                vec![],
+                RttChannelConfig::default(),
+            ))?
+            .handle;
+        self.rtt_client = Some(rtt_client);
+        let channels = self.block_on(self.session.get_rtt_channels(rtt_client))?;
+
+        if channels.location.is_none() {
+            outputln!(out, "The RTT control block was not found yet");
+        }
+        for channel in &channels.up {
+            outputln!(out, "Up channel {}: {}", channel.number, channel.name);
+        }
+        Ok(())
+    }
+
+    fn monitor_rtt_stop(&mut self, out: &mut ConsoleOutput<'_>) -> anyhow::Result<()> {
+        let rtt_client = self
+            .rtt_client
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("RTT was not started"))?;
+        self.block_on(self.session.clean_up_rtt(rtt_client))?;
+        outputln!(out, "RTT stopped");
+        Ok(())
+    }
+
+    fn monitor_rtt(&mut self, channel: &[&str], out: &mut ConsoleOutput<'_>) -> anyhow::Result<()> {
+        let rtt_client = self
+            .rtt_client
+            .ok_or_else(|| anyhow::anyhow!("RTT was not started, use `monitor rtt start`"))?;
+
+        let results = self.block_on(async {
+            let channels = match channel {
+                [] => self
+                    .session
+                    .get_rtt_channels(rtt_client)
+                    .await?
+                    .up
+                    .iter()
+                    .map(|channel| channel.number)
+                    .collect(),
+                [channel] => vec![channel.parse()?],
+                _ => anyhow::bail!("Expected at most one channel"),
+            };
+            Ok(self.session.poll_rtt_up(rtt_client, channels).await?)
+        })?;
+
+        for result in results {
+            match result.result {
+                Ok(data) if data.is_empty() => {}
+                Ok(data) => out.write_raw(&data),
+                Err(e) => outputln!(out, "Error reading channel {}: {}", result.channel, e),
+            }
+        }
+        Ok(())
+    }
+
+    fn monitor_speed(&mut self, speed: &[&str], out: &mut ConsoleOutput<'_>) -> anyhow::Result<()> {
+        let speed_khz = match speed {
+            [] => None,
+            [speed] => Some(speed.parse()?),
+            _ => anyhow::bail!("Expected at most one speed"),
+        };
+
+        let speed = self.block_on(self.session.probe_speed(speed_khz))?;
+        outputln!(out, "Probe speed: {} kHz", speed);
+        Ok(())
+    }
+
+    fn monitor_swo_start(
+        &mut self,
+        clk: &str,
+        baud: &str,
+        out: &mut ConsoleOutput<'_>,
+    ) -> anyhow::Result<()> {
+        let core = self.cores[0].index as u32;
+        self.block_on(self.session.start_swo(core, clk.parse()?, baud.parse()?))?;
+        outputln!(out, "SWO capture started");
+        Ok(())
+    }
+
+    fn monitor_swo_stop(&mut self, out: &mut ConsoleOutput<'_>) -> anyhow::Result<()> {
+        let core = self.cores[0].index as u32;
+        self.block_on(self.session.stop_swo(core))?;
+        outputln!(out, "SWO capture stopped");
+        Ok(())
+    }
+
+    fn monitor_swo(&mut self, out: &mut ConsoleOutput<'_>) -> anyhow::Result<()> {
+        let data = self.block_on(self.session.read_swo())?;
+        let decoder = itm::Decoder::new(data.as_slice(), itm::DecoderOptions { ignore_eof: false });
+        for packet in decoder.singles() {
+            outputln!(out, "{:?}", packet);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use std::io::Write;
+use std::net::TcpStream;
+
+use super::RuntimeTarget;
+
+use probe_rs_rpc::core_ops::{
+    WireBreakpointCause, WireCoreStatus, WireHaltReason, WireSemihostingCommand,
+    WireSemihostingUiEvent,
+};
+
+/// The largest console output sent to GDB in one packet.
+const CONSOLE_OUTPUT_CHUNK: usize = 256;
+
+/// What happened to a core after its semihosting request was handled.
+pub(crate) enum SemihostingOutcome {
+    /// The request was handled and the core runs again.
+    Resumed,
+    /// The application exited with a status code.
+    Exited(u8),
+    /// The request could not be handled, and the core stays halted.
+    Halted,
+}
+
+impl RuntimeTarget {
+    /// Handles the semihosting request a core halted on.
+    ///
+    /// The server performs the request next to the target, like for the debug adapter. Console
+    /// and file output is forwarded to GDB as console output.
+    pub(crate) fn handle_semihosting(
+        &mut self,
+        core_index: usize,
+        conn: &mut TcpStream,
+    ) -> Result<SemihostingOutcome, anyhow::Error> {
+        let result = self.block_on(self.core(core_index).handle_semihosting())?;
+
+        for event in result.events {
+            match event {
+                WireSemihostingUiEvent::LogToConsole(message) => {
+                    write_console_output(conn, &format!("{message}\n"))?;
+                }
+                WireSemihostingUiEvent::RttOutput { data, .. } => {
+                    write_console_output(conn, &data)?;
+                }
+                WireSemihostingUiEvent::RttWindow { handle, path, .. } => {
+                    tracing::debug!("Semihosting opened {path} as handle {handle}");
+                }
+            }
+        }
+
+        Ok(match result.status {
+            WireCoreStatus::Halted(WireHaltReason::Breakpoint(
+                WireBreakpointCause::Semihosting(command),
+            )) => match command {
+                WireSemihostingCommand::ExitSuccess => SemihostingOutcome::Exited(0),
+                WireSemihostingCommand::ExitError(details) => {
+                    SemihostingOutcome::Exited(details.exit_status.unwrap_or(1) as u8)
+                }
+                command => {
+                    tracing::warn!("Unhandled semihosting request {command:?}");
+                    SemihostingOutcome::Halted
+                }
+            },
+            WireCoreStatus::Halted(_) => SemihostingOutcome::Halted,
+            _ => SemihostingOutcome::Resumed,
+        })
+    }
+}
+
+/// Sends console output to GDB with `O` packets, which GDB accepts while the target runs.
+fn write_console_output(conn: &mut impl Write, text: &str) -> std::io::Result<()> {
+    for chunk in text.as_bytes().chunks(CONSOLE_OUTPUT_CHUNK) {
+        let mut packet = String::from("O");
+        for byte in chunk {
+            packet.push_str(&format!("{byte:02x}"));
+        }
+        let checksum = packet.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+
+        write!(conn, "${packet}#{checksum:02x}")?;
+    }
+    conn.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn console_output_packets() {
+        let mut output = Vec::new();
+        write_console_output(&mut output, "Hi\n").unwrap();
+        assert_eq!(output, b"$O48690a#bb");
+    }
+}
@@ -0,0 +1,93 @@
+use crate::cmd::gdb_server::rtos::{Rtos, RtosCore, RtosThread, TargetMemory};
+use crate::cmd::gdb_server::target::utils::copy_to_buf;
+use crate::rpc::functions::core_ops::convert::{from_wire_register_value, to_wire_register_id};
+
+use super::RuntimeTarget;
+
+use gdbstub::target::ext::thread_extra_info::ThreadExtraInfo;
+
+impl ThreadExtraInfo for RuntimeTarget {
+    fn thread_extra_info(
+        &self,
+        tid: gdbstub::common::Tid,
+        buf: &mut [u8],
+    ) -> Result<usize, Self::Error> {
+        if let Some(thread) = self.rtos_thread(tid) {
+            return Ok(copy_to_buf(thread.description().as_bytes(), buf));
+        }
+
+        let name = self
+            .cores
+            .iter()
+            .find(|c| c.index + 1 == tid.get())
+            .map(|c| c.name.as_str())
+            .unwrap_or("unknown");
+
+        Ok(copy_to_buf(name.as_bytes(), buf))
+    }
+}
+
+/// Target memory read through a core, for the RTOS thread awareness.
+struct CoreMemory<'a> {
+    target: &'a RuntimeTarget,
+    core_index: usize,
+}
+
+impl TargetMemory for CoreMemory<'_> {
+    fn read(&mut self, address: u64, length: usize) -> anyhow::Result<Vec<u8>> {
+        let core = self.target.core(self.core_index);
+        let bytes = self.target.block_on(core.read_bytes(address, length))?;
+        anyhow::ensure!(
+            bytes.len() == length,
+            "Failed to read {length} bytes at {address:#010x}"
+        );
+        Ok(bytes)
+    }
+}
+
+impl RuntimeTarget {
+    /// Reads the tasks of the RTOS, after the core halted.
+    ///
+    /// Errors are logged, and GDB falls back to one thread per core.
+    pub(crate) fn update_threads(&mut self) {
+        self.threads.clear();
+        let Some(rtos) = self.rtos.clone() else {
+            return;
+        };
+
+        match self.read_threads(rtos.as_ref()) {
+            Ok(mut threads) => {
+                // Thread IDs stay the same while no tasks are created or deleted.
+                threads.sort_by_key(|thread| thread.id);
+                self.threads = threads;
+            }
+            Err(e) => tracing::warn!("Failed to read the {} tasks: {e:#}", rtos.name()),
+        }
+    }
+
+    fn read_threads(&self, rtos: &dyn Rtos) -> anyhow::Result<Vec<RtosThread>> {
+        let core_cache = &self.cores[0];
+        let pc = core_cache
+            .registers
+            .pc()
+            .ok_or_else(|| anyhow::anyhow!("Core has no program counter"))?;
+        let pc = self.block_on(
+            self.core(core_cache.index)
+                .read_core_reg(to_wire_register_id(pc.id())),
+        )?;
+
+        let core = RtosCore {
+            core_type: core_cache.core_type,
+            has_fpu: core_cache.registers.fpu_registers().is_some(),
+            pc: from_wire_register_value(pc)
+                .try_into()
+                .map_err(|e| anyhow::anyhow!("{e:?}"))?,
+        };
+
+        let mut memory = CoreMemory {
+            target: self,
+            core_index: core_cache.index,
+        };
+        rtos.threads(&mut memory, core)
+    }
+}
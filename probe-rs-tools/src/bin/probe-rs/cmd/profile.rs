@@ -1,5 +1,5 @@
 mod callstack;
-mod flat;
+pub(crate) mod flat;
 
 use probe_rs::config::Registry;
 use probe_rs::probe::list::Lister;
@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use crate::util::cli::{self, CliRttClient, FileMetadata, parse_metadata};
+use crate::util::rtt::recording::RttReplay;
+
+#[derive(clap::Parser)]
+pub struct Cmd {
+    /// The recording, created with `--rtt-record`.
+    recording: PathBuf,
+
+    /// The ELF file used to decode defmt frames. It does not have to be the ELF file the
+    /// recording was made with, as long as its defmt table matches.
+    #[clap(long)]
+    elf: Option<PathBuf>,
+
+    /// The format string to use when printing defmt encoded log messages.
+    ///
+    /// You can also use one of two presets: oneline (default) and full.
+    ///
+    /// See <https://defmt.ferrous-systems.com/custom-log-output>
+    #[clap(long)]
+    log_format: Option<String>,
+
+    /// File name to store formatted output at, see `probe-rs run --target-output-file`.
+    #[clap(long)]
+    target_output_file: Vec<String>,
+
+    /// RTT up channels to display. By default, all channels are displayed.
+    #[clap(long)]
+    rtt_up_channels: Vec<u32>,
+
+    /// Suppress filename and line number information
+    #[clap(long)]
+    no_location: bool,
+
+    /// Suppress timestamps
+    #[clap(long)]
+    no_timestamps: bool,
+
+    /// Replay the recording at the speed it was recorded, instead of as fast as possible.
+    #[clap(long)]
+    realtime: bool,
+}
+
+impl Cmd {
+    pub async fn run(self) -> anyhow::Result<()> {
+        let file_meta = match &self.elf {
+            Some(path) => parse_metadata(path).await?.0,
+            None => FileMetadata::default(),
+        };
+
+        let rtt_client = CliRttClient::offline(
+            file_meta.defmt_data,
+            self.log_format,
+            !self.no_timestamps,
+            !self.no_location,
+        );
+
+        cli::replay(
+            RttReplay::open(&self.recording)?,
+            rtt_client,
+            &self.target_output_file,
+            &self.rtt_up_channels,
+            self.realtime,
+        )
+        .await
+    }
+}
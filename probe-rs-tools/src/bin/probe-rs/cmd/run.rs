@@ -8,6 +8,8 @@ use probe_rs_rpc_client::RpcClient;
 
 use crate::util::cli::{self, parse_metadata, rtt_client};
 use crate::util::common_options::{BinaryDownloadOptions, ProbeOptions};
+use crate::util::coverage::CoverageOptions;
+use crate::util::parse_u32;
 use probe_rs_rpc::format::FormatOptions;
 use probe_rs_rpc::rtt_config::ChannelMode;
 
@@ -199,6 +201,12 @@ pub(crate) struct MonitoringOptions {
     #[clap(long, default_value = "0", help_heading = "LOG CONFIGURATION / RTT")]
     pub(crate) rtt_down_channel: u32,
 
+    /// Record the raw data of all RTT channels to this file.
+    ///
+    /// The recording can be decoded later, without a probe, with `probe-rs rtt-replay`.
+    #[clap(long, help_heading = "LOG CONFIGURATION / RTT")]
+    pub(crate) rtt_record: Option<PathBuf>,
+
     /// List RTT channels and exit.
     #[clap(
         long,
@@ -219,6 +227,30 @@ pub(crate) struct MonitoringOptions {
     )]
     pub(crate) stack_frame_limit: u32,
 
+    /// Record branches with the Micro Trace Buffer (MTB) and print the last N of them when the
+    /// firmware exits unexpectedly.
+    ///
+    /// The MTB writes the branches into the SRAM of the target, at `--mtb-buffer-offset`.
+    #[clap(
+        long,
+        requires = "mtb_buffer_offset",
+        help_heading = "LOG CONFIGURATION / STACK TRACE"
+    )]
+    pub(crate) mtb_branches: Option<u32>,
+
+    /// Offset of the MTB trace buffer from the SRAM base address of the MTB.
+    ///
+    /// The buffer is sized to hold `--mtb-branches` branches, rounded up to a power of two, and
+    /// the offset has to be a multiple of that size. The firmware must not use this memory, so
+    /// there is no default: the MTB overwrites whatever is stored there.
+    #[clap(
+        long,
+        requires = "mtb_branches",
+        value_parser = parse_u32,
+        help_heading = "LOG CONFIGURATION / STACK TRACE"
+    )]
+    pub(crate) mtb_buffer_offset: Option<u32>,
+
     /// Suppress filename and line number information
     #[clap(long, help_heading = "LOG CONFIGURATION")]
     pub(crate) no_location: bool,
@@ -236,6 +268,18 @@ pub(crate) struct MonitoringOptions {
     /// "^/(\d).(\d)$=/path$1/file$2.txt"`).
     #[arg(long, help_heading = "SEMIHOSTING CONFIGURATION")]
     pub semihosting_file: Vec<String>,
+
+    /// Host directory in which the target may run commands (`SYS_SYSTEM`) and create temporary
+    /// files (`SYS_TMPNAM`). The directory is created if it does not exist.
+    ///
+    /// This is not a security boundary: the commands run in a host shell (`sh -c` or `cmd /C`)
+    /// with the permissions of probe-rs, and only start in this directory. Setting it gives the
+    /// firmware full shell access to the host.
+    #[arg(long, help_heading = "SEMIHOSTING CONFIGURATION")]
+    pub semihosting_sandbox: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub(crate) coverage: CoverageOptions,
 }
 
 impl Cmd {
@@ -263,7 +307,7 @@ impl Cmd {
             &self.path,
             self.format_options,
             self.download_options,
-            Some(rtt_client.handle()),
+            rtt_client.handle(),
             None,
         )
         .await?;
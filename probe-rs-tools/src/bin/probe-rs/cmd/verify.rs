@@ -35,9 +35,14 @@ impl Cmd {
         } else {
             Some(CliProgressBars::new())
         };
+        let uploaded_manifest =
+            cli::upload_manifest_images(&session, &self.path, &self.format_options).await?;
         let loader = session
             .build_flash_loader(
-                self.path.to_path_buf(),
+                uploaded_manifest
+                    .as_deref()
+                    .unwrap_or(&self.path)
+                    .to_path_buf(),
                 self.format_options,
                 None,
                 self.read_flasher_rtt,
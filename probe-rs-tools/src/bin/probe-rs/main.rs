@@ -51,6 +51,10 @@ pub(crate) struct Config {
 
     /// A named set of `--key=value` pairs.
     pub presets: HashMap<String, ConfigPreset>,
+
+    /// Plugins to load at startup. A path is either a shared library, or a directory of
+    /// shared libraries.
+    pub plugins: Vec<PathBuf>,
 }
 
 #[derive(clap::Parser)]
@@ -115,6 +119,14 @@ struct Cli {
     /// take precedence over environment variables.
     #[arg(long, global = true, env = "PROBE_RS_CONFIG_PRESET")]
     preset: Option<String>,
+
+    /// Load a plugin from a shared library, or all shared libraries in a directory.
+    ///
+    /// Plugins have to be built with the same version and features of probe-rs and the same
+    /// Rust compiler as this binary. Requires the `dynamic-plugins` feature, which is enabled by
+    /// default.
+    #[arg(long = "plugin", global = true, value_name = "PATH")]
+    plugins: Vec<PathBuf>,
 }
 
 impl Cli {
@@ -138,9 +150,13 @@ impl Cli {
             Subcommand::Attach(cmd) => cmd.run(client, utc_offset).await,
             Subcommand::Verify(cmd) => cmd.run(client).await,
             Subcommand::Erase(cmd) => cmd.run(client).await,
+            Subcommand::RttReplay(cmd) => cmd.run().await,
+            Subcommand::DumpReplay(cmd) => cmd.run(),
             Subcommand::Trace(cmd) => cmd.run(&mut registry, &lister),
             Subcommand::Itm(cmd) => cmd.run(&mut registry, &lister),
+            Subcommand::Etm(cmd) => cmd.run(&mut registry, &lister),
             Subcommand::Chip(cmd) => cmd.run(client).await,
+            Subcommand::ConfigRegion(cmd) => cmd.run(&mut registry, &lister),
             Subcommand::Benchmark(cmd) => cmd.run(&mut registry, &lister),
             Subcommand::Profile(cmd) => cmd.run(&mut registry, &lister),
             Subcommand::Read(cmd) => cmd.run(client).await,
@@ -187,13 +203,22 @@ enum Subcommand {
     /// Attach to rtt logging
     #[clap(name = "attach")]
     Attach(cmd::attach::Cmd),
+    /// Decode an RTT recording without a probe
+    RttReplay(cmd::rtt_replay::Cmd),
+    /// Print the stack of every core in a core or session dump without a probe
+    DumpReplay(cmd::dump_replay::Cmd),
     /// Trace a memory location on the target
     #[clap(name = "trace")]
     Trace(cmd::trace::Cmd),
     /// Configure and monitor ITM trace packets from the target.
     #[clap(name = "itm")]
     Itm(cmd::itm::Cmd),
+    /// Record and print the instructions executed by a core using the ETM.
+    #[clap(name = "etm")]
+    Etm(cmd::etm::Cmd),
     Chip(cmd::chip::Cmd),
+    /// Read and program option bytes, OTP memory and fuses
+    ConfigRegion(cmd::config_region::Cmd),
     /// Measure the throughput of the selected debug probe
     Benchmark(cmd::benchmark::Cmd),
     /// Profile on-target runtime performance of target ELF program
@@ -359,6 +384,8 @@ async fn main() -> Result<()> {
         setup_logging(log_path.as_deref(), None)
     };
 
+    load_plugins(&config, &cli.plugins)?;
+
     let elf = cli.elf();
     let report_path = cli.report.clone();
 
@@ -568,6 +595,36 @@ fn compile_report(
     Ok(())
 }
 
+/// Loads the plugins listed in the configuration and the ones given on the command line.
+///
+/// A path is either a shared library, or a directory of shared libraries. Called after the logger
+/// is set up, so that the plugins can log during registration.
+#[cfg(feature = "dynamic-plugins")]
+pub(crate) fn load_plugins(config: &Config, paths: &[PathBuf]) -> anyhow::Result<()> {
+    for path in config.plugins.iter().chain(paths) {
+        if path.is_dir() {
+            // SAFETY: The plugins were selected by the user.
+            let loaded = unsafe { probe_rs::plugin::load_plugins_from_dir(path) }?;
+            tracing::debug!("Loaded {} plugins from {}", loaded.len(), path.display());
+        } else {
+            // SAFETY: The plugin was selected by the user.
+            unsafe { probe_rs::plugin::load_plugin(path) }?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "dynamic-plugins"))]
+pub(crate) fn load_plugins(config: &Config, paths: &[PathBuf]) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        config.plugins.is_empty() && paths.is_empty(),
+        "Loading plugins requires probe-rs to be built with the `dynamic-plugins` feature."
+    );
+
+    Ok(())
+}
+
 fn load_config() -> anyhow::Result<Config> {
     // Paths to search for the configuration file.
     // cwd
@@ -10,11 +10,12 @@ use crate::rpc::{
         breakpoints::{resolve_source_breakpoints, resolve_source_locations},
         chip::{chip_info, list_families, load_chip_family},
         core_ops::{
-            core_clear_hw_bps, core_dump, core_enable_vc, core_halt, core_handle_semihosting,
-            core_metadata, core_read_registers, core_run, core_set_hw_bps, core_status, core_step,
-            core_write_reg,
+            core_clear_hw_bps, core_clear_sw_bps, core_clear_watchpoints, core_dump,
+            core_enable_vc, core_halt, core_handle_semihosting, core_metadata, core_read_registers,
+            core_run, core_set_hw_bps, core_set_sw_bps, core_set_watchpoints, core_status,
+            core_step, core_write_reg,
         },
-        cores::{cores_status, halt_cores, resume_cores},
+        cores::{cores_status, dump_cores, halt_cores, resume_cores, set_halt_group},
         debug_vars::{
             clear_core_debug_state, evaluate as debug_evaluate, load_svd as debug_load_svd,
             scopes as debug_scopes, set_variable as debug_set_variable,
@@ -22,12 +23,13 @@ use crate::rpc::{
         },
         disassemble::disassemble as disassemble_handler,
         flash::{
-            boot, build, erase_all, erase_range, flash, load_region, new_flash_loader, verify,
+            boot, build, erase_all, erase_range, flash, inject_data, load_region, new_flash_loader,
+            verify,
         },
         info::{target_info, target_metadata},
         memory::{read_bytes, read_memory, write_memory},
         monitor::monitor,
-        probe::{attach, list_probes, select_probe},
+        probe::{attach, list_probes, probe_speed, select_probe},
         reset::{reset, reset_and_halt},
         rtt_client::{
             clean_up_rtt, clear_rtt_control_block, create_rtt_client, get_rtt_channels,
@@ -35,6 +37,7 @@ use crate::rpc::{
         },
         stack_trace::{load_debug_info, take_rich_stack_trace, take_stack_trace},
         test::{list_tests, run_test, test_kickoff},
+        trace::{branch_trace_read, swo_read, swo_start, swo_stop},
     },
 };
 use probe_rs_rpc::transport::memory::{WireRx, WireTx};
@@ -74,6 +77,7 @@ pub mod reset;
 pub mod rtt_client;
 pub mod stack_trace;
 pub mod test;
+pub mod trace;
 
 #[derive(Clone)]
 pub struct RpcSpawnContext {
@@ -474,10 +478,13 @@ postcard_rpc::define_dispatch! {
         | ListProbesEndpoint        | blocking  | list_probes       |
         | SelectProbeEndpoint       | async     | select_probe      |
         | AttachEndpoint            | spawn     | attach            |
+        | ProbeSpeedEndpoint        | async     | probe_speed       |
 
         | HaltCoresEndpoint                | async | halt_cores                 |
         | ResumeCoresEndpoint              | async | resume_cores               |
         | CoresStatusEndpoint              | async | cores_status               |
+        | DumpCoresEndpoint                | async | dump_cores                 |
+        | SetHaltGroupEndpoint             | async | set_halt_group             |
         | CreateRttClientEndpoint          | async | create_rtt_client          |
         | TakeStackTraceEndpoint           | async | take_stack_trace           |
         | TakeRichStackTraceEndpoint       | async | take_rich_stack_trace      |
@@ -494,6 +501,7 @@ postcard_rpc::define_dispatch! {
         | NewFlashLoaderEndpoint           | async | new_flash_loader           |
         | BuildEndpoint                    | async | build                      |
         | LoadRegionEndpoint               | async | load_region                |
+        | InjectDataEndpoint               | async | inject_data                |
         | FlashEndpoint                    | async | flash                      |
         | EraseAllEndpoint                 | async | erase_all                  |
         | EraseRangeEndpoint               | async | erase_range                |
@@ -510,6 +518,11 @@ postcard_rpc::define_dispatch! {
         | RunTestEndpoint                  | spawn | run_test                   |
         | TestKickoffEndpoint              | async | test_kickoff               |
 
+        | SwoStartEndpoint                 | async | swo_start                  |
+        | SwoReadEndpoint                  | async | swo_read                   |
+        | SwoStopEndpoint                  | async | swo_stop                   |
+        | BranchTraceReadEndpoint          | async | branch_trace_read          |
+
         | CreateTempFileEndpoint           | async | create_temp_file           |
         | TempFileDataEndpoint             | async | append_temp_file           |
 
@@ -529,6 +542,10 @@ postcard_rpc::define_dispatch! {
         | CoreWriteRegEndpoint             | async | core_write_reg             |
         | CoreSetHwBpsEndpoint             | async | core_set_hw_bps            |
         | CoreClearHwBpsEndpoint           | async | core_clear_hw_bps          |
+        | CoreSetSwBpsEndpoint             | async | core_set_sw_bps            |
+        | CoreClearSwBpsEndpoint           | async | core_clear_sw_bps          |
+        | CoreSetWatchpointsEndpoint       | async | core_set_watchpoints       |
+        | CoreClearWatchpointsEndpoint     | async | core_clear_watchpoints     |
         | CoreEnableVcEndpoint             | async | core_enable_vc             |
         | CoreMetadataEndpoint             | async | core_metadata              |
         | CoreReadRegistersEndpoint        | async | core_read_registers        |
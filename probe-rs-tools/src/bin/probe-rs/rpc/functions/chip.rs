@@ -48,8 +48,8 @@ pub async fn load_chip_family(
 pub(crate) mod convert {
     use probe_rs::Target;
     use probe_rs_rpc::chip::{
-        Chip, ChipData, ChipFamily, Core, CoreType, GenericRegion, JEP106Code, MemoryAccess,
-        MemoryRegion, NvmRegion, RamRegion,
+        Chip, ChipData, ChipFamily, ConfigRegion, ConfigRegionKind, Core, CoreType, GenericRegion,
+        JEP106Code, MemoryAccess, MemoryRegion, NvmRegion, RamRegion,
     };
 
     pub(crate) fn to_wire_jep106_code(value: jep106::JEP106Code) -> JEP106Code {
@@ -120,6 +120,9 @@ pub(crate) mod convert {
                 MemoryRegion::Generic(to_wire_generic_region(gr))
             }
             probe_rs_target::MemoryRegion::Nvm(nr) => MemoryRegion::Nvm(to_wire_nvm_region(nr)),
+            probe_rs_target::MemoryRegion::Config(cr) => {
+                MemoryRegion::Config(to_wire_config_region(cr))
+            }
         }
     }
 
@@ -151,6 +154,19 @@ pub(crate) mod convert {
         }
     }
 
+    pub(crate) fn to_wire_config_region(value: probe_rs_target::ConfigRegion) -> ConfigRegion {
+        ConfigRegion {
+            name: value.name,
+            kind: match value.kind {
+                probe_rs_target::ConfigRegionKind::OptionBytes => ConfigRegionKind::OptionBytes,
+                probe_rs_target::ConfigRegionKind::Otp => ConfigRegionKind::Otp,
+                probe_rs_target::ConfigRegionKind::Fuses => ConfigRegionKind::Fuses,
+            },
+            range: (value.range.start, value.range.end),
+            cores: value.cores,
+        }
+    }
+
     pub(crate) fn to_wire_memory_access(value: probe_rs_target::MemoryAccess) -> MemoryAccess {
         MemoryAccess {
             read: value.read,
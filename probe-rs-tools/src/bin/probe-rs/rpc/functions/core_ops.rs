@@ -6,12 +6,13 @@ use std::time::Duration;
 use postcard_rpc::header::VarHeader;
 use probe_rs_rpc::core_ops::{
     CoreAccessRequest, CoreBreakpointsRequest, CoreDumpRequest, CoreHaltRequest,
-    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWriteRegRequest,
+    CoreReadRegistersRequest, CoreVectorCatchRequest, CoreWatchpointsRequest, CoreWriteRegRequest,
     HandleSemihostingRequest, HandleSemihostingResponse, HandleSemihostingResult, StepRequest,
     StepResponse, StepResult, WireBreakpointCause, WireCoreDump, WireCoreInformation,
     WireCoreMetadata, WireCoreStatus, WireCoreType, WireExitErrorDetails, WireHaltReason,
     WireInstructionSet, WireRegisterId, WireRegisterReadResult, WireRegisterValue,
     WireSemihostingCommand, WireSemihostingUiEvent, WireSteppingMode, WireVectorCatchCondition,
+    WireWatchpoint, WireWatchpointKind,
 };
 use probe_rs_rpc::rtt_config::DataFormat;
 
@@ -170,6 +171,49 @@ pub async fn core_set_hw_bps(
     Ok(results)
 }
 
+/// Set a batch of software breakpoints.
+///
+/// Like [`core_set_hw_bps`], per-address failures are reported in place. Breakpoints in flash
+/// are only set if the session was attached with the permission to reprogram the flash.
+pub async fn core_set_sw_bps(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreBreakpointsRequest,
+) -> RpcResult<Vec<Result<(), RpcError>>> {
+    let mut session = ctx.session(request.sessid).await;
+    let results = request
+        .addresses
+        .iter()
+        .map(|address| {
+            session
+                .set_sw_breakpoint(request.core as usize, *address)
+                .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)
+        })
+        .collect::<Vec<_>>();
+    Ok(results)
+}
+
+pub async fn core_clear_sw_bps(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreBreakpointsRequest,
+) -> NoResponse {
+    let mut session = ctx.session(request.sessid).await;
+    for address in request.addresses {
+        probe_rs_try!(
+            session
+                .clear_sw_breakpoint(request.core as usize, address)
+                .or_else(|e| match e {
+                    probe_rs::Error::BreakpointOperation(probe_rs::BreakpointError::NotFound(
+                        _,
+                    )) => Ok(()),
+                    e => Err(e),
+                })
+        );
+    }
+    Ok(())
+}
+
 pub async fn core_clear_hw_bps(
     ctx: &mut RpcContext,
     _header: VarHeader,
@@ -188,6 +232,46 @@ pub async fn core_clear_hw_bps(
     Ok(())
 }
 
+/// Set a batch of hardware watchpoints.
+///
+/// Like [`core_set_hw_bps`], per-watchpoint failures are reported in place,
+/// so running out of comparators only fails the watchpoints that did not fit.
+pub async fn core_set_watchpoints(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreWatchpointsRequest,
+) -> RpcResult<Vec<Result<(), RpcError>>> {
+    let results = with_core!(ctx, request.sessid, request.core, |core| {
+        request
+            .watchpoints
+            .iter()
+            .map(|watchpoint| {
+                core.set_hw_watchpoint(convert::from_wire_watchpoint(*watchpoint))
+                    .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)
+            })
+            .collect::<Vec<_>>()
+    });
+    Ok(results)
+}
+
+pub async fn core_clear_watchpoints(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoreBreakpointsRequest,
+) -> NoResponse {
+    with_core!(ctx, request.sessid, request.core, |core| {
+        for address in request.addresses {
+            probe_rs_try!(core.clear_hw_watchpoint(address).or_else(|e| match e {
+                probe_rs::Error::BreakpointOperation(
+                    probe_rs::BreakpointError::WatchpointNotFound(_),
+                ) => Ok(()),
+                e => Err(e),
+            }));
+        }
+    });
+    Ok(())
+}
+
 pub async fn core_enable_vc(
     ctx: &mut RpcContext,
     _header: VarHeader,
@@ -321,9 +405,8 @@ pub async fn core_handle_semihosting(
         });
     };
 
-    let Some(state) = guard.get_mut(&request.sessid) else {
-        Err("No debug state for session")?
-    };
+    // GDB sessions have no debug state until their first semihosting request.
+    let state = guard.entry(request.sessid).or_default();
     let sh = state.semihosting_state(request.core as usize);
 
     let mut events = Vec::new();
@@ -442,6 +525,11 @@ fn handle_semihosting_impl(
             )));
         }
 
+        SemihostingCommand::Unknown(details) if details.has_registered_handler() => {
+            tracing::debug!("Semihosting request: plugin operation {details:?}");
+            details.run_registered_handler(core)?;
+        }
+
         unhandled => {
             tracing::warn!("Unhandled semihosting command: {:?}", unhandled);
             return Ok(CoreStatus::Halted(HaltReason::Breakpoint(
@@ -459,10 +547,11 @@ pub(crate) mod convert {
         WireBreakpointCause, WireCoreInformation, WireCoreStatus, WireCoreType,
         WireExitErrorDetails, WireHaltReason, WireInstructionSet, WireRegisterId,
         WireRegisterValue, WireSemihostingCommand, WireSteppingMode, WireVectorCatchCondition,
+        WireWatchpoint, WireWatchpointKind,
     };
     use probe_rs::{
         CoreInformation, CoreStatus, HaltReason, InstructionSet, RegisterId, RegisterValue,
-        VectorCatchCondition,
+        VectorCatchCondition, Watchpoint, WatchpointKind,
         semihosting::{ExitErrorDetails, SemihostingCommand, UnknownCommandDetails},
     };
     use probe_rs_debug::SteppingMode;
@@ -553,7 +642,9 @@ pub(crate) mod convert {
                 }
             }),
             HaltReason::Exception => WireHaltReason::Exception,
-            HaltReason::Watchpoint => WireHaltReason::Watchpoint,
+            HaltReason::Watchpoint(watchpoint) => {
+                WireHaltReason::Watchpoint(watchpoint.map(to_wire_watchpoint))
+            }
             HaltReason::Step => WireHaltReason::Step,
             HaltReason::Request => WireHaltReason::Request,
             HaltReason::External => WireHaltReason::External,
@@ -608,7 +699,9 @@ pub(crate) mod convert {
                 HaltReason::Breakpoint(from_wire_breakpoint_cause(cause))
             }
             WireHaltReason::Exception => HaltReason::Exception,
-            WireHaltReason::Watchpoint => HaltReason::Watchpoint,
+            WireHaltReason::Watchpoint(watchpoint) => {
+                HaltReason::Watchpoint(watchpoint.map(from_wire_watchpoint))
+            }
             WireHaltReason::Step => HaltReason::Step,
             WireHaltReason::Request => HaltReason::Request,
             WireHaltReason::External => HaltReason::External,
@@ -652,6 +745,30 @@ pub(crate) mod convert {
         }
     }
 
+    pub(crate) fn to_wire_watchpoint(value: Watchpoint) -> WireWatchpoint {
+        WireWatchpoint {
+            address: value.address,
+            length: value.length,
+            kind: match value.kind {
+                WatchpointKind::Read => WireWatchpointKind::Read,
+                WatchpointKind::Write => WireWatchpointKind::Write,
+                WatchpointKind::Access => WireWatchpointKind::Access,
+            },
+        }
+    }
+
+    pub(crate) fn from_wire_watchpoint(value: WireWatchpoint) -> Watchpoint {
+        Watchpoint {
+            address: value.address,
+            length: value.length,
+            kind: match value.kind {
+                WireWatchpointKind::Read => WatchpointKind::Read,
+                WireWatchpointKind::Write => WatchpointKind::Write,
+                WireWatchpointKind::Access => WatchpointKind::Access,
+            },
+        }
+    }
+
     pub(crate) fn to_wire_instruction_set(value: InstructionSet) -> WireInstructionSet {
         match value {
             InstructionSet::Thumb2 => WireInstructionSet::Thumb2,
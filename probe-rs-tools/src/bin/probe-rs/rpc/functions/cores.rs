@@ -1,8 +1,10 @@
-use std::time::Duration;
-
 use postcard_rpc::header::VarHeader;
-use probe_rs::Error;
-use probe_rs_rpc::cores::{CoresRequest, CoresStatusMap, CoresStatusResponse, HaltCoresRequest};
+use probe_rs::{Error, SessionDump};
+use probe_rs_rpc::NoResponse;
+use probe_rs_rpc::cores::{
+    CoresRequest, CoresStatusMap, CoresStatusResponse, DumpCoresRequest, DumpCoresResponse,
+    HaltCoresRequest,
+};
 
 use crate::rpc::functions::RpcContext;
 use crate::rpc::functions::core_ops::convert::to_wire_core_status;
@@ -14,17 +16,12 @@ pub async fn halt_cores(
 ) -> CoresStatusResponse {
     let mut session = ctx.session(request.sessid).await;
     let core_indices = resolve_core_indices(&session, request.cores.as_deref())?;
-    let mut statuses = Vec::with_capacity(core_indices.len());
 
-    for core_index in core_indices {
-        match operate_halt(&mut session, core_index, request.timeout) {
-            Ok(status) => statuses.push((core_index as u32, to_wire_core_status(status))),
-            Err(Error::CoreDisabled(_)) => {}
-            Err(error) => return Err(crate::rpc::functions::convert::rpc_error_probe_rs(error)),
-        }
-    }
+    session
+        .halt_cores(&core_indices, request.timeout)
+        .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)?;
 
-    Ok(CoresStatusMap { statuses })
+    cores_status_map(&mut session, core_indices)
 }
 
 pub async fn resume_cores(
@@ -34,17 +31,26 @@ pub async fn resume_cores(
 ) -> CoresStatusResponse {
     let mut session = ctx.session(request.sessid).await;
     let core_indices = resolve_core_indices(&session, request.cores.as_deref())?;
-    let mut statuses = Vec::with_capacity(core_indices.len());
 
-    for core_index in core_indices {
-        match operate_resume(&mut session, core_index) {
-            Ok(status) => statuses.push((core_index as u32, to_wire_core_status(status))),
-            Err(Error::CoreDisabled(_)) => {}
-            Err(error) => return Err(crate::rpc::functions::convert::rpc_error_probe_rs(error)),
-        }
-    }
+    session
+        .resume_cores(&core_indices)
+        .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)?;
 
-    Ok(CoresStatusMap { statuses })
+    cores_status_map(&mut session, core_indices)
+}
+
+/// Make the selected cores halt and resume together.
+pub async fn set_halt_group(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: CoresRequest,
+) -> NoResponse {
+    let mut session = ctx.session(request.sessid).await;
+    let core_indices = resolve_core_indices(&session, request.cores.as_deref())?;
+
+    session
+        .set_halt_group(&core_indices)
+        .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)
 }
 
 pub async fn cores_status(
@@ -54,17 +60,33 @@ pub async fn cores_status(
 ) -> CoresStatusResponse {
     let mut session = ctx.session(request.sessid).await;
     let core_indices = resolve_core_indices(&session, request.cores.as_deref())?;
-    let mut statuses = Vec::with_capacity(core_indices.len());
 
-    for core_index in core_indices {
-        match operate_status(&mut session, core_index) {
-            Ok(status) => statuses.push((core_index as u32, to_wire_core_status(status))),
-            Err(Error::CoreDisabled(_)) => {}
-            Err(error) => return Err(crate::rpc::functions::convert::rpc_error_probe_rs(error)),
+    cores_status_map(&mut session, core_indices)
+}
+
+/// Dump every core of the session, including the RAM regions of each core and the register
+/// blocks of the requested SVD peripherals.
+pub async fn dump_cores(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: DumpCoresRequest,
+) -> DumpCoresResponse {
+    let peripheral_ranges = match request.svd_path.as_deref() {
+        Some(path) if !request.peripherals.is_empty() => {
+            crate::rpc::svd::peripheral_ranges(std::path::Path::new(path), &request.peripherals)
+                .map_err(crate::rpc::functions::convert::rpc_error_debug)?
         }
-    }
+        None if !request.peripherals.is_empty() => {
+            return Err("Dumping peripherals requires an SVD file.".into());
+        }
+        _ => Vec::new(),
+    };
 
-    Ok(CoresStatusMap { statuses })
+    let mut session = ctx.session(request.sessid).await;
+    let dump = SessionDump::dump_session(&mut session, &peripheral_ranges)
+        .map_err(crate::rpc::functions::convert::rpc_error_probe_rs)?;
+
+    crate::rpc::functions::convert::lift(dump.to_bytes(probe_rs::DumpCompression::None))
 }
 
 fn resolve_core_indices(
@@ -77,32 +99,19 @@ fn resolve_core_indices(
     }
 }
 
-fn operate_halt(
+fn cores_status_map(
     session: &mut probe_rs::Session,
-    core_index: usize,
-    timeout: Duration,
-) -> Result<probe_rs::CoreStatus, Error> {
-    let mut core = session.core(core_index)?;
-    if !core.core_halted()? {
-        core.halt(timeout)?;
-    }
-    core.status()
-}
+    core_indices: Vec<usize>,
+) -> CoresStatusResponse {
+    let mut statuses = Vec::with_capacity(core_indices.len());
 
-fn operate_resume(
-    session: &mut probe_rs::Session,
-    core_index: usize,
-) -> Result<probe_rs::CoreStatus, Error> {
-    let mut core = session.core(core_index)?;
-    if core.core_halted()? {
-        core.run()?;
+    for core_index in core_indices {
+        match session.core(core_index).and_then(|mut core| core.status()) {
+            Ok(status) => statuses.push((core_index as u32, to_wire_core_status(status))),
+            Err(Error::CoreDisabled(_)) => {}
+            Err(error) => return Err(crate::rpc::functions::convert::rpc_error_probe_rs(error)),
+        }
     }
-    core.status()
-}
 
-fn operate_status(
-    session: &mut probe_rs::Session,
-    core_index: usize,
-) -> Result<probe_rs::CoreStatus, Error> {
-    session.core(core_index)?.status()
+    Ok(CoresStatusMap { statuses })
 }
@@ -150,6 +150,7 @@ pub async fn variables(
                 name: register.get_register_name(),
                 evaluate_name: Some(register.get_register_name()),
                 memory_reference: None,
+                byte_size: None,
                 indexed_variables: None,
                 named_variables: None,
                 type_: Some(format!("{}", VariableName::RegistersRoot)),
@@ -172,6 +173,7 @@ pub async fn variables(
                     name: variable.name().to_string(),
                     evaluate_name: None,
                     memory_reference: variable.memory_reference(),
+                    byte_size: None,
                     indexed_variables: Some(0),
                     named_variables: Some(child_count),
                     type_: variable.type_name(),
@@ -247,6 +249,7 @@ pub async fn variables(
                 name: variable.name.to_string(),
                 evaluate_name: None,
                 memory_reference: Some(variable.memory_location.to_string()),
+                byte_size: variable.byte_size,
                 indexed_variables: Some(indexed_cnt),
                 named_variables: Some(named_cnt),
                 type_: Some(variable.type_name()),
@@ -7,8 +7,9 @@ use probe_rs::{
 };
 use probe_rs_rpc::flash::{
     BootInfo, BootRequest, BuildRequest, BuildResponse, BuildResult, EraseAllRequest,
-    EraseRangeRequest, FlashRequest, LoadRegionRequest, NewFlashLoaderRequest,
-    NewFlashLoaderResponse, Operation, ProgressEvent, VerifyRequest, VerifyResponse, VerifyResult,
+    EraseRangeRequest, FlashRequest, InjectDataRequest, InjectedData, InjectionLocation,
+    LoadRegionRequest, NewFlashLoaderRequest, NewFlashLoaderResponse, Operation, ProgressEvent,
+    VerifyRequest, VerifyResponse, VerifyResult,
 };
 use tokio::sync::mpsc::Sender;
 
@@ -25,7 +26,8 @@ fn flash_request_download_options(request: &FlashRequest) -> flashing::DownloadO
     options.keep_unwritten_bytes = request.options.keep_unwritten_bytes;
     options.do_chip_erase = request.options.do_chip_erase;
     options.skip_erase = request.options.skip_erase;
-    options.preverify = false;
+    options.preverify = request.options.preverify_checksum;
+    options.preverify_strategy = flashing::PreverifyStrategy::Checksum;
     options.verify = request.options.verify;
     options.disable_double_buffering = request.options.disable_double_buffering;
     options.preferred_algos = request.options.preferred_algos.clone();
@@ -117,6 +119,28 @@ pub async fn load_region(
     Ok(())
 }
 
+pub async fn inject_data(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: InjectDataRequest,
+) -> NoResponse {
+    let location = match request.location {
+        InjectionLocation::Address(address) => flashing::InjectionLocation::Address(address),
+        InjectionLocation::Placeholder { start, end } => {
+            flashing::InjectionLocation::Placeholder(start..end)
+        }
+    };
+
+    let mut loader = ctx.object_mut(request.loader).await;
+    match request.data {
+        InjectedData::Bytes(data) => lift(loader.inject_data(location, &data))?,
+        InjectedData::Crc32 { start, end } => {
+            lift(loader.inject_crc32(start..end, location))?;
+        }
+    }
+    Ok(())
+}
+
 pub async fn build(
     ctx: &mut RpcContext,
     _header: VarHeader,
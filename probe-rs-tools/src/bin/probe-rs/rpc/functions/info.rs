@@ -11,7 +11,7 @@ use probe_rs::{
             self, ApAddress, ApV2Address, ArmDebugInterface,
             ap::{ApClass, ApRegister, ApType, IDR},
             armv6m::Demcr,
-            component::Scs,
+            component::{Mtb, Scs},
             dp::{self, Ctrl, DLPIDR, DPIDR, DpRegister, TARGETID},
             memory::{
                 ArmMemoryInterface, Component, ComponentId, CoresightComponent, PeripheralType,
@@ -689,6 +689,13 @@ fn process_component_entry(
 
             tree.push(cpu_tree);
         }
+        PeripheralType::Mtb => {
+            let cc = &CoresightComponent::new(component.clone(), access_port.clone());
+            let mtb = &mut Mtb::new(interface, cc);
+
+            tree.push(format!("SRAM base: {:#010x}", mtb.sram_base()?));
+            tree.push(format!("Buffer size: {} bytes", mtb.buffer_size()?));
+        }
         PeripheralType::MemAp => {
             let dp = access_port.dp();
             let ApAddress::V2(addr) = access_port.ap() else {
@@ -789,6 +796,7 @@ pub(crate) mod convert {
                 cycle_power: false,
                 dry_run: request.dry_run,
                 allow_erase_all: false,
+                allow_flash_breakpoints: false,
                 attach_timeout: None,
             }
         }
@@ -127,6 +127,16 @@ fn monitor_impl(
     {
         let mut session = shared_session.session_blocking();
         prepare_monitor_mode(&request.mode, &mut session, run_loop.core_id)?;
+        // The MTB is set up after a possible reset, which would disable it again, but before the
+        // run loop starts the core, so that the firmware does not run while it is configured.
+        if let Some(config) = request.options.branch_trace {
+            let mut core = session.core(run_loop.core_id)?;
+            if !core.core_halted()? {
+                core.halt(Duration::from_millis(100))?;
+            }
+            drop(core);
+            session.setup_branch_trace(run_loop.core_id, config.offset, config.size)?;
+        }
     }
 
     let poller = client_key.map(|client| RttPoller {
@@ -264,6 +274,12 @@ impl<F: FnMut(SemihostingEvent)> MonitorEventHandler<F> {
                     subcode: details.exit_status.or(details.subcode),
                 }),
             ))),
+            // Checked before `Unknown`, because plugins can implement unknown operations.
+            other if SemihostingFileManager::can_handle(other) => {
+                self.semihosting_file_manager
+                    .handle(other, core, &mut self.sender)?;
+                Ok(None)
+            }
             SemihostingCommand::Unknown(details) => {
                 tracing::warn!(
                     "Target wanted to run semihosting operation {:#x} with parameter {:#x},\
@@ -284,11 +300,6 @@ impl<F: FnMut(SemihostingEvent)> MonitorEventHandler<F> {
                 Ok(None)
             }
             SemihostingCommand::Errno(_) => Ok(None),
-            other if SemihostingFileManager::can_handle(other) => {
-                self.semihosting_file_manager
-                    .handle(other, core, &mut self.sender)?;
-                Ok(None)
-            }
             other => Ok(Some(MonitorExitReason::UnexpectedExit(format!(
                 "Unexpected semihosting command {other:?}",
             )))),
@@ -3,11 +3,11 @@ use std::time::{Duration, Instant};
 use postcard_rpc::{header::VarHeader, server::Sender};
 use probe_rs::probe::DebugProbeSelector;
 use probe_rs_rpc::probe::{
-    AttachRequest, AttachResult, DebugProbeEntry, ListProbesResponse, SelectProbeRequest,
-    SelectProbeResponse, SelectProbeResult, WireProtocol,
+    AttachRequest, AttachResult, DebugProbeEntry, ListProbesResponse, ProbeSpeedRequest,
+    ProbeSpeedResponse, SelectProbeRequest, SelectProbeResponse, SelectProbeResult, WireProtocol,
 };
 
-use crate::rpc::functions::{RpcContext, RpcSpawnContext, WireTxImpl};
+use crate::rpc::functions::{RpcContext, RpcSpawnContext, WireTxImpl, convert::lift};
 use crate::util::common_options::{
     OPEN_RETRY_INTERVAL, OperationError, ProbeOptions, probe_may_become_available,
 };
@@ -174,8 +174,6 @@ fn attach_once(
     selector: DebugProbeSelector,
     resume_target: bool,
 ) -> RpcResult<AttachAttempt> {
-    use crate::rpc::functions::convert::lift;
-
     let lister = ctx.lister();
     let mut registry = ctx.registry_blocking();
     let loaded = probe_options.load(&mut registry)?;
@@ -200,6 +198,20 @@ fn attach_once(
     Ok(AttachAttempt::Attached(Box::new(session)))
 }
 
+pub async fn probe_speed(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: ProbeSpeedRequest,
+) -> ProbeSpeedResponse {
+    let mut session = ctx.session(request.sessid).await;
+    match request.speed_khz {
+        Some(speed_khz) => lift(session.set_speed(speed_khz)),
+        None => session
+            .speed_khz()
+            .ok_or_else(|| "The speed of the probe is not known".into()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -366,6 +378,7 @@ mod tests {
                 connect_under_reset: false,
                 dry_run: false,
                 allow_erase_all: false,
+                allow_flash_breakpoints: false,
                 resume_target: false,
                 wait_for_probe,
             })
@@ -512,6 +525,7 @@ pub(crate) mod convert {
                 cycle_power: false,
                 dry_run: request.dry_run,
                 allow_erase_all: request.allow_erase_all,
+                allow_flash_breakpoints: request.allow_flash_breakpoints,
                 // `attach_impl` runs the wait, so that it can also retry a
                 // probe that has dropped out of the probe list, and so that the
                 // client can cancel it.
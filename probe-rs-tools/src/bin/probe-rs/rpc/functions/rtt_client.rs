@@ -7,7 +7,7 @@ use probe_rs::rtt;
 use probe_rs_rpc::rtt_client::{
     CreateRttClientRequest, CreateRttClientResponse, PollRttUpRequest, PollRttUpResponse,
     RttChannelMeta, RttChannelRequest, RttChannels, RttChannelsResponse, RttClientData,
-    RttDownRequest, RttDownResponse, RttPollResult, ScanRegion,
+    RttDiscoveryStrategy, RttDownRequest, RttDownResponse, RttLocation, RttPollResult, ScanRegion,
 };
 use probe_rs_rpc::{NoResponse, RpcError};
 use std::time::{Duration, Instant};
@@ -19,12 +19,17 @@ pub async fn create_rtt_client(
 ) -> CreateRttClientResponse {
     let session = ctx.session(request.sessid).await;
 
-    let rtt_scan_regions = match request.scan_regions {
-        ScanRegion::Ram => rtt::ScanRegion::Ram,
-        ScanRegion::Ranges(ranges) => {
-            rtt::ScanRegion::Ranges(ranges.into_iter().map(|(start, end)| start..end).collect())
-        }
-        ScanRegion::Exact(addr) => rtt::ScanRegion::Exact(addr),
+    let discovery = rtt::RttDiscovery {
+        symbol: request.discovery.symbol,
+        pinned: request.discovery.pinned,
+        scan: request.discovery.scan.map(|scan| match scan {
+            ScanRegion::Ram => rtt::ScanRegion::Ram,
+            ScanRegion::Ranges(ranges) => {
+                rtt::ScanRegion::Ranges(ranges.into_iter().map(|(start, end)| start..end).collect())
+            }
+            ScanRegion::Exact(addr) => rtt::ScanRegion::Exact(addr),
+        }),
+        ..Default::default()
     };
 
     let client = crate::util::rtt::client::RttClient::new(
@@ -33,7 +38,7 @@ pub async fn create_rtt_client(
             channels: request.config,
             default_config: request.default_config,
         },
-        rtt_scan_regions,
+        discovery,
         session.target(),
     );
 
@@ -130,7 +135,16 @@ pub async fn get_rtt_channels(
         })
         .collect();
 
-    Ok(RttChannels { up, down })
+    let location = rtt_client.location().map(|location| RttLocation {
+        address: location.address,
+        strategy: match location.strategy {
+            rtt::RttDiscoveryStrategy::Symbol => RttDiscoveryStrategy::Symbol,
+            rtt::RttDiscoveryStrategy::Pinned => RttDiscoveryStrategy::Pinned,
+            rtt::RttDiscoveryStrategy::Scan => RttDiscoveryStrategy::Scan,
+        },
+    });
+
+    Ok(RttChannels { up, down, location })
 }
 
 pub async fn clear_rtt_control_block(
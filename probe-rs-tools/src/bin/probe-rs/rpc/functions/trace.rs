@@ -0,0 +1,60 @@
+use postcard_rpc::header::VarHeader;
+use probe_rs::architecture::arm::{component::TraceSink, swo::SwoConfig};
+use probe_rs_rpc::trace::{
+    Branch, BranchTraceReadRequest, BranchTraceReadResponse, SwoReadRequest, SwoReadResponse,
+    SwoStartRequest, SwoStopRequest,
+};
+
+use crate::rpc::functions::{RpcContext, convert::lift};
+use probe_rs_rpc::NoResponse;
+
+pub async fn swo_start(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: SwoStartRequest,
+) -> NoResponse {
+    let mut session = ctx.session(request.sessid).await;
+    lift(session.setup_tracing(
+        request.core as usize,
+        TraceSink::Swo(SwoConfig::new(request.clk).set_baud(request.baud)),
+    ))
+}
+
+pub async fn swo_read(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: SwoReadRequest,
+) -> SwoReadResponse {
+    let mut session = ctx.session(request.sessid).await;
+    lift(session.read_trace_data())
+}
+
+pub async fn swo_stop(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: SwoStopRequest,
+) -> NoResponse {
+    let mut session = ctx.session(request.sessid).await;
+    lift(session.disable_swv(request.core as usize))?;
+    let interface = lift(session.get_arm_interface())?;
+    lift(interface.disable_swo())
+}
+
+pub async fn branch_trace_read(
+    ctx: &mut RpcContext,
+    _header: VarHeader,
+    request: BranchTraceReadRequest,
+) -> BranchTraceReadResponse {
+    let mut session = ctx.session(request.sessid).await;
+    let branches = lift(session.read_branch_trace(request.core as usize))?;
+
+    Ok(branches
+        .into_iter()
+        .map(|branch| Branch {
+            source: branch.source,
+            destination: branch.destination,
+            exception: branch.exception,
+            start: branch.start,
+        })
+        .collect())
+}
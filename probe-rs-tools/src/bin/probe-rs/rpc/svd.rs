@@ -8,6 +8,7 @@
 
 use std::collections::BTreeMap;
 use std::io::Read;
+use std::ops::Range;
 use std::path::Path;
 
 use probe_rs::MemoryInterface;
@@ -292,10 +293,41 @@ impl SvdVariable {
 /// Read and parse an SVD file at `path`, then build the variable cache.
 #[tracing::instrument(skip_all)]
 pub fn parse_svd_file(path: &Path) -> Result<SvdVariableCache, DebugError> {
+    build_svd_cache(&read_svd_device(path)?)
+}
+
+/// Resolve the address blocks of the named peripherals in the SVD file at `path`.
+///
+/// Peripheral names are matched case-insensitively.
+pub fn peripheral_ranges(path: &Path, names: &[String]) -> Result<Vec<Range<u64>>, DebugError> {
+    let device = read_svd_device(path)?;
+
+    let mut ranges = Vec::new();
+    for name in names {
+        let Some(peripheral) = device
+            .peripherals
+            .iter()
+            .find(|peripheral| peripheral.name.eq_ignore_ascii_case(name))
+        else {
+            return Err(DebugError::Other(format!(
+                "Peripheral {name:?} not found in CMSIS-SVD file: {path:?}"
+            )));
+        };
+
+        for block in peripheral.address_block.iter().flatten() {
+            let start = peripheral.base_address + u64::from(block.offset);
+            ranges.push(start..start + u64::from(block.size));
+        }
+    }
+
+    Ok(ranges)
+}
+
+fn read_svd_device(path: &Path) -> Result<svd_parser::svd::Device, DebugError> {
     let mut svd_xml = String::new();
     std::fs::File::open(path)?.read_to_string(&mut svd_xml)?;
 
-    let device = svd_parser::parse_with_config(
+    svd_parser::parse_with_config(
         &svd_xml,
         // `expand_properties` pushes each peripheral's / the device's
         // `defaultRegisterProperties` (including `access`) down onto the individual
@@ -310,9 +342,7 @@ pub fn parse_svd_file(path: &Path) -> Result<SvdVariableCache, DebugError> {
         DebugError::Other(format!(
             "Unable to parse CMSIS-SVD file: {path:?}. {error:?}"
         ))
-    })?;
-
-    build_svd_cache(&device)
+    })
 }
 
 /// Build an [`SvdVariableCache`] from a parsed SVD `Device`.
@@ -1,8 +1,9 @@
 use probe_rs::{
     Core,
     semihosting::{
-        CloseRequest, FileLengthRequest, OpenRequest, ReadRequest, RemoveRequest, RenameRequest,
-        SeekRequest, SemihostingCommand, WriteRequest,
+        CloseRequest, FileLengthRequest, HeapInfo, IsTtyRequest, OpenRequest, ReadRequest,
+        RemoveRequest, RenameRequest, SeekRequest, SemihostingCommand, SystemRequest,
+        TmpNamRequest, WriteRequest,
     },
 };
 use probe_rs_rpc::monitor::SemihostingEvent;
@@ -14,8 +15,14 @@ use std::{
     io::{Read, Seek, SeekFrom, Write},
     net::TcpStream,
     num::NonZeroU32,
+    path::{Component, Path, PathBuf},
+    process::Command,
+    time::Instant,
 };
 
+/// The tick frequency reported to the target for `SYS_ELAPSED`.
+const TICKS_PER_SECOND: u32 = 1_000_000;
+
 enum FileHandle {
     Stdout,
     Stderr,
@@ -60,8 +67,58 @@ fn map_file(options: &SemihostingOptions, value: &str) -> Option<String> {
     None
 }
 
+/// Returns the name of a temporary file in the sandbox directory.
+fn tmpnam_path(sandbox: &str, identifier: u8) -> PathBuf {
+    Path::new(sandbox).join(format!("tmp{identifier:03}.tmp"))
+}
+
+/// Resolves a temporary file name handed out by `SYS_TMPNAM`.
+///
+/// Only these names are opened in the sandbox directory, every other file has to be exposed
+/// via a mapping.
+fn sandbox_tmp_file(options: &SemihostingOptions, value: &str) -> Option<String> {
+    let path = Path::new(value);
+    let identifier = path
+        .file_name()?
+        .to_str()?
+        .strip_prefix("tmp")?
+        .strip_suffix(".tmp")?
+        .parse::<u8>()
+        .ok()?;
+
+    (tmpnam_path(options.sandbox()?, identifier) == path).then(|| value.to_string())
+}
+
+/// Redirects coverage data written by the target into the coverage directory.
+///
+/// The path on the target is kept, below the coverage directory, so that tools like `gcov` can
+/// still match the data files to the object files.
+fn coverage_file(options: &SemihostingOptions, value: &str) -> Option<PathBuf> {
+    let dir = options.coverage_dir()?;
+    let path = Path::new(value);
+
+    if !path
+        .extension()
+        .is_some_and(|ext| ext == "gcda" || ext == "profraw")
+    {
+        return None;
+    }
+
+    let relative = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part),
+            _ => None,
+        })
+        .collect::<PathBuf>();
+
+    Some(Path::new(dir).join(relative))
+}
+
 fn find_file(options: &SemihostingOptions, value: &str) -> Option<FileVariant> {
-    let v = map_file(options, value)?;
+    let Some(v) = map_file(options, value) else {
+        return sandbox_tmp_file(options, value).map(FileVariant::File);
+    };
 
     if let Some(r) = v.strip_prefix("file:") {
         return Some(FileVariant::File(r.into()));
@@ -81,6 +138,7 @@ fn find_file(options: &SemihostingOptions, value: &str) -> Option<FileVariant> {
 pub struct SemihostingFileManager {
     file_handles: Vec<Option<FileHandle>>,
     semihosting_options: SemihostingOptions,
+    started: Instant,
 }
 
 impl SemihostingFileManager {
@@ -88,6 +146,7 @@ impl SemihostingFileManager {
         Self {
             file_handles: vec![],
             semihosting_options,
+            started: Instant::now(),
         }
     }
 
@@ -103,7 +162,14 @@ impl SemihostingFileManager {
                 | SemihostingCommand::FileLength(_)
                 | SemihostingCommand::Remove(_)
                 | SemihostingCommand::Rename(_)
-        )
+                | SemihostingCommand::IsTty(_)
+                | SemihostingCommand::TmpNam(_)
+                | SemihostingCommand::System(_)
+                | SemihostingCommand::Clock(_)
+                | SemihostingCommand::Elapsed(_)
+                | SemihostingCommand::TickFreq(_)
+                | SemihostingCommand::HeapInfo(_)
+        ) || matches!(other, SemihostingCommand::Unknown(details) if details.has_registered_handler())
     }
 
     pub fn handle(
@@ -132,6 +198,33 @@ impl SemihostingFileManager {
             SemihostingCommand::FileLength(request) => self.handle_file_length(core, request),
             SemihostingCommand::Remove(request) => self.handle_remove(core, request),
             SemihostingCommand::Rename(request) => self.handle_rename(core, request),
+            SemihostingCommand::IsTty(request) => self.handle_is_tty(core, request),
+            SemihostingCommand::TmpNam(request) => self.handle_tmpnam(core, request),
+            SemihostingCommand::System(request) => self.handle_system(core, request, send_output),
+            SemihostingCommand::Clock(request) => {
+                let centiseconds = self.started.elapsed().as_millis() / 10;
+                request.write_centiseconds(core, centiseconds as u32)?;
+                Ok(())
+            }
+            SemihostingCommand::Elapsed(request) => {
+                let ticks = self.started.elapsed().as_micros();
+                request.write_ticks(core, ticks as u64)?;
+                Ok(())
+            }
+            SemihostingCommand::TickFreq(request) => {
+                request.write_frequency(core, TICKS_PER_SECOND)?;
+                Ok(())
+            }
+            SemihostingCommand::HeapInfo(request) => {
+                // We don't know the memory layout of the application, so let the C library
+                // use its defaults.
+                request.write_heap_info(core, HeapInfo::default())?;
+                Ok(())
+            }
+            SemihostingCommand::Unknown(details) => {
+                details.run_registered_handler(core)?;
+                Ok(())
+            }
 
             _ => Ok(()),
         }
@@ -223,6 +316,16 @@ impl SemihostingFileManager {
 
         let f = if path == ":tt" {
             self.open_tt(request.mode())
+        } else if let Some(path) = coverage_file(&self.semihosting_options, &path) {
+            if let Some(parent) = path.parent()
+                && let Err(err) = std::fs::create_dir_all(parent)
+            {
+                tracing::warn!(
+                    "Failed to create coverage directory {}: {err:?}",
+                    parent.display()
+                );
+            }
+            self.open_file(&path.to_string_lossy(), request.mode())
         } else if let Some(path) = find_file(&self.semihosting_options, &path) {
             match path {
                 FileVariant::File(path) => self.open_file(&path, request.mode()),
@@ -353,13 +456,37 @@ impl SemihostingFileManager {
         Ok(())
     }
 
+    fn find_host_file(&self, action: &str, path: &str) -> Option<String> {
+        match find_file(&self.semihosting_options, path) {
+            Some(FileVariant::File(host_path)) => Some(host_path),
+            Some(_) => {
+                tracing::warn!(
+                    "Target wanted to {action} {path} which is not a regular file. Continuing..."
+                );
+                None
+            }
+            None => {
+                tracing::warn!(
+                    "Target wanted to {action} {path} which is not configured \
+                    for access via semihosting. Continuing..."
+                );
+                None
+            }
+        }
+    }
+
     fn handle_remove(&mut self, core: &mut Core<'_>, request: RemoveRequest) -> anyhow::Result<()> {
         let path = request.path(core)?;
+        let Some(host_path) = self.find_host_file("remove file", &path) else {
+            return Ok(());
+        };
 
-        tracing::warn!(
-            "Target wanted to remove file {path}, \
-            but probe-rs does not support this operation yet. Continuing..."
-        );
+        match std::fs::remove_file(&host_path) {
+            Ok(()) => request.success(core)?,
+            Err(err) => tracing::warn!(
+                "Target wanted to remove file {path}, but it failed with {err:?}. Continuing..."
+            ),
+        }
 
         Ok(())
     }
@@ -367,11 +494,98 @@ impl SemihostingFileManager {
     fn handle_rename(&mut self, core: &mut Core<'_>, request: RenameRequest) -> anyhow::Result<()> {
         let from_path = request.from_path(core)?;
         let to_path = request.to_path(core)?;
+        let Some(host_from) = self.find_host_file("rename file", &from_path) else {
+            return Ok(());
+        };
+        let Some(host_to) = self.find_host_file("rename a file to", &to_path) else {
+            return Ok(());
+        };
 
-        tracing::warn!(
-            "Target wanted to rename file {from_path} to {to_path}, \
-            but probe-rs does not support this operation yet. Continuing..."
-        );
+        match std::fs::rename(&host_from, &host_to) {
+            Ok(()) => request.success(core)?,
+            Err(err) => tracing::warn!(
+                "Target wanted to rename file {from_path} to {to_path}, \
+                but it failed with {err:?}. Continuing..."
+            ),
+        }
+
+        Ok(())
+    }
+
+    fn handle_is_tty(&mut self, core: &mut Core<'_>, request: IsTtyRequest) -> anyhow::Result<()> {
+        let action = "check the terminal status of";
+        let Some((f, _)) = self.get_file_handle(action, request.file_handle()) else {
+            return Ok(());
+        };
+
+        let is_tty = matches!(f, FileHandle::Stdout | FileHandle::Stderr);
+        request.write_is_tty(core, is_tty)?;
+
+        Ok(())
+    }
+
+    fn handle_tmpnam(&mut self, core: &mut Core<'_>, request: TmpNamRequest) -> anyhow::Result<()> {
+        let Some(sandbox) = self.semihosting_options.sandbox() else {
+            tracing::warn!(
+                "Target wanted a temporary file name, \
+                but no semihosting sandbox directory is configured. Continuing..."
+            );
+            request.write_failure(core)?;
+            return Ok(());
+        };
+
+        let name = tmpnam_path(sandbox, request.identifier());
+        request.write_name(core, &name.to_string_lossy())?;
+
+        Ok(())
+    }
+
+    fn handle_system(
+        &mut self,
+        core: &mut Core<'_>,
+        request: SystemRequest,
+        mut send_output: impl FnMut(&str, String),
+    ) -> anyhow::Result<()> {
+        let command = request.command(core)?;
+        let Some(sandbox) = self.semihosting_options.sandbox() else {
+            tracing::warn!(
+                "Target wanted to run the command `{command}`, \
+                but no semihosting sandbox directory is configured. Continuing..."
+            );
+            request.write_status(core, -1)?;
+            return Ok(());
+        };
+
+        let mut shell = if cfg!(windows) {
+            let mut shell = Command::new("cmd");
+            shell.arg("/C");
+            shell
+        } else {
+            let mut shell = Command::new("sh");
+            shell.arg("-c");
+            shell
+        };
+
+        let output = match shell.arg(&command).current_dir(sandbox).output() {
+            Ok(output) => output,
+            Err(err) => {
+                tracing::warn!(
+                    "Target wanted to run the command `{command}`, \
+                    but it failed with {err:?}. Continuing..."
+                );
+                request.write_status(core, -1)?;
+                return Ok(());
+            }
+        };
+
+        if !output.stdout.is_empty() {
+            send_output("stdout", String::from_utf8_lossy(&output.stdout).into());
+        }
+        if !output.stderr.is_empty() {
+            send_output("stderr", String::from_utf8_lossy(&output.stderr).into());
+        }
+
+        request.write_status(core, output.status.code().unwrap_or(-1))?;
 
         Ok(())
     }
@@ -450,3 +664,59 @@ impl FileHandleLog {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sandboxed_options() -> SemihostingOptions {
+        let mut options = SemihostingOptions::new();
+        options.set_sandbox("/sandbox".to_string());
+        options
+    }
+
+    #[test]
+    fn sandbox_opens_temporary_files() {
+        let options = sandboxed_options();
+        assert_eq!(
+            sandbox_tmp_file(&options, "/sandbox/tmp000.tmp").as_deref(),
+            Some("/sandbox/tmp000.tmp")
+        );
+        assert_eq!(
+            sandbox_tmp_file(&options, "/sandbox/tmp255.tmp").as_deref(),
+            Some("/sandbox/tmp255.tmp")
+        );
+    }
+
+    #[test]
+    fn sandbox_rejects_other_files() {
+        let options = sandboxed_options();
+        assert_eq!(sandbox_tmp_file(&options, "result.txt"), None);
+        assert_eq!(sandbox_tmp_file(&options, "/sandbox/result.txt"), None);
+        assert_eq!(sandbox_tmp_file(&options, "tmp000.tmp"), None);
+        assert_eq!(sandbox_tmp_file(&options, "/sandbox/../tmp000.tmp"), None);
+        assert_eq!(sandbox_tmp_file(&options, "/sandbox/tmp0.tmp"), None);
+    }
+
+    #[test]
+    fn coverage_files_are_redirected() {
+        let mut options = SemihostingOptions::new();
+        options.set_coverage_dir("/coverage/test_a".to_string());
+
+        assert_eq!(
+            coverage_file(&options, "/home/ci/build/main.gcda"),
+            Some(PathBuf::from("/coverage/test_a/home/ci/build/main.gcda"))
+        );
+        assert_eq!(
+            coverage_file(&options, "../default.profraw"),
+            Some(PathBuf::from("/coverage/test_a/default.profraw"))
+        );
+        assert_eq!(coverage_file(&options, "/home/ci/log.txt"), None);
+    }
+
+    #[test]
+    fn no_sandbox_no_fallback() {
+        let options = SemihostingOptions::new();
+        assert_eq!(sandbox_tmp_file(&options, "/sandbox/tmp000.tmp"), None);
+    }
+}
@@ -3,6 +3,7 @@
 use std::fmt::Display;
 use std::future::pending;
 use std::io::Write;
+use std::sync::Arc;
 use std::time::Duration;
 use std::{future::Future, ops::DerefMut, path::Path, time::Instant};
 
@@ -20,6 +21,7 @@ use tokio::sync::{Mutex, MutexGuard, Notify};
 use tokio::{runtime::Handle, sync::mpsc::UnboundedSender};
 use tokio_util::sync::CancellationToken;
 
+use crate::cmd::profile::flat::Symbols;
 use crate::cmd::run::{EmbeddedTestElfInfo, MonitoringOptions};
 use crate::rpc::Key;
 use crate::rpc::RttClient;
@@ -30,24 +32,29 @@ use crate::rpc::utils::run_loop::VectorCatchConfig;
 use crate::util::pwr::power_reset;
 use crate::util::{
     common_options::{BinaryDownloadOptions, ProbeOptions},
+    coverage::CoverageCollector,
     flash::CliProgressBars,
     logging,
-    rtt::{DefmtProcessor, DefmtState, RttDecoder},
+    rtt::{
+        DefmtProcessor, DefmtState, RttDecoder,
+        recording::{RecordingSender, RttRecording, RttReplay},
+    },
 };
 use probe_rs_rpc::CancelTopic;
 use probe_rs_rpc::flash::{BootInfo, DownloadOptions, FlashLayout, ProgressEvent, VerifyResult};
-use probe_rs_rpc::format::FormatOptions;
+use probe_rs_rpc::format::{FormatKind, FormatOptions};
 use probe_rs_rpc::monitor::{ChannelInfo, MonitorExitReason};
 use probe_rs_rpc::monitor::{MonitorMode, MonitorOptions, RttEvent, SemihostingEvent};
 use probe_rs_rpc::probe::{
     AttachRequest, AttachResult, DebugProbeEntry, DebugProbeSelector, SelectProbeResult,
 };
-use probe_rs_rpc::rtt_client::ScanRegion;
+use probe_rs_rpc::rtt_client::RttDiscovery;
 use probe_rs_rpc::rtt_config::RttChannelConfig;
 use probe_rs_rpc::semihosting_options::SemihostingOptions;
 use probe_rs_rpc::stack_trace::StackTrace;
 use probe_rs_rpc::stack_trace::StackTraceFrame;
 use probe_rs_rpc::test::{Test, TestResult};
+use probe_rs_rpc::trace::BranchTraceConfig;
 use probe_rs_rpc_client::{MonitorEvent, RpcClient, SessionInterface};
 
 type TargetOutputFiles = std::collections::HashMap<ChannelIdentifier, tokio::fs::File>;
@@ -111,6 +118,7 @@ pub async fn attach_probe(
         connect_under_reset: probe_options.connect_under_reset,
         dry_run: probe_options.dry_run,
         allow_erase_all: probe_options.allow_erase_all,
+        allow_flash_breakpoints: probe_options.allow_flash_breakpoints,
         resume_target,
         wait_for_probe: probe_options.attach_timeout,
     }))
@@ -396,8 +404,21 @@ pub(crate) async fn connect_target_output_files(
     Ok(map)
 }
 
-pub(crate) fn parse_semihosting_options(arg: &[String]) -> anyhow::Result<SemihostingOptions> {
+pub(crate) fn parse_semihosting_options(
+    arg: &[String],
+    sandbox: Option<&Path>,
+) -> anyhow::Result<SemihostingOptions> {
     let mut options = SemihostingOptions::new();
+    if let Some(sandbox) = sandbox {
+        std::fs::create_dir_all(sandbox).with_context(|| {
+            format!(
+                "Failed to create semihosting sandbox directory {}",
+                sandbox.display()
+            )
+        })?;
+        let sandbox = dunce::canonicalize(sandbox)?;
+        options.set_sandbox(sandbox.to_string_lossy().into_owned());
+    }
     for component in arg {
         let parts: Vec<&str> = component.splitn(2, "=").collect();
         match parts[..] {
@@ -428,7 +449,8 @@ pub(crate) fn parse_semihosting_options(arg: &[String]) -> anyhow::Result<Semiho
 #[derive(Default)]
 pub struct FileMetadata {
     pub defmt_data: Option<DefmtState>,
-    pub scan_regions: Option<ScanRegion>,
+    /// The address of the `_SEGGER_RTT` symbol.
+    pub rtt_symbol: Option<u64>,
 }
 
 pub async fn parse_metadata(path: &Path) -> anyhow::Result<(FileMetadata, Option<ElfMetadata>)> {
@@ -437,14 +459,14 @@ pub async fn parse_metadata(path: &Path) -> anyhow::Result<(FileMetadata, Option
         .with_context(|| format!("Failed to read firmware from {}", path.display()))?;
 
     let mut elf_meta = None;
-    let mut scan_regions = None;
+    let mut rtt_symbol = None;
     let mut load_defmt_data = false;
 
     if let Ok((rtt_block, meta)) = find_rtt_control_block_and_metadata_in_raw_file(&elf) {
         elf_meta = Some(meta);
         match rtt_block {
             Some(addr) => {
-                scan_regions = Some(ScanRegion::Exact(addr));
+                rtt_symbol = Some(addr);
                 load_defmt_data = true;
             }
             None => load_defmt_data = !elf.is_empty(),
@@ -460,7 +482,7 @@ pub async fn parse_metadata(path: &Path) -> anyhow::Result<(FileMetadata, Option
     Ok((
         FileMetadata {
             defmt_data,
-            scan_regions,
+            rtt_symbol,
         },
         elf_meta,
     ))
@@ -472,15 +494,16 @@ pub async fn rtt_client(
     monitor_options: &MonitoringOptions,
     timestamp_offset: Option<UtcOffset>,
 ) -> anyhow::Result<CliRttClient> {
-    let scan_regions = match &meta.scan_regions {
-        Some(scan_regions) => scan_regions.clone(),
-        None => monitor_options.scan_region.clone(),
+    // The symbol from the ELF file takes precedence, the user given region is the fallback.
+    let discovery = RttDiscovery {
+        symbol: meta.rtt_symbol,
+        ..RttDiscovery::from_scan_region(monitor_options.scan_region.clone())
     };
 
     // We don't really know what to configure here, so we set a default configuration if we can, but that's it.
     let rtt_client = session
         .create_rtt_client(
-            scan_regions,
+            discovery,
             vec![],
             RttChannelConfig {
                 mode: Some(monitor_options.rtt_channel_mode),
@@ -489,18 +512,58 @@ pub async fn rtt_client(
         )
         .await?;
 
+    let recorder = match &monitor_options.rtt_record {
+        Some(path) => Some(RttRecording::create(path)?),
+        None => None,
+    };
+
     // The actual data processor objects will be created once we have the channel names.
     Ok(CliRttClient {
-        handle: rtt_client.handle,
+        handle: Some(rtt_client.handle),
+        core_id: rtt_client.core_id,
         timestamp_offset,
         show_timestamps: !monitor_options.no_timestamps,
         show_location: !monitor_options.no_location,
         channel_processors: vec![],
         defmt_data: meta.defmt_data.clone(),
         log_format: monitor_options.log_format.clone(),
+        recorder,
     })
 }
 
+/// Uploads the images a manifest refers to, if the session is remote.
+///
+/// Returns the path of a manifest that refers to the uploaded images, which has to be used
+/// instead of `path`. Local sessions read the images directly, and get `None`.
+pub async fn upload_manifest_images(
+    session: &SessionInterface,
+    path: &Path,
+    format: &FormatOptions,
+) -> anyhow::Result<Option<tempfile::TempPath>> {
+    if format.binary_format != FormatKind::Manifest || session.client().is_local_session() {
+        return Ok(None);
+    }
+
+    let yaml = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut manifest = probe_rs::flashing::Manifest::from_yaml(&yaml)?;
+
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    for image in &mut manifest.images {
+        // The uploaded file has no extension the format could be derived from.
+        image.format = image.format_name();
+        image.path = session
+            .resolve_upload(&base_dir.join(&image.path))
+            .await?
+            .server_path()
+            .to_path_buf();
+    }
+
+    let mut file = tempfile::Builder::new().suffix(".yaml").tempfile()?;
+    file.write_all(manifest.to_yaml()?.as_bytes())?;
+    Ok(Some(file.into_temp_path()))
+}
+
 pub async fn flash(
     session: &SessionInterface,
     path: &Path,
@@ -516,6 +579,7 @@ pub async fn flash(
         keep_unwritten_bytes: download_options.restore_unwritten,
         do_chip_erase: download_options.chip_erase,
         skip_erase: false,
+        preverify_checksum: download_options.preverify_checksum,
         verify: download_options.verify,
         disable_double_buffering: download_options.disable_double_buffering,
         preferred_algos: download_options.prefer_flash_algorithm,
@@ -524,18 +588,22 @@ pub async fn flash(
 
     options.sanitize();
 
+    let uploaded_manifest = upload_manifest_images(session, path, &format).await?;
     let loader = session
         .build_flash_loader(
-            path.to_path_buf(),
+            uploaded_manifest.as_deref().unwrap_or(path).to_path_buf(),
             format,
             image_target,
             download_options.read_flasher_rtt,
         )
         .await?;
 
+    let injections = download_options.inject_options.resolve(path)?;
+    injections.apply(session, loader.loader).await?;
+
     let mut flash_layout = None;
 
-    let run_flash = if download_options.preverify {
+    let run_flash = if download_options.preverify && !download_options.preverify_checksum {
         let pb = if download_options.disable_progressbars {
             None
         } else {
@@ -579,6 +647,8 @@ pub async fn flash(
                 }
             })
             .await?;
+
+        injections.increment_counters()?;
     }
 
     // Visualise flash layout to file if requested.
@@ -618,6 +688,7 @@ impl MonitorUiContext {
         let ui_state = Mutex::new(MonitorUiState {
             exited: false,
             rtt_client: None,
+            recorder: None,
             up_channels: Vec::new(),
             down_channels: Vec::new(),
             selected_down_channel,
@@ -653,6 +724,7 @@ impl MonitorUiContext {
 struct MonitorUiState {
     exited: bool,
     rtt_client: Option<Key<RttClient>>,
+    recorder: Option<RecordingSender>,
     up_channels: Vec<ChannelInfo>,
     down_channels: Vec<ChannelInfo>,
     selected_down_channel: u32,
@@ -681,7 +753,14 @@ pub async fn monitor(
     mut rtt_client: Option<CliRttClient>,
     vector_catch: VectorCatchConfig,
 ) -> anyhow::Result<()> {
-    let semihosting_options = parse_semihosting_options(&monitor_options.semihosting_file)?;
+    let mut semihosting_options = parse_semihosting_options(
+        &monitor_options.semihosting_file,
+        monitor_options.semihosting_sandbox.as_deref(),
+    )?;
+    let coverage = coverage_collector(monitor_options, path)?;
+    if let Some(coverage) = coverage.as_ref() {
+        semihosting_options.set_coverage_dir(coverage.dir().to_string_lossy().into_owned());
+    }
     let mut target_output_files =
         connect_target_output_files(&monitor_options.target_output_file).await?;
 
@@ -690,9 +769,18 @@ pub async fn monitor(
         catch_hardfault: vector_catch.catch_hardfault,
         catch_svc: vector_catch.catch_svc,
         catch_hlt: vector_catch.catch_hlt,
-        rtt_client: rtt_client.as_ref().map(|client| client.handle()),
+        rtt_client: rtt_client.as_ref().and_then(|client| client.handle()),
         semihosting_options,
+        branch_trace: monitor_options
+            .mtb_branches
+            .zip(monitor_options.mtb_buffer_offset)
+            .map(|(branches, offset)| BranchTraceConfig {
+                offset,
+                size: mtb_buffer_size(branches),
+            }),
     };
+    // The server monitors the core of the RTT client.
+    let core = rtt_client.as_ref().map_or(0, |client| client.core_id());
 
     // The mutex around the context should only be held for a short period of time.
     let ui_context = MonitorUiContext::new(monitor_options.rtt_down_channel);
@@ -710,7 +798,11 @@ pub async fn monitor(
                 .update(|state| {
                     state.up_channels = up_channels.clone();
                     state.down_channels = down_channels.clone();
-                    state.rtt_client = client.as_ref().map(|client| client.handle());
+                    state.rtt_client = client.as_ref().and_then(|client| client.handle());
+                    state.recorder = client
+                        .as_ref()
+                        .and_then(|client| client.recorder.as_ref())
+                        .map(RttRecording::sender);
                 })
                 .await
         };
@@ -773,6 +865,9 @@ pub async fn monitor(
                 Ok(ReadlineEvent::Line(mut line)) => {
                     rl.add_history_entry(line.clone());
                     line.push('\n');
+                    if let Some(recorder) = data.recorder.as_ref() {
+                        recorder.down(selected_channel, line.as_bytes());
+                    }
                     if let Some(client) = data.rtt_client
                         && let Err(error) = session
                             .send_to_rtt(client, selected_channel, line.into_bytes(), 0)
@@ -873,6 +968,19 @@ pub async fn monitor(
         _ = terminate => unreachable!(),
     };
 
+    if let (Some(coverage), Some(path)) = (coverage.as_ref(), path)
+        && result.is_ok()
+    {
+        // A coverage problem must not replace the exit status of the firmware.
+        if let Err(err) = coverage.collect(session, core as usize, "default").await {
+            eprintln!("Failed to collect coverage: {err:?}");
+        } else if monitor_options.coverage.coverage_lcov
+            && let Err(err) = coverage.write_lcov_report(path)
+        {
+            eprintln!("Failed to write the coverage report: {err:?}");
+        }
+    }
+
     let (print_stack_trace, result) = match result {
         Ok(MonitorExitReason::Success | MonitorExitReason::SemihostingExit(Ok(_))) => {
             println!("Firmware exited successfully");
@@ -938,6 +1046,10 @@ pub async fn monitor(
         } else {
             eprintln!("Can not print stack trace because firmware is not available");
         }
+
+        if let Some(branches) = monitor_options.mtb_branches {
+            display_branch_trace(session, core, path, branches).await?;
+        }
     }
 
     result
@@ -958,11 +1070,19 @@ pub async fn test(
     let mut target_output_files =
         connect_target_output_files(&monitor_options.target_output_file).await?;
 
-    let semihosting_options = parse_semihosting_options(&monitor_options.semihosting_file)?;
+    let semihosting_options = parse_semihosting_options(
+        &monitor_options.semihosting_file,
+        monitor_options.semihosting_sandbox.as_deref(),
+    )?;
+    let coverage = coverage_collector(monitor_options, Some(path))?.map(Arc::new);
 
     let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel::<MonitorEvent>();
 
-    let rtt_handle = rtt_client.as_ref().map(|rtt| rtt.handle);
+    let rtt_handle = rtt_client.as_ref().and_then(|rtt| rtt.handle);
+    // The server runs the tests on the core of the RTT client.
+    let core = rtt_client
+        .as_ref()
+        .map_or(0, |client| client.core_id() as usize);
     let test = async {
         let tests = if elf_info.version == 0 {
             // In embedded test < 0.7, we have to query the tests from the target via semihosting
@@ -996,18 +1116,26 @@ pub async fn test(
                     &token,
                     test,
                     monitor_options.stack_frame_limit,
+                    coverage.clone(),
+                    core,
                 )
             })
             .collect::<Vec<_>>();
 
-        tokio::task::spawn_blocking(move || {
-            if libtest_mimic::run(&libtest_args, tests).has_failed() {
-                anyhow::bail!("Some tests failed");
-            }
+        let conclusion =
+            tokio::task::spawn_blocking(move || libtest_mimic::run(&libtest_args, tests)).await?;
 
-            Ok(())
-        })
-        .await?
+        if let Some(coverage) = coverage.as_ref()
+            && monitor_options.coverage.coverage_lcov
+        {
+            coverage.write_lcov_report(path)?;
+        }
+
+        if conclusion.has_failed() {
+            anyhow::bail!("Some tests failed");
+        }
+
+        Ok(())
     };
 
     let log = async {
@@ -1054,6 +1182,8 @@ fn create_trial(
     token: &CancellationToken,
     test: Test,
     stack_frame_limit: u32,
+    coverage: Option<Arc<CoverageCollector>>,
+    core: usize,
 ) -> Trial {
     let name = test.name.clone();
     let ignored = test.ignored;
@@ -1070,12 +1200,27 @@ fn create_trial(
             }
 
             let handle = tokio::spawn(async move {
-                match session
+                let mut semihosting_options = semihosting_options;
+                if let Some(coverage) = coverage.as_ref() {
+                    let dir = coverage.test_dir(&test.name);
+                    semihosting_options.set_coverage_dir(dir.to_string_lossy().into_owned());
+                }
+                let test_name = test.name.clone();
+
+                let result = session
                     .run_test(test, rtt_client, semihosting_options, async move |msg| {
                         sender.send(msg).unwrap()
                     })
-                    .await
+                    .await;
+
+                if let Some(coverage) = coverage.as_ref()
+                    && matches!(result, Ok(TestResult::Success | TestResult::Failed(_)))
+                    && let Err(err) = coverage.collect(&session, core, &test_name).await
                 {
+                    eprintln!("Failed to collect coverage for {test_name}: {err:?}");
+                }
+
+                match result {
                     Ok(TestResult::Success) => Ok(()),
                     Ok(TestResult::Cancelled) => {
                         eprintln!("Cancelled");
@@ -1099,6 +1244,21 @@ fn create_trial(
     .with_ignored_flag(ignored)
 }
 
+fn coverage_collector(
+    monitor_options: &MonitoringOptions,
+    path: Option<&Path>,
+) -> anyhow::Result<Option<CoverageCollector>> {
+    if monitor_options.coverage.coverage_dir.is_none() {
+        return Ok(None);
+    }
+
+    let Some(path) = path else {
+        anyhow::bail!("Collecting coverage requires the firmware ELF file");
+    };
+
+    CoverageCollector::new(&monitor_options.coverage, path)
+}
+
 async fn display_stack_trace(
     session: &SessionInterface,
     path: &Path,
@@ -1121,6 +1281,49 @@ async fn display_stack_trace(
     Ok(())
 }
 
+/// The size of the MTB buffer needed to hold the given number of branches.
+fn mtb_buffer_size(branches: u32) -> u32 {
+    // Each branch is recorded as a packet of two words.
+    branches.saturating_mul(8).next_power_of_two().max(16)
+}
+
+async fn display_branch_trace(
+    session: &SessionInterface,
+    core: u32,
+    path: Option<&Path>,
+    limit: u32,
+) -> anyhow::Result<()> {
+    let branches = session.read_branch_trace(core).await?;
+    let symbols = path.and_then(|path| Symbols::try_from(path).ok());
+    let describe = |address: u32| match symbols
+        .as_ref()
+        .and_then(|symbols| symbols.get_name(address as u64))
+    {
+        Some(name) => format!("{address:#010x} ({name})"),
+        None => format!("{address:#010x}"),
+    };
+
+    println!("Last branches of core {core}, oldest first");
+    let skip = branches.len().saturating_sub(limit as usize);
+    for branch in &branches[skip..] {
+        if branch.start {
+            println!("    -- trace started --");
+        }
+        let kind = if branch.exception {
+            "exception"
+        } else {
+            "branch"
+        };
+        println!(
+            "    {kind:<9} {} -> {}",
+            describe(branch.source),
+            describe(branch.destination)
+        );
+    }
+
+    Ok(())
+}
+
 /// Formats a single stack frame for display.
 ///
 /// `colorize` controls ANSI styling: `None` uses the `PROBE_RS_COLOR` default,
@@ -1192,8 +1395,13 @@ where
 }
 
 pub struct CliRttClient {
-    handle: Key<RttClient>,
+    /// The server side RTT client. `None` when replaying a recording.
+    handle: Option<Key<RttClient>>,
+    /// The core that owns the RTT control block, and is monitored.
+    core_id: u32,
     channel_processors: Vec<Channel>,
+    /// The recording of the RTT traffic, closed when the client is dropped.
+    recorder: Option<RttRecording>,
 
     // Data necessary to create the channel processors once we know the channel names.
     log_format: Option<String>,
@@ -1204,19 +1412,46 @@ pub struct CliRttClient {
 }
 
 impl CliRttClient {
-    pub fn handle(&self) -> Key<RttClient> {
+    /// Creates a client that decodes a recording, without a server side RTT client.
+    pub fn offline(
+        defmt_data: Option<DefmtState>,
+        log_format: Option<String>,
+        show_timestamps: bool,
+        show_location: bool,
+    ) -> Self {
+        Self {
+            handle: None,
+            core_id: 0,
+            channel_processors: vec![],
+            recorder: None,
+            log_format,
+            show_timestamps,
+            show_location,
+            // Host timestamps would be those of the replay, not of the recording.
+            timestamp_offset: None,
+            defmt_data,
+        }
+    }
+
+    pub fn handle(&self) -> Option<Key<RttClient>> {
         self.handle
     }
 
+    pub fn core_id(&self) -> u32 {
+        self.core_id
+    }
+
     fn on_channels_discovered(&mut self, up_channels: &[ChannelInfo]) {
         // Already configured.
         if !self.channel_processors.is_empty() {
             return;
         }
 
-        // Apply our heuristics based on channel names.
+        // Apply our heuristics based on channel names. Plugins can claim channels first.
         for channel in up_channels.iter() {
-            let decoder = if channel.name == "defmt" {
+            let decoder = if let Some(decoder) = RttDecoder::plugin_for_channel(&channel.name) {
+                decoder
+            } else if channel.name == "defmt" {
                 if let Some(defmt_data) = self.defmt_data.clone() {
                     RttDecoder::Defmt {
                         processor: DefmtProcessor::new(
@@ -1260,11 +1495,17 @@ async fn handle_monitor_event(
     up_channels: &[u32],
 ) {
     match event {
-        MonitorEvent::Rtt(RttEvent::Discovered { up_channels, .. }) => {
+        MonitorEvent::Rtt(RttEvent::Discovered {
+            up_channels,
+            down_channels,
+        }) => {
             let Some(client) = rtt_client else {
                 return;
             };
 
+            if let Some(recorder) = client.recorder.as_ref() {
+                recorder.sender().channels(&up_channels, &down_channels);
+            }
             client.on_channels_discovered(&up_channels);
         }
         MonitorEvent::Rtt(RttEvent::Output { channel, bytes }) => {
@@ -1272,6 +1513,11 @@ async fn handle_monitor_event(
                 return;
             };
 
+            // Record every channel, the filter only applies to what is displayed.
+            if let Some(recorder) = client.recorder.as_ref() {
+                recorder.sender().up(channel, &bytes);
+            }
+
             if !up_channels.is_empty() && !up_channels.contains(&channel) {
                 return;
             }
@@ -1310,6 +1556,42 @@ async fn handle_monitor_event(
     }
 }
 
+/// Feeds a recording through the same decoders as a live session.
+///
+/// If `realtime` is set, the records are delayed by the time they were apart in the recording.
+pub async fn replay(
+    recording: RttReplay<impl std::io::Read>,
+    mut rtt_client: CliRttClient,
+    target_output_file: &[String],
+    up_channels: &[u32],
+    realtime: bool,
+) -> anyhow::Result<()> {
+    let mut target_output_files = connect_target_output_files(target_output_file).await?;
+
+    let start = Instant::now();
+    for record in recording {
+        let record = record?;
+        if realtime {
+            tokio::time::sleep_until((start + record.timestamp).into()).await;
+        }
+
+        let Some(event) = record.into_monitor_event() else {
+            continue;
+        };
+
+        handle_monitor_event(
+            &mut Some(&mut rtt_client),
+            event,
+            &mut target_output_files,
+            &async |message| print!("{message}"),
+            up_channels,
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
 struct ChannelInfoPrinter<'a>(&'a ChannelInfo);
 
 impl<'a> std::fmt::Display for ChannelInfoPrinter<'a> {
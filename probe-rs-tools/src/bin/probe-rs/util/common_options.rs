@@ -5,6 +5,7 @@ use std::{
 };
 
 use super::cargo::ArtifactError;
+use crate::util::inject::InjectOptions;
 use crate::util::parse_u64;
 use probe_rs::{
     Permissions, Session, Target,
@@ -68,6 +69,13 @@ pub struct BinaryDownloadOptions {
     /// Before flashing, read back all the flashed data to skip flashing if the device is up to date.
     #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
     pub preverify: bool,
+    /// Before flashing, compute a checksum of every flash sector on the target, and only erase
+    /// and program the sectors that differ.
+    ///
+    /// This is faster than `--preverify` on slow probe connections, as only the checksums are
+    /// read back.
+    #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
+    pub preverify_checksum: bool,
     /// After flashing, read back all the flashed data to verify it has been written correctly.
     #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
     pub verify: bool,
@@ -96,6 +104,9 @@ pub struct BinaryDownloadOptions {
     /// If unset, each RAM region is written in one go, so progress jumps straight to 100%.
     #[arg(long, help_heading = "DOWNLOAD CONFIGURATION")]
     pub ram_chunk_size: Option<u64>,
+
+    #[clap(flatten)]
+    pub inject_options: InjectOptions,
 }
 
 /// Supported bit-widths for read/write commands (not every device may support each width).
@@ -186,6 +197,14 @@ pub struct ProbeOptions {
         help_heading = "PROBE CONFIGURATION"
     )]
     pub allow_erase_all: bool,
+    /// Use this flag to allow software breakpoints in flash, which reprogram
+    /// the flash sector containing the breakpoint.
+    #[arg(
+        long,
+        env = "PROBE_RS_ALLOW_FLASH_BREAKPOINTS",
+        help_heading = "PROBE CONFIGURATION"
+    )]
+    pub allow_flash_breakpoints: bool,
 
     /// How long to wait for a busy probe, in seconds.
     ///
@@ -413,6 +432,9 @@ impl<'r> LoadedProbeOptions<'r> {
         if self.0.allow_erase_all {
             permissions = permissions.allow_erase_all();
         }
+        if self.0.allow_flash_breakpoints {
+            permissions = permissions.allow_flash_breakpoints();
+        }
 
         let session = if self.0.connect_under_reset {
             probe.attach_under_reset_with_registry(target, permissions, self.1)
@@ -815,6 +837,7 @@ mod tests {
             connect_under_reset: false,
             dry_run: false,
             allow_erase_all: false,
+            allow_flash_breakpoints: false,
             attach_timeout,
         }
     }
@@ -0,0 +1,426 @@
+//! Collection of source-based code coverage from instrumented firmware.
+//!
+//! Firmware built with `-Cinstrument-coverage` (or clang's `-fprofile-instr-generate`) keeps its
+//! profile counters in the `__llvm_prf_cnts` section in RAM. The per-function records and the
+//! function names are constant and can be taken directly from the ELF file. After a test run the
+//! counters are read back from the target, and the three parts are combined into a `.profraw`
+//! file that `llvm-profdata` understands.
+//!
+//! Firmware which writes its own coverage data (e.g. gcov's `__gcov_dump` or `minicov`) via
+//! semihosting is supported by redirecting `.gcda` and `.profraw` files into the coverage
+//! directory, see [`SemihostingOptions::set_coverage_dir`].
+//!
+//! [`SemihostingOptions::set_coverage_dir`]: probe_rs_rpc::semihosting_options::SemihostingOptions::set_coverage_dir
+
+use std::{
+    ops::Range,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::Context;
+use object::{Object, ObjectSection, ObjectSymbol};
+use probe_rs_rpc_client::SessionInterface;
+
+const PRF_DATA: &str = "__llvm_prf_data";
+const PRF_CNTS: &str = "__llvm_prf_cnts";
+const PRF_BITS: &str = "__llvm_prf_bits";
+const PRF_NAMES: &str = "__llvm_prf_names";
+const PRF_RAW_VERSION: &str = "__llvm_profile_raw_version";
+
+/// Magic number of a raw profile created on a 64-bit target (`\xfflprofr\x81`).
+const RAW_MAGIC_64: u64 = u64::from_be_bytes([0xff, b'l', b'p', b'r', b'o', b'f', b'r', 0x81]);
+/// Magic number of a raw profile created on a 32-bit target (`\xfflprofR\x81`).
+const RAW_MAGIC_32: u64 = u64::from_be_bytes([0xff, b'l', b'p', b'r', b'o', b'f', b'R', 0x81]);
+
+/// Counters are a single byte instead of a `u64` when this flag is set in the raw version.
+const VARIANT_MASK_BYTE_COVERAGE: u64 = 1 << 60;
+
+/// Coverage settings for `probe-rs run`.
+#[derive(Debug, Clone, clap::Args)]
+pub struct CoverageOptions {
+    /// Collect code coverage from instrumented firmware into this directory.
+    ///
+    /// A `.profraw` file is written for every test (or for the whole run, if the firmware is not
+    /// a test binary). `.gcda` and `.profraw` files that the firmware writes via semihosting are
+    /// stored in the same directory.
+    #[arg(long, help_heading = "COVERAGE")]
+    pub coverage_dir: Option<PathBuf>,
+
+    /// Merge all collected `.profraw` files into an lcov report (`lcov.info`) in the coverage
+    /// directory.
+    ///
+    /// Requires `llvm-profdata` and `llvm-cov` in the PATH, e.g. from the `llvm-tools` rustup
+    /// component.
+    #[arg(long, requires = "coverage_dir", help_heading = "COVERAGE")]
+    pub coverage_lcov: bool,
+}
+
+/// The parts of an LLVM instrumentation profile which are found in the ELF file.
+pub struct LlvmProfile {
+    raw_version: u64,
+    is_64bit: bool,
+    is_little_endian: bool,
+    data: Vec<u8>,
+    data_address: u64,
+    names: Vec<u8>,
+    names_address: u64,
+    counters: Range<u64>,
+    bitmap: Option<Range<u64>>,
+}
+
+impl LlvmProfile {
+    /// Extracts the profile sections from an ELF file.
+    ///
+    /// Returns `None` if the firmware is not instrumented.
+    pub fn from_elf(elf: &[u8]) -> anyhow::Result<Option<Self>> {
+        let file = object::File::parse(elf)?;
+
+        let Some(counters) = file.section_by_name(PRF_CNTS) else {
+            return Ok(None);
+        };
+        let data = file
+            .section_by_name(PRF_DATA)
+            .with_context(|| format!("{PRF_CNTS} is present, but {PRF_DATA} is missing"))?;
+        let names = file
+            .section_by_name(PRF_NAMES)
+            .with_context(|| format!("{PRF_CNTS} is present, but {PRF_NAMES} is missing"))?;
+
+        let raw_version = file
+            .symbols()
+            .find(|symbol| symbol.name() == Ok(PRF_RAW_VERSION))
+            .with_context(|| format!("The symbol {PRF_RAW_VERSION} is missing"))?;
+        let raw_version = read_symbol_u64(&file, &raw_version)?;
+
+        Ok(Some(Self {
+            raw_version,
+            is_64bit: file.is_64(),
+            is_little_endian: file.is_little_endian(),
+            data: data.data()?.to_vec(),
+            data_address: data.address(),
+            names: names.data()?.to_vec(),
+            names_address: names.address(),
+            counters: counters.address()..counters.address() + counters.size(),
+            bitmap: file
+                .section_by_name(PRF_BITS)
+                .map(|bits| bits.address()..bits.address() + bits.size()),
+        }))
+    }
+
+    /// The raw profile format version, without the variant flags.
+    pub fn version(&self) -> u32 {
+        self.raw_version as u32
+    }
+
+    /// The target memory holding the counters.
+    pub fn counter_range(&self) -> Range<u64> {
+        self.counters.clone()
+    }
+
+    /// The target memory holding the MC/DC bitmap, if any.
+    pub fn bitmap_range(&self) -> Option<Range<u64>> {
+        self.bitmap.clone()
+    }
+
+    fn data_record_size(&self) -> anyhow::Result<usize> {
+        let pointer: usize = if self.is_64bit { 8 } else { 4 };
+        let size = match self.version() {
+            // NameRef, FuncHash, CounterPtr, FunctionPointer, Values, NumCounters, NumValueSites
+            8 => 8 + 8 + 3 * pointer + 4 + 2 * 2,
+            // Version 9 adds BitmapPtr and NumBitmapBytes
+            9 | 10 => 8 + 8 + 4 * pointer + 4 + 2 * 2 + 4,
+            other => anyhow::bail!(
+                "Unsupported raw profile version {other}. Versions 8 to 10 (LLVM 15 and newer) are supported."
+            ),
+        };
+
+        Ok(size.next_multiple_of(8))
+    }
+
+    /// Assembles a `.profraw` file from the counters (and bitmap) read from the target.
+    pub fn profraw(&self, counters: &[u8], bitmap: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let version = self.version();
+        let record_size = self.data_record_size()?;
+        let counter_size = if self.raw_version & VARIANT_MASK_BYTE_COVERAGE != 0 {
+            1
+        } else {
+            8
+        };
+
+        let num_data = (self.data.len() / record_size) as u64;
+        let num_counters = (counters.len() / counter_size) as u64;
+
+        let magic = if self.is_64bit {
+            RAW_MAGIC_64
+        } else {
+            RAW_MAGIC_32
+        };
+        let bitmap_address = self.bitmap.as_ref().map(|r| r.start).unwrap_or_default();
+
+        let mut header = vec![
+            magic,
+            self.raw_version,
+            // BinaryIdsSize
+            0,
+            num_data,
+            // PaddingBytesBeforeCounters
+            0,
+            num_counters,
+            padding(counters.len()) as u64,
+        ];
+        if version >= 9 {
+            header.extend([bitmap.len() as u64, padding(bitmap.len()) as u64]);
+        }
+        header.extend([
+            self.names.len() as u64,
+            self.counters.start.wrapping_sub(self.data_address),
+        ]);
+        if version >= 9 {
+            header.push(bitmap_address.wrapping_sub(self.data_address));
+        }
+        header.push(self.names_address);
+        if version >= 10 {
+            // NumVTables, VNamesSize
+            header.extend([0, 0]);
+        }
+        // ValueKindLast
+        header.push(if version >= 10 { 2 } else { 1 });
+
+        let mut out = Vec::new();
+        // The header uses the byte order of the target, just like the data copied from it.
+        for field in header {
+            if self.is_little_endian {
+                out.extend_from_slice(&field.to_le_bytes());
+            } else {
+                out.extend_from_slice(&field.to_be_bytes());
+            }
+        }
+        out.extend_from_slice(&self.data);
+        for part in [counters, bitmap, &self.names] {
+            out.extend_from_slice(part);
+            out.resize(out.len() + padding(part.len()), 0);
+        }
+
+        Ok(out)
+    }
+}
+
+fn padding(len: usize) -> usize {
+    (8 - len % 8) % 8
+}
+
+fn read_symbol_u64(file: &object::File, symbol: &object::Symbol) -> anyhow::Result<u64> {
+    let section = symbol
+        .section_index()
+        .map(|index| file.section_by_index(index))
+        .transpose()?
+        .with_context(|| format!("{PRF_RAW_VERSION} is not defined"))?;
+
+    let bytes = section
+        .data_range(symbol.address(), 8)?
+        .with_context(|| format!("{PRF_RAW_VERSION} is not initialized"))?;
+
+    let bytes: [u8; 8] = bytes.try_into()?;
+    Ok(if file.is_little_endian() {
+        u64::from_le_bytes(bytes)
+    } else {
+        u64::from_be_bytes(bytes)
+    })
+}
+
+/// Collects coverage data after the firmware has stopped.
+pub struct CoverageCollector {
+    dir: PathBuf,
+    profile: Option<LlvmProfile>,
+}
+
+impl CoverageCollector {
+    pub fn new(options: &CoverageOptions, elf: &Path) -> anyhow::Result<Option<Self>> {
+        let Some(dir) = options.coverage_dir.clone() else {
+            return Ok(None);
+        };
+
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create coverage directory {}", dir.display()))?;
+
+        let elf = std::fs::read(elf)
+            .with_context(|| format!("Failed to read firmware from {}", elf.display()))?;
+        let profile = LlvmProfile::from_elf(&elf)?;
+        if profile.is_none() {
+            tracing::info!(
+                "The firmware contains no {PRF_CNTS} section, only coverage data written via semihosting will be collected."
+            );
+        }
+
+        Ok(Some(Self { dir, profile }))
+    }
+
+    /// The directory receiving the coverage data.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The directory for coverage data of the given test.
+    pub fn test_dir(&self, test_name: &str) -> PathBuf {
+        self.dir.join(sanitize_filename::sanitize(test_name))
+    }
+
+    /// Reads the counters from the target and writes them to `<name>.profraw`.
+    pub async fn collect(
+        &self,
+        session: &SessionInterface,
+        core: usize,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        let Some(profile) = self.profile.as_ref() else {
+            return Ok(());
+        };
+
+        let core = session.core(core);
+        let counter_range = profile.counter_range();
+        let counters = core
+            .read_memory_8(counter_range.start, counter_range.clone().count())
+            .await
+            .context("Failed to read the coverage counters")?;
+        let bitmap = match profile.bitmap_range() {
+            Some(range) => core
+                .read_memory_8(range.start, range.clone().count())
+                .await
+                .context("Failed to read the coverage bitmap")?,
+            None => vec![],
+        };
+
+        let path = self
+            .dir
+            .join(format!("{}.profraw", sanitize_filename::sanitize(name)));
+        std::fs::write(&path, profile.profraw(&counters, &bitmap)?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        tracing::info!("Coverage data written to {}", path.display());
+
+        Ok(())
+    }
+
+    /// Merges all `.profraw` files in the coverage directory into `lcov.info`.
+    pub fn write_lcov_report(&self, elf: &Path) -> anyhow::Result<()> {
+        let mut profiles = Vec::new();
+        collect_profraw_files(&self.dir, &mut profiles)?;
+        if profiles.is_empty() {
+            anyhow::bail!(
+                "No .profraw files found in {}, can not create an lcov report",
+                self.dir.display()
+            );
+        }
+
+        let profdata = self.dir.join("merged.profdata");
+        let status = Command::new("llvm-profdata")
+            .arg("merge")
+            .arg("--sparse")
+            .args(&profiles)
+            .arg("-o")
+            .arg(&profdata)
+            .status()
+            .context("Failed to run llvm-profdata")?;
+        anyhow::ensure!(status.success(), "llvm-profdata failed with {status}");
+
+        let output = Command::new("llvm-cov")
+            .arg("export")
+            .arg("--format=lcov")
+            .arg("--instr-profile")
+            .arg(&profdata)
+            .arg(elf)
+            .output()
+            .context("Failed to run llvm-cov")?;
+        anyhow::ensure!(
+            output.status.success(),
+            "llvm-cov failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let report = self.dir.join("lcov.info");
+        std::fs::write(&report, output.stdout)
+            .with_context(|| format!("Failed to write {}", report.display()))?;
+        println!("Coverage report written to {}", report.display());
+
+        Ok(())
+    }
+}
+
+fn collect_profraw_files(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_profraw_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "profraw") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn profile(raw_version: u64) -> LlvmProfile {
+        LlvmProfile {
+            raw_version,
+            is_64bit: false,
+            is_little_endian: true,
+            // Two records of 48 bytes each (version 9/10 on a 32-bit target)
+            data: vec![0xaa; 96],
+            data_address: 0x1000,
+            names: vec![0xbb; 5],
+            names_address: 0x2000,
+            counters: 0x2000_0000..0x2000_0010,
+            bitmap: None,
+        }
+    }
+
+    fn header(profraw: &[u8]) -> Vec<u64> {
+        profraw
+            .chunks_exact(8)
+            .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn profraw_v10_layout() {
+        let profile = profile(10);
+        let profraw = profile.profraw(&[1; 16], &[]).unwrap();
+
+        let fields = header(&profraw[..16 * 8]);
+        assert_eq!(fields[0], RAW_MAGIC_32);
+        assert_eq!(fields[1], 10);
+        // NumData
+        assert_eq!(fields[3], 2);
+        // NumCounters
+        assert_eq!(fields[5], 2);
+        // NamesSize
+        assert_eq!(fields[9], 5);
+        // CountersDelta
+        assert_eq!(fields[10], 0x2000_0000 - 0x1000);
+        // NamesDelta
+        assert_eq!(fields[12], 0x2000);
+        // ValueKindLast
+        assert_eq!(fields[15], 2);
+
+        // Header, data, counters, names padded to 8 bytes
+        assert_eq!(profraw.len(), 16 * 8 + 96 + 16 + 8);
+    }
+
+    #[test]
+    fn profraw_byte_coverage() {
+        let profile = profile(10 | VARIANT_MASK_BYTE_COVERAGE);
+        let profraw = profile.profraw(&[1; 16], &[]).unwrap();
+
+        let fields = header(&profraw[..16 * 8]);
+        assert_eq!(fields[5], 16);
+    }
+
+    #[test]
+    fn unsupported_version() {
+        let profile = profile(5);
+        assert!(profile.profraw(&[], &[]).is_err());
+    }
+}
@@ -3,6 +3,8 @@ use probe_rs_rpc::format::{EspFlashFrequency, EspFlashMode, FormatKind, FormatOp
 
 use super::common_options::{BinaryDownloadOptions, LoadedProbeOptions, OperationError};
 use super::logging;
+#[cfg(feature = "mcuboot")]
+use super::mcuboot::{McubootLoader, McubootOptions};
 
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -15,7 +17,8 @@ use parking_lot::Mutex;
 use probe_rs::InstructionSet;
 use probe_rs::flashing::{
     BinLoader, BinOptions, ElfLoader, ElfOptions, FlashError, FlashProgress, HexLoader,
-    ImageLoader, Uf2Loader,
+    ImageLoader, ManifestLoader, ManifestOptions, PreverifyStrategy, SpiNorFlash, Uf2Loader,
+    spi_nor_controller,
 };
 use probe_rs::{
     Session, Target,
@@ -54,7 +57,10 @@ fn run_flash_download_inner(
     options.do_chip_erase = download_options.chip_erase;
     options.disable_double_buffering = download_options.disable_double_buffering;
     options.verify = download_options.verify;
-    options.preverify = download_options.preverify;
+    // The whole image is preverified below, only the checksum preverify is done per sector
+    // while flashing.
+    options.preverify = download_options.preverify_checksum;
+    options.preverify_strategy = PreverifyStrategy::Checksum;
     options.ram_chunk_size = download_options.ram_chunk_size;
 
     let pb = if download_options.disable_progressbars {
@@ -91,7 +97,7 @@ fn run_flash_download_inner(
     // Start timer.
     let flash_timer = Instant::now();
 
-    let run_flash = if options.preverify {
+    let run_flash = if download_options.preverify && !download_options.preverify_checksum {
         match loader.verify(session, &mut options.progress) {
             Ok(_) => false,
             Err(FlashError::Verify) => true,
@@ -160,7 +166,51 @@ pub fn resolve_format_kind(kind: FormatKind, target: &Target) -> FormatKind {
     kind.resolve_default_format(target.default_format.as_deref())
 }
 
-fn format_options_image_loader(options: &FormatOptions, target: &Target) -> Box<dyn ImageLoader> {
+#[cfg(not(feature = "mcuboot"))]
+fn format_options_image_loader(
+    options: &FormatOptions,
+    target: &Target,
+    path: &Path,
+) -> Result<Box<dyn ImageLoader>, FileDownloadError> {
+    if options.mcuboot_options.mcuboot {
+        return Err(probe_rs::flashing::into_format_error(
+            "mcuboot",
+            std::io::Error::other("probe-rs was built without the `mcuboot` feature"),
+        ));
+    }
+
+    Ok(image_loader(options, target, path))
+}
+
+#[cfg(feature = "mcuboot")]
+fn format_options_image_loader(
+    options: &FormatOptions,
+    target: &Target,
+    path: &Path,
+) -> Result<Box<dyn ImageLoader>, FileDownloadError> {
+    let loader = image_loader(options, target, path);
+
+    let mcuboot = &options.mcuboot_options;
+    if !mcuboot.mcuboot {
+        return Ok(loader);
+    }
+
+    Ok(Box::new(McubootLoader {
+        options: McubootOptions {
+            key: mcuboot.mcuboot_key.as_ref().map(PathBuf::from),
+            slot: mcuboot.mcuboot_slot,
+            slot_size: mcuboot.mcuboot_slot_size,
+            header_size: mcuboot.mcuboot_header_size,
+            version: mcuboot.mcuboot_version.clone(),
+            test_swap: mcuboot.mcuboot_test_swap,
+            confirm: mcuboot.mcuboot_confirm,
+            align: mcuboot.mcuboot_align,
+        },
+        image: loader,
+    }))
+}
+
+fn image_loader(options: &FormatOptions, target: &Target, path: &Path) -> Box<dyn ImageLoader> {
     match resolve_format_kind(options.binary_format, target) {
         FormatKind::Target => unreachable!(),
         FormatKind::Bin => Box::new(BinLoader(BinOptions {
@@ -173,6 +223,18 @@ fn format_options_image_loader(options: &FormatOptions, target: &Target) -> Box<
             skip_sections: options.elf_options.skip_section.clone(),
         })),
         FormatKind::Uf2 => Box::new(Uf2Loader),
+        FormatKind::Manifest => Box::new(ManifestLoader(ManifestOptions {
+            base_dir: path.parent().map(Path::to_path_buf),
+            variables: options
+                .manifest_options
+                .manifest_var
+                .iter()
+                .map(|var| {
+                    let (name, value) = var.split_once('=').unwrap_or((var, ""));
+                    (name.to_string(), value.to_string())
+                })
+                .collect(),
+        })),
 
         FormatKind::Idf => Box::new(IdfLoader {
             bootloader: options
@@ -204,8 +266,30 @@ pub fn build_loader(
     format_options: FormatOptions,
     image_instruction_set: Option<InstructionSet>,
 ) -> Result<FlashLoader, FileDownloadError> {
-    let loader = format_options_image_loader(&format_options, session.target());
-    probe_rs::flashing::build_loader(session, path, loader, image_instruction_set)
+    let image_loader =
+        format_options_image_loader(&format_options, session.target(), path.as_ref())?;
+
+    if !format_options.external_flash_options.external_flash {
+        return probe_rs::flashing::build_loader(
+            session,
+            path,
+            image_loader,
+            image_instruction_set,
+        );
+    }
+
+    // The external flash has to be known before the image is added to the loader.
+    let controller =
+        spi_nor_controller(session.target()).ok_or_else(|| FlashError::NoSpiNorController {
+            target: session.target().name.clone(),
+        })?;
+    let mut loader = session.target().flash_loader();
+    loader.add_external_flash(SpiNorFlash::probe(session, controller)?);
+
+    let mut file = std::fs::File::open(path).map_err(FileDownloadError::IO)?;
+    loader.load_image(session, &mut file, image_loader, image_instruction_set)?;
+
+    Ok(loader)
 }
 
 #[derive(Default)]
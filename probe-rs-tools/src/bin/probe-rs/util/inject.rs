@@ -0,0 +1,334 @@
+//! Injection of device specific data, like serial numbers or MAC addresses, at flash time.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context as _;
+use probe_rs::flashing::{ManifestPatch, PatchKind, elf_symbol_load_range};
+use probe_rs_rpc::flash::{InjectedData, InjectionLocation};
+use probe_rs_rpc_client::SessionInterface;
+
+use crate::rpc::{FlashLoader, Key};
+use crate::util::parse_u64;
+
+/// Options to inject device specific data into the image.
+///
+/// A location is an address, or the name of an ELF symbol. Data at an address is added to the
+/// image and must not overlap it. Data for a symbol replaces the placeholder value of the symbol,
+/// and must have its size; strings are padded with zeros. A type is one of `u8`, `u16`, `u32`,
+/// `u64` (little endian), `string` or `bytes` (a hex string).
+#[derive(Debug, Default, clap::Parser)]
+pub struct InjectOptions {
+    /// Injects a value, as `LOCATION:TYPE=VALUE`, e.g. `SERIAL_NUMBER:u32=1234`.
+    #[arg(
+        long,
+        value_name = "LOCATION:TYPE=VALUE",
+        help_heading = "DATA INJECTION"
+    )]
+    pub inject: Vec<String>,
+
+    /// Injects the number stored in a counter file, as `LOCATION:TYPE=FILE`. The counter is
+    /// incremented after the device was flashed.
+    #[arg(
+        long,
+        value_name = "LOCATION:TYPE=FILE",
+        help_heading = "DATA INJECTION"
+    )]
+    pub inject_counter: Vec<String>,
+
+    /// Injects a row of a CSV file. The header of each column is `LOCATION:TYPE`. Values
+    /// containing commas or quotes are quoted as usual in CSV.
+    #[arg(long, requires = "inject_csv_row", help_heading = "DATA INJECTION")]
+    pub inject_csv: Option<PathBuf>,
+
+    /// The row of the CSV file to inject, starting at 1 for the row after the header.
+    #[arg(long, requires = "inject_csv", help_heading = "DATA INJECTION")]
+    pub inject_csv_row: Option<usize>,
+
+    /// Injects the CRC32 of an address range as a little endian word, as `START..END=LOCATION`.
+    /// The checksum is computed after all other data was injected.
+    #[arg(
+        long,
+        value_name = "START..END=LOCATION",
+        help_heading = "DATA INJECTION"
+    )]
+    pub inject_crc: Vec<String>,
+}
+
+/// Injections that are resolved on the host, and can be sent to the flash loader.
+#[derive(Debug, Default)]
+pub struct Injections {
+    data: Vec<(InjectionLocation, InjectedData)>,
+    counters: Vec<(PathBuf, u64)>,
+}
+
+impl InjectOptions {
+    /// Reads all values, and resolves the locations with the symbols of the ELF `image`.
+    pub fn resolve(&self, image: &Path) -> anyhow::Result<Injections> {
+        let mut resolver = LocationResolver { image, elf: None };
+        let mut injections = Injections::default();
+
+        for inject in &self.inject {
+            let (location, kind, value) = parse_assignment(inject)?;
+            let location = resolver.resolve(location)?;
+            injections.push_value(location, kind, value)?;
+        }
+
+        for counter in &self.inject_counter {
+            let (location, kind, file) = parse_assignment(counter)?;
+            let location = resolver.resolve(location)?;
+
+            let path = PathBuf::from(file);
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read the counter file {}", path.display()))?;
+            let count = parse_u64(content.trim())
+                .with_context(|| format!("{} does not contain a number", path.display()))?;
+
+            injections.push_value(location, kind, &count.to_string())?;
+            injections.counters.push((path, count));
+        }
+
+        if let (Some(csv), Some(row)) = (&self.inject_csv, self.inject_csv_row) {
+            let (columns, values) = read_csv_row(csv, row)?;
+            for (column, value) in columns.iter().zip(values.iter()) {
+                let (location, kind) = parse_location(column)?;
+                let location = resolver.resolve(location)?;
+                injections.push_value(location, kind, value)?;
+            }
+        }
+
+        for crc in &self.inject_crc {
+            let (range, location) = crc
+                .split_once('=')
+                .with_context(|| format!("Expected `START..END=LOCATION`, got `{crc}`"))?;
+            let (start, end) = range
+                .split_once("..")
+                .with_context(|| format!("Expected an address range, got `{range}`"))?;
+
+            let location = resolver.resolve(location.trim())?;
+            injections.data.push((
+                location,
+                InjectedData::Crc32 {
+                    start: parse_u64(start.trim())?,
+                    end: parse_u64(end.trim())?,
+                },
+            ));
+        }
+
+        Ok(injections)
+    }
+}
+
+impl Injections {
+    fn push_value(
+        &mut self,
+        location: InjectionLocation,
+        kind: PatchKind,
+        value: &str,
+    ) -> anyhow::Result<()> {
+        let (address, size) = match location {
+            InjectionLocation::Address(address) => (address, None),
+            InjectionLocation::Placeholder { start, end } => (start, Some((end - start) as usize)),
+        };
+        let patch = ManifestPatch {
+            address,
+            kind,
+            value: value.to_string(),
+            size,
+        };
+        self.data.push((
+            location,
+            InjectedData::Bytes(patch.encode(&HashMap::new())?),
+        ));
+        Ok(())
+    }
+
+    /// Adds the data to the flash loader. Checksums are added last, so they cover all other data.
+    pub async fn apply(
+        &self,
+        session: &SessionInterface,
+        loader: Key<FlashLoader>,
+    ) -> anyhow::Result<()> {
+        let (checksums, values): (Vec<_>, Vec<_>) = self
+            .data
+            .iter()
+            .partition(|(_, data)| matches!(data, InjectedData::Crc32 { .. }));
+
+        for (location, data) in values.into_iter().chain(checksums) {
+            session
+                .inject_data(loader, location.clone(), data.clone())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Increments the counters, after the device was flashed.
+    pub fn increment_counters(&self) -> anyhow::Result<()> {
+        for (path, count) in &self.counters {
+            std::fs::write(path, format!("{}\n", count + 1))
+                .with_context(|| format!("Failed to update the counter file {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Resolves locations, and reads the ELF image only if a symbol is used.
+struct LocationResolver<'a> {
+    image: &'a Path,
+    elf: Option<Vec<u8>>,
+}
+
+impl LocationResolver<'_> {
+    /// Resolves an address, or the placeholder value of a symbol.
+    fn resolve(&mut self, location: &str) -> anyhow::Result<InjectionLocation> {
+        if let Ok(address) = parse_u64(location) {
+            return Ok(InjectionLocation::Address(address));
+        }
+
+        let elf = match &mut self.elf {
+            Some(elf) => elf,
+            elf => elf.insert(
+                std::fs::read(self.image)
+                    .with_context(|| format!("Failed to read {}", self.image.display()))?,
+            ),
+        };
+
+        let range = elf_symbol_load_range(elf, location)
+            .context("Symbols can only be used with ELF images")?
+            .with_context(|| format!("The image has no loadable symbol `{location}`"))?;
+
+        Ok(InjectionLocation::Placeholder {
+            start: range.start,
+            end: range.end,
+        })
+    }
+}
+
+/// Reads the header and the row `row` of a CSV file, starting at 1 for the row after the header.
+fn read_csv_row(path: &Path, row: usize) -> anyhow::Result<(Vec<String>, Vec<String>)> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_path(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let columns = reader
+        .headers()
+        .with_context(|| format!("Failed to read the header of {}", path.display()))?
+        .iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+    let values = reader
+        .records()
+        .nth(row.checked_sub(1).context("CSV rows start at 1")?)
+        .with_context(|| format!("The CSV file has no row {row}"))?
+        .with_context(|| format!("Failed to read row {row} of {}", path.display()))?
+        .iter()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    Ok((columns, values))
+}
+
+/// Splits `LOCATION:TYPE=VALUE`.
+fn parse_assignment(input: &str) -> anyhow::Result<(&str, PatchKind, &str)> {
+    let (target, value) = input
+        .split_once('=')
+        .with_context(|| format!("Expected `LOCATION:TYPE=VALUE`, got `{input}`"))?;
+    let (location, kind) = parse_location(target)?;
+    Ok((location, kind, value.trim()))
+}
+
+/// Splits `LOCATION:TYPE`.
+fn parse_location(input: &str) -> anyhow::Result<(&str, PatchKind)> {
+    let (location, kind) = input
+        .trim()
+        .rsplit_once(':')
+        .with_context(|| format!("Expected `LOCATION:TYPE`, got `{input}`"))?;
+
+    let kind = match kind.trim().to_ascii_lowercase().as_str() {
+        "u8" => PatchKind::U8,
+        "u16" => PatchKind::U16,
+        "u32" => PatchKind::U32,
+        "u64" => PatchKind::U64,
+        "string" | "str" => PatchKind::String,
+        "bytes" | "hex" => PatchKind::Bytes,
+        other => anyhow::bail!("Unknown type `{other}`"),
+    };
+
+    Ok((location.trim(), kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_injections() {
+        let options = InjectOptions {
+            inject: vec!["0x20000000:u16=0x1234".to_string()],
+            inject_crc: vec!["0x0..0x10=0x10".to_string()],
+            ..Default::default()
+        };
+
+        let injections = options.resolve(Path::new("image.elf")).unwrap();
+        assert!(matches!(
+            &injections.data[0],
+            (InjectionLocation::Address(0x2000_0000), InjectedData::Bytes(data))
+                if data == &[0x34, 0x12]
+        ));
+        assert!(matches!(
+            injections.data[1],
+            (
+                InjectionLocation::Address(0x10),
+                InjectedData::Crc32 {
+                    start: 0,
+                    end: 0x10
+                }
+            )
+        ));
+    }
+
+    #[test]
+    fn pad_strings_to_the_placeholder() {
+        let mut injections = Injections::default();
+        injections
+            .push_value(
+                InjectionLocation::Placeholder { start: 0, end: 4 },
+                PatchKind::String,
+                "AB",
+            )
+            .unwrap();
+        assert!(matches!(
+            &injections.data[0],
+            (_, InjectedData::Bytes(data)) if data == b"AB\0\0"
+        ));
+    }
+
+    #[test]
+    fn read_quoted_csv_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("devices.csv");
+        std::fs::write(
+            &path,
+            "0x100:string, 0x200:u32\n\"A, \"\"B\"\"\", 1\n\"C\", 2\n",
+        )
+        .unwrap();
+
+        let (columns, values) = read_csv_row(&path, 1).unwrap();
+        assert_eq!(columns, ["0x100:string", "0x200:u32"]);
+        assert_eq!(values, ["A, \"B\"", "1"]);
+        assert_eq!(read_csv_row(&path, 2).unwrap().1, ["C", "2"]);
+        assert!(read_csv_row(&path, 3).is_err());
+    }
+
+    #[test]
+    fn reject_invalid_injections() {
+        assert!(parse_assignment("0x1000=1").is_err());
+        assert!(parse_assignment("0x1000:u128=1").is_err());
+        assert_eq!(
+            parse_location(" SERIAL : string ").unwrap(),
+            ("SERIAL", PatchKind::String)
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! Reconstruction of the executed instructions from a decoded ETM instruction trace.
+//!
+//! The trace only tells which branches were taken, so the instructions between them are read
+//! from the program image and disassembled to find the next branch.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, anyhow};
+use capstone::{
+    Capstone, InsnGroupId, InsnGroupType, RegId,
+    arch::{
+        ArchOperand, BuildsCapstone, BuildsCapstoneEndian, BuildsCapstoneExtraMode,
+        arm::{self, ArmOperandType, ArmReg},
+        arm64::{self, Arm64OperandType},
+    },
+};
+use object::{Object, ObjectSection, SectionKind};
+use probe_rs::{
+    CoreType, InstructionSet,
+    architecture::arm::etm::{Protocol, TraceElement},
+};
+
+/// The maximum number of instructions to follow without reaching a waypoint.
+///
+/// This stops the reconstruction from running off into data if the trace does not match the
+/// program image.
+const MAX_INSTRUCTIONS_WITHOUT_WAYPOINT: usize = 4096;
+
+/// An entry of the execution history.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryEntry {
+    /// An instruction was executed.
+    Instruction {
+        address: u64,
+        /// The disassembled instruction.
+        text: String,
+        /// `false` if the condition of the instruction failed.
+        executed: bool,
+    },
+    /// An exception was taken.
+    Exception { number: u16 },
+    /// The core returned from an exception.
+    ExceptionReturn,
+    /// The execution history is incomplete here, because trace data was lost or did not match
+    /// the program image.
+    Gap,
+}
+
+/// The code sections of a program.
+pub struct ProgramImage {
+    sections: Vec<(u64, Vec<u8>)>,
+}
+
+impl ProgramImage {
+    /// Loads the code sections of an ELF file.
+    pub fn from_elf(elf: &[u8]) -> anyhow::Result<Self> {
+        let file = object::File::parse(elf).context("Failed to parse ELF file")?;
+
+        let mut sections = vec![];
+        for section in file.sections() {
+            if section.kind() == SectionKind::Text {
+                sections.push((section.address(), section.data()?.to_vec()));
+            }
+        }
+
+        if sections.is_empty() {
+            return Err(anyhow!("The ELF file contains no code"));
+        }
+        Ok(Self { sections })
+    }
+
+    fn read(&self, address: u64) -> Option<&[u8]> {
+        self.sections.iter().find_map(|(start, data)| {
+            let offset = usize::try_from(address.checked_sub(*start)?).ok()?;
+            data.get(offset..)
+        })
+    }
+}
+
+/// A decoded instruction.
+#[derive(Clone)]
+struct Instruction {
+    size: u64,
+    text: String,
+    branch: Option<Branch>,
+}
+
+#[derive(Clone)]
+enum Branch {
+    /// The branch target is encoded in the instruction.
+    Direct(u64),
+    /// The branch target is only known at runtime, and reported by the trace.
+    Indirect,
+}
+
+/// Reconstructs the executed instructions from the trace `elements`.
+pub fn reconstruct(
+    protocol: Protocol,
+    core_type: CoreType,
+    elements: &[TraceElement],
+    image: &ProgramImage,
+) -> anyhow::Result<Vec<HistoryEntry>> {
+    let mut reconstruction = Reconstruction {
+        protocol,
+        core_type,
+        image,
+        disassemblers: vec![],
+        cache: HashMap::new(),
+        position: None,
+        history: vec![],
+    };
+
+    for element in elements {
+        reconstruction.apply(element)?;
+    }
+
+    Ok(reconstruction.history)
+}
+
+struct Reconstruction<'a> {
+    protocol: Protocol,
+    core_type: CoreType,
+    image: &'a ProgramImage,
+    disassemblers: Vec<(InstructionSet, Capstone)>,
+    /// The disassembled instructions by address and instruction set.
+    cache: HashMap<(u64, u8), Option<Instruction>>,
+    /// The address and instruction set of the next instruction, if known.
+    position: Option<(u64, InstructionSet)>,
+    history: Vec<HistoryEntry>,
+}
+
+impl Reconstruction<'_> {
+    fn apply(&mut self, element: &TraceElement) -> anyhow::Result<()> {
+        match *element {
+            TraceElement::Sync {
+                address,
+                instruction_set,
+            }
+            | TraceElement::Address {
+                address,
+                instruction_set,
+            } => self.position = Some((address, instruction_set)),
+            TraceElement::Atom { executed } => {
+                if self.protocol.is_waypoint_based() {
+                    self.follow_to_waypoint(executed)?;
+                } else {
+                    self.execute_one(executed)?;
+                }
+            }
+            TraceElement::Exception {
+                number,
+                return_address,
+            } => {
+                if let Some(return_address) = return_address {
+                    self.follow_to(return_address)?;
+                }
+                self.history.push(HistoryEntry::Exception { number });
+                // The next address element is the exception handler.
+                self.position = None;
+            }
+            TraceElement::ExceptionReturn => self.history.push(HistoryEntry::ExceptionReturn),
+            TraceElement::Discontinuity => self.gap(),
+        }
+        Ok(())
+    }
+
+    fn gap(&mut self) {
+        self.position = None;
+        if !matches!(self.history.last(), None | Some(HistoryEntry::Gap)) {
+            self.history.push(HistoryEntry::Gap);
+        }
+    }
+
+    /// Executes the instructions up to and including the next branch.
+    fn follow_to_waypoint(&mut self, executed: bool) -> anyhow::Result<()> {
+        for _ in 0..MAX_INSTRUCTIONS_WITHOUT_WAYPOINT {
+            let Some((address, instruction_set)) = self.position else {
+                return Ok(());
+            };
+            let Some(instruction) = self.disassemble(address, instruction_set)? else {
+                self.gap();
+                return Ok(());
+            };
+
+            let is_branch = instruction.branch.is_some();
+            self.push_instruction(
+                address,
+                instruction_set,
+                instruction,
+                executed || !is_branch,
+            );
+            if is_branch {
+                return Ok(());
+            }
+        }
+
+        self.gap();
+        Ok(())
+    }
+
+    /// Executes one instruction.
+    fn execute_one(&mut self, executed: bool) -> anyhow::Result<()> {
+        let Some((address, instruction_set)) = self.position else {
+            return Ok(());
+        };
+        match self.disassemble(address, instruction_set)? {
+            Some(instruction) => {
+                self.push_instruction(address, instruction_set, instruction, executed)
+            }
+            None => self.gap(),
+        }
+        Ok(())
+    }
+
+    /// Executes the instructions up to `end`, which must not contain any branch.
+    fn follow_to(&mut self, end: u64) -> anyhow::Result<()> {
+        for _ in 0..MAX_INSTRUCTIONS_WITHOUT_WAYPOINT {
+            let Some((address, instruction_set)) = self.position else {
+                return Ok(());
+            };
+            if address == end {
+                return Ok(());
+            }
+            match self.disassemble(address, instruction_set)? {
+                Some(instruction) if instruction.branch.is_none() => {
+                    self.push_instruction(address, instruction_set, instruction, true)
+                }
+                _ => break,
+            }
+        }
+
+        self.gap();
+        Ok(())
+    }
+
+    fn push_instruction(
+        &mut self,
+        address: u64,
+        instruction_set: InstructionSet,
+        instruction: Instruction,
+        executed: bool,
+    ) {
+        let next = address + instruction.size;
+        self.position = match instruction.branch {
+            Some(Branch::Direct(target)) if executed => Some((target, instruction_set)),
+            // The target is reported by the next address element.
+            Some(Branch::Indirect) if executed => None,
+            _ => Some((next, instruction_set)),
+        };
+
+        self.history.push(HistoryEntry::Instruction {
+            address,
+            text: instruction.text,
+            executed,
+        });
+    }
+
+    /// Disassembles the instruction at `address`, or returns `None` if it is not part of the
+    /// program image.
+    fn disassemble(
+        &mut self,
+        address: u64,
+        instruction_set: InstructionSet,
+    ) -> anyhow::Result<Option<Instruction>> {
+        let key = (address, instruction_set as u8);
+        if let Some(cached) = self.cache.get(&key) {
+            return Ok(cached.clone());
+        }
+
+        let instruction = match self.image.read(address) {
+            // A64 and A32 instructions are 4 bytes, T32 instructions are 2 or 4 bytes.
+            Some(code) => {
+                self.disassemble_uncached(&code[..code.len().min(4)], address, instruction_set)?
+            }
+            None => None,
+        };
+        self.cache.insert(key, instruction.clone());
+        Ok(instruction)
+    }
+
+    fn disassemble_uncached(
+        &mut self,
+        code: &[u8],
+        address: u64,
+        instruction_set: InstructionSet,
+    ) -> anyhow::Result<Option<Instruction>> {
+        let index = match self
+            .disassemblers
+            .iter()
+            .position(|(set, _)| *set == instruction_set)
+        {
+            Some(index) => index,
+            None => {
+                let disassembler = create_disassembler(instruction_set, self.core_type)?;
+                self.disassemblers.push((instruction_set, disassembler));
+                self.disassemblers.len() - 1
+            }
+        };
+        let disassembler = &self.disassemblers[index].1;
+
+        let instructions = disassembler
+            .disasm_count(code, address, 1)
+            .map_err(|error| anyhow!("Failed to disassemble {address:#010x}: {error}"))?;
+        let Some(instruction) = instructions.iter().next() else {
+            return Ok(None);
+        };
+        let detail = disassembler
+            .insn_detail(instruction)
+            .map_err(|error| anyhow!("Failed to disassemble {address:#010x}: {error}"))?;
+
+        let is_branch = detail.groups().iter().any(|&InsnGroupId(group)| {
+            matches!(
+                u32::from(group),
+                InsnGroupType::CS_GRP_JUMP
+                    | InsnGroupType::CS_GRP_CALL
+                    | InsnGroupType::CS_GRP_RET
+                    | InsnGroupType::CS_GRP_IRET
+                    | InsnGroupType::CS_GRP_BRANCH_RELATIVE
+            )
+        });
+
+        let operands = detail.arch_detail().operands();
+        // Instructions like `pop {pc}` and `ldr pc, [..]` are branches too.
+        let writes_pc = operands.iter().any(|operand| match operand {
+            ArchOperand::ArmOperand(arm::ArmOperand {
+                op_type: ArmOperandType::Reg(RegId(reg)),
+                access,
+                ..
+            }) => {
+                u32::from(*reg) == ArmReg::ARM_REG_PC
+                    && access.is_some_and(|access| access.is_writable())
+            }
+            _ => false,
+        });
+
+        let branch = if is_branch || writes_pc {
+            // The target of a direct branch is its last operand.
+            Some(match operands.last() {
+                Some(ArchOperand::ArmOperand(arm::ArmOperand {
+                    op_type: ArmOperandType::Imm(target),
+                    ..
+                })) => Branch::Direct(u64::from(*target as u32)),
+                Some(ArchOperand::Arm64Operand(arm64::Arm64Operand {
+                    op_type: Arm64OperandType::Imm(target),
+                    ..
+                })) => Branch::Direct(*target as u64),
+                _ => Branch::Indirect,
+            })
+        } else {
+            None
+        };
+
+        let text = format!(
+            "{} {}",
+            instruction.mnemonic().unwrap_or("<unknown>"),
+            instruction.op_str().unwrap_or_default()
+        );
+        Ok(Some(Instruction {
+            size: instruction.len() as u64,
+            text: text.trim_end().to_string(),
+            branch,
+        }))
+    }
+}
+
+fn create_disassembler(
+    instruction_set: InstructionSet,
+    core_type: CoreType,
+) -> anyhow::Result<Capstone> {
+    let mut disassembler = match instruction_set {
+        InstructionSet::Thumb2 => {
+            let mut extra_modes = vec![];
+            if core_type.is_cortex_m() {
+                extra_modes.push(arm::ArchExtraMode::MClass);
+            }
+            if matches!(core_type, CoreType::Armv8m | CoreType::Armv8a) {
+                extra_modes.push(arm::ArchExtraMode::V8);
+            }
+            Capstone::new()
+                .arm()
+                .mode(arm::ArchMode::Thumb)
+                .endian(capstone::Endian::Little)
+                .extra_mode(extra_modes.into_iter())
+                .build()
+        }
+        InstructionSet::A32 => Capstone::new()
+            .arm()
+            .mode(arm::ArchMode::Arm)
+            .endian(capstone::Endian::Little)
+            .build(),
+        InstructionSet::A64 => Capstone::new()
+            .arm64()
+            .mode(arm64::ArchMode::Arm)
+            .endian(capstone::Endian::Little)
+            .build(),
+        other => return Err(anyhow!("Instruction trace is not supported for {other:?}")),
+    }
+    .map_err(|error| anyhow!("Error creating capstone: {error:?}"))?;
+
+    disassembler
+        .set_detail(true)
+        .map_err(|error| anyhow!("Error creating capstone: {error:?}"))?;
+    Ok(disassembler)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reconstruct_thumb_branches() {
+        let code: [u16; 7] = [
+            0x2001, // 0x100: movs r0, #1
+            0xE001, // 0x102: b 0x108
+            0x2002, // 0x104: movs r0, #2
+            0x4770, // 0x106: bx lr
+            0x2803, // 0x108: cmp r0, #3
+            0xD0FB, // 0x10A: beq 0x104
+            0x4770, // 0x10C: bx lr
+        ];
+        let image = ProgramImage {
+            sections: vec![(0x100, code.iter().flat_map(|c| c.to_le_bytes()).collect())],
+        };
+
+        let elements = [
+            TraceElement::Sync {
+                address: 0x100,
+                instruction_set: InstructionSet::Thumb2,
+            },
+            TraceElement::Atom { executed: true },
+            TraceElement::Atom { executed: false },
+            TraceElement::Atom { executed: true },
+            TraceElement::Address {
+                address: 0x200,
+                instruction_set: InstructionSet::Thumb2,
+            },
+            TraceElement::Atom { executed: true },
+        ];
+
+        let history = reconstruct(Protocol::EtmV4, CoreType::Armv8m, &elements, &image).unwrap();
+
+        let instructions = history
+            .iter()
+            .map(|entry| match entry {
+                HistoryEntry::Instruction {
+                    address, executed, ..
+                } => Some((*address, *executed)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            instructions,
+            vec![
+                Some((0x100, true)),
+                Some((0x102, true)),
+                Some((0x108, true)),
+                Some((0x10A, false)),
+                Some((0x10C, true)),
+                None,
+            ]
+        );
+        assert_eq!(history.last(), Some(&HistoryEntry::Gap));
+    }
+}
@@ -1,8 +1,13 @@
 pub mod cargo;
 pub mod cli;
 pub mod common_options;
+pub mod coverage;
 pub mod flash;
+pub mod inject;
+pub mod instruction_trace;
 pub mod logging;
+#[cfg(feature = "mcuboot")]
+pub mod mcuboot;
 pub mod meta;
 pub mod pwr;
 pub mod rtt;
@@ -11,6 +16,10 @@ pub mod visualizer;
 
 use std::num::ParseIntError;
 
+pub fn parse_u32(input: &str) -> Result<u32, ParseIntError> {
+    parse_int::parse(input)
+}
+
 pub fn parse_u64(input: &str) -> Result<u64, ParseIntError> {
     parse_int::parse(input)
 }
@@ -6,6 +6,7 @@ use serde::{Deserialize, Serialize};
 
 pub(crate) mod client;
 pub(crate) mod processing;
+pub(crate) mod recording;
 
 pub use processing::*;
 
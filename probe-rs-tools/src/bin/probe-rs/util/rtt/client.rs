@@ -2,18 +2,18 @@ use crate::util::rtt::{RttActiveDownChannel, RttActiveUpChannel, RttConfig, RttC
 use probe_rs::{
     Core, MemoryInterface, Target,
     flashing::FlashLoader,
-    rtt::{Error, Rtt, ScanRegion},
+    rtt::{Error, Rtt, RttDiscovery, RttDiscoveryStrategy, RttLocation},
 };
 use probe_rs_rpc::rtt_config::ChannelMode;
 
 pub struct RttClient {
-    pub scan_region: ScanRegion,
+    pub discovery: RttDiscovery,
     channel_modes: Vec<Option<ChannelMode>>,
     need_configure: bool,
 
     /// The internal RTT handle, if we have successfully attached to the target.
     target: Option<RttConnection>,
-    last_control_block_address: Option<u64>,
+    location: Option<RttLocation>,
 
     /// If the control block is initialized by the flasher, this flag is used to prevent
     /// clearing the control block when the target is reset.
@@ -31,20 +31,19 @@ pub struct RttClient {
 }
 
 impl RttClient {
-    pub fn new(config: RttConfig, scan_region: ScanRegion, target: &Target) -> Self {
-        let core_id = if let ScanRegion::Exact(address) = scan_region {
-            target.core_index_by_address(address).unwrap_or(0)
-        } else {
-            0
-        };
+    pub fn new(config: RttConfig, discovery: RttDiscovery, target: &Target) -> Self {
+        let core_id = discovery
+            .known_addresses()
+            .find_map(|address| target.core_index_by_address(address))
+            .unwrap_or(0);
 
         Self {
-            scan_region,
+            discovery,
             channel_modes: config.channels.iter().map(|c| c.mode).collect(),
             need_configure: true,
 
             target: None,
-            last_control_block_address: None,
+            location: None,
             disallow_clearing_rtt_header: false,
             try_attaching: true,
             polled_data: false,
@@ -62,8 +61,10 @@ impl RttClient {
         // header's final address in RAM, then it's not relocated on startup and we should not
         // clear it. This impacts static RTT headers, like used in defmt_rtt.
 
-        if let ScanRegion::Exact(address) = self.scan_region
-            && loader.has_data_for_address(address)
+        if self
+            .discovery
+            .known_addresses()
+            .any(|address| loader.has_data_for_address(address))
         {
             tracing::debug!(
                 "RTT control block is initialized by flash loader. Disabling clearing."
@@ -76,6 +77,11 @@ impl RttClient {
         self.target.is_some()
     }
 
+    /// Returns where the control block was found, and by which strategy.
+    pub fn location(&self) -> Option<RttLocation> {
+        self.location
+    }
+
     fn try_attach_impl(&mut self, core: &mut Core) -> Result<bool, Error> {
         if self.is_attached() {
             return Ok(true);
@@ -85,10 +91,10 @@ impl RttClient {
             return Ok(false);
         }
 
-        let location = if let Some(location) = self.last_control_block_address {
+        let location = if let Some(location) = self.location {
             location
         } else {
-            let location = match Rtt::find_control_block(core, &self.scan_region) {
+            let location = match self.discovery.locate(core) {
                 Ok(location) => location,
                 Err(Error::ControlBlockNotFound) => {
                     tracing::debug!("Failed to attach - control block not found");
@@ -102,14 +108,14 @@ impl RttClient {
                 Err(error) => return Err(error),
             };
 
-            self.last_control_block_address = Some(location);
+            self.location = Some(location);
             location
         };
 
-        let rtt = match Rtt::attach_at(core, location) {
+        let rtt = match Rtt::attach_at(core, location.address) {
             Ok(rtt) => rtt,
             Err(Error::ControlBlockNotFound) => {
-                self.last_control_block_address = None;
+                self.location = None;
                 tracing::debug!("Failed to attach - control block not found");
                 return Ok(false);
             }
@@ -121,7 +127,14 @@ impl RttClient {
         };
 
         match RttConnection::new(rtt) {
-            Ok(rtt) => self.target = Some(rtt),
+            Ok(rtt) => {
+                tracing::info!(
+                    "Attached to RTT control block at {:#010x}, found by {}",
+                    location.address,
+                    location.strategy
+                );
+                self.target = Some(rtt);
+            }
             Err(Error::ControlBlockCorrupted(error)) => {
                 tracing::debug!("Failed to attach - control block corrupted: {}", error);
             }
@@ -221,26 +234,24 @@ impl RttClient {
             // While the entire block isn't valid in itself, some parts of it may be.
             // Depending on the firmware, the control block may be initialized in such
             // an order where probe-rs can attach to it before it is fully valid.
-            if let Some(location) = self.last_control_block_address.take() {
-                if let ScanRegion::Exact(scan_location) = self.scan_region {
+            if let Some(location) = self.location.take() {
+                if location.strategy != RttDiscoveryStrategy::Scan {
                     // If we know the exact location where a control block should be, we can clear
                     // the whole block.
-                    if location == scan_location {
-                        if core.is_64_bit() {
-                            const SIZE_64B: usize = 16 + 2 * 8;
-                            core.write_8(location, &[0; SIZE_64B])?;
-                        } else {
-                            const SIZE_32B: usize = 16 + 2 * 4;
-                            core.write_8(location, &[0; SIZE_32B])?;
-                        }
+                    if core.is_64_bit() {
+                        const SIZE_64B: usize = 16 + 2 * 8;
+                        core.write_8(location.address, &[0; SIZE_64B])?;
+                    } else {
+                        const SIZE_32B: usize = 16 + 2 * 4;
+                        core.write_8(location.address, &[0; SIZE_32B])?;
                     }
                 } else {
                     // If we have to scan for the location or we somehow found the magic string
                     // somewhere else, we can only clear the magic string.
                     let mut magic = [0; Rtt::RTT_ID.len()];
-                    core.read_8(location, &mut magic)?;
+                    core.read_8(location.address, &mut magic)?;
                     if magic == Rtt::RTT_ID {
-                        core.write_8(location, &[0; 16])?;
+                        core.write_8(location.address, &[0; 16])?;
                     }
                 }
             }
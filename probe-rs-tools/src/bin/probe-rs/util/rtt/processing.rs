@@ -3,7 +3,7 @@ use defmt_decoder::{
     DecodeError, StreamDecoder,
     log::format::{Formatter, FormatterConfig, FormatterFormat},
 };
-use probe_rs::rtt::Error;
+use probe_rs::rtt::{Error, RttChannelDecoder, rtt_decoder, rtt_decoder_for_channel};
 use time::{OffsetDateTime, UtcOffset, macros::format_description};
 
 use std::{
@@ -27,6 +27,10 @@ pub enum RttDecoder {
     Defmt {
         processor: DefmtProcessor,
     },
+    /// A decoder registered by a plugin.
+    Plugin {
+        decoder: Box<dyn RttChannelDecoder>,
+    },
 }
 
 impl From<&RttDecoder> for DataFormat {
@@ -35,6 +39,7 @@ impl From<&RttDecoder> for DataFormat {
             RttDecoder::String { .. } => DataFormat::String,
             RttDecoder::BinaryLE => DataFormat::BinaryLE,
             RttDecoder::Defmt { .. } => DataFormat::Defmt,
+            RttDecoder::Plugin { .. } => DataFormat::String,
         }
     }
 }
@@ -54,11 +59,28 @@ impl fmt::Debug for RttDecoder {
                 .finish(),
             RttDecoder::BinaryLE => f.debug_struct("BinaryLE").finish(),
             RttDecoder::Defmt { .. } => f.debug_struct("Defmt").finish_non_exhaustive(),
+            RttDecoder::Plugin { .. } => f.debug_struct("Plugin").finish_non_exhaustive(),
         }
     }
 }
 
 impl RttDecoder {
+    /// Creates a decoder for the channel with the plugin decoder of the given name.
+    pub fn plugin(name: &str, channel_name: &str) -> Option<Self> {
+        let factory = rtt_decoder(name)?;
+        Some(RttDecoder::Plugin {
+            decoder: factory.create(channel_name),
+        })
+    }
+
+    /// Creates a decoder for the channel if a plugin decoder claims the channel name.
+    pub fn plugin_for_channel(channel_name: &str) -> Option<Self> {
+        let factory = rtt_decoder_for_channel(channel_name)?;
+        Some(RttDecoder::Plugin {
+            decoder: factory.create(channel_name),
+        })
+    }
+
     /// Returns whether the channel is expected to output binary data (`true`)
     /// or human-readable strings (`false`).
     pub fn is_binary(&self) -> bool {
@@ -91,6 +113,7 @@ impl RttDecoder {
 
                 ProcessedRttData::String(string)
             }
+            RttDecoder::Plugin { decoder } => ProcessedRttData::String(decoder.decode(buffer)?),
         };
 
         Ok(Some(data))
@@ -0,0 +1,515 @@
+//! Recording of raw RTT traffic, and replay of recordings without a probe.
+//!
+//! A recording starts with an 8 byte magic and a little endian `u32` version, followed by
+//! records. Every record starts with a tag byte and the time since the start of the recording
+//! in microseconds as a little endian `u64`:
+//!
+//! | Tag | Record   | Content                                                     |
+//! |-----|----------|-------------------------------------------------------------|
+//! | 0   | Channels | `u32` up channel count, up channels, the same for down      |
+//! | 1   | Up       | `u32` channel number, `u32` length, the bytes read          |
+//! | 2   | Down     | `u32` channel number, `u32` length, the bytes written       |
+//!
+//! A channel is stored as `u32` number, `u64` buffer size, `u32` name length and the name.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, bail};
+use probe_rs_rpc::monitor::{ChannelInfo, RttEvent};
+use probe_rs_rpc_client::MonitorEvent;
+
+const MAGIC: [u8; 8] = *b"PRSRTT\0\0";
+const VERSION: u32 = 1;
+
+const TAG_CHANNELS: u8 = 0;
+const TAG_UP: u8 = 1;
+const TAG_DOWN: u8 = 2;
+
+/// The longest time records are buffered before they are written to the recording file.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An RTT channel as it was discovered on the target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedChannel {
+    pub number: u32,
+    pub name: String,
+    pub buffer_size: u64,
+}
+
+/// The content of a single record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordKind {
+    /// The channels of the control block. Written whenever the channels are discovered.
+    Channels {
+        up: Vec<RecordedChannel>,
+        down: Vec<RecordedChannel>,
+    },
+    /// A chunk of data read from an up channel.
+    Up { channel: u32, data: Vec<u8> },
+    /// Data written to a down channel.
+    Down { channel: u32, data: Vec<u8> },
+}
+
+/// A timestamped record of an RTT recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// Time since the start of the recording.
+    pub timestamp: Duration,
+    pub kind: RecordKind,
+}
+
+impl Record {
+    /// Converts the record into the event the monitor would have received for it.
+    ///
+    /// Down channel writes are not reported by the monitor and return `None`.
+    pub fn into_monitor_event(self) -> Option<MonitorEvent> {
+        let channel_info = |mut channels: Vec<RecordedChannel>| {
+            channels.sort_by_key(|channel| channel.number);
+            channels
+                .into_iter()
+                .map(|channel| ChannelInfo {
+                    name: channel.name,
+                    buffer_size: channel.buffer_size,
+                })
+                .collect()
+        };
+
+        match self.kind {
+            RecordKind::Channels { up, down } => Some(MonitorEvent::Rtt(RttEvent::Discovered {
+                up_channels: channel_info(up),
+                down_channels: channel_info(down),
+            })),
+            RecordKind::Up { channel, data } => Some(MonitorEvent::Rtt(RttEvent::Output {
+                channel,
+                bytes: data,
+            })),
+            RecordKind::Down { .. } => None,
+        }
+    }
+}
+
+impl RecordedChannel {
+    fn from_channel_info(channels: &[ChannelInfo]) -> Vec<Self> {
+        channels
+            .iter()
+            .enumerate()
+            .map(|(number, channel)| Self {
+                number: number as u32,
+                name: channel.name.clone(),
+                buffer_size: channel.buffer_size,
+            })
+            .collect()
+    }
+}
+
+/// Writes RTT traffic to a recording.
+pub struct RttRecorder<W: Write> {
+    writer: W,
+    start: Instant,
+}
+
+impl RttRecorder<BufWriter<File>> {
+    /// Creates a recording file, replacing an existing one.
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create RTT recording {}", path.display()))?;
+        Ok(Self::new(BufWriter::new(file))?)
+    }
+}
+
+impl<W: Write> RttRecorder<W> {
+    /// Starts a recording. The timestamps of the records are relative to this call.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    /// Writes a record with its own timestamp.
+    pub fn write(&mut self, record: &Record) -> io::Result<()> {
+        let (tag, channel, data) = match &record.kind {
+            RecordKind::Channels { up, down } => {
+                self.header(TAG_CHANNELS, record.timestamp)?;
+                for channels in [up, down] {
+                    self.writer
+                        .write_all(&(channels.len() as u32).to_le_bytes())?;
+                    for channel in channels {
+                        self.writer.write_all(&channel.number.to_le_bytes())?;
+                        self.writer.write_all(&channel.buffer_size.to_le_bytes())?;
+                        self.bytes(channel.name.as_bytes())?;
+                    }
+                }
+                return Ok(());
+            }
+            RecordKind::Up { channel, data } => (TAG_UP, channel, data),
+            RecordKind::Down { channel, data } => (TAG_DOWN, channel, data),
+        };
+
+        self.header(tag, record.timestamp)?;
+        self.writer.write_all(&channel.to_le_bytes())?;
+        self.bytes(data)
+    }
+
+    /// Flushes the buffered records.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn header(&mut self, tag: u8, timestamp: Duration) -> io::Result<()> {
+        self.writer.write_all(&[tag])?;
+        self.writer
+            .write_all(&(timestamp.as_micros() as u64).to_le_bytes())
+    }
+
+    fn bytes(&mut self, data: &[u8]) -> io::Result<()> {
+        self.writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.writer.write_all(data)
+    }
+}
+
+enum WriterMessage {
+    Record(Record),
+    Close,
+}
+
+/// Writes a recording file from a background thread, so recording never blocks the monitor.
+///
+/// Records are buffered and flushed every [`FLUSH_INTERVAL`], and when the recording is dropped.
+pub struct RttRecording {
+    sender: RecordingSender,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RttRecording {
+    /// Creates a recording file, replacing an existing one.
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let mut recorder = RttRecorder::create(path)?;
+        let start = recorder.start;
+        let (sender, receiver) = mpsc::channel();
+
+        let thread = std::thread::spawn(move || {
+            let mut last_flush = Instant::now();
+            loop {
+                let result = match receiver.recv_timeout(FLUSH_INTERVAL) {
+                    Ok(WriterMessage::Record(record)) => recorder.write(&record),
+                    Ok(WriterMessage::Close) | Err(RecvTimeoutError::Disconnected) => {
+                        if let Err(error) = recorder.flush() {
+                            tracing::warn!("Failed to write RTT recording: {error}");
+                        }
+                        return;
+                    }
+                    Err(RecvTimeoutError::Timeout) => Ok(()),
+                };
+
+                let result = result.and_then(|_| {
+                    if last_flush.elapsed() >= FLUSH_INTERVAL {
+                        last_flush = Instant::now();
+                        recorder.flush()
+                    } else {
+                        Ok(())
+                    }
+                });
+                if let Err(error) = result {
+                    tracing::warn!("Failed to write RTT recording: {error}");
+                }
+            }
+        });
+
+        Ok(Self {
+            sender: RecordingSender { sender, start },
+            thread: Some(thread),
+        })
+    }
+
+    /// Returns a sender that adds records to this recording.
+    pub fn sender(&self) -> RecordingSender {
+        self.sender.clone()
+    }
+}
+
+impl Drop for RttRecording {
+    fn drop(&mut self) {
+        let _ = self.sender.sender.send(WriterMessage::Close);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Adds records to an [`RttRecording`]. Records sent after the recording is closed are dropped.
+#[derive(Clone)]
+pub struct RecordingSender {
+    sender: mpsc::Sender<WriterMessage>,
+    start: Instant,
+}
+
+impl RecordingSender {
+    /// Records the channels found on the target.
+    pub fn channels(&self, up: &[ChannelInfo], down: &[ChannelInfo]) {
+        self.send(RecordKind::Channels {
+            up: RecordedChannel::from_channel_info(up),
+            down: RecordedChannel::from_channel_info(down),
+        });
+    }
+
+    /// Records a chunk of data read from an up channel.
+    pub fn up(&self, channel: u32, data: &[u8]) {
+        self.send(RecordKind::Up {
+            channel,
+            data: data.to_vec(),
+        });
+    }
+
+    /// Records data written to a down channel.
+    pub fn down(&self, channel: u32, data: &[u8]) {
+        self.send(RecordKind::Down {
+            channel,
+            data: data.to_vec(),
+        });
+    }
+
+    fn send(&self, kind: RecordKind) {
+        let record = Record {
+            timestamp: self.start.elapsed(),
+            kind,
+        };
+        let _ = self.sender.send(WriterMessage::Record(record));
+    }
+}
+
+/// Reads the records of a recording, in the order they were written.
+pub struct RttReplay<R: Read> {
+    reader: R,
+}
+
+impl RttReplay<BufReader<File>> {
+    /// Opens a recording file.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open RTT recording {}", path.display()))?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read> RttReplay<R> {
+    /// Checks the header of the recording.
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            bail!("Not an RTT recording");
+        }
+
+        let version = read_u32(&mut reader)?;
+        if version > VERSION {
+            bail!("Unsupported RTT recording version {version}");
+        }
+
+        Ok(Self { reader })
+    }
+
+    fn next_record(&mut self) -> anyhow::Result<Option<Record>> {
+        let mut tag = [0];
+        match self.reader.read_exact(&mut tag) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let timestamp = Duration::from_micros(read_u64(&mut self.reader)?);
+        let kind = match tag[0] {
+            TAG_CHANNELS => RecordKind::Channels {
+                up: self.read_channels()?,
+                down: self.read_channels()?,
+            },
+            TAG_UP => RecordKind::Up {
+                channel: read_u32(&mut self.reader)?,
+                data: read_bytes(&mut self.reader)?,
+            },
+            TAG_DOWN => RecordKind::Down {
+                channel: read_u32(&mut self.reader)?,
+                data: read_bytes(&mut self.reader)?,
+            },
+            other => bail!("Unknown record type {other} in RTT recording"),
+        };
+
+        Ok(Some(Record { timestamp, kind }))
+    }
+
+    fn read_channels(&mut self) -> anyhow::Result<Vec<RecordedChannel>> {
+        let count = read_u32(&mut self.reader)?;
+        (0..count)
+            .map(|_| {
+                Ok(RecordedChannel {
+                    number: read_u32(&mut self.reader)?,
+                    buffer_size: read_u64(&mut self.reader)?,
+                    name: String::from_utf8(read_bytes(&mut self.reader)?)
+                        .context("Invalid channel name in RTT recording")?,
+                })
+            })
+            .collect()
+    }
+}
+
+impl<R: Read> Iterator for RttReplay<R> {
+    type Item = anyhow::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let len = read_u32(reader)? as usize;
+    let mut data = Vec::new();
+    reader.take(len as u64).read_to_end(&mut data)?;
+    if data.len() != len {
+        return Err(ErrorKind::UnexpectedEof.into());
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(kind: RecordKind) -> Record {
+        Record {
+            timestamp: Duration::from_micros(42),
+            kind,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let records = [
+            RecordKind::Channels {
+                up: RecordedChannel::from_channel_info(&[
+                    ChannelInfo {
+                        name: "Terminal".to_string(),
+                        buffer_size: 1024,
+                    },
+                    ChannelInfo {
+                        name: "defmt".to_string(),
+                        buffer_size: 512,
+                    },
+                ]),
+                down: RecordedChannel::from_channel_info(&[ChannelInfo {
+                    name: "Terminal".to_string(),
+                    buffer_size: 16,
+                }]),
+            },
+            RecordKind::Up {
+                channel: 1,
+                data: vec![1, 2, 3],
+            },
+            RecordKind::Down {
+                channel: 0,
+                data: b"help\n".to_vec(),
+            },
+        ]
+        .map(record);
+
+        let mut recorder = RttRecorder::new(Vec::new()).unwrap();
+        for record in &records {
+            recorder.write(record).unwrap();
+        }
+
+        let replayed = RttReplay::new(recorder.writer.as_slice())
+            .unwrap()
+            .map(|record| record.unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(replayed, records);
+        assert_eq!(
+            replayed[0].kind,
+            RecordKind::Channels {
+                up: vec![
+                    RecordedChannel {
+                        number: 0,
+                        name: "Terminal".to_string(),
+                        buffer_size: 1024,
+                    },
+                    RecordedChannel {
+                        number: 1,
+                        name: "defmt".to_string(),
+                        buffer_size: 512,
+                    },
+                ],
+                down: vec![RecordedChannel {
+                    number: 0,
+                    name: "Terminal".to_string(),
+                    buffer_size: 16,
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn truncated_record_is_an_error() {
+        let mut recorder = RttRecorder::new(Vec::new()).unwrap();
+        recorder
+            .write(&record(RecordKind::Up {
+                channel: 0,
+                data: b"hello".to_vec(),
+            }))
+            .unwrap();
+
+        let mut data = recorder.writer;
+        data.pop();
+
+        let mut replay = RttReplay::new(data.as_slice()).unwrap();
+        assert!(replay.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn recording_is_flushed_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording");
+
+        let recording = RttRecording::create(&path).unwrap();
+        let sender = recording.sender();
+        sender.up(0, b"hello");
+        drop(recording);
+        // Records sent after the recording is closed are dropped.
+        sender.up(0, b"world");
+
+        let records = RttReplay::open(&path)
+            .unwrap()
+            .map(|record| record.unwrap().kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            records,
+            [RecordKind::Up {
+                channel: 0,
+                data: b"hello".to_vec(),
+            }]
+        );
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        assert!(RttReplay::new(&b"\x7fELF\x01\x01\x01\0\0\0\0\0"[..]).is_err());
+    }
+}
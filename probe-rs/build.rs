@@ -14,6 +14,29 @@ fn main() {
     println!("cargo:rerun-if-changed=targets");
     println!("cargo:rerun-if-env-changed=PROBE_RS_TARGETS_DIR");
 
+    // Dynamically loaded plugins must be built with the same compiler as the host.
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = std::process::Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .unwrap_or_default();
+    println!(
+        "cargo:rustc-env=PROBE_RS_RUSTC_VERSION={}",
+        rustc_version.trim()
+    );
+
+    // Features change the layout of the types shared with plugins, so they have to match too.
+    let mut features = std::env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase())
+        })
+        .collect::<Vec<_>>();
+    features.sort();
+    println!("cargo:rustc-env=PROBE_RS_FEATURES={}", features.join(","));
+
     handle_builtin_targets();
 }
 
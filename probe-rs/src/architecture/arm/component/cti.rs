@@ -0,0 +1,245 @@
+//! Arm cross trigger interface (CTI) CoreSight Component
+//!
+//! # Description
+//! A CTI connects the trigger inputs and outputs of a core, such as "core halted" and "debug
+//! request", to the channels of the cross trigger matrix (CTM). Events on a channel are broadcast
+//! to all CTIs connected to the same matrix, which allows halting and restarting several cores in
+//! the same cycle.
+//!
+//! See the CoreSight SoC-400 Technical Reference Manual for more information.
+use super::super::memory::romtable::CoresightComponent;
+use super::DebugComponentInterface;
+use crate::architecture::arm::{ArmDebugInterface, ArmError};
+use crate::{MemoryMappedRegister, memory_mapped_bitfield_register};
+
+const REGISTER_OFFSET_ACCESS: u32 = 0xFB0;
+
+/// The trigger input signalling that the core has entered debug state.
+///
+/// This is the same for all Cortex-M and Cortex-A cores.
+pub const TRIGGER_IN_HALTED: usize = 0;
+
+/// The trigger output requesting the core to enter debug state.
+pub const TRIGGER_OUT_DEBUG_REQUEST: usize = 0;
+
+/// The trigger output requesting the core to leave debug state.
+pub const TRIGGER_OUT_RESTART: usize = 1;
+
+/// The channel used to halt all cores of a halt group.
+///
+/// Channels 0 and 1 are used by the ARMv8-A core driver to halt and restart a single core.
+pub const HALT_GROUP_CHANNEL: usize = 2;
+
+/// The channel used to restart all cores of a halt group.
+pub const RESTART_GROUP_CHANNEL: usize = 3;
+
+/// Cross trigger interface unit
+pub struct Cti<'a> {
+    component: &'a CoresightComponent,
+    interface: &'a mut dyn ArmDebugInterface,
+}
+
+impl<'a> Cti<'a> {
+    /// Construct a new CTI component.
+    pub fn new(
+        interface: &'a mut dyn ArmDebugInterface,
+        component: &'a CoresightComponent,
+    ) -> Self {
+        Cti {
+            component,
+            interface,
+        }
+    }
+
+    /// Unlock the CTI registers for writing.
+    pub fn unlock(&mut self) -> Result<(), ArmError> {
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_ACCESS, 0xC5AC_CE55)?;
+
+        Ok(())
+    }
+
+    /// Enable or disable the mapping of triggers to channels.
+    pub fn enable(&mut self, enabled: bool) -> Result<(), ArmError> {
+        let mut control = CtiControl::load(self.component, self.interface)?;
+        control.set_glben(enabled);
+        control.store(self.component, self.interface)
+    }
+
+    /// Returns the number of channels and triggers of this CTI.
+    pub fn num_channels_and_triggers(&mut self) -> Result<(usize, usize), ArmError> {
+        let devid = CtiDevid::load(self.component, self.interface)?;
+        Ok((devid.num_channels() as usize, devid.num_triggers() as usize))
+    }
+
+    /// Makes an event on trigger input `trigger` generate an event on `channel`.
+    pub fn map_trigger_in(
+        &mut self,
+        trigger: usize,
+        channel: usize,
+        mapped: bool,
+    ) -> Result<(), ArmError> {
+        let offset = CtiInen::ADDRESS_OFFSET as u32 + 4 * trigger as u32;
+        let mut inen = CtiInen(self.component.read_reg(self.interface, offset)?);
+        inen.set_inen(channel, mapped.into());
+        self.component
+            .write_reg(self.interface, offset, inen.into())
+    }
+
+    /// Makes an event on `channel` generate an event on trigger output `trigger`.
+    pub fn map_trigger_out(
+        &mut self,
+        trigger: usize,
+        channel: usize,
+        mapped: bool,
+    ) -> Result<(), ArmError> {
+        let offset = CtiOuten::ADDRESS_OFFSET as u32 + 4 * trigger as u32;
+        let mut outen = CtiOuten(self.component.read_reg(self.interface, offset)?);
+        outen.set_outen(channel, mapped.into());
+        self.component
+            .write_reg(self.interface, offset, outen.into())
+    }
+
+    /// Opens or closes the gate which passes events on `channel` between this CTI and the cross
+    /// trigger matrix.
+    pub fn set_channel_gate(&mut self, channel: usize, open: bool) -> Result<(), ArmError> {
+        let mut gate = CtiGate::load(self.component, self.interface)?;
+        gate.set_en(channel, open.into());
+        gate.store(self.component, self.interface)
+    }
+
+    /// Generates an event on `channel`.
+    pub fn pulse_channel(&mut self, channel: usize) -> Result<(), ArmError> {
+        let mut pulse = CtiApppulse(0);
+        pulse.set_apppulse(channel, 1);
+        pulse.store(self.component, self.interface)
+    }
+
+    /// Deasserts trigger output `trigger`.
+    ///
+    /// Trigger outputs stay asserted until they are acknowledged. A core with an asserted debug
+    /// request cannot leave debug state.
+    pub fn acknowledge_trigger_out(&mut self, trigger: usize) -> Result<(), ArmError> {
+        let mut ack = CtiIntack(0);
+        ack.set_ack(trigger, 1);
+        ack.store(self.component, self.interface)
+    }
+
+    /// Returns `true` if trigger output `trigger` is asserted.
+    pub fn trigger_out_active(&mut self, trigger: usize) -> Result<bool, ArmError> {
+        let status = CtiTrigoutstatus::load(self.component, self.interface)?;
+        Ok(status.status(trigger) != 0)
+    }
+
+    /// Adds the core of this CTI to the halt group, or removes it.
+    ///
+    /// Members of the halt group halt when any member halts, and are restarted when the
+    /// [`RESTART_GROUP_CHANNEL`] is pulsed.
+    pub fn set_halt_group_member(&mut self, member: bool) -> Result<(), ArmError> {
+        self.unlock()?;
+        self.enable(true)?;
+        self.map_trigger_in(TRIGGER_IN_HALTED, HALT_GROUP_CHANNEL, member)?;
+        self.map_trigger_out(TRIGGER_OUT_DEBUG_REQUEST, HALT_GROUP_CHANNEL, member)?;
+        self.map_trigger_out(TRIGGER_OUT_RESTART, RESTART_GROUP_CHANNEL, member)?;
+        self.set_channel_gate(HALT_GROUP_CHANNEL, member)?;
+        self.set_channel_gate(RESTART_GROUP_CHANNEL, member)
+    }
+}
+
+memory_mapped_bitfield_register! {
+    /// CTICONTROL - CTI control register
+    pub struct CtiControl(u32);
+    0x000, "CTICONTROL",
+    impl From;
+
+    /// Enables or disables the CTI mapping functions.
+    pub glben, set_glben: 0;
+}
+
+impl DebugComponentInterface for CtiControl {}
+
+memory_mapped_bitfield_register! {
+    /// CTIINTACK - CTI output trigger acknowledge register
+    pub struct CtiIntack(u32);
+    0x010, "CTIINTACK",
+    impl From;
+
+    /// Deasserts trigger output N.
+    pub ack, set_ack: 0, 0, 32;
+}
+
+impl DebugComponentInterface for CtiIntack {}
+
+memory_mapped_bitfield_register! {
+    /// CTIAPPPULSE - CTI application pulse register
+    pub struct CtiApppulse(u32);
+    0x01C, "CTIAPPPULSE",
+    impl From;
+
+    /// Generates an event on channel N.
+    pub apppulse, set_apppulse: 0, 0, 32;
+}
+
+impl DebugComponentInterface for CtiApppulse {}
+
+memory_mapped_bitfield_register! {
+    /// CTIINEN<n> - CTI trigger to channel enable register
+    ///
+    /// There is one register for every trigger input, at a stride of 4 bytes.
+    pub struct CtiInen(u32);
+    0x020, "CTIINEN",
+    impl From;
+
+    /// Enables trigger input n generating an event on channel N.
+    pub inen, set_inen: 0, 0, 32;
+}
+
+memory_mapped_bitfield_register! {
+    /// CTIOUTEN<n> - CTI channel to trigger enable register
+    ///
+    /// There is one register for every trigger output, at a stride of 4 bytes.
+    pub struct CtiOuten(u32);
+    0x0A0, "CTIOUTEN",
+    impl From;
+
+    /// Enables an event on channel N generating trigger output n.
+    pub outen, set_outen: 0, 0, 32;
+}
+
+memory_mapped_bitfield_register! {
+    /// CTITRIGOUTSTATUS - CTI trigger out status register
+    pub struct CtiTrigoutstatus(u32);
+    0x134, "CTITRIGOUTSTATUS",
+    impl From;
+
+    /// Whether trigger output N is asserted.
+    pub status, _: 0, 0, 32;
+}
+
+impl DebugComponentInterface for CtiTrigoutstatus {}
+
+memory_mapped_bitfield_register! {
+    /// CTIGATE - CTI channel gate enable register
+    pub struct CtiGate(u32);
+    0x140, "CTIGATE",
+    impl From;
+
+    /// Passes events on channel N to and from the cross trigger matrix.
+    pub en, set_en: 0, 0, 32;
+}
+
+impl DebugComponentInterface for CtiGate {}
+
+memory_mapped_bitfield_register! {
+    /// DEVID - CTI device configuration register
+    pub struct CtiDevid(u32);
+    0xFC8, "DEVID",
+    impl From;
+
+    /// The number of channels.
+    pub num_channels, _: 19, 16;
+    /// The number of triggers.
+    pub num_triggers, _: 15, 8;
+}
+
+impl DebugComponentInterface for CtiDevid {}
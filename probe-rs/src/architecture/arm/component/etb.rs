@@ -0,0 +1,172 @@
+//! Arm embedded trace buffer (ETB) CoreSight Component
+//!
+//! # Description
+//! The ETB is a circular buffer in on-chip RAM, which holds the most recent formatted trace data.
+//! Unlike the [`super::TraceMemoryController`], it can only be read out after the capture has been
+//! stopped.
+//!
+//! See the CoreSight Components Technical Reference Manual (DDI0314H), chapter 3, for more
+//! information.
+use std::time::{Duration, Instant};
+
+use super::super::memory::romtable::CoresightComponent;
+use super::DebugComponentInterface;
+use crate::architecture::arm::{ArmDebugInterface, ArmError};
+use crate::memory_mapped_bitfield_register;
+
+const REGISTER_OFFSET_RDP: u32 = 0x004;
+const REGISTER_OFFSET_RRD: u32 = 0x010;
+const REGISTER_OFFSET_RRP: u32 = 0x014;
+const REGISTER_OFFSET_RWP: u32 = 0x018;
+const REGISTER_OFFSET_TRG: u32 = 0x01C;
+const REGISTER_OFFSET_CTL: u32 = 0x020;
+const REGISTER_OFFSET_ACCESS: u32 = 0xFB0;
+
+/// The time to wait for the formatter to flush the trace data into the buffer.
+const FLUSH_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Embedded trace buffer unit
+pub struct Etb<'a> {
+    component: &'a CoresightComponent,
+    interface: &'a mut dyn ArmDebugInterface,
+}
+
+impl<'a> Etb<'a> {
+    /// Construct a new ETB component.
+    pub fn new(
+        interface: &'a mut dyn ArmDebugInterface,
+        component: &'a CoresightComponent,
+    ) -> Self {
+        Etb {
+            component,
+            interface,
+        }
+    }
+
+    /// Unlock the ETB registers for writing.
+    pub fn unlock(&mut self) -> Result<(), ArmError> {
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_ACCESS, 0xC5AC_CE55)?;
+
+        Ok(())
+    }
+
+    /// Clears the buffer and starts capturing formatted trace data.
+    pub fn enable_capture(&mut self) -> Result<(), ArmError> {
+        self.unlock()?;
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_CTL, 0)?;
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_RWP, 0)?;
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_TRG, 0)?;
+
+        let mut ffcr = EtbFormatFlushControl(0);
+        ffcr.set_enftc(true);
+        ffcr.set_stopfl(true);
+        ffcr.store(self.component, self.interface)?;
+
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_CTL, 1)
+    }
+
+    /// Flushes the trace data into the buffer and stops the capture.
+    pub fn disable_capture(&mut self) -> Result<(), ArmError> {
+        self.unlock()?;
+        let mut ffcr = EtbFormatFlushControl::load(self.component, self.interface)?;
+        ffcr.set_stopfl(true);
+        ffcr.set_fonman(true);
+        ffcr.store(self.component, self.interface)?;
+
+        let start = Instant::now();
+        while !EtbFormatFlushStatus::load(self.component, self.interface)?.ftstopped() {
+            if start.elapsed() > FLUSH_TIMEOUT {
+                return Err(ArmError::Timeout);
+            }
+        }
+
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_CTL, 0)
+    }
+
+    /// Get the size of the buffer in bytes.
+    pub fn buffer_size(&mut self) -> Result<u32, ArmError> {
+        let depth = self
+            .component
+            .read_reg(self.interface, REGISTER_OFFSET_RDP)?;
+        Ok(depth * core::mem::size_of::<u32>() as u32)
+    }
+
+    /// Reads the captured trace data, oldest first.
+    ///
+    /// # Note
+    /// The capture has to be stopped with [`Etb::disable_capture`] before reading the buffer.
+    pub fn read(&mut self) -> Result<Vec<u8>, ArmError> {
+        let depth = self
+            .component
+            .read_reg(self.interface, REGISTER_OFFSET_RDP)?;
+        let write_pointer = self
+            .component
+            .read_reg(self.interface, REGISTER_OFFSET_RWP)?;
+        let status = EtbStatus::load(self.component, self.interface)?;
+
+        // Once the buffer has wrapped, the oldest data is at the write pointer.
+        let (start, words) = if status.full() {
+            (write_pointer, depth)
+        } else {
+            (0, write_pointer)
+        };
+
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_RRP, start)?;
+        let mut data = Vec::with_capacity(words as usize * 4);
+        for _ in 0..words {
+            let word = self
+                .component
+                .read_reg(self.interface, REGISTER_OFFSET_RRD)?;
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+
+        Ok(data)
+    }
+}
+
+memory_mapped_bitfield_register! {
+    /// STS - ETB status register
+    pub struct EtbStatus(u32);
+    0x00C, "ETB_STS",
+    impl From;
+
+    /// The write pointer has wrapped around.
+    pub full, _: 0;
+}
+
+impl DebugComponentInterface for EtbStatus {}
+
+memory_mapped_bitfield_register! {
+    /// FFSR - ETB formatter and flush status register
+    pub struct EtbFormatFlushStatus(u32);
+    0x300, "ETB_FFSR",
+    impl From;
+
+    /// The formatter has stopped.
+    pub ftstopped, _: 1;
+}
+
+impl DebugComponentInterface for EtbFormatFlushStatus {}
+
+memory_mapped_bitfield_register! {
+    /// FFCR - ETB formatter and flush control register
+    pub struct EtbFormatFlushControl(u32);
+    0x304, "ETB_FFCR",
+    impl From;
+
+    /// Stops the formatter when a flush completes.
+    pub stopfl, set_stopfl: 12;
+    /// Generates a manual flush.
+    pub fonman, set_fonman: 6;
+    /// Enables the formatter.
+    pub enftc, set_enftc: 0;
+}
+
+impl DebugComponentInterface for EtbFormatFlushControl {}
@@ -0,0 +1,403 @@
+//! Arm embedded trace macrocell (ETM) CoreSight Component
+//!
+//! # Description
+//! The ETM generates a compressed trace of the instructions executed by a core. The trace only
+//! contains the outcome of branches and the targets of indirect branches, so it has to be decoded
+//! against the program that was running to recover the executed instructions, see
+//! [`crate::architecture::arm::etm`].
+//!
+//! This driver supports the ETMv3 and PTM (program flow trace, PFT) units of Cortex-M3/M4 and
+//! Cortex-A/R cores, and the ETMv4 units of Armv8-M and Armv8-A cores. All of them are configured
+//! to trace every instruction, without cycle counts, timestamps or context IDs.
+use std::time::{Duration, Instant};
+
+use super::super::memory::romtable::CoresightComponent;
+use super::DebugComponentInterface;
+use crate::architecture::arm::{ArmDebugInterface, ArmError, etm::Protocol};
+use crate::memory_mapped_bitfield_register;
+
+const REGISTER_OFFSET_ACCESS: u32 = 0xFB0;
+
+/// The time to wait for the ETM to enter or leave the programming state.
+const PROGRAMMING_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Embedded trace macrocell unit
+pub struct Etm<'a> {
+    component: &'a CoresightComponent,
+    interface: &'a mut dyn ArmDebugInterface,
+}
+
+impl<'a> Etm<'a> {
+    /// Construct a new ETM component.
+    pub fn new(
+        interface: &'a mut dyn ArmDebugInterface,
+        component: &'a CoresightComponent,
+    ) -> Self {
+        Etm {
+            component,
+            interface,
+        }
+    }
+
+    /// Unlock the ETM registers for writing, and clear the OS lock.
+    pub fn unlock(&mut self) -> Result<(), ArmError> {
+        self.component
+            .write_reg(self.interface, REGISTER_OFFSET_ACCESS, 0xC5AC_CE55)?;
+        OsLockAccess(0).store(self.component, self.interface)
+    }
+
+    /// Returns the trace protocol generated by this ETM.
+    pub fn protocol(&mut self) -> Result<Protocol, ArmError> {
+        let id = EtmId::load(self.component, self.interface)?;
+        match id.major() {
+            0b0010 => Ok(Protocol::EtmV3 {
+                alternative_branch_encoding: id.alternative_branch_encoding(),
+            }),
+            0b0011 => Ok(Protocol::Ptm),
+            0b0100 => Ok(Protocol::EtmV4),
+            major => Err(ArmError::Other(format!(
+                "Unsupported ETM architecture version {major}.{}",
+                id.minor()
+            ))),
+        }
+    }
+
+    /// Configures the ETM to trace all instructions and enables it.
+    ///
+    /// The trace is tagged with `trace_id` on the trace bus.
+    pub fn enable(&mut self, trace_id: u8) -> Result<(), ArmError> {
+        self.unlock()?;
+        match self.protocol()? {
+            Protocol::EtmV3 { .. } | Protocol::Ptm => self.enable_v3(trace_id),
+            Protocol::EtmV4 => self.enable_v4(trace_id),
+        }
+    }
+
+    /// Stops tracing.
+    pub fn disable(&mut self) -> Result<(), ArmError> {
+        self.unlock()?;
+        match self.protocol()? {
+            Protocol::EtmV3 { .. } | Protocol::Ptm => {
+                let mut control = EtmControl::load(self.component, self.interface)?;
+                control.set_programming(true);
+                control.store(self.component, self.interface)?;
+                self.wait_for(|etm| {
+                    Ok(EtmStatus::load(etm.component, etm.interface)?.programming())
+                })?;
+
+                control.set_power_down(true);
+                control.store(self.component, self.interface)
+            }
+            Protocol::EtmV4 => {
+                TrcPrgctlr(0).store(self.component, self.interface)?;
+                self.wait_for(|etm| Ok(TrcStatr::load(etm.component, etm.interface)?.idle()))
+            }
+        }
+    }
+
+    fn enable_v3(&mut self, trace_id: u8) -> Result<(), ArmError> {
+        let mut control = EtmControl::load(self.component, self.interface)?;
+        control.set_power_down(false);
+        control.set_programming(true);
+        control.store(self.component, self.interface)?;
+        self.wait_for(|etm| Ok(EtmStatus::load(etm.component, etm.interface)?.programming()))?;
+
+        EtmTraceId(trace_id.into()).store(self.component, self.interface)?;
+        // Resource 0x6F is hard-wired to always be true, so tracing is always enabled.
+        EtmTraceEnableEvent(0x6F).store(self.component, self.interface)?;
+        let mut trace_enable = EtmTraceEnableControl1(0);
+        // Exclude nothing, which traces all addresses.
+        trace_enable.set_exclude(true);
+        trace_enable.store(self.component, self.interface)?;
+
+        control.set_branch_output(false);
+        control.set_cycle_accurate(false);
+        control.set_timestamp(false);
+        control.set_programming(false);
+        control.store(self.component, self.interface)?;
+        self.wait_for(|etm| Ok(!EtmStatus::load(etm.component, etm.interface)?.programming()))
+    }
+
+    fn enable_v4(&mut self, trace_id: u8) -> Result<(), ArmError> {
+        let mut power_down = TrcPdcr::load(self.component, self.interface)?;
+        power_down.set_power_up(true);
+        power_down.store(self.component, self.interface)?;
+
+        TrcPrgctlr(0).store(self.component, self.interface)?;
+        self.wait_for(|etm| Ok(TrcStatr::load(etm.component, etm.interface)?.idle()))?;
+
+        // Bit 0 is RES1, everything else selects plain instruction trace.
+        TrcConfigr(1).store(self.component, self.interface)?;
+        TrcEventctl0r(0).store(self.component, self.interface)?;
+        TrcEventctl1r(0).store(self.component, self.interface)?;
+        TrcStallctlr(0).store(self.component, self.interface)?;
+        TrcTsctlr(0).store(self.component, self.interface)?;
+        TrcCcctlr(0).store(self.component, self.interface)?;
+        TrcBbctlr(0).store(self.component, self.interface)?;
+        // Emit a synchronization packet every 4096 bytes.
+        TrcSyncpr(12).store(self.component, self.interface)?;
+        TrcTraceidr(trace_id.into()).store(self.component, self.interface)?;
+
+        let mut view_inst = TrcVictlr(0);
+        // Resource 1 is hard-wired to always be true, so tracing is always enabled.
+        view_inst.set_event(1);
+        view_inst.set_started(true);
+        view_inst.store(self.component, self.interface)?;
+        TrcViiectlr(0).store(self.component, self.interface)?;
+        TrcVissctlr(0).store(self.component, self.interface)?;
+
+        TrcPrgctlr(1).store(self.component, self.interface)?;
+        self.wait_for(|etm| Ok(!TrcStatr::load(etm.component, etm.interface)?.idle()))
+    }
+
+    fn wait_for(
+        &mut self,
+        mut condition: impl FnMut(&mut Self) -> Result<bool, ArmError>,
+    ) -> Result<(), ArmError> {
+        let start = Instant::now();
+        while !condition(self)? {
+            if start.elapsed() > PROGRAMMING_TIMEOUT {
+                return Err(ArmError::Timeout);
+            }
+        }
+        Ok(())
+    }
+}
+
+memory_mapped_bitfield_register! {
+    /// ETMCR - ETMv3 and PTM main control register
+    pub struct EtmControl(u32);
+    0x000, "ETMCR",
+    impl From;
+
+    /// Enables timestamps.
+    pub timestamp, set_timestamp: 28;
+    /// Enables cycle accurate tracing.
+    pub cycle_accurate, set_cycle_accurate: 12;
+    /// Stops tracing while the ETM is being programmed.
+    pub programming, set_programming: 10;
+    /// Emits the address of every branch, instead of only indirect branches.
+    pub branch_output, set_branch_output: 8;
+    /// Powers down the ETM.
+    pub power_down, set_power_down: 0;
+}
+
+impl DebugComponentInterface for EtmControl {}
+
+memory_mapped_bitfield_register! {
+    /// ETMSR - ETMv3 and PTM status register
+    pub struct EtmStatus(u32);
+    0x010, "ETMSR",
+    impl From;
+
+    /// The effective value of the programming bit.
+    pub programming, _: 1;
+}
+
+impl DebugComponentInterface for EtmStatus {}
+
+memory_mapped_bitfield_register! {
+    /// ETMTEEVR - ETMv3 and PTM trace enable event register
+    pub struct EtmTraceEnableEvent(u32);
+    0x020, "ETMTEEVR",
+    impl From;
+}
+
+impl DebugComponentInterface for EtmTraceEnableEvent {}
+
+memory_mapped_bitfield_register! {
+    /// ETMTECR1 - ETMv3 and PTM trace enable control register 1
+    pub struct EtmTraceEnableControl1(u32);
+    0x024, "ETMTECR1",
+    impl From;
+
+    /// The address ranges are excluded from tracing, instead of included.
+    pub exclude, set_exclude: 24;
+}
+
+impl DebugComponentInterface for EtmTraceEnableControl1 {}
+
+memory_mapped_bitfield_register! {
+    /// ETMIDR / TRCIDR1 - ID register
+    ///
+    /// ETMv3, PTM and ETMv4 all report their architecture version at this offset.
+    pub struct EtmId(u32);
+    0x1E4, "ETMIDR",
+    impl From;
+
+    /// ETMv3 uses the alternative branch address packet encoding.
+    pub alternative_branch_encoding, _: 17;
+    /// The major architecture version.
+    pub major, _: 11, 8;
+    /// The minor architecture version.
+    pub minor, _: 7, 4;
+}
+
+impl DebugComponentInterface for EtmId {}
+
+memory_mapped_bitfield_register! {
+    /// ETMTRACEIDR - ETMv3 and PTM CoreSight trace ID register
+    pub struct EtmTraceId(u32);
+    0x200, "ETMTRACEIDR",
+    impl From;
+}
+
+impl DebugComponentInterface for EtmTraceId {}
+
+memory_mapped_bitfield_register! {
+    /// ETMOSLAR / TRCOSLAR - OS lock access register
+    pub struct OsLockAccess(u32);
+    0x300, "ETMOSLAR",
+    impl From;
+}
+
+impl DebugComponentInterface for OsLockAccess {}
+
+memory_mapped_bitfield_register! {
+    /// TRCPRGCTLR - ETMv4 programming control register
+    pub struct TrcPrgctlr(u32);
+    0x004, "TRCPRGCTLR",
+    impl From;
+
+    /// Enables the trace unit.
+    pub en, set_en: 0;
+}
+
+impl DebugComponentInterface for TrcPrgctlr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCSTATR - ETMv4 status register
+    pub struct TrcStatr(u32);
+    0x00C, "TRCSTATR",
+    impl From;
+
+    /// The trace unit is idle and can be programmed.
+    pub idle, _: 0;
+}
+
+impl DebugComponentInterface for TrcStatr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCCONFIGR - ETMv4 trace configuration register
+    pub struct TrcConfigr(u32);
+    0x010, "TRCCONFIGR",
+    impl From;
+}
+
+impl DebugComponentInterface for TrcConfigr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCEVENTCTL0R - ETMv4 event control 0 register
+    pub struct TrcEventctl0r(u32);
+    0x020, "TRCEVENTCTL0R",
+    impl From;
+}
+
+impl DebugComponentInterface for TrcEventctl0r {}
+
+memory_mapped_bitfield_register! {
+    /// TRCEVENTCTL1R - ETMv4 event control 1 register
+    pub struct TrcEventctl1r(u32);
+    0x024, "TRCEVENTCTL1R",
+    impl From;
+}
+
+impl DebugComponentInterface for TrcEventctl1r {}
+
+memory_mapped_bitfield_register! {
+    /// TRCSTALLCTLR - ETMv4 stall control register
+    pub struct TrcStallctlr(u32);
+    0x02C, "TRCSTALLCTLR",
+    impl From;
+}
+
+impl DebugComponentInterface for TrcStallctlr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCTSCTLR - ETMv4 global timestamp control register
+    pub struct TrcTsctlr(u32);
+    0x030, "TRCTSCTLR",
+    impl From;
+}
+
+impl DebugComponentInterface for TrcTsctlr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCSYNCPR - ETMv4 synchronization period register
+    pub struct TrcSyncpr(u32);
+    0x034, "TRCSYNCPR",
+    impl From;
+}
+
+impl DebugComponentInterface for TrcSyncpr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCCCCTLR - ETMv4 cycle count control register
+    pub struct TrcCcctlr(u32);
+    0x038, "TRCCCCTLR",
+    impl From;
+}
+
+impl DebugComponentInterface for TrcCcctlr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCBBCTLR - ETMv4 branch broadcast control register
+    pub struct TrcBbctlr(u32);
+    0x03C, "TRCBBCTLR",
+    impl From;
+}
+
+impl DebugComponentInterface for TrcBbctlr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCTRACEIDR - ETMv4 trace ID register
+    pub struct TrcTraceidr(u32);
+    0x040, "TRCTRACEIDR",
+    impl From;
+}
+
+impl DebugComponentInterface for TrcTraceidr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCVICTLR - ETMv4 ViewInst main control register
+    pub struct TrcVictlr(u32);
+    0x080, "TRCVICTLR",
+    impl From;
+
+    /// The start/stop logic is in the started state.
+    pub started, set_started: 9;
+    /// The resource selector which enables tracing.
+    pub u8, event, set_event: 7, 0;
+}
+
+impl DebugComponentInterface for TrcVictlr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCVIIECTLR - ETMv4 ViewInst include/exclude control register
+    pub struct TrcViiectlr(u32);
+    0x084, "TRCVIIECTLR",
+    impl From;
+}
+
+impl DebugComponentInterface for TrcViiectlr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCVISSCTLR - ETMv4 ViewInst start/stop control register
+    pub struct TrcVissctlr(u32);
+    0x088, "TRCVISSCTLR",
+    impl From;
+}
+
+impl DebugComponentInterface for TrcVissctlr {}
+
+memory_mapped_bitfield_register! {
+    /// TRCPDCR - ETMv4 power down control register
+    pub struct TrcPdcr(u32);
+    0x310, "TRCPDCR",
+    impl From;
+
+    /// Requests the trace unit to stay powered up.
+    pub power_up, set_power_up: 3;
+}
+
+impl DebugComponentInterface for TrcPdcr {}
@@ -1,7 +1,11 @@
 //! Types and functions for interacting with CoreSight Components
 
+mod cti;
 mod dwt;
+mod etb;
+mod etm;
 mod itm;
+mod mtb;
 mod scs;
 mod swo;
 mod tmc;
@@ -19,7 +23,14 @@ use crate::{
 };
 
 pub use self::itm::Itm;
+pub use cti::{
+    Cti, HALT_GROUP_CHANNEL, RESTART_GROUP_CHANNEL, TRIGGER_IN_HALTED, TRIGGER_OUT_DEBUG_REQUEST,
+    TRIGGER_OUT_RESTART,
+};
 pub use dwt::Dwt;
+pub use etb::Etb;
+pub use etm::Etm;
+pub use mtb::{Mtb, MtbBranch};
 pub use scs::Scs;
 pub use swo::Swo;
 pub use tmc::TraceMemoryController;
@@ -44,6 +55,12 @@ pub enum TraceSink {
     TraceMemory,
 }
 
+/// The trace ID of the ITM, see [`Itm::tx_enable`].
+pub const ITM_TRACE_ID: u8 = 13;
+
+/// The trace ID of the ETM, see [`Etm::enable`].
+pub const ETM_TRACE_ID: u8 = 16;
+
 /// An error when operating a core ROM table component occurred.
 #[derive(thiserror::Error, Debug)]
 pub enum ComponentError {
@@ -268,11 +285,41 @@ pub(crate) fn setup_tracing(
     Ok(())
 }
 
+/// Sets up the ETM of a core to trace all executed instructions into trace memory.
+///
+/// Expects to be given a list of all ROM table `components` as the second argument, and the ETM
+/// of the core to trace as the third. The trace is captured by the TMC if there is one, and by
+/// the ETB otherwise.
+pub(crate) fn setup_instruction_trace(
+    interface: &mut dyn ArmDebugInterface,
+    components: &[CoresightComponent],
+    etm: &CoresightComponent,
+) -> Result<(), Error> {
+    if let Ok(component) = find_component(components, PeripheralType::Tmc) {
+        let mut tmc = TraceMemoryController::new(interface, component);
+
+        // Clear out the TMC FIFO before initiating the capture.
+        tmc.disable_capture()?;
+        while !tmc.ready()? {}
+
+        tmc.set_mode(tmc::Mode::Software)?;
+        tmc.enable_capture()?;
+    } else {
+        let mut etb = Etb::new(interface, find_component(components, PeripheralType::Etb)?);
+        etb.enable_capture()?;
+    }
+
+    Etm::new(interface, etm).enable(ETM_TRACE_ID)?;
+
+    Ok(())
+}
+
 /// Read trace data from internal trace memory
 ///
 /// # Args
 /// * `interface` - The interface with the debug probe.
 /// * `components` - The CoreSight debug components identified in the system.
+/// * `trace_id` - The trace ID of the trace source to return the data of.
 ///
 /// # Note
 /// This function will read any available trace data in trace memory without blocking. At most,
@@ -280,14 +327,32 @@ pub(crate) fn setup_tracing(
 /// filled while trace data is being extracted, this function can be called again to return that
 /// data.
 ///
+/// Targets without a TMC are read from the ETB instead. The ETB capture is stopped while reading
+/// and restarted afterwards, so only the most recent trace data fitting into the buffer is
+/// returned.
+///
 /// # Returns
 /// All data stored in trace memory, with an upper bound at the size of internal trace memory.
 pub(crate) fn read_trace_memory(
     interface: &mut dyn ArmDebugInterface,
     components: &[CoresightComponent],
+    trace_id: u8,
 ) -> Result<Vec<u8>, ArmError> {
-    let mut tmc =
-        TraceMemoryController::new(interface, find_component(components, PeripheralType::Tmc)?);
+    let component = match find_component(components, PeripheralType::Tmc) {
+        Ok(component) => component,
+        Err(error) => {
+            let Ok(component) = find_component(components, PeripheralType::Etb) else {
+                return Err(error);
+            };
+            let mut etb = Etb::new(interface, component);
+            etb.disable_capture()?;
+            let etb_trace = etb.read()?;
+            etb.enable_capture()?;
+
+            return Ok(deframe_trace_memory(&etb_trace, trace_id));
+        }
+    };
+    let mut tmc = TraceMemoryController::new(interface, component);
 
     let fifo_size = tmc.fifo_size()?;
 
@@ -318,20 +383,23 @@ pub(crate) fn read_trace_memory(
         }
     }
 
-    // The TMC formats data into frames, as it contains trace data from multiple data sources. We
-    // need to deserialize the frames and pull out only the data source of interest. For now, all
-    // we care about is the ITM data.
+    Ok(deframe_trace_memory(&etf_trace, trace_id))
+}
 
+/// Extracts the data of the trace source `trace_id` from formatted trace memory frames.
+///
+/// The TMC and ETB format data into frames, as they contain trace data from multiple data
+/// sources. We need to deserialize the frames and pull out only the data source of interest.
+fn deframe_trace_memory(formatted: &[u8], trace_id: u8) -> Vec<u8> {
     let mut id = 0.into();
-    let mut itm_trace = Vec::new();
+    let mut trace = Vec::new();
 
     // Process each formatted frame and extract the multiplexed trace data.
-    for frame_buffer in etf_trace.chunks_exact(16) {
+    for frame_buffer in formatted.chunks_exact(16) {
         let mut frame = tmc::Frame::new(frame_buffer, id);
         for (id, data) in &mut frame {
             match id.into() {
-                // ITM ATID, see Itm::tx_enable()
-                13 => itm_trace.push(data),
+                id if id == trace_id => trace.push(data),
                 0 => (),
                 id => tracing::warn!("Unexpected trace source ATID {id}: {data}, ignoring"),
             }
@@ -339,7 +407,7 @@ pub(crate) fn read_trace_memory(
         id = frame.id();
     }
 
-    Ok(itm_trace)
+    trace
 }
 
 /// Configures DWT trace unit `unit` to begin tracing `address`.
@@ -0,0 +1,257 @@
+//! Arm micro trace buffer (MTB) CoreSight Component
+//!
+//! # Description
+//! The MTB records the non-sequential program flow of a Cortex-M0+ or Cortex-M23 core into a
+//! window of on-chip SRAM. Every branch, exception entry and exception return is stored as a
+//! packet of two words: the source and the destination address. The SRAM window is a circular
+//! buffer, so it always holds the most recent branches.
+//!
+//! See the Arm CoreSight MTB-M0+ Technical Reference Manual (DDI0486), chapter 3, for more
+//! information.
+use super::super::memory::romtable::CoresightComponent;
+use super::DebugComponentInterface;
+use crate::architecture::arm::{ArmDebugInterface, ArmError};
+use crate::memory_mapped_bitfield_register;
+
+/// The size of a single branch packet in bytes.
+const PACKET_SIZE: u32 = 8;
+
+/// A branch recorded by the MTB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MtbBranch {
+    /// The address of the branch instruction, or the address at which the core was interrupted
+    /// for an exception entry.
+    pub source: u32,
+    /// The address the core continued at.
+    pub destination: u32,
+    /// The branch was an exception entry or an exception return.
+    pub exception: bool,
+    /// This is the first branch recorded after the trace was started.
+    pub start: bool,
+}
+
+/// Micro trace buffer unit
+pub struct Mtb<'a> {
+    component: &'a CoresightComponent,
+    interface: &'a mut dyn ArmDebugInterface,
+}
+
+impl<'a> Mtb<'a> {
+    /// Construct a new MTB component.
+    pub fn new(
+        interface: &'a mut dyn ArmDebugInterface,
+        component: &'a CoresightComponent,
+    ) -> Self {
+        Mtb {
+            component,
+            interface,
+        }
+    }
+
+    /// Get the address of the SRAM the MTB writes the trace to.
+    pub fn sram_base(&mut self) -> Result<u32, ArmError> {
+        Ok(MtbBase::load(self.component, self.interface)?.0)
+    }
+
+    /// Get the size of the configured trace buffer in bytes.
+    pub fn buffer_size(&mut self) -> Result<u32, ArmError> {
+        let master = MtbMaster::load(self.component, self.interface)?;
+        Ok(1 << (master.mask() + 4))
+    }
+
+    /// Configure the SRAM window used as the trace buffer and clear it.
+    ///
+    /// The window starts `offset` bytes after [`Mtb::sram_base`]. The size has to be a power of
+    /// two of at least 16 bytes, and the offset a multiple of the size. The tracing is disabled by
+    /// this, use [`Mtb::enable`] to start recording.
+    pub fn configure(&mut self, offset: u32, size: u32) -> Result<(), ArmError> {
+        if !size.is_power_of_two() || size < 16 {
+            return Err(ArmError::Other(format!(
+                "The MTB buffer size {size} is not a power of two of at least 16 bytes"
+            )));
+        }
+        if !offset.is_multiple_of(size) {
+            return Err(ArmError::Other(format!(
+                "The MTB buffer offset {offset:#x} is not aligned to its size {size:#x}"
+            )));
+        }
+
+        let mut master = MtbMaster(0);
+        master.set_mask(size.trailing_zeros() - 4);
+        master.store(self.component, self.interface)?;
+
+        MtbFlow(0).store(self.component, self.interface)?;
+
+        let mut position = MtbPosition(0);
+        position.set_pointer(offset >> 3);
+        position.store(self.component, self.interface)?;
+
+        Ok(())
+    }
+
+    /// Start recording branches.
+    pub fn enable(&mut self) -> Result<(), ArmError> {
+        let mut master = MtbMaster::load(self.component, self.interface)?;
+        master.set_en(true);
+        master.store(self.component, self.interface)?;
+
+        Ok(())
+    }
+
+    /// Stop recording branches.
+    pub fn disable(&mut self) -> Result<(), ArmError> {
+        let mut master = MtbMaster::load(self.component, self.interface)?;
+        master.set_en(false);
+        master.store(self.component, self.interface)?;
+
+        Ok(())
+    }
+
+    /// Reads the raw contents of the trace buffer, oldest packet first.
+    ///
+    /// # Note
+    /// The core should be halted, as the MTB keeps overwriting the buffer while the core runs.
+    pub fn read(&mut self) -> Result<Vec<u8>, ArmError> {
+        let base = self.sram_base()?;
+        let size = self.buffer_size()?;
+        let position = MtbPosition::load(self.component, self.interface)?;
+
+        let pointer = position.pointer() << 3;
+        let start = pointer & !(size - 1);
+        let written = pointer - start;
+
+        // Once the buffer has wrapped, the oldest packet is at the write pointer.
+        let len = if position.wrap() { size } else { written };
+        let mut data = vec![0; len as usize];
+        let (older, newer) = data.split_at_mut((len - written) as usize);
+
+        let mut memory = self
+            .interface
+            .memory_interface(&self.component.ap_address)?;
+        memory.read_8(base as u64 + pointer as u64, older)?;
+        memory.read_8(base as u64 + start as u64, newer)?;
+
+        Ok(data)
+    }
+
+    /// Reads the branches in the trace buffer, oldest first.
+    ///
+    /// # Note
+    /// The core should be halted, as the MTB keeps overwriting the buffer while the core runs.
+    pub fn read_branches(&mut self) -> Result<Vec<MtbBranch>, ArmError> {
+        Ok(decode_branches(&self.read()?))
+    }
+}
+
+/// Decodes the packets read from the trace buffer.
+fn decode_branches(data: &[u8]) -> Vec<MtbBranch> {
+    data.chunks_exact(PACKET_SIZE as usize)
+        .map(|packet| {
+            let source = u32::from_le_bytes(packet[0..4].try_into().unwrap());
+            let destination = u32::from_le_bytes(packet[4..8].try_into().unwrap());
+
+            MtbBranch {
+                source: source & !1,
+                destination: destination & !1,
+                exception: source & 1 != 0,
+                start: destination & 1 != 0,
+            }
+        })
+        .collect()
+}
+
+memory_mapped_bitfield_register! {
+    /// POSITION - MTB position register
+    pub struct MtbPosition(u32);
+    0x000, "MTB_POSITION",
+    impl From;
+
+    /// The offset in the SRAM the next packet is written to, in units of 8 bytes.
+    pub pointer, set_pointer: 31, 3;
+    /// The pointer has wrapped around the end of the buffer.
+    pub wrap, set_wrap: 2;
+}
+
+impl DebugComponentInterface for MtbPosition {}
+
+memory_mapped_bitfield_register! {
+    /// MASTER - MTB master register
+    pub struct MtbMaster(u32);
+    0x004, "MTB_MASTER",
+    impl From;
+
+    /// Enables the recording of branches.
+    pub en, set_en: 31;
+    /// Halts the core when the watermark is reached.
+    pub haltreq, set_haltreq: 9;
+    /// Only allows privileged accesses to the trace buffer.
+    pub rampriv, set_rampriv: 8;
+    /// Only allows privileged writes to the MTB registers.
+    pub sfrwpriv, set_sfrwpriv: 7;
+    /// Stops recording on a TSTOP input.
+    pub tstopen, set_tstopen: 6;
+    /// Starts recording on a TSTART input.
+    pub tstarten, set_tstarten: 5;
+    /// The size of the trace buffer, as a power of two of 16 bytes.
+    pub mask, set_mask: 4, 0;
+}
+
+impl DebugComponentInterface for MtbMaster {}
+
+memory_mapped_bitfield_register! {
+    /// FLOW - MTB flow register
+    pub struct MtbFlow(u32);
+    0x008, "MTB_FLOW",
+    impl From;
+
+    /// The offset in the SRAM at which the watermark action is taken, in units of 8 bytes.
+    pub watermark, set_watermark: 31, 3;
+    /// Halts the core when the pointer reaches the watermark.
+    pub autohalt, set_autohalt: 1;
+    /// Stops recording when the pointer reaches the watermark.
+    pub autostop, set_autostop: 0;
+}
+
+impl DebugComponentInterface for MtbFlow {}
+
+memory_mapped_bitfield_register! {
+    /// BASE - MTB base register
+    pub struct MtbBase(u32);
+    0x00C, "MTB_BASE",
+    impl From;
+}
+
+impl DebugComponentInterface for MtbBase {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_branch_packets() {
+        let mut data = vec![];
+        for word in [0x0000_0101u32, 0x0000_0201, 0x0000_0210, 0x0000_0300] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        // A trailing partial packet is ignored.
+        data.extend_from_slice(&[0xFF; 4]);
+
+        assert_eq!(
+            decode_branches(&data),
+            vec![
+                MtbBranch {
+                    source: 0x100,
+                    destination: 0x200,
+                    exception: true,
+                    start: true,
+                },
+                MtbBranch {
+                    source: 0x210,
+                    destination: 0x300,
+                    exception: false,
+                    start: false,
+                },
+            ]
+        );
+    }
+}
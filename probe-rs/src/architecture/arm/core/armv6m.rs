@@ -1,11 +1,11 @@
 //! Register types and the core interface for armv6-M
 
-use super::{CortexMState, Dfsr, registers::cortex_m::*};
+use super::{CortexMState, Dfsr, cortex_m::DwtVersion, registers::cortex_m::*};
 use crate::{
     Architecture, BreakpointCause, CoreInformation, CoreInterface, CoreRegister, CoreStatus,
     CoreType, HaltReason, InstructionSet, MemoryInterface, MemoryMappedRegister,
     architecture::arm::{ArmError, memory::ArmMemoryInterface, sequences::ArmDebugSequence},
-    core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition},
+    core::{CoreRegisters, RegisterId, RegisterValue, VectorCatchCondition, Watchpoint},
     error::Error,
     memory::{CoreMemoryInterface, valid_32bit_address},
 };
@@ -525,6 +525,12 @@ impl CoreInterface for Armv6m<'_> {
             let mut reason = dfsr.halt_reason();
             reason = self.state.resolve_halt_reason(reason);
 
+            if reason == HaltReason::Watchpoint(None) {
+                let watchpoint =
+                    super::cortex_m::dwt_hit_watchpoint(&mut *self.memory, DwtVersion::V7m)?;
+                reason = HaltReason::Watchpoint(watchpoint);
+            }
+
             // Clear bits from Dfsr register
             self.memory
                 .write_word_32(Dfsr::get_mmio_address(), Dfsr::clear_all().into())?;
@@ -870,6 +876,36 @@ impl CoreInterface for Armv6m<'_> {
         self.state.hw_breakpoints_enabled
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(super::cortex_m::dwt_watchpoint_units(&mut *self.memory)?)
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        Ok(super::cortex_m::dwt_watchpoints(
+            &mut *self.memory,
+            DwtVersion::V7m,
+        )?)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        super::cortex_m::set_dwt_watchpoint(
+            &mut *self.memory,
+            DwtVersion::V7m,
+            unit_index,
+            watchpoint,
+        )
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        super::cortex_m::clear_dwt_watchpoint(&mut *self.memory, unit_index)?;
+        self.memory.flush()?;
+        Ok(())
+    }
+
     fn architecture(&self) -> Architecture {
         Architecture::Arm
     }
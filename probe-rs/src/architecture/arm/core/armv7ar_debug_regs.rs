@@ -221,7 +221,7 @@ impl Dbgdscr {
                 // Breakpoint debug event
                 0b0001 => HaltReason::Breakpoint(BreakpointCause::Hardware),
                 // Async watchpoint debug event
-                0b0010 => HaltReason::Watchpoint,
+                0b0010 => HaltReason::Watchpoint(None),
                 // BKPT instruction
                 0b0011 => HaltReason::Breakpoint(BreakpointCause::Software),
                 // External halt request
@@ -231,7 +231,7 @@ impl Dbgdscr {
                 // OS Unlock vector catch
                 0b1000 => HaltReason::Exception,
                 // Sync watchpoint debug event
-                0b1010 => HaltReason::Watchpoint,
+                0b1010 => HaltReason::Watchpoint(None),
                 // All other values are reserved
                 _ => HaltReason::Unknown,
             }
@@ -2,7 +2,7 @@
 
 use super::{
     CortexMState, Dfsr,
-    cortex_m::Mvfr0,
+    cortex_m::{DwtVersion, Mvfr0},
     registers::cortex_m::{
         CORTEX_M_CORE_REGISTERS, CORTEX_M_WITH_FP_CORE_REGISTERS, FP, PC, RA, SP,
     },
@@ -15,7 +15,7 @@ use crate::{
     },
     core::{
         Architecture, CoreInformation, CoreInterface, CoreRegisters, CoreStatus, HaltReason,
-        MemoryMappedRegister, RegisterId, RegisterValue, VectorCatchCondition,
+        MemoryMappedRegister, RegisterId, RegisterValue, VectorCatchCondition, Watchpoint,
     },
     error::Error,
     memory::{CoreMemoryInterface, valid_32bit_address},
@@ -748,6 +748,12 @@ impl CoreInterface for Armv7m<'_> {
             let mut reason = dfsr.halt_reason();
             reason = self.state.resolve_halt_reason(reason);
 
+            if reason == HaltReason::Watchpoint(None) {
+                let watchpoint =
+                    super::cortex_m::dwt_hit_watchpoint(&mut *self.memory, DwtVersion::V7m)?;
+                reason = HaltReason::Watchpoint(watchpoint);
+            }
+
             // Clear bits from Dfsr register
             self.memory
                 .write_word_32(Dfsr::get_mmio_address(), Dfsr::clear_all().into())?;
@@ -852,6 +858,25 @@ impl CoreInterface for Armv7m<'_> {
         Ok(())
     }
 
+    fn prepare_synchronized_run(&mut self) -> Result<bool, Error> {
+        self.step()?;
+        self.state.clear_pending_step();
+
+        // Stepping masks interrupts, which has to be undone before the cross trigger restarts
+        // the core.
+        let mut dhcsr = Dhcsr(self.memory.read_word_32(Dhcsr::get_mmio_address())?);
+        if dhcsr.c_maskints() || dhcsr.c_step() {
+            dhcsr.set_c_maskints(false);
+            dhcsr.set_c_step(false);
+            dhcsr.enable_write();
+            self.memory
+                .write_word_32(Dhcsr::get_mmio_address(), dhcsr.into())?;
+            self.memory.flush()?;
+        }
+
+        Ok(true)
+    }
+
     fn reset(&mut self) -> Result<(), Error> {
         self.state.semihosting_command = None;
         self.state.clear_pending_step();
@@ -1151,6 +1176,36 @@ impl CoreInterface for Armv7m<'_> {
         self.state.hw_breakpoints_enabled
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(super::cortex_m::dwt_watchpoint_units(&mut *self.memory)?)
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        Ok(super::cortex_m::dwt_watchpoints(
+            &mut *self.memory,
+            DwtVersion::V7m,
+        )?)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        super::cortex_m::set_dwt_watchpoint(
+            &mut *self.memory,
+            DwtVersion::V7m,
+            unit_index,
+            watchpoint,
+        )
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        super::cortex_m::clear_dwt_watchpoint(&mut *self.memory, unit_index)?;
+        self.memory.flush()?;
+        Ok(())
+    }
+
     fn architecture(&self) -> Architecture {
         Architecture::Arm
     }
@@ -1195,6 +1195,12 @@ impl CoreInterface for Armv8a<'_> {
         Ok(())
     }
 
+    fn prepare_synchronized_run(&mut self) -> Result<bool, Error> {
+        self.writeback_registers()?;
+        self.ack_cti_halt()?;
+        Ok(true)
+    }
+
     fn reset(&mut self) -> Result<(), Error> {
         self.sequence.reset_system(
             &mut *self.memory,
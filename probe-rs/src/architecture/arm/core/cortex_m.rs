@@ -1,9 +1,11 @@
 //! Common functions and data types for Cortex-M core variants
 
 use crate::{
-    CoreInterface, Error, MemoryMappedRegister,
+    CoreInterface, Error, MemoryMappedRegister, Watchpoint, WatchpointKind,
     architecture::arm::{ArmError, memory::ArmMemoryInterface},
     core::RegisterId,
+    error::BreakpointError,
+    memory::valid_32bit_address,
     memory_mapped_bitfield_register,
     semihosting::SemihostingCommand,
     semihosting::decode_semihosting_syscall,
@@ -130,6 +132,253 @@ impl IdPfr1 {
     }
 }
 
+memory_mapped_bitfield_register! {
+    /// DWT Control Register
+    pub struct DwtCtrl(u32);
+    0xE000_1000, "DWT_CTRL",
+    impl From;
+    /// The number of comparators implemented.
+    pub numcomp, _: 31, 28;
+}
+
+memory_mapped_bitfield_register! {
+    /// DWT Comparator Function Register 0
+    ///
+    /// The registers of comparator `n` are located `16 * n` bytes after the registers of comparator 0.
+    pub struct DwtFunction(u32);
+    0xE000_1028, "DWT_FUNCTION0",
+    impl From;
+    /// Set when the comparator matched, cleared on read.
+    pub matched, _: 24;
+    /// The size of the watched data on ARMv8-M, as the log2 of the number of bytes.
+    pub datavsize, set_datavsize: 11, 10;
+    /// The action on a match on ARMv8-M.
+    pub action, set_action: 5, 4;
+    /// The comparator function on ARMv6-M and ARMv7-M, and the match type on ARMv8-M.
+    pub function, set_function: 3, 0;
+}
+
+/// DWT Comparator Register 0
+const DWT_COMP0: u64 = 0xE000_1020;
+/// DWT Comparator Mask Register 0, only on ARMv6-M and ARMv7-M.
+const DWT_MASK0: u64 = 0xE000_1024;
+/// The distance between the registers of two comparators.
+const DWT_COMPARATOR_STRIDE: u64 = 16;
+
+/// Debug Exception and Monitor Control Register
+const DEMCR: u64 = 0xE000_EDFC;
+/// Enables the DWT and ITM units.
+const DEMCR_TRCENA: u32 = 1 << 24;
+
+/// Generate a debug event on a match, for the `action` field on ARMv8-M.
+const DWT_ACTION_DEBUG_EVENT: u32 = 0b01;
+
+/// The encoding of the DWT comparator registers, which was changed by ARMv8-M.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DwtVersion {
+    /// The DWT of ARMv6-M and ARMv7-M, which watches power-of-two ranges with a mask register.
+    V7m,
+    /// The DWT of ARMv8-M, which watches single accesses of up to 4 bytes.
+    V8m,
+}
+
+impl DwtVersion {
+    fn function(self, kind: WatchpointKind) -> u32 {
+        match (self, kind) {
+            (DwtVersion::V7m, WatchpointKind::Read) => 0b0101,
+            (DwtVersion::V7m, WatchpointKind::Write) => 0b0110,
+            (DwtVersion::V7m, WatchpointKind::Access) => 0b0111,
+            (DwtVersion::V8m, WatchpointKind::Access) => 0b0100,
+            (DwtVersion::V8m, WatchpointKind::Write) => 0b0101,
+            (DwtVersion::V8m, WatchpointKind::Read) => 0b0110,
+        }
+    }
+
+    fn kind(self, function: DwtFunction) -> Option<WatchpointKind> {
+        match (self, function.function()) {
+            (DwtVersion::V7m, 0b0101) => Some(WatchpointKind::Read),
+            (DwtVersion::V7m, 0b0110) => Some(WatchpointKind::Write),
+            (DwtVersion::V7m, 0b0111) => Some(WatchpointKind::Access),
+            (DwtVersion::V8m, _) if function.action() != DWT_ACTION_DEBUG_EVENT => None,
+            (DwtVersion::V8m, 0b0100) => Some(WatchpointKind::Access),
+            (DwtVersion::V8m, 0b0101) => Some(WatchpointKind::Write),
+            (DwtVersion::V8m, 0b0110) => Some(WatchpointKind::Read),
+            _ => None,
+        }
+    }
+}
+
+fn dwt_register(base: u64, unit_index: usize) -> u64 {
+    base + unit_index as u64 * DWT_COMPARATOR_STRIDE
+}
+
+/// Returns `true` if the DWT is enabled, which is required to access its registers.
+fn dwt_enabled(memory: &mut dyn ArmMemoryInterface) -> Result<bool, ArmError> {
+    Ok(memory.read_word_32(DEMCR)? & DEMCR_TRCENA != 0)
+}
+
+/// Returns the number of DWT comparators, which can be used as watchpoints.
+///
+/// If the DWT is disabled, it is only enabled while the number is read, so that querying the
+/// watchpoint units does not change the trace configuration of the target.
+pub(crate) fn dwt_watchpoint_units(memory: &mut dyn ArmMemoryInterface) -> Result<u32, ArmError> {
+    let demcr = memory.read_word_32(DEMCR)?;
+    if demcr & DEMCR_TRCENA != 0 {
+        return Ok(DwtCtrl(memory.read_word_32(DwtCtrl::get_mmio_address())?).numcomp());
+    }
+
+    memory.write_word_32(DEMCR, demcr | DEMCR_TRCENA)?;
+    let ctrl = memory.read_word_32(DwtCtrl::get_mmio_address());
+    memory.write_word_32(DEMCR, demcr)?;
+
+    Ok(DwtCtrl(ctrl?).numcomp())
+}
+
+/// Reads the watchpoints configured in the DWT comparators.
+///
+/// Comparators used for something else, like data tracing, are reported as free. While the DWT
+/// is disabled, no watchpoint is active.
+pub(crate) fn dwt_watchpoints(
+    memory: &mut dyn ArmMemoryInterface,
+    version: DwtVersion,
+) -> Result<Vec<Option<Watchpoint>>, ArmError> {
+    let units = dwt_watchpoint_units(memory)? as usize;
+    if !dwt_enabled(memory)? {
+        return Ok(vec![None; units]);
+    }
+
+    let mut watchpoints = Vec::with_capacity(units);
+    for unit_index in 0..units {
+        let function = DwtFunction(
+            memory.read_word_32(dwt_register(DwtFunction::get_mmio_address(), unit_index))?,
+        );
+        watchpoints.push(read_dwt_watchpoint(memory, version, unit_index, function)?);
+    }
+
+    Ok(watchpoints)
+}
+
+/// Returns the watchpoint whose comparator matched, after the core halted on a watchpoint.
+///
+/// Reading the function registers clears their `MATCHED` bit, so this only works once per halt.
+pub(crate) fn dwt_hit_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    version: DwtVersion,
+) -> Result<Option<Watchpoint>, ArmError> {
+    if !dwt_enabled(memory)? {
+        return Ok(None);
+    }
+    let units = dwt_watchpoint_units(memory)? as usize;
+
+    for unit_index in 0..units {
+        let function = DwtFunction(
+            memory.read_word_32(dwt_register(DwtFunction::get_mmio_address(), unit_index))?,
+        );
+        if function.matched()
+            && let Some(watchpoint) = read_dwt_watchpoint(memory, version, unit_index, function)?
+        {
+            return Ok(Some(watchpoint));
+        }
+    }
+
+    Ok(None)
+}
+
+fn read_dwt_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    version: DwtVersion,
+    unit_index: usize,
+    function: DwtFunction,
+) -> Result<Option<Watchpoint>, ArmError> {
+    let Some(kind) = version.kind(function) else {
+        return Ok(None);
+    };
+
+    let address = memory.read_word_32(dwt_register(DWT_COMP0, unit_index))? as u64;
+    let length = match version {
+        DwtVersion::V7m => 1 << memory.read_word_32(dwt_register(DWT_MASK0, unit_index))?,
+        DwtVersion::V8m => 1 << function.datavsize(),
+    };
+
+    Ok(Some(Watchpoint {
+        address,
+        length,
+        kind,
+    }))
+}
+
+/// Configures DWT comparator `unit_index` to halt the core on `watchpoint`.
+pub(crate) fn set_dwt_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    version: DwtVersion,
+    unit_index: usize,
+    watchpoint: Watchpoint,
+) -> Result<(), Error> {
+    let address = valid_32bit_address(watchpoint.address)?;
+    let unsupported = |reason| {
+        Error::BreakpointOperation(BreakpointError::UnsupportedWatchpoint {
+            address: watchpoint.address,
+            length: watchpoint.length,
+            reason,
+        })
+    };
+
+    if !watchpoint.is_naturally_aligned() {
+        return Err(unsupported(
+            "the length must be a power of two, and the address aligned to it",
+        ));
+    }
+    let size = watchpoint.length.trailing_zeros();
+
+    // The comparators only work while the DWT is enabled.
+    let demcr = memory.read_word_32(DEMCR)?;
+    if demcr & DEMCR_TRCENA == 0 {
+        memory.write_word_32(DEMCR, demcr | DEMCR_TRCENA)?;
+    }
+
+    // Disable the comparator while it is reconfigured.
+    clear_dwt_watchpoint(memory, unit_index)?;
+    memory.write_word_32(dwt_register(DWT_COMP0, unit_index), address)?;
+
+    let mut function = DwtFunction(0);
+    function.set_function(version.function(watchpoint.kind));
+    match version {
+        DwtVersion::V7m => {
+            let mask = dwt_register(DWT_MASK0, unit_index);
+            memory.write_word_32(mask, size)?;
+            // The mask register only implements as many bits as the largest supported range needs.
+            if memory.read_word_32(mask)? != size {
+                return Err(unsupported(
+                    "the range is larger than the comparators can watch",
+                ));
+            }
+        }
+        DwtVersion::V8m => {
+            if size > 2 {
+                return Err(unsupported("at most 4 bytes can be watched"));
+            }
+            function.set_datavsize(size);
+            function.set_action(DWT_ACTION_DEBUG_EVENT);
+        }
+    }
+
+    memory.write_word_32(
+        dwt_register(DwtFunction::get_mmio_address(), unit_index),
+        function.into(),
+    )?;
+    memory.flush()?;
+
+    Ok(())
+}
+
+/// Disables DWT comparator `unit_index`.
+pub(crate) fn clear_dwt_watchpoint(
+    memory: &mut dyn ArmMemoryInterface,
+    unit_index: usize,
+) -> Result<(), ArmError> {
+    memory.write_word_32(dwt_register(DwtFunction::get_mmio_address(), unit_index), 0)
+}
+
 pub(crate) fn read_core_reg(
     memory: &mut dyn ArmMemoryInterface,
     addr: RegisterId,
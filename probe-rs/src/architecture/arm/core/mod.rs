@@ -101,7 +101,7 @@ impl Dfsr {
         } else if self.external() {
             HaltReason::External
         } else if self.dwttrap() {
-            HaltReason::Watchpoint
+            HaltReason::Watchpoint(None)
         } else if self.halted() {
             HaltReason::Request
         } else if self.vcatch() {
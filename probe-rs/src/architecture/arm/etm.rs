@@ -0,0 +1,693 @@
+//! Decoding of ETM and PTM instruction trace.
+//!
+//! The trace generated by an [`Etm`](super::component::Etm) does not contain the executed
+//! instructions themselves. It only tells whether each waypoint (a branch, or for ETMv3 every
+//! instruction) was executed, and where execution continued after indirect branches and
+//! exceptions. This module turns the packet stream into a list of [`TraceElement`]s, which then
+//! have to be applied to the program image to recover the executed instructions.
+
+use crate::InstructionSet;
+
+/// The trace protocol generated by an embedded trace macrocell.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Protocol {
+    /// ETMv3, as implemented by Cortex-M3/M4 and Cortex-R cores.
+    EtmV3 {
+        /// Branch address packets use the alternative encoding, where the last address byte
+        /// indicates if exception information follows.
+        alternative_branch_encoding: bool,
+    },
+    /// Program flow trace (PFT), as implemented by the PTM of Cortex-A9/A15 cores.
+    Ptm,
+    /// ETMv4, as implemented by Armv8-M and Armv8-A cores.
+    EtmV4,
+}
+
+impl Protocol {
+    /// Returns `true` if atoms are only generated for branch instructions.
+    ///
+    /// ETMv3 generates an atom for every instruction instead.
+    pub fn is_waypoint_based(&self) -> bool {
+        !matches!(self, Protocol::EtmV3 { .. })
+    }
+}
+
+/// An element of the decoded instruction trace.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TraceElement {
+    /// The trace was synchronized, and execution continues at `address`.
+    Sync {
+        /// The address of the next instruction.
+        address: u64,
+        /// The instruction set the core is executing.
+        instruction_set: InstructionSet,
+    },
+    /// Execution continued at `address` after an indirect branch or an exception.
+    Address {
+        /// The address of the next instruction.
+        address: u64,
+        /// The instruction set the core is executing.
+        instruction_set: InstructionSet,
+    },
+    /// The next waypoint was executed, or its condition failed.
+    Atom {
+        /// `false` if the condition of the waypoint failed.
+        executed: bool,
+    },
+    /// An exception was taken.
+    Exception {
+        /// The architecture specific exception number.
+        number: u16,
+        /// The address the exception returns to, if the protocol reports it.
+        ///
+        /// All instructions up to this address were executed before the exception was taken.
+        return_address: Option<u64>,
+    },
+    /// The core returned from an exception.
+    ExceptionReturn,
+    /// Trace data was lost, for example because the trace memory overflowed. The trace does not
+    /// continue from the previous element.
+    Discontinuity,
+}
+
+/// Decodes the instruction trace `data` generated with `protocol`.
+///
+/// Data before the first synchronization packet is skipped. Unsupported packets, such as cycle
+/// counts or data trace, cause the decoder to skip to the next synchronization packet.
+pub fn decode(protocol: Protocol, data: &[u8]) -> Vec<TraceElement> {
+    let mut decoder = Decoder {
+        protocol,
+        data,
+        position: 0,
+        elements: vec![],
+        address: 0,
+        instruction_set: InstructionSet::Thumb2,
+        history: [(0, InstructionSet::Thumb2); 3],
+        pending_exception: None,
+        synchronizing: false,
+    };
+    decoder.run();
+    decoder.elements
+}
+
+enum DecodeError {
+    /// The data ended in the middle of a packet.
+    Truncated,
+    /// The packet is not supported by the decoder.
+    Unsupported(u8),
+}
+
+struct Decoder<'a> {
+    protocol: Protocol,
+    data: &'a [u8],
+    position: usize,
+    elements: Vec<TraceElement>,
+
+    /// The last address of an ETMv3 or PTM branch address packet.
+    address: u64,
+    /// The instruction set of the last ETMv3 or PTM branch address packet.
+    instruction_set: InstructionSet,
+    /// The ETMv4 address history, most recent first.
+    history: [(u64, InstructionSet); 3],
+    /// An ETMv4 exception, which is completed by the following address packet.
+    pending_exception: Option<u16>,
+    /// The next ETMv4 address packet completes a synchronization.
+    synchronizing: bool,
+}
+
+impl Decoder<'_> {
+    fn run(&mut self) {
+        while self.find_alignment_sync() {
+            let result = match self.protocol {
+                Protocol::EtmV3 { .. } | Protocol::Ptm => self.decode_v3(),
+                Protocol::EtmV4 => self.decode_v4(),
+            };
+
+            match result {
+                Ok(()) | Err(DecodeError::Truncated) => break,
+                Err(DecodeError::Unsupported(header)) => {
+                    tracing::debug!(
+                        "Unsupported {:?} packet header {header:#04x}, waiting for synchronization",
+                        self.protocol
+                    );
+                    self.elements.push(TraceElement::Discontinuity);
+                    self.pending_exception = None;
+                }
+            }
+        }
+    }
+
+    /// Skips to the end of the next alignment synchronization packet.
+    ///
+    /// Returns `false` if there is none.
+    fn find_alignment_sync(&mut self) -> bool {
+        let min_zeros = match self.protocol {
+            Protocol::EtmV3 { .. } | Protocol::Ptm => 5,
+            Protocol::EtmV4 => 11,
+        };
+
+        let mut zeros = 0;
+        while let Some(&byte) = self.data.get(self.position) {
+            self.position += 1;
+            match byte {
+                0x00 => zeros += 1,
+                0x80 if zeros >= min_zeros => {
+                    self.synchronizing = true;
+                    return true;
+                }
+                _ => zeros = 0,
+            }
+        }
+        false
+    }
+
+    fn next(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self.data.get(self.position).ok_or(DecodeError::Truncated)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn next_u32(&mut self) -> Result<u32, DecodeError> {
+        let mut value = 0;
+        for i in 0..4 {
+            value |= (self.next()? as u32) << (8 * i);
+        }
+        Ok(value)
+    }
+
+    /// Skips a field where every byte has a continuation bit, up to `max_len` bytes.
+    fn skip_continued(&mut self, max_len: usize) -> Result<(), DecodeError> {
+        for _ in 0..max_len {
+            if self.next()? & 0x80 == 0 {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Skips the zeros of an alignment synchronization packet after its header.
+    fn skip_alignment_sync(&mut self, header: u8) -> Result<(), DecodeError> {
+        loop {
+            match self.next()? {
+                0x00 => {}
+                0x80 => return Ok(()),
+                _ => return Err(DecodeError::Unsupported(header)),
+            }
+        }
+    }
+
+    fn decode_v3(&mut self) -> Result<(), DecodeError> {
+        let is_ptm = self.protocol == Protocol::Ptm;
+        loop {
+            let header = self.next()?;
+            match header {
+                0x00 => self.skip_alignment_sync(header)?,
+                0x08 => self.instruction_sync_v3()?,
+                // Trigger and ignore packets.
+                0x0C | 0x66 => {}
+                0x76 => self.elements.push(TraceElement::ExceptionReturn),
+                // VMID packet.
+                0x3C => _ = self.next()?,
+                // Timestamp packets.
+                0x42 | 0x46 => self.skip_continued(9)?,
+                _ if header & 0x01 != 0 => self.branch_address_v3(header)?,
+                // PTM atom packet, without cycle count.
+                _ if is_ptm && header & 0x81 == 0x80 => {
+                    self.push_atoms(1, u32::from(header & 0x02 == 0));
+                }
+                // ETMv3 format 1 P-header: a number of E atoms, followed by up to one N atom.
+                _ if !is_ptm && header & 0x83 == 0x80 => {
+                    let executed = (header >> 2) & 0x0F;
+                    let not_executed = (header >> 6) & 0x01;
+                    self.push_atoms(executed + not_executed, (1 << executed) - 1);
+                }
+                // ETMv3 format 2 P-header: two atoms.
+                _ if !is_ptm && header & 0xF3 == 0x82 => {
+                    let atoms = (header >> 2) & 0x03;
+                    // A set bit marks an N atom, and the first atom is in bit 1.
+                    let first = u32::from(atoms & 0x02 == 0);
+                    let second = u32::from(atoms & 0x01 == 0);
+                    self.push_atoms(2, first | (second << 1));
+                }
+                _ => return Err(DecodeError::Unsupported(header)),
+            }
+        }
+    }
+
+    /// Decodes an ETMv3 or PTM instruction synchronization packet, without context ID.
+    fn instruction_sync_v3(&mut self) -> Result<(), DecodeError> {
+        let (info, address) = if self.protocol == Protocol::Ptm {
+            let address = self.next_u32()?;
+            (self.next()?, address)
+        } else {
+            let info = self.next()?;
+            (info, self.next_u32()?)
+        };
+
+        // The reason for the synchronization is a restart after an overflow.
+        if (info >> 5) & 0x03 == 0b10 {
+            self.elements.push(TraceElement::Discontinuity);
+        }
+
+        self.instruction_set = if address & 1 != 0 {
+            InstructionSet::Thumb2
+        } else {
+            InstructionSet::A32
+        };
+        self.address = u64::from(address & !1);
+        self.elements.push(TraceElement::Sync {
+            address: self.address,
+            instruction_set: self.instruction_set,
+        });
+        Ok(())
+    }
+
+    /// Decodes an ETMv3 or PTM branch address packet, and the exception information following it.
+    ///
+    /// Only the address bits which changed since the last branch address packet are included.
+    fn branch_address_v3(&mut self, header: u8) -> Result<(), DecodeError> {
+        let alternative_encoding = match self.protocol {
+            Protocol::EtmV3 {
+                alternative_branch_encoding,
+            } => alternative_branch_encoding,
+            _ => true,
+        };
+
+        let mut bytes = vec![header];
+        while bytes.len() < 5 && bytes[bytes.len() - 1] & 0x80 != 0 {
+            bytes.push(self.next()?);
+        }
+
+        let mut bits = u64::from((header >> 1) & 0x3F);
+        let mut width = 6;
+        let mut exception_follows = false;
+        for (i, &byte) in bytes.iter().enumerate().take(4).skip(1) {
+            if alternative_encoding && i == bytes.len() - 1 {
+                // The last byte of a short packet indicates if exception information follows.
+                bits |= u64::from(byte & 0x3F) << width;
+                width += 6;
+                exception_follows = byte & 0x40 != 0;
+            } else {
+                bits |= u64::from(byte & 0x7F) << width;
+                width += 7;
+            }
+        }
+
+        let mut instruction_set = self.instruction_set;
+        if let Some(&byte) = bytes.get(4) {
+            exception_follows = byte & 0x40 != 0;
+            if byte & 0x20 != 0 {
+                // Jazelle state is not supported.
+                return Err(DecodeError::Unsupported(header));
+            } else if byte & 0x10 != 0 {
+                instruction_set = InstructionSet::Thumb2;
+                bits |= u64::from(byte & 0x0F) << width;
+                width += 4;
+            } else {
+                instruction_set = InstructionSet::A32;
+                bits |= u64::from(byte & 0x07) << width;
+                width += 3;
+            }
+        }
+
+        // Thumb addresses are halfword aligned, Arm addresses are word aligned.
+        let shift = if instruction_set == InstructionSet::Thumb2 {
+            1
+        } else {
+            2
+        };
+        let mask = ((1u64 << width) - 1) << shift;
+        self.address = ((self.address & !mask) | (bits << shift)) & 0xFFFF_FFFF;
+        self.instruction_set = instruction_set;
+
+        if exception_follows {
+            let info = self.next()?;
+            let mut number = u16::from((info >> 1) & 0x0F);
+            if info & 0x80 != 0 {
+                let info = self.next()?;
+                number |= u16::from(info & 0x1F) << 4;
+                if info & 0x80 != 0 {
+                    self.skip_continued(usize::MAX)?;
+                }
+            }
+            self.elements.push(TraceElement::Exception {
+                number,
+                return_address: None,
+            });
+        }
+
+        self.elements.push(TraceElement::Address {
+            address: self.address,
+            instruction_set,
+        });
+        Ok(())
+    }
+
+    fn decode_v4(&mut self) -> Result<(), DecodeError> {
+        loop {
+            let header = self.next()?;
+            match header {
+                0x00 => match self.next()? {
+                    0x00 => {
+                        self.skip_alignment_sync(header)?;
+                        self.synchronizing = true;
+                    }
+                    // Discard and overflow packets.
+                    0x03 | 0x05 => {
+                        self.pending_exception = None;
+                        self.elements.push(TraceElement::Discontinuity);
+                    }
+                    _ => return Err(DecodeError::Unsupported(header)),
+                },
+                0x01 => self.trace_info_v4()?,
+                // Timestamp packets, optionally followed by a cycle count.
+                0x02 | 0x03 => {
+                    for i in 0..9 {
+                        if self.next()? & 0x80 == 0 && i < 8 {
+                            break;
+                        }
+                    }
+                    if header & 0x01 != 0 {
+                        self.skip_continued(3)?;
+                    }
+                }
+                // Trace on packet.
+                0x04 => self.elements.push(TraceElement::Discontinuity),
+                0x06 => {
+                    let info = self.next()?;
+                    let mut number = u16::from((info >> 1) & 0x1F);
+                    if info & 0x80 != 0 {
+                        number |= u16::from(self.next()? & 0x1F) << 5;
+                    }
+                    // The address packet which follows is the preferred return address.
+                    self.pending_exception = Some(number);
+                }
+                0x07 => self.elements.push(TraceElement::ExceptionReturn),
+                // Ignore and event packets.
+                0x70..=0x7F => {}
+                // Context packets.
+                0x80 => {}
+                0x81 => self.context_info_v4()?,
+                0x82 | 0x83 | 0x85 | 0x86 => {
+                    self.long_address_v4(header)?;
+                    self.context_info_v4()?;
+                }
+                0x90..=0x92 => {
+                    let (address, instruction_set) = self.history[usize::from(header & 0x03)];
+                    self.push_address_v4(address, instruction_set);
+                }
+                0x95 | 0x96 => self.short_address_v4(header)?,
+                0x9A | 0x9B | 0x9D | 0x9E => self.long_address_v4(header)?,
+                // Atom format 1.
+                0xF6 | 0xF7 => self.push_atoms(1, u32::from(header & 0x01)),
+                // Atom format 2.
+                0xD8..=0xDB => self.push_atoms(2, u32::from(header & 0x03)),
+                // Atom format 3.
+                0xF8..=0xFF => self.push_atoms(3, u32::from(header & 0x07)),
+                // Atom format 4.
+                0xDC..=0xDF => {
+                    const PATTERNS: [u32; 4] = [0b1110, 0b0000, 0b1010, 0b0101];
+                    self.push_atoms(4, PATTERNS[usize::from(header & 0x03)]);
+                }
+                // Atom format 5.
+                0xD5..=0xD7 | 0xF5 => {
+                    let executed = match ((header >> 3) & 0x04) | (header & 0x03) {
+                        0b101 => 0b11110,
+                        0b001 => 0b00000,
+                        0b010 => 0b01010,
+                        _ => 0b10101,
+                    };
+                    self.push_atoms(5, executed);
+                }
+                // Atom format 6: a number of E atoms, followed by an E or N atom.
+                0xC0..=0xD4 | 0xE0..=0xF4 => {
+                    let count = (header & 0x1F) + 3;
+                    let last = u32::from(header & 0x20 == 0);
+                    self.push_atoms(count + 1, ((1 << count) - 1) | (last << count));
+                }
+                _ => return Err(DecodeError::Unsupported(header)),
+            }
+        }
+    }
+
+    /// Skips an ETMv4 trace info packet, which resets the address history.
+    fn trace_info_v4(&mut self) -> Result<(), DecodeError> {
+        let present = self.next()?;
+        if present & 0x80 != 0 {
+            return Err(DecodeError::Unsupported(0x01));
+        }
+
+        // The INFO, KEY, SPEC and CYCT sections.
+        for section in 0..4 {
+            if present & (1 << section) != 0 {
+                self.skip_continued(usize::MAX)?;
+            }
+        }
+
+        self.history = [(0, InstructionSet::A32); 3];
+        self.synchronizing = true;
+        Ok(())
+    }
+
+    /// Skips the payload of an ETMv4 context packet.
+    fn context_info_v4(&mut self) -> Result<(), DecodeError> {
+        let info = self.next()?;
+        if info & 0x40 != 0 {
+            // VMID
+            self.next()?;
+        }
+        if info & 0x80 != 0 {
+            // Context ID
+            self.next_u32()?;
+        }
+        Ok(())
+    }
+
+    /// The instruction set of an ETMv4 IS0 address.
+    ///
+    /// IS0 is A64 for 64-bit addresses, and A32 otherwise.
+    fn is0_instruction_set(&self, wide: bool) -> InstructionSet {
+        if wide || self.history[0].1 == InstructionSet::A64 {
+            InstructionSet::A64
+        } else {
+            InstructionSet::A32
+        }
+    }
+
+    fn short_address_v4(&mut self, header: u8) -> Result<(), DecodeError> {
+        let (shift, instruction_set) = if header == 0x95 {
+            (2, self.is0_instruction_set(false))
+        } else {
+            (1, InstructionSet::Thumb2)
+        };
+
+        let first = self.next()?;
+        let mut bits = u64::from(first & 0x7F) << shift;
+        let mut width = 7 + shift;
+        if first & 0x80 != 0 {
+            bits |= u64::from(self.next()?) << width;
+            width += 8;
+        }
+
+        let mask = (1u64 << width) - 1;
+        let address = (self.history[0].0 & !mask) | bits;
+        self.push_address_v4(address, instruction_set);
+        Ok(())
+    }
+
+    fn long_address_v4(&mut self, header: u8) -> Result<(), DecodeError> {
+        let thumb = matches!(header, 0x83 | 0x86 | 0x9B | 0x9E);
+        let wide = matches!(header, 0x85 | 0x86 | 0x9D | 0x9E);
+
+        let first = self.next()?;
+        let second = self.next()?;
+        let mut address = if thumb {
+            u64::from(first & 0x7F) << 1 | u64::from(second) << 8
+        } else {
+            u64::from(first & 0x7F) << 2 | u64::from(second & 0x7F) << 9
+        };
+        address |= u64::from(self.next()?) << 16;
+        address |= u64::from(self.next()?) << 24;
+        if wide {
+            address |= u64::from(self.next_u32()?) << 32;
+        } else {
+            address |= self.history[0].0 & !0xFFFF_FFFF;
+        }
+
+        let instruction_set = if thumb {
+            InstructionSet::Thumb2
+        } else {
+            self.is0_instruction_set(wide)
+        };
+        self.push_address_v4(address, instruction_set);
+        Ok(())
+    }
+
+    fn push_address_v4(&mut self, address: u64, instruction_set: InstructionSet) {
+        self.history = [(address, instruction_set), self.history[0], self.history[1]];
+
+        let element = if let Some(number) = self.pending_exception.take() {
+            TraceElement::Exception {
+                number,
+                return_address: Some(address),
+            }
+        } else if std::mem::take(&mut self.synchronizing) {
+            TraceElement::Sync {
+                address,
+                instruction_set,
+            }
+        } else {
+            TraceElement::Address {
+                address,
+                instruction_set,
+            }
+        };
+        self.elements.push(element);
+    }
+
+    /// Pushes `count` atoms, where bit N of `executed` is set for an E atom, oldest first.
+    fn push_atoms(&mut self, count: u8, executed: u32) {
+        for i in 0..count {
+            self.elements.push(TraceElement::Atom {
+                executed: executed & (1 << i) != 0,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ETMV4_ASYNC: [u8; 12] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x80];
+
+    fn atoms(pattern: &str) -> Vec<TraceElement> {
+        pattern
+            .chars()
+            .map(|c| TraceElement::Atom { executed: c == 'E' })
+            .collect()
+    }
+
+    #[test]
+    fn etmv4_skips_data_before_sync() {
+        let mut data = vec![0xF7, 0x12, 0x34];
+        data.extend_from_slice(&ETMV4_ASYNC);
+        data.extend_from_slice(&[0x01, 0x00, 0xF7]);
+
+        assert_eq!(decode(Protocol::EtmV4, &data), atoms("E"));
+    }
+
+    #[test]
+    fn etmv4_thumb_addresses() {
+        let mut data = ETMV4_ASYNC.to_vec();
+        // Trace info without sections, then a long IS1 address with context.
+        data.extend_from_slice(&[0x01, 0x00, 0x83, 0x02, 0x04, 0x00, 0x08, 0x00]);
+        // E, N, E atoms.
+        data.push(0xFD);
+        // Short IS1 address, replacing bits [7:1].
+        data.extend_from_slice(&[0x96, 0x10]);
+        // Exact match of the first address.
+        data.push(0x91);
+
+        let mut expected = vec![TraceElement::Sync {
+            address: 0x0800_0404,
+            instruction_set: InstructionSet::Thumb2,
+        }];
+        expected.extend(atoms("ENE"));
+        expected.push(TraceElement::Address {
+            address: 0x0800_0420,
+            instruction_set: InstructionSet::Thumb2,
+        });
+        expected.push(TraceElement::Address {
+            address: 0x0800_0404,
+            instruction_set: InstructionSet::Thumb2,
+        });
+
+        assert_eq!(decode(Protocol::EtmV4, &data), expected);
+    }
+
+    #[test]
+    fn etmv4_atom_formats() {
+        let mut data = ETMV4_ASYNC.to_vec();
+        // Formats 2, 4, 5 and 6.
+        data.extend_from_slice(&[0x01, 0x00, 0xD9, 0xDC, 0xD6, 0xE1]);
+
+        assert_eq!(decode(Protocol::EtmV4, &data), atoms("ENNEEENENENEEEEN"));
+    }
+
+    #[test]
+    fn etmv4_exception() {
+        let mut data = ETMV4_ASYNC.to_vec();
+        data.extend_from_slice(&[0x01, 0x00, 0x9B, 0x00, 0x01, 0x00, 0x00]);
+        // Exception 3, with the preferred return address and the handler address.
+        data.extend_from_slice(&[0x06, 0x06, 0x96, 0x08, 0x96, 0x40]);
+
+        assert_eq!(
+            decode(Protocol::EtmV4, &data),
+            vec![
+                TraceElement::Sync {
+                    address: 0x100,
+                    instruction_set: InstructionSet::Thumb2,
+                },
+                TraceElement::Exception {
+                    number: 3,
+                    return_address: Some(0x110),
+                },
+                TraceElement::Address {
+                    address: 0x180,
+                    instruction_set: InstructionSet::Thumb2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn etmv3_instruction_sync_and_branches() {
+        let protocol = Protocol::EtmV3 {
+            alternative_branch_encoding: true,
+        };
+        let mut data = vec![0, 0, 0, 0, 0, 0x80];
+        // I-sync at 0x2000_0100 in Thumb state.
+        data.extend_from_slice(&[0x08, 0x00, 0x01, 0x01, 0x00, 0x20]);
+        // Three E atoms and one N atom, then E and N.
+        data.extend_from_slice(&[0xCC, 0x86]);
+        // Branch to 0x2000_0140.
+        data.extend_from_slice(&[0xC1, 0x02]);
+        // Branch with exception 11 to 0x2000_0200.
+        data.extend_from_slice(&[0x81, 0x44, 0x16]);
+
+        let mut expected = vec![TraceElement::Sync {
+            address: 0x2000_0100,
+            instruction_set: InstructionSet::Thumb2,
+        }];
+        expected.extend(atoms("EEENEN"));
+        expected.push(TraceElement::Address {
+            address: 0x2000_0140,
+            instruction_set: InstructionSet::Thumb2,
+        });
+        expected.push(TraceElement::Exception {
+            number: 11,
+            return_address: None,
+        });
+        expected.push(TraceElement::Address {
+            address: 0x2000_0200,
+            instruction_set: InstructionSet::Thumb2,
+        });
+
+        assert_eq!(decode(protocol, &data), expected);
+    }
+
+    #[test]
+    fn unsupported_packets_wait_for_sync() {
+        let mut data = ETMV4_ASYNC.to_vec();
+        data.extend_from_slice(&[0x01, 0x00, 0xF7, 0x2D, 0xF7]);
+        data.extend_from_slice(&ETMV4_ASYNC);
+        data.extend_from_slice(&[0x01, 0x00, 0xF6]);
+
+        let mut expected = atoms("E");
+        expected.push(TraceElement::Discontinuity);
+        expected.extend(atoms("N"));
+
+        assert_eq!(decode(Protocol::EtmV4, &data), expected);
+    }
+}
@@ -78,6 +78,12 @@ pub enum RiscvError {
     /// The hart is unavailable
     #[error("The requested hart is unavailable.")]
     HartUnavailable,
+    /// The debug module cannot select multiple harts at once.
+    #[error("The debug module does not support selecting multiple harts.")]
+    HartArrayNotSupported,
+    /// The debug module does not implement halt groups.
+    #[error("The debug module does not support halt groups.")]
+    HaltGroupsNotSupported,
 }
 
 impl From<RiscvError> for ProbeRsError {
@@ -2670,6 +2676,138 @@ impl<'state> RiscvCommunicationInterface<'state> {
         self.wait_for_resume_ack(Duration::from_millis(50))
     }
 
+    /// Halts all harts in `harts` with a single request.
+    ///
+    /// Returns [`RiscvError::HartArrayNotSupported`] if the debug module cannot select multiple
+    /// harts, in which case the harts have to be halted one by one.
+    pub fn halt_harts(&mut self, harts: &[u32], timeout: Duration) -> Result<(), RiscvError> {
+        let mut dmcontrol = self.select_hart_array(harts)?;
+
+        dmcontrol.set_haltreq(true);
+        self.write_dm_register(dmcontrol)?;
+
+        // With `hasel` set, `allhalted` covers all selected harts.
+        let start = Instant::now();
+        let result = loop {
+            let dmstatus: Dmstatus = self.read_dm_register()?;
+            if dmstatus.allhalted() {
+                break Ok(());
+            }
+            if start.elapsed() >= timeout {
+                break Err(RiscvError::Timeout);
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+
+        dmcontrol.set_haltreq(false);
+        self.deselect_hart_array(dmcontrol)?;
+
+        // The selected hart may not have been part of the request.
+        self.state.is_halted = false;
+
+        result
+    }
+
+    /// Resumes all harts in `harts` with a single request.
+    ///
+    /// Returns [`RiscvError::HartArrayNotSupported`] if the debug module cannot select multiple
+    /// harts, in which case the harts have to be resumed one by one.
+    pub fn resume_harts(&mut self, harts: &[u32]) -> Result<(), RiscvError> {
+        let mut dmcontrol = self.select_hart_array(harts)?;
+
+        dmcontrol.set_resumereq(true);
+        self.schedule_write_dm_register(dmcontrol)?;
+
+        dmcontrol.set_resumereq(false);
+        self.write_dm_register(dmcontrol)?;
+
+        let result = self.wait_for_resume_ack(Duration::from_millis(50));
+
+        self.deselect_hart_array(dmcontrol)?;
+        self.state.is_halted = false;
+
+        result
+    }
+
+    /// Puts the harts in `harts` into halt group `group`, and all other harts into group 0.
+    ///
+    /// Harts in the same halt group (other than group 0) are halted by the debug module as soon
+    /// as one of them halts, for example on a breakpoint.
+    pub fn set_halt_group(&mut self, harts: &[u32], group: u32) -> Result<(), RiscvError> {
+        let mut result = Ok(());
+
+        for hart in 0..self.state.num_harts {
+            if !self.hart_enabled(hart) {
+                continue;
+            }
+
+            let mut dmcontrol = self.state.current_dmcontrol;
+            dmcontrol.set_dmactive(true);
+            dmcontrol.set_hartsel(hart);
+            self.schedule_write_dm_register(dmcontrol)?;
+
+            let hart_group = if harts.contains(&hart) { group } else { 0 };
+
+            let mut dmcs2 = Dmcs2(0);
+            dmcs2.set_group(hart_group);
+            dmcs2.set_hgwrite(true);
+            self.schedule_write_dm_register(dmcs2)?;
+
+            // Halt groups are optional, the group field reads 0 if they are not implemented.
+            let dmcs2: Dmcs2 = self.read_dm_register()?;
+            if dmcs2.group() != hart_group {
+                result = Err(RiscvError::HaltGroupsNotSupported);
+                break;
+            }
+        }
+
+        let mut dmcontrol = self.state.current_dmcontrol;
+        dmcontrol.set_hartsel(self.state.last_selected_hart);
+        self.write_dm_register(dmcontrol)?;
+
+        result
+    }
+
+    /// Selects `harts` using the hart array mask, and returns the `dmcontrol` value which
+    /// addresses requests to all of them.
+    fn select_hart_array(&mut self, harts: &[u32]) -> Result<Dmcontrol, RiscvError> {
+        let mut mask = 0;
+        for &hart in harts {
+            if !self.hart_enabled(hart) {
+                return Err(RiscvError::HartUnavailable);
+            }
+            mask |= 1 << hart;
+        }
+
+        // All harts fit into the first window, as we support at most 32 harts.
+        self.schedule_write_dm_register(Hawindowsel(0))?;
+        self.schedule_write_dm_register(Hawindow(mask))?;
+
+        // `hartsel` is always selected as well, so it has to be one of `harts`.
+        let mut dmcontrol = self.state.current_dmcontrol;
+        dmcontrol.set_dmactive(true);
+        dmcontrol.set_hartsel(harts.first().copied().unwrap_or_default());
+        dmcontrol.set_hasel(true);
+        self.schedule_write_dm_register(dmcontrol)?;
+
+        // `hasel` and the hart array mask are optional, and read back as 0 if not implemented.
+        let hawindow: Hawindow = self.read_dm_register()?;
+        let readback: Dmcontrol = self.read_dm_register()?;
+        if !readback.hasel() || hawindow.maskdata() != mask {
+            self.deselect_hart_array(dmcontrol)?;
+            return Err(RiscvError::HartArrayNotSupported);
+        }
+
+        Ok(dmcontrol)
+    }
+
+    /// Selects the previously selected hart again, after [`Self::select_hart_array`].
+    fn deselect_hart_array(&mut self, mut dmcontrol: Dmcontrol) -> Result<(), RiscvError> {
+        dmcontrol.set_hasel(false);
+        dmcontrol.set_hartsel(self.state.last_selected_hart);
+        self.write_dm_register(dmcontrol)
+    }
+
     /// Some cores (WCH Qingke) update dmstatus only after a brief delay; poll
     /// for the ack bit or for the hart actually running.
     fn wait_for_resume_ack(&mut self, timeout: Duration) -> Result<(), RiscvError> {
@@ -2,11 +2,12 @@
 
 use crate::{
     CoreInterface, CoreRegister, CoreStatus, CoreType, Error, HaltReason, InstructionSet,
-    MemoryInterface, MemoryMappedRegister,
+    MemoryInterface, MemoryMappedRegister, Watchpoint, WatchpointKind,
     architecture::riscv::sequences::RiscvDebugSequence,
     core::{
         Architecture, BreakpointCause, CoreInformation, CoreRegisters, RegisterId, RegisterValue,
     },
+    error::BreakpointError,
     memory::{CoreMemoryInterface, valid_32bit_address},
     memory_mapped_bitfield_register,
     probe::DebugProbeError,
@@ -345,10 +346,147 @@ impl<'state, X: XlenMode> RiscvCore<'state, X> {
 
     fn resume_core(&mut self) -> Result<(), Error> {
         self.state.semihosting_command = None;
+        self.clear_watchpoint_hit()?;
         self.interface.resume_core()?;
         Ok(())
     }
 
+    /// Reads the watchpoint configured in trigger `unit_index`, and whether the trigger fired.
+    ///
+    /// The core must be halted.
+    fn read_trigger_watchpoint(
+        &mut self,
+        unit_index: usize,
+    ) -> Result<Option<(Watchpoint, bool)>, Error> {
+        const TSELECT: u16 = 0x7a0;
+        const TDATA1: u16 = 0x7a1;
+        const TDATA2: u16 = 0x7a2;
+
+        self.interface.write_csr(TSELECT, unit_index as u64)?;
+        let (trigger_type, ctrl_low32) = X::unpack_tdata1(self.interface.read_csr(TDATA1)?);
+        let tdata_value = Mcontrol(ctrl_low32);
+
+        let kind = match (tdata_value.load(), tdata_value.store()) {
+            (true, true) => WatchpointKind::Access,
+            (true, false) => WatchpointKind::Read,
+            (false, true) => WatchpointKind::Write,
+            (false, false) => return Ok(None),
+        };
+
+        // The upper bits differ between mcontrol and mcontrol6, the lower ones are shared.
+        let (select, hit) = match trigger_type {
+            2 => (tdata_value.select(), tdata_value.hit()),
+            6 => {
+                let ctrl = Mcontrol6(ctrl_low32);
+                (ctrl.select(), ctrl.hit0() || ctrl.hit1())
+            }
+            _ => return Ok(None),
+        };
+
+        // Only return address triggers which enter debug mode on a data access.
+        if tdata_value.action() != 1
+            || tdata_value.execute()
+            || select
+            || !(tdata_value.m() || tdata_value.s() || tdata_value.u())
+        {
+            return Ok(None);
+        }
+
+        let tdata2 = self.interface.read_csr(TDATA2)?;
+        let watchpoint = match tdata_value.match_() {
+            // An exact address match.
+            0 => Watchpoint {
+                address: tdata2,
+                length: 1,
+                kind,
+            },
+            // A naturally aligned power-of-two range, with the size encoded in the low bits.
+            1 => {
+                let length = 1 << (tdata2.trailing_ones() + 1);
+                Watchpoint {
+                    address: tdata2 & !(length - 1),
+                    length,
+                    kind,
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some((watchpoint, hit)))
+    }
+
+    /// Returns the watchpoint that fired, after the core halted on a trigger.
+    ///
+    /// The `hit` bit of triggers is optional, so `None` is returned if the hardware does not
+    /// implement it, or if the trigger was a breakpoint.
+    fn hit_watchpoint(&mut self) -> Result<Option<Watchpoint>, Error> {
+        if let Some(unit_index) = self.state.watchpoint_hit {
+            return Ok(self
+                .read_trigger_watchpoint(unit_index)?
+                .map(|(watchpoint, _)| watchpoint));
+        }
+
+        for unit_index in 0..self.available_breakpoint_units()? as usize {
+            if let Some((watchpoint, true)) = self.read_trigger_watchpoint(unit_index)? {
+                self.state.watchpoint_hit = Some(unit_index);
+                return Ok(Some(watchpoint));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Clears the `hit` bit of the trigger that fired, so it does not show up on the next halt.
+    fn clear_watchpoint_hit(&mut self) -> Result<(), Error> {
+        const TSELECT: u16 = 0x7a0;
+        const TDATA1: u16 = 0x7a1;
+
+        let Some(unit_index) = self.state.watchpoint_hit.take() else {
+            return Ok(());
+        };
+
+        self.interface.write_csr(TSELECT, unit_index as u64)?;
+        let raw = self.interface.read_csr(TDATA1)?;
+        let (trigger_type, ctrl_low32) = X::unpack_tdata1(raw);
+        let ctrl_low32 = if trigger_type == 6 {
+            let mut ctrl = Mcontrol6(ctrl_low32);
+            ctrl.set_hit0(false);
+            ctrl.set_hit1(false);
+            ctrl.0
+        } else {
+            let mut ctrl = Mcontrol(ctrl_low32);
+            ctrl.set_hit(false);
+            ctrl.0
+        };
+        self.interface
+            .write_csr(TDATA1, X::repack_tdata1(raw, ctrl_low32))?;
+
+        Ok(())
+    }
+
+    /// Returns the length of the EBREAK (ebreak or c.ebreak) instruction at the dpc.
+    ///
+    /// Returns `None` if there is no EBREAK instruction, for example because the debugger restored
+    /// the original instruction to step over a software breakpoint.
+    fn ebreak_length_at_pc(&mut self) -> Result<Option<usize>, Error> {
+        const EBREAK: u32 = 0x0010_0073;
+        const C_EBREAK: u16 = 0x9002;
+
+        let debug_pc: u64 = self.read_core_reg(RegisterId(0x7b1))?.try_into()?;
+        let mut instruction = [0; 4];
+        self.read_8(debug_pc, &mut instruction)?;
+
+        Ok(if u32::from_le_bytes(instruction) == EBREAK {
+            Some(4)
+        } else if self.instruction_set()? == X::compressed_instruction_set()
+            && u16::from_le_bytes([instruction[0], instruction[1]]) == C_EBREAK
+        {
+            Some(2)
+        } else {
+            None
+        })
+    }
+
     /// Check if the current breakpoint is a semihosting call.
     ///
     /// The Riscv Semihosting Specification, specifies the following sequence of instructions,
@@ -506,7 +644,10 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
                     //       operations are skipped
                 }
                 // Trigger module caused halt
-                2 => HaltReason::Breakpoint(BreakpointCause::Hardware),
+                2 => match self.hit_watchpoint()? {
+                    Some(watchpoint) => HaltReason::Watchpoint(Some(watchpoint)),
+                    None => HaltReason::Breakpoint(BreakpointCause::Hardware),
+                },
                 // Debugger requested a halt
                 3 => HaltReason::Request,
                 // Core halted after single step
@@ -550,6 +691,15 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
         Ok(())
     }
 
+    fn prepare_synchronized_run(&mut self) -> Result<bool, Error> {
+        if !self.state.pc_written {
+            self.step()?;
+        }
+        self.state.semihosting_command = None;
+        self.clear_watchpoint_hit()?;
+        Ok(true)
+    }
+
     fn reset(&mut self) -> Result<(), Error> {
         self.reset_and_halt(Duration::from_secs(1))?;
         self.resume_core()?;
@@ -568,29 +718,22 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
 
     fn step(&mut self) -> Result<CoreInformation, Error> {
         let halt_reason = self.status()?;
-        if matches!(
+        let ebreak_length = if matches!(
             halt_reason,
             CoreStatus::Halted(HaltReason::Breakpoint(
                 BreakpointCause::Software | BreakpointCause::Semihosting(_)
             ))
         ) {
+            self.ebreak_length_at_pc()?
+        } else {
+            None
+        };
+
+        if let Some(ebreak_length) = ebreak_length {
             // If we are halted on a software breakpoint, we can skip the
             // single step and manually advance the dpc.
             let mut debug_pc = self.read_core_reg(RegisterId(0x7b1))?;
-            // Advance the dpc by the size of the EBREAK (ebreak or c.ebreak) instruction.
-            if self.instruction_set()? == X::compressed_instruction_set() {
-                // We may have been halted by either an EBREAK or a C.EBREAK instruction.
-                // We need to read back the instruction to determine how many bytes to skip.
-                let instruction = self.read_word_32(debug_pc.try_into().unwrap())?;
-                if instruction & 0x3 != 0x3 {
-                    // Compressed instruction.
-                    debug_pc.increment_address(2)?;
-                } else {
-                    debug_pc.increment_address(4)?;
-                }
-            } else {
-                debug_pc.increment_address(4)?;
-            }
+            debug_pc.increment_address(ebreak_length)?;
             self.write_core_reg(RegisterId(0x7b1), debug_pc)?;
             return Ok(CoreInformation {
                 pc: debug_pc.try_into()?,
@@ -677,8 +820,6 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
     }
 
     /// See docs on the [`CoreInterface::hw_breakpoints`] trait.
-    ///
-    /// NOTE: For riscv, this assumes that only execution breakpoints are used.
     fn hw_breakpoints(&mut self) -> Result<Vec<Option<u64>>, Error> {
         // This can be called w/o halting the core via Session::new -
         // temporarily halt if not halted.
@@ -710,16 +851,15 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
 
             // The trigger must be active in at least a single mode
             let trigger_any_mode_active = tdata_value.m() || tdata_value.s() || tdata_value.u();
-            let trigger_any_action_enabled =
-                tdata_value.execute() || tdata_value.store() || tdata_value.load();
 
             // Only return if the trigger is for an execution debug action in all modes.
+            // Load and store triggers are watchpoints, see `hw_watchpoints`.
             // Accept both type 2 (mcontrol) and type 6 (mcontrol6, spec 1.0).
             if (trigger_type == 2 || trigger_type == 6)
                 && tdata_value.action() == 1
                 && tdata_value.match_() == 0
                 && trigger_any_mode_active
-                && trigger_any_action_enabled
+                && tdata_value.execute()
             {
                 let breakpoint = self.interface.read_csr(TDATA2)?;
                 breakpoints.push(Some(breakpoint));
@@ -841,6 +981,123 @@ impl<X: XlenMode> CoreInterface for RiscvCore<'_, X> {
         Ok(())
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        // Watchpoints use the same triggers as breakpoints.
+        self.available_breakpoint_units()
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        // This can be called w/o halting the core via Session::new -
+        // temporarily halt if not halted.
+        let was_running = !self.core_halted()?;
+        if was_running {
+            self.halt(Duration::from_millis(100))?;
+        }
+
+        let mut watchpoints = vec![];
+        for unit_index in 0..self.available_breakpoint_units()? as usize {
+            watchpoints.push(self.read_trigger_watchpoint(unit_index)?.map(|(wp, _)| wp));
+        }
+
+        if was_running {
+            self.resume_core()?;
+        }
+
+        Ok(watchpoints)
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        let address = X::validate_bp_address(watchpoint.address)?;
+        if !watchpoint.is_naturally_aligned() {
+            return Err(Error::BreakpointOperation(
+                BreakpointError::UnsupportedWatchpoint {
+                    address,
+                    length: watchpoint.length,
+                    reason: "the length must be a power of two, and the address aligned to it",
+                },
+            ));
+        }
+
+        const TSELECT: u16 = 0x7a0;
+        const TDATA1: u16 = 0x7a1;
+        const TDATA2: u16 = 0x7a2;
+
+        tracing::info!("Setting watchpoint {} at {:#x}", unit_index, address);
+
+        self.interface.write_csr(TSELECT, unit_index as u64)?;
+
+        // Verify the trigger is a supported type for address matches:
+        // type 2 = mcontrol (spec 0.13), type 6 = mcontrol6 (spec 1.0).
+        let (trigger_type, _) = X::unpack_tdata1(self.interface.read_csr(TDATA1)?);
+        if trigger_type != 2 && trigger_type != 6 {
+            return Err(RiscvError::UnexpectedTriggerType(trigger_type).into());
+        }
+
+        let mut data_watchpoint = Mcontrol(0);
+        // Enter debug mode on trigger fire
+        data_watchpoint.set_action(1);
+        data_watchpoint.set_m(true);
+        data_watchpoint.set_u(true);
+        data_watchpoint.set_load(watchpoint.kind != WatchpointKind::Write);
+        data_watchpoint.set_store(watchpoint.kind != WatchpointKind::Read);
+
+        let tdata2 = if watchpoint.length == 1 {
+            // Exact address match
+            data_watchpoint.set_match(0);
+            address
+        } else {
+            // Match the naturally aligned power-of-two range
+            data_watchpoint.set_match(1);
+            address | ((watchpoint.length >> 1) - 1)
+        };
+
+        // Match on address, not data value. `select` is at a different bit in mcontrol6.
+        let ctrl_low32 = if trigger_type == 6 {
+            let mut ctrl = Mcontrol6(data_watchpoint.0);
+            ctrl.set_select(false);
+            ctrl.0
+        } else {
+            data_watchpoint.set_select(false);
+            data_watchpoint.0
+        };
+
+        let tdata1_val = X::build_new_exec_tdata1(trigger_type, ctrl_low32);
+
+        self.interface.write_csr(TDATA1, 0)?;
+        self.interface.write_csr(TDATA2, tdata2)?;
+        self.interface.write_csr(TDATA1, tdata1_val)?;
+
+        // Not every trigger supports every match type, the written value reads back differently then.
+        let (_, written) = X::unpack_tdata1(self.interface.read_csr(TDATA1)?);
+        if Mcontrol(written).match_() != data_watchpoint.match_()
+            || self.interface.read_csr(TDATA2)? != tdata2
+        {
+            self.interface.write_csr(TDATA1, 0)?;
+            return Err(Error::BreakpointOperation(
+                BreakpointError::UnsupportedWatchpoint {
+                    address,
+                    length: watchpoint.length,
+                    reason: "the trigger cannot match this range",
+                },
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        // Watchpoints are cleared like breakpoints, as both use the same triggers.
+        self.clear_hw_breakpoint(unit_index)
+    }
+
+    fn watchpoints_use_breakpoint_units(&self) -> bool {
+        true
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         X::registers(self.state.fp_present)
     }
@@ -947,6 +1204,9 @@ pub struct RiscvCoreState {
 
     /// Whether the MISA CSR has been read.
     misa_read: bool,
+
+    /// The trigger of the watchpoint the core halted on, if the trigger reported the hit.
+    watchpoint_hit: Option<usize>,
 }
 
 impl RiscvCoreState {
@@ -958,6 +1218,7 @@ impl RiscvCoreState {
             semihosting_command: None,
             fp_present: false,
             misa_read: false,
+            watchpoint_hit: None,
         }
     }
 }
@@ -1175,6 +1436,51 @@ memory_mapped_bitfield_register! {
     dataaddr, _: 11, 0;
 }
 
+memory_mapped_bitfield_register! {
+    /// Hart Array Window Select (see 3.14.3)
+    pub struct Hawindowsel(u32);
+    0x14, "hawindowsel",
+    impl From;
+
+    /// The high bits of the hart indices selected by `hawindow`. Each window covers 32 harts.
+    pub hawindowsel, set_hawindowsel: 14, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// Hart Array Window (see 3.14.4)
+    ///
+    /// Bit `n` selects hart `32 * hawindowsel + n` when `hasel` is set in `dmcontrol`.
+    pub struct Hawindow(u32);
+    0x15, "hawindow",
+    impl From;
+
+    /// The harts of the current window selected with `hasel`.
+    pub maskdata, set_maskdata: 31, 0;
+}
+
+memory_mapped_bitfield_register! {
+    /// Debug Module Control and Status 2 (see 3.14.15)
+    pub struct Dmcs2(u32);
+    0x32, "dmcs2",
+    impl From;
+
+    /// 0 to operate on halt groups, 1 to operate on resume groups.
+    pub grouptype, set_grouptype: 11;
+
+    /// The external trigger selected by `hgselect`.
+    pub dmexttrigger, set_dmexttrigger: 10, 7;
+
+    /// The group of the selected harts or external trigger. Group 0 does not halt or resume
+    /// other harts.
+    pub group, set_group: 6, 2;
+
+    /// Writing 1 moves the selected harts or external trigger to `group`.
+    pub _, set_hgwrite: 1;
+
+    /// 0 to operate on harts, 1 to operate on the external trigger `dmexttrigger`.
+    pub hgselect, set_hgselect: 0;
+}
+
 memory_mapped_bitfield_register! { pub struct Data0(u32); 0x04, "data0", impl From; }
 memory_mapped_bitfield_register! { pub struct Data1(u32); 0x05, "data1", impl From; }
 memory_mapped_bitfield_register! { pub struct Data2(u32); 0x06, "data2", impl From; }
@@ -128,6 +128,9 @@ pub struct XtensaCoreProperties {
     /// The number of hardware breakpoints the target supports. CPU-specific configuration value.
     pub hw_breakpoint_num: u32,
 
+    /// The number of hardware watchpoints the target supports. CPU-specific configuration value.
+    pub hw_watchpoint_num: u32,
+
     /// The interrupt level at which debug exceptions are generated. CPU-specific configuration value.
     pub debug_level: DebugLevel,
 
@@ -142,6 +145,7 @@ impl Default for XtensaCoreProperties {
     fn default() -> Self {
         Self {
             hw_breakpoint_num: 2,
+            hw_watchpoint_num: 2,
             debug_level: DebugLevel::L6,
             memory_ranges: HashMap::new(),
             window_option_properties: WindowProperties::lx(64),
@@ -307,6 +311,13 @@ impl<'probe> XtensaCommunicationInterface<'probe> {
         self.core_properties.hw_breakpoint_num
     }
 
+    /// Returns the number of hardware watchpoints the target supports.
+    ///
+    /// On the Xtensa architecture this is the `NDBREAK` configuration parameter.
+    pub fn available_watchpoint_units(&self) -> u32 {
+        self.core_properties.hw_watchpoint_num
+    }
+
     /// Returns whether the core is halted.
     pub fn core_halted(&mut self) -> Result<bool, XtensaError> {
         if !self.state.is_halted {
@@ -1188,7 +1199,7 @@ impl DebugCause {
         } else if is_breakpoint {
             HaltReason::Breakpoint(BreakpointCause::Software)
         } else if is_dbreak_exception {
-            HaltReason::Watchpoint
+            HaltReason::Watchpoint(None)
         } else if is_debug_interrupt {
             HaltReason::Request
         } else {
@@ -1233,6 +1244,22 @@ u32_register!(ProgramStatus, Register::CurrentPs);
 pub struct IBreakEn(pub u32);
 u32_register!(IBreakEn, SpecialRegister::IBreakEnable);
 
+bitfield::bitfield! {
+    /// A `DBREAKC` (Data Breakpoint Control) register.
+    #[derive(Copy, Clone)]
+    pub struct DBreakC(u32);
+    impl Debug;
+
+    /// Break on stores to the watched range.
+    pub store_break,  set_store_break : 31;
+
+    /// Break on loads from the watched range.
+    pub load_break,   set_load_break  : 30;
+
+    /// The address bits compared with `DBREAKA`, as a mask of the low 6 bits.
+    pub mask,         set_mask        : 5, 0;
+}
+
 /// The `ICOUNT` (Instruction Counter) register.
 #[derive(Copy, Clone, Debug)]
 pub struct ICount(pub u32);
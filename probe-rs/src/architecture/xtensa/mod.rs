@@ -5,21 +5,23 @@ use std::{sync::Arc, time::Duration};
 use probe_rs_target::{Architecture, CoreType, InstructionSet};
 
 use crate::{
-    CoreInformation, CoreInterface, CoreRegister, CoreStatus, Error, HaltReason, MemoryInterface,
+    BreakpointError, CoreInformation, CoreInterface, CoreRegister, CoreStatus, Error, HaltReason,
+    MemoryInterface,
     architecture::xtensa::{
         arch::{
             CpuRegister, Register, SpecialRegister,
             instruction::{Instruction, InstructionEncoding},
         },
         communication_interface::{
-            DebugCause, IBreakEn, ProgramStatus, WindowProperties, XtensaCommunicationInterface,
+            DBreakC, DebugCause, IBreakEn, ProgramStatus, WindowProperties,
+            XtensaCommunicationInterface,
         },
         registers::{FP, PC, RA, SP, XTENSA_CORE_REGISTERS},
         sequences::XtensaDebugSequence,
         xdm::PowerStatus,
     },
     core::{
-        BreakpointCause,
+        BreakpointCause, Watchpoint, WatchpointKind,
         registers::{CoreRegisters, RegisterId, RegisterValue},
     },
     memory::CoreMemoryInterface,
@@ -87,6 +89,13 @@ pub struct Xtensa<'probe> {
 impl<'probe> Xtensa<'probe> {
     const IBREAKA_REGS: [SpecialRegister; 2] =
         [SpecialRegister::IBreakA0, SpecialRegister::IBreakA1];
+    const DBREAKA_REGS: [SpecialRegister; 2] =
+        [SpecialRegister::DBreakA0, SpecialRegister::DBreakA1];
+    const DBREAKC_REGS: [SpecialRegister; 2] =
+        [SpecialRegister::DBreakC0, SpecialRegister::DBreakC1];
+
+    /// The largest range a data breakpoint can watch, limited by the 6 bit mask of `DBREAKC`.
+    const MAX_WATCHPOINT_LENGTH: u64 = 64;
 
     /// Create a new Xtensa interface for a particular core.
     pub fn new(
@@ -159,18 +168,38 @@ impl<'probe> Xtensa<'probe> {
         Ok(CoreInformation { pc: pc.try_into()? })
     }
 
+    /// Returns the length of the `BREAK` or `BREAK.N` instruction at the program counter.
+    ///
+    /// Returns 0 if there is no break instruction, for example because the debugger restored the
+    /// original instruction to step over a software breakpoint.
+    fn break_length_at_pc(&mut self) -> Result<u32, Error> {
+        let pc = self.interface.read_register_untyped(Register::CurrentPc)?;
+        let mut instruction = [0u8; 3];
+        self.read_8(pc as u64, &mut instruction)?;
+        let instruction = u32::from_le_bytes([instruction[0], instruction[1], instruction[2], 0]);
+
+        // BREAK s, t: 0000 0000 0100 s t 0000
+        // BREAK.N s:  1111 s 0010 1101
+        Ok(if instruction & 0xFF_F00F == 0x00_4000 {
+            3
+        } else if instruction & 0xF0FF == 0xF02D {
+            2
+        } else {
+            0
+        })
+    }
+
     fn skip_breakpoint(&mut self) -> Result<(), Error> {
         self.state.semihosting_command = None;
         if !self.state.pc_written {
             let debug_cause = self.debug_cause()?;
 
-            let pc_increment = if debug_cause.break_instruction() {
-                3
-            } else if debug_cause.break_n_instruction() {
-                2
-            } else {
-                0
-            };
+            let pc_increment =
+                if debug_cause.break_instruction() || debug_cause.break_n_instruction() {
+                    self.break_length_at_pc()?
+                } else {
+                    0
+                };
 
             if pc_increment > 0 {
                 // Step through the breakpoint
@@ -279,6 +308,37 @@ impl<'probe> Xtensa<'probe> {
         result
     }
 
+    /// Returns the address and control registers of data breakpoint unit `unit_index`.
+    fn dbreak_registers(unit_index: usize) -> Result<(SpecialRegister, SpecialRegister), Error> {
+        Self::DBREAKA_REGS
+            .get(unit_index)
+            .zip(Self::DBREAKC_REGS.get(unit_index))
+            .map(|(&address, &control)| (address, control))
+            .ok_or_else(|| Error::Other(format!("Watchpoint unit {unit_index} does not exist")))
+    }
+
+    /// Reads the watchpoint configured in data breakpoint unit `unit_index`. The core must be halted.
+    fn read_watchpoint(&mut self, unit_index: usize) -> Result<Option<Watchpoint>, Error> {
+        let Ok((address, control)) = Self::dbreak_registers(unit_index) else {
+            return Ok(None);
+        };
+        let control = DBreakC(self.interface.read_register_untyped(control)?);
+
+        let kind = match (control.load_break(), control.store_break()) {
+            (false, false) => return Ok(None),
+            (true, false) => WatchpointKind::Read,
+            (false, true) => WatchpointKind::Write,
+            (true, true) => WatchpointKind::Access,
+        };
+        let address = self.interface.read_register_untyped(address)?;
+
+        Ok(Some(Watchpoint {
+            address: address as u64,
+            length: 1 << control.mask().trailing_zeros().min(6),
+            kind,
+        }))
+    }
+
     fn current_ps(&mut self) -> Result<ProgramStatus, Error> {
         // Reading ProgramStatus using `read_register` would return the value
         // after the debug interrupt has been taken.
@@ -363,7 +423,10 @@ impl CoreInterface for Xtensa<'_> {
             let debug_cause = self.debug_cause()?;
 
             let mut reason = debug_cause.halt_reason();
-            if reason == HaltReason::Breakpoint(BreakpointCause::Software) {
+            if reason == HaltReason::Watchpoint(None) {
+                let watchpoint = self.read_watchpoint(debug_cause.dbreak_num() as usize)?;
+                reason = HaltReason::Watchpoint(watchpoint);
+            } else if reason == HaltReason::Breakpoint(BreakpointCause::Software) {
                 // The chip initiated this halt, therefore we need to update pc_written state
                 self.state.pc_written = false;
                 // Check if the breakpoint is a semihosting call
@@ -518,6 +581,64 @@ impl CoreInterface for Xtensa<'_> {
         })
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        // Only the units with known registers can be used.
+        Ok(self
+            .interface
+            .available_watchpoint_units()
+            .min(Self::DBREAKC_REGS.len() as u32))
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        self.halted_access(|this| {
+            (0..this.available_watchpoint_units()? as usize)
+                .map(|unit_index| this.read_watchpoint(unit_index))
+                .collect()
+        })
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        if !watchpoint.is_naturally_aligned() || watchpoint.length > Self::MAX_WATCHPOINT_LENGTH {
+            return Err(Error::BreakpointOperation(
+                BreakpointError::UnsupportedWatchpoint {
+                    address: watchpoint.address,
+                    length: watchpoint.length,
+                    reason: "the length must be a power of two up to 64, and the address aligned to it",
+                },
+            ));
+        }
+
+        let mut control = DBreakC(0);
+        control.set_mask(!(watchpoint.length as u32 - 1));
+        control.set_load_break(watchpoint.kind != WatchpointKind::Write);
+        control.set_store_break(watchpoint.kind != WatchpointKind::Read);
+
+        let (address_reg, control_reg) = Self::dbreak_registers(unit_index)?;
+        self.halted_access(|this| {
+            // Disable the unit while it is reconfigured.
+            this.interface.write_register_untyped(control_reg, 0)?;
+            this.interface
+                .write_register_untyped(address_reg, watchpoint.address as u32)?;
+            this.interface
+                .write_register_untyped(control_reg, control.0)?;
+
+            Ok(())
+        })
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        let (_, control_reg) = Self::dbreak_registers(unit_index)?;
+        self.halted_access(|this| {
+            this.interface.write_register_untyped(control_reg, 0)?;
+
+            Ok(())
+        })
+    }
+
     fn registers(&self) -> &'static CoreRegisters {
         &XTENSA_CORE_REGISTERS
     }
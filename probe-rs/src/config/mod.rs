@@ -27,9 +27,10 @@ pub(crate) mod registry;
 mod target;
 
 pub use probe_rs_target::{
-    Chip, ChipFamily, Core, CoreType, Endian, FlashProperties, GenericRegion, InstructionSet,
-    MemoryAccess, MemoryRange, MemoryRegion, NvmRegion, PageInfo, RamRegion, RawFlashAlgorithm,
-    ScanChainElement, SectorDescription, SectorInfo, TargetDescriptionSource,
+    Chip, ChipFamily, ConfigField, ConfigFieldValue, ConfigRegion, ConfigRegionKind, Core,
+    CoreType, Endian, FlashProperties, GenericRegion, InstructionSet, MemoryAccess, MemoryRange,
+    MemoryRegion, NvmRegion, PageInfo, RamRegion, RawFlashAlgorithm, ScanChainElement,
+    SectorDescription, SectorInfo, TargetDescriptionSource,
 };
 
 pub use registry::{Registry, RegistryError};
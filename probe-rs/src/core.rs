@@ -21,11 +21,15 @@ pub mod core_status;
 pub mod dump;
 pub mod memory_mapped_registers;
 pub mod registers;
+pub mod sw_breakpoints;
+pub mod watchpoints;
 
 pub use core_state::*;
 pub use core_status::*;
 pub use memory_mapped_registers::MemoryMappedRegister;
 pub use registers::*;
+pub use sw_breakpoints::*;
+pub use watchpoints::*;
 
 /// An struct for storing the current state of a core.
 #[derive(Debug, Clone)]
@@ -54,6 +58,15 @@ pub trait CoreInterface: MemoryInterface {
     /// Continue to execute instructions.
     fn run(&mut self) -> Result<(), Error>;
 
+    /// Prepares the core to be resumed by a cross trigger, together with other cores.
+    ///
+    /// This does everything [`CoreInterface::run`] does, except letting the core go. Returns
+    /// `false` if the core cannot be resumed by a cross trigger, in which case it has to be
+    /// resumed with [`CoreInterface::run`].
+    fn prepare_synchronized_run(&mut self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
     /// Reset the core, and then continue to execute instructions. If the core
     /// should be halted after reset, use the [`reset_and_halt`] function.
     ///
@@ -110,6 +123,36 @@ pub trait CoreInterface: MemoryInterface {
     /// Returns `true` if hardware breakpoints are enabled, `false` otherwise.
     fn hw_breakpoints_enabled(&self) -> bool;
 
+    /// Returns the number of hardware watchpoint units of the core.
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        Ok(0)
+    }
+
+    /// Reads the hardware watchpoints, with one entry for each watchpoint unit.
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        Ok(vec![])
+    }
+
+    /// Configures the watchpoint unit `unit_index` to halt on `watchpoint`.
+    fn set_hw_watchpoint(
+        &mut self,
+        _unit_index: usize,
+        _watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        Err(Error::NotImplemented("watchpoints"))
+    }
+
+    /// Clears the watchpoint configured in unit `unit_index`.
+    fn clear_hw_watchpoint(&mut self, _unit_index: usize) -> Result<(), Error> {
+        Err(Error::NotImplemented("watchpoints"))
+    }
+
+    /// Returns `true` if watchpoints are set in the same units as breakpoints, like the
+    /// RISC-V triggers, so a unit used by one is not free for the other.
+    fn watchpoints_use_breakpoint_units(&self) -> bool {
+        false
+    }
+
     /// Get the `Architecture` of the Core.
     fn architecture(&self) -> Architecture;
 
@@ -186,26 +229,53 @@ pub struct Core<'probe> {
     id: usize,
     name: &'probe str,
     target: &'probe Target,
+    sw_breakpoints: &'probe mut SoftwareBreakpoints,
+
+    inner: CoreInner<'probe>,
+}
 
-    inner: Box<dyn CoreInterface + 'probe>,
+/// The architecture specific implementation of a [`Core`], borrowed when the core is reborrowed.
+enum CoreInner<'probe> {
+    Owned(Box<dyn CoreInterface + 'probe>),
+    Borrowed(&'probe mut (dyn CoreInterface + 'probe)),
+}
+
+impl<'probe> std::ops::Deref for CoreInner<'probe> {
+    type Target = dyn CoreInterface + 'probe;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            CoreInner::Owned(core) => core.as_ref(),
+            CoreInner::Borrowed(core) => &**core,
+        }
+    }
+}
+
+impl std::ops::DerefMut for CoreInner<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            CoreInner::Owned(core) => core.as_mut(),
+            CoreInner::Borrowed(core) => &mut **core,
+        }
+    }
 }
 
 impl CoreMemoryInterface for Core<'_> {
     type ErrorType = Error;
 
     fn memory(&self) -> &dyn MemoryInterface<Self::ErrorType> {
-        self.inner.as_ref()
+        &*self.inner
     }
 
     fn memory_mut(&mut self) -> &mut dyn MemoryInterface<Self::ErrorType> {
-        self.inner.as_mut()
+        &mut *self.inner
     }
 }
 
 impl<'probe> Core<'probe> {
-    /// Borrow the boxed CoreInterface mutable.
-    pub fn inner_mut(&mut self) -> &mut Box<dyn CoreInterface + 'probe> {
-        &mut self.inner
+    /// Borrow the CoreInterface mutable.
+    pub fn inner_mut(&mut self) -> &mut (dyn CoreInterface + 'probe) {
+        &mut *self.inner
     }
 
     /// Create a new [`Core`].
@@ -213,13 +283,27 @@ impl<'probe> Core<'probe> {
         id: usize,
         name: &'probe str,
         target: &'probe Target,
+        sw_breakpoints: &'probe mut SoftwareBreakpoints,
         core: impl CoreInterface + 'probe,
     ) -> Core<'probe> {
         Self {
             id,
             name,
             target,
-            inner: Box::new(core),
+            sw_breakpoints,
+            inner: CoreInner::Owned(Box::new(core)),
+        }
+    }
+
+    /// Borrows this core as a new [`Core`], which can be handed over by value, e.g. to a flash
+    /// algorithm.
+    pub(crate) fn reborrow(&mut self) -> Core<'_> {
+        Core {
+            id: self.id,
+            name: self.name,
+            target: self.target,
+            sw_breakpoints: &mut *self.sw_breakpoints,
+            inner: CoreInner::Borrowed(&mut *self.inner),
         }
     }
 
@@ -276,11 +360,30 @@ impl<'probe> Core<'probe> {
     }
 
     /// Continue to execute instructions.
+    ///
+    /// If the core is halted on a software breakpoint, the replaced instruction is executed first.
+    /// Changes to software breakpoints in flash are programmed before the core runs.
     #[tracing::instrument(skip(self))]
     pub fn run(&mut self) -> Result<(), Error> {
+        self.prepare_run()?;
         self.inner.run()
     }
 
+    /// Prepares the core to be resumed together with other cores.
+    ///
+    /// See [`CoreInterface::prepare_synchronized_run`].
+    pub(crate) fn prepare_synchronized_run(&mut self) -> Result<bool, Error> {
+        self.prepare_run()?;
+        self.inner.prepare_synchronized_run()
+    }
+
+    /// Steps over the software breakpoint the core is halted on, if any, and programs the
+    /// changes to software breakpoints in flash.
+    fn prepare_run(&mut self) -> Result<(), Error> {
+        self.step_over_sw_breakpoint()?;
+        self.update_flash_breakpoints(None)
+    }
+
     /// Reset the core, and then continue to execute instructions. If the core
     /// should be halted after reset, use the [`reset_and_halt`] function.
     ///
@@ -300,9 +403,14 @@ impl<'probe> Core<'probe> {
     }
 
     /// Steps one instruction and then enters halted state again.
+    ///
+    /// If the core is halted on a software breakpoint, the replaced instruction is executed.
     #[tracing::instrument(skip(self))]
     pub fn step(&mut self) -> Result<CoreInformation, Error> {
-        self.inner.step()
+        match self.step_over_sw_breakpoint()? {
+            Some(core_information) => Ok(core_information),
+            None => self.inner.step(),
+        }
     }
 
     /// Returns the current status of the core.
@@ -408,12 +516,21 @@ impl<'probe> Core<'probe> {
 
         // If there is a breakpoint set already, return its bp_unit_index, else find the next free index.
         let breakpoints = self.inner.hw_breakpoints()?;
+        // Units holding a watchpoint are not free either, if both are set in the same units.
+        let watchpoints = if self.inner.watchpoints_use_breakpoint_units() {
+            self.inner.hw_watchpoints()?
+        } else {
+            vec![]
+        };
         let breakpoint_comparator_index =
             match breakpoints.iter().position(|&bp| bp == Some(address)) {
                 Some(breakpoint_comparator_index) => breakpoint_comparator_index,
                 None => breakpoints
                     .iter()
-                    .position(|bp| bp.is_none())
+                    .enumerate()
+                    .position(|(unit, bp)| {
+                        bp.is_none() && watchpoints.get(unit).is_none_or(|wp| wp.is_none())
+                    })
                     .ok_or_else(|| Error::Other("No available hardware breakpoints".to_string()))?,
             };
 
@@ -490,6 +607,259 @@ impl<'probe> Core<'probe> {
         Ok(())
     }
 
+    /// Set a software breakpoint
+    ///
+    /// This function will replace the instruction at `address` with a break instruction.
+    /// Only code in RAM can be patched by the core. Use
+    /// [`Session::set_sw_breakpoint`](crate::Session::set_sw_breakpoint) for code in flash.
+    #[tracing::instrument(skip(self))]
+    pub fn set_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        if self.sw_breakpoints.get(address).is_some() {
+            return Ok(());
+        }
+        if self.is_in_flash(address) {
+            return Err(Error::BreakpointOperation(BreakpointError::InFlash(
+                address,
+            )));
+        }
+
+        let mut breakpoint =
+            self.prepare_sw_breakpoint(address, SoftwareBreakpointLocation::Ram)?;
+        breakpoint.programmed = true;
+
+        tracing::debug!("Writing break instruction to {:#010x}", address);
+        self.inner.write_8(address, breakpoint.instruction)?;
+
+        let mut read_back = vec![0; breakpoint.instruction.len()];
+        self.inner.read_8(address, &mut read_back)?;
+        if read_back != breakpoint.instruction {
+            self.inner.write_8(address, &breakpoint.original)?;
+            return Err(Error::BreakpointOperation(BreakpointError::NotWritable(
+                address,
+            )));
+        }
+
+        self.sw_breakpoints.insert(breakpoint);
+        Ok(())
+    }
+
+    /// Clear a software breakpoint
+    ///
+    /// This function will restore the instruction replaced by the software breakpoint at `address`.
+    /// Use [`Session::clear_sw_breakpoint`](crate::Session::clear_sw_breakpoint) for breakpoints in flash.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_sw_breakpoint(&mut self, address: u64) -> Result<(), Error> {
+        let breakpoint = self
+            .sw_breakpoints
+            .get(address)
+            .ok_or(Error::BreakpointOperation(BreakpointError::NotFound(
+                address,
+            )))?;
+        if breakpoint.location == SoftwareBreakpointLocation::Flash {
+            return Err(Error::BreakpointOperation(BreakpointError::InFlash(
+                address,
+            )));
+        }
+
+        let original = breakpoint.original.clone();
+        self.inner.write_8(address, &original)?;
+        self.sw_breakpoints.remove(address);
+        Ok(())
+    }
+
+    /// Returns the software breakpoints set on the core.
+    pub fn sw_breakpoints(&self) -> &SoftwareBreakpoints {
+        self.sw_breakpoints
+    }
+
+    pub(crate) fn sw_breakpoints_mut(&mut self) -> &mut SoftwareBreakpoints {
+        self.sw_breakpoints
+    }
+
+    /// Returns `true` if `address` is in a flash region of the core.
+    pub(crate) fn is_in_flash(&self, address: u64) -> bool {
+        self.memory_regions()
+            .any(|region| region.is_nvm() && region.contains(address))
+    }
+
+    /// Reads the instruction at `address` and creates the software breakpoint replacing it,
+    /// without writing the break instruction.
+    pub(crate) fn prepare_sw_breakpoint(
+        &mut self,
+        address: u64,
+        location: SoftwareBreakpointLocation,
+    ) -> Result<SoftwareBreakpoint, Error> {
+        let instruction_set = self.inner.instruction_set()?;
+
+        let mut first_byte = [0];
+        self.inner.read_8(address, &mut first_byte)?;
+        let instruction =
+            break_instruction(instruction_set, first_byte[0]).ok_or(Error::BreakpointOperation(
+                BreakpointError::SoftwareBreakpointsUnsupported(instruction_set),
+            ))?;
+
+        let mut original = vec![0; instruction.len()];
+        self.inner.read_8(address, &mut original)?;
+
+        Ok(SoftwareBreakpoint {
+            address,
+            location,
+            original,
+            instruction,
+            programmed: false,
+        })
+    }
+
+    /// Returns the address of the break instruction the core is halted on, if any.
+    ///
+    /// This includes cleared breakpoints in flash, whose break instruction may still be
+    /// programmed.
+    fn sw_breakpoint_at_pc(&mut self) -> Result<Option<u64>, Error> {
+        if self.sw_breakpoints.is_empty() && !self.sw_breakpoints.has_pending_flash_changes() {
+            return Ok(None);
+        }
+        if !self.inner.core_halted()? {
+            return Ok(None);
+        }
+
+        let pc: u64 = self.read_core_reg(self.program_counter())?;
+        Ok(self.sw_breakpoints.has_break_instruction(pc).then_some(pc))
+    }
+
+    /// Executes the instruction replaced by the break instruction the core is halted on, if any.
+    ///
+    /// In RAM, the original instruction is restored for the duration of a single step. In flash,
+    /// the original instruction is programmed together with the other pending changes, and the
+    /// break instruction is only programmed again the next time the core runs.
+    fn step_over_sw_breakpoint(&mut self) -> Result<Option<CoreInformation>, Error> {
+        let Some(address) = self.sw_breakpoint_at_pc()? else {
+            return Ok(None);
+        };
+
+        tracing::debug!("Stepping over software breakpoint at {:#010x}", address);
+        match self.sw_breakpoints.get(address).cloned() {
+            Some(breakpoint) if breakpoint.location == SoftwareBreakpointLocation::Ram => {
+                self.inner.write_8(address, &breakpoint.original)?;
+                let step_result = self.inner.step();
+                self.inner.write_8(address, breakpoint.instruction)?;
+
+                step_result.map(Some)
+            }
+            _ => {
+                self.update_flash_breakpoints(Some(address))?;
+                self.inner.step().map(Some)
+            }
+        }
+    }
+
+    /// Programs the pending changes to the software breakpoints in flash, leaving out the
+    /// breakpoint at `suspended`.
+    ///
+    /// Each affected flash sector is reprogrammed once, using the flash algorithm on this core.
+    fn update_flash_breakpoints(&mut self, suspended: Option<u64>) -> Result<(), Error> {
+        let patches = self
+            .sw_breakpoints
+            .flash_patches(suspended)
+            .into_iter()
+            .map(|(address, data)| (address, data.to_vec()))
+            .collect::<Vec<_>>();
+        if patches.is_empty() {
+            return Ok(());
+        }
+
+        tracing::debug!("Updating {} software breakpoints in flash", patches.len());
+        self.patch_flash(&patches)?;
+        self.sw_breakpoints.flash_patched(suspended);
+
+        Ok(())
+    }
+
+    /// Writes the patches, given as address and data, to the flash, reprogramming each affected
+    /// sector once.
+    pub(crate) fn patch_flash(&mut self, patches: &[(u64, Vec<u8>)]) -> Result<(), Error> {
+        crate::flashing::patch_flash(self, patches).map_err(|error| {
+            Error::BreakpointOperation(BreakpointError::FlashPatch(Box::new(error)))
+        })
+    }
+
+    /// Returns the number of hardware watchpoint units of the core.
+    pub fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        self.inner.available_watchpoint_units()
+    }
+
+    /// Set a hardware watchpoint
+    ///
+    /// This function will reuse the unit watching the same address, or take the next free unit.
+    /// The amount of hardware watchpoints which are supported is chip specific,
+    /// and can be queried using the `available_watchpoint_units` function.
+    #[tracing::instrument(skip(self))]
+    pub fn set_hw_watchpoint(&mut self, watchpoint: Watchpoint) -> Result<(), Error> {
+        let watchpoints = self.inner.hw_watchpoints()?;
+        // Units holding a breakpoint are not free either, if both are set in the same units.
+        let breakpoints = if self.inner.watchpoints_use_breakpoint_units() {
+            self.inner.hw_breakpoints()?
+        } else {
+            vec![]
+        };
+        let unit_index = match watchpoints
+            .iter()
+            .position(|wp| wp.is_some_and(|wp| wp.address == watchpoint.address))
+        {
+            Some(unit_index) => unit_index,
+            None => watchpoints
+                .iter()
+                .enumerate()
+                .position(|(unit, wp)| {
+                    wp.is_none() && breakpoints.get(unit).is_none_or(|bp| bp.is_none())
+                })
+                .ok_or(Error::BreakpointOperation(
+                    BreakpointError::NoWatchpointUnits(watchpoints.len()),
+                ))?,
+        };
+
+        tracing::debug!(
+            "Trying to set HW watchpoint #{} at address {:#010x}",
+            unit_index,
+            watchpoint.address
+        );
+
+        self.inner.set_hw_watchpoint(unit_index, watchpoint)
+    }
+
+    /// Clear a hardware watchpoint
+    ///
+    /// This function will clear the hardware watchpoint at `address`, if there is one.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_hw_watchpoint(&mut self, address: u64) -> Result<(), Error> {
+        let unit_index = self
+            .inner
+            .hw_watchpoints()?
+            .iter()
+            .position(|wp| wp.is_some_and(|wp| wp.address == address))
+            .ok_or(Error::BreakpointOperation(
+                BreakpointError::WatchpointNotFound(address),
+            ))?;
+
+        self.inner.clear_hw_watchpoint(unit_index)
+    }
+
+    /// Clear all hardware watchpoints
+    ///
+    /// This function will clear all HW watchpoints which are configured on the target,
+    /// regardless if they are set by probe-rs.
+    #[tracing::instrument(skip(self))]
+    pub fn clear_all_hw_watchpoints(&mut self) -> Result<(), Error> {
+        let watchpoints = self.inner.hw_watchpoints()?;
+        for (unit_index, _) in watchpoints
+            .iter()
+            .enumerate()
+            .filter(|(_, wp)| wp.is_some())
+        {
+            self.inner.clear_hw_watchpoint(unit_index)?;
+        }
+        Ok(())
+    }
+
     /// Returns the architecture of the core.
     pub fn architecture(&self) -> Architecture {
         self.inner.architecture()
@@ -574,6 +944,10 @@ impl CoreInterface for Core<'_> {
         self.run()
     }
 
+    fn prepare_synchronized_run(&mut self) -> Result<bool, Error> {
+        self.prepare_synchronized_run()
+    }
+
     fn reset(&mut self) -> Result<(), Error> {
         self.reset()
     }
@@ -639,6 +1013,30 @@ impl CoreInterface for Core<'_> {
         self.inner.hw_breakpoints_enabled()
     }
 
+    fn available_watchpoint_units(&mut self) -> Result<u32, Error> {
+        self.available_watchpoint_units()
+    }
+
+    fn hw_watchpoints(&mut self) -> Result<Vec<Option<Watchpoint>>, Error> {
+        self.inner.hw_watchpoints()
+    }
+
+    fn set_hw_watchpoint(
+        &mut self,
+        unit_index: usize,
+        watchpoint: Watchpoint,
+    ) -> Result<(), Error> {
+        self.inner.set_hw_watchpoint(unit_index, watchpoint)
+    }
+
+    fn clear_hw_watchpoint(&mut self, unit_index: usize) -> Result<(), Error> {
+        self.inner.clear_hw_watchpoint(unit_index)
+    }
+
+    fn watchpoints_use_breakpoint_units(&self) -> bool {
+        self.inner.watchpoints_use_breakpoint_units()
+    }
+
     fn architecture(&self) -> Architecture {
         self.architecture()
     }
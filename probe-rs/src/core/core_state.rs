@@ -11,7 +11,7 @@ use crate::{
     },
 };
 
-use super::ResolvedCoreOptions;
+use super::{ResolvedCoreOptions, SoftwareBreakpoints};
 
 #[derive(Debug)]
 pub(crate) struct CombinedCoreState {
@@ -67,12 +67,14 @@ impl CombinedCoreState {
                 self.id,
                 name,
                 target,
+                &mut self.core_state.sw_breakpoints,
                 crate::architecture::arm::armv6m::Armv6m::new(memory, s, debug_sequence)?,
             ),
             SpecificCoreState::Armv7a(s) => Core::new(
                 self.id,
                 name,
                 target,
+                &mut self.core_state.sw_breakpoints,
                 crate::architecture::arm::armv7ar::Armv7ar::new(
                     memory,
                     s,
@@ -85,6 +87,7 @@ impl CombinedCoreState {
                 self.id,
                 name,
                 target,
+                &mut self.core_state.sw_breakpoints,
                 crate::architecture::arm::armv7ar::Armv7ar::new(
                     memory,
                     s,
@@ -97,12 +100,14 @@ impl CombinedCoreState {
                 self.id,
                 name,
                 target,
+                &mut self.core_state.sw_breakpoints,
                 crate::architecture::arm::armv7m::Armv7m::new(memory, s, debug_sequence)?,
             ),
             SpecificCoreState::Armv8a(s) => Core::new(
                 self.id,
                 name,
                 target,
+                &mut self.core_state.sw_breakpoints,
                 crate::architecture::arm::armv8a::Armv8a::new(
                     memory,
                     s,
@@ -115,6 +120,7 @@ impl CombinedCoreState {
                 self.id,
                 name,
                 target,
+                &mut self.core_state.sw_breakpoints,
                 crate::architecture::arm::armv8m::Armv8m::new(memory, s, debug_sequence)?,
             ),
             _ => {
@@ -198,12 +204,14 @@ impl CombinedCoreState {
                 self.id,
                 name,
                 target,
+                &mut self.core_state.sw_breakpoints,
                 crate::architecture::riscv::Riscv32::new(interface, s, debug_sequence)?,
             )),
             SpecificCoreState::Riscv64(s) => Ok(Core::new(
                 self.id,
                 name,
                 target,
+                &mut self.core_state.sw_breakpoints,
                 Riscv64::new(interface, s, debug_sequence)?,
             )),
             _ => unreachable!(
@@ -240,6 +248,7 @@ impl CombinedCoreState {
             self.id,
             name,
             target,
+            &mut self.core_state.sw_breakpoints,
             crate::architecture::xtensa::Xtensa::new(interface, s, debug_sequence)?,
         ))
     }
@@ -259,6 +268,9 @@ impl CombinedCoreState {
 pub struct CoreState {
     /// Information needed to access the core
     core_access_options: ResolvedCoreOptions,
+
+    /// The software breakpoints set on the core
+    sw_breakpoints: SoftwareBreakpoints,
 }
 
 impl CoreState {
@@ -266,13 +278,39 @@ impl CoreState {
     pub fn new(core_access_options: ResolvedCoreOptions) -> Self {
         Self {
             core_access_options,
+            sw_breakpoints: SoftwareBreakpoints::default(),
         }
     }
 
+    pub(crate) fn sw_breakpoints(&self) -> &SoftwareBreakpoints {
+        &self.sw_breakpoints
+    }
+
+    pub(crate) fn sw_breakpoints_mut(&mut self) -> &mut SoftwareBreakpoints {
+        &mut self.sw_breakpoints
+    }
+
     pub(crate) fn is_arm(&self) -> bool {
         matches!(&self.core_access_options, ResolvedCoreOptions::Arm { .. })
     }
 
+    /// The base address of the cross trigger interface of an Arm core, if the target
+    /// description specifies it.
+    pub(crate) fn cti_base(&self) -> Option<u64> {
+        match &self.core_access_options {
+            ResolvedCoreOptions::Arm { options, .. } => options.cti_base,
+            _ => None,
+        }
+    }
+
+    /// The hart ID of a RISC-V core.
+    pub(crate) fn riscv_hart_id(&self) -> Option<u32> {
+        match &self.core_access_options {
+            ResolvedCoreOptions::Riscv { options, .. } => Some(options.hart_id.unwrap_or_default()),
+            _ => None,
+        }
+    }
+
     pub(crate) fn memory_ap(&self) -> FullyQualifiedApAddress {
         let ResolvedCoreOptions::Arm { options, .. } = &self.core_access_options else {
             unreachable!(
@@ -1,3 +1,4 @@
+use crate::core::Watchpoint;
 use crate::semihosting::SemihostingCommand;
 
 /// The status of the core.
@@ -58,8 +59,9 @@ pub enum HaltReason {
     /// Core halted due to an exception, e.g. an
     /// an interrupt.
     Exception,
-    /// Core halted due to a data watchpoint
-    Watchpoint,
+    /// Core halted due to a data watchpoint. Contains the watchpoint that was hit, if the core can
+    /// tell which one.
+    Watchpoint(Option<Watchpoint>),
     /// Core halted after single step
     Step,
     /// Core halted because of a debugger request
@@ -1,9 +1,10 @@
+use crate::architecture::arm::core::registers::cortex_m::XPSR;
 use crate::architecture::riscv::registers::RISCV_CORE_REGISTERS;
 use crate::architecture::xtensa::arch::{Register as XtensaRegister, SpecialRegister};
 use crate::architecture::xtensa::registers::XTENSA_CORE_REGISTERS;
 use crate::{Core, CoreRegisters, CoreType, Error, InstructionSet, MemoryInterface};
 use crate::{RegisterId, RegisterValue};
-use object::elf::PT_NOTE;
+use object::elf::{EM_ARM, EM_RISCV, EM_XTENSA, NT_ARM_VFP, NT_FPREGSET, NT_PRSTATUS, PT_NOTE};
 use object::read::elf::ProgramHeader;
 use object::{Object, ObjectSegment};
 use probe_rs_target::MemoryRange;
@@ -14,10 +15,33 @@ use std::sync::LazyLock;
 use std::{
     collections::HashMap,
     fs::OpenOptions,
+    io::Write,
     ops::Range,
     path::{Path, PathBuf},
 };
 
+mod elf;
+mod format;
+mod session;
+
+pub use format::DumpCompression;
+pub use session::{SessionCoreDump, SessionDump};
+
+/// The granularity in bytes at which delta dumps compare memory.
+const DELTA_CHUNK_SIZE: usize = 256;
+
+/// Describes the note that holds the floating point registers in an ELF core file.
+struct FpNote {
+    /// The name of the note.
+    name: &'static [u8],
+    /// The type of the note.
+    note_type: u32,
+    /// The size of the floating point register area in bytes, if it is fixed.
+    ///
+    /// The floating point status register directly follows the register area.
+    register_area_size: Option<usize>,
+}
+
 trait Processor {
     /// Returns the instruction set of the processor.
     fn instruction_set(&self) -> InstructionSet;
@@ -58,6 +82,66 @@ trait Processor {
         let value = u32::from_le_bytes(note_data[idx * 4..][..4].try_into().unwrap());
         Ok(RegisterValue::U32(value))
     }
+
+    /// Returns the `e_machine` value of the processor.
+    fn elf_machine(&self) -> u16;
+
+    /// Returns the layout of the floating point note, if the processor has one.
+    fn fp_note(&self) -> Option<FpNote> {
+        None
+    }
+
+    /// Serializes the registers into .elf note data. This is the inverse of `read_registers`.
+    ///
+    /// Registers that are not part of the map, or not present in `registers`, are written as 0.
+    fn write_registers(&self, registers: &HashMap<RegisterId, RegisterValue>) -> Vec<u8> {
+        let mut note_data = vec![0; self.register_data_len()];
+        for (idx, reg_id) in self.register_map().iter().copied() {
+            let value = registers
+                .get(&reg_id)
+                .and_then(|value| TryInto::<u32>::try_into(*value).ok())
+                .unwrap_or_default();
+            note_data[idx * 4..][..4].copy_from_slice(&value.to_le_bytes());
+        }
+        note_data
+    }
+}
+
+struct ArmProcessor {
+    core_type: CoreType,
+}
+impl Processor for ArmProcessor {
+    fn instruction_set(&self) -> InstructionSet {
+        InstructionSet::Thumb2
+    }
+    fn core_type(&self) -> CoreType {
+        self.core_type
+    }
+    fn register_data_len(&self) -> usize {
+        // R0-R15, CPSR and ORIG_R0
+        18 * 4
+    }
+    fn register_map(&self) -> &[(usize, RegisterId)] {
+        static REGS: LazyLock<[(usize, RegisterId); 17]> = LazyLock::new(|| {
+            array::from_fn(|idx| match idx {
+                0..16 => (idx, RegisterId(idx as u16)),
+                // Cortex-M cores store the xPSR where A-profile cores store the CPSR.
+                _ => (idx, XPSR.id()),
+            })
+        });
+        &*REGS
+    }
+    fn elf_machine(&self) -> u16 {
+        EM_ARM.0
+    }
+    fn fp_note(&self) -> Option<FpNote> {
+        Some(FpNote {
+            name: b"LINUX",
+            note_type: NT_ARM_VFP.0,
+            // D0-D31
+            register_area_size: Some(32 * 8),
+        })
+    }
 }
 
 struct XtensaProcessor;
@@ -100,6 +184,9 @@ impl Processor for XtensaProcessor {
 
         &*REGS
     }
+    fn elf_machine(&self) -> u16 {
+        EM_XTENSA.0
+    }
 }
 
 struct RiscvProcessor;
@@ -129,6 +216,16 @@ impl Processor for RiscvProcessor {
         });
         &*REGS
     }
+    fn elf_machine(&self) -> u16 {
+        EM_RISCV.0
+    }
+    fn fp_note(&self) -> Option<FpNote> {
+        Some(FpNote {
+            name: b"CORE",
+            note_type: NT_FPREGSET.0,
+            register_area_size: None,
+        })
+    }
 }
 
 /// A snapshot representation of a core state.
@@ -185,33 +282,233 @@ impl CoreDump {
 
     /// Store the dumped core to a file.
     pub fn store(&self, path: &Path) -> Result<(), CoreDumpError> {
-        let mut file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(true)
-            .open(path)
-            .map_err(|e| {
-                CoreDumpError::CoreDumpFileWrite(e, dunce::canonicalize(path).unwrap_or_default())
-            })?;
-        rmp_serde::encode::write_named(&mut file, self).map_err(CoreDumpError::EncodingCoreDump)?;
-        Ok(())
+        self.store_compressed(path, DumpCompression::None)
+    }
+
+    /// Store the dumped core to a file, compressing the registers and memory ranges.
+    pub fn store_compressed(
+        &self,
+        path: &Path,
+        compression: DumpCompression,
+    ) -> Result<(), CoreDumpError> {
+        write_dump_file(path, &self.to_bytes(compression, None)?)
+    }
+
+    /// Store only the memory that differs from `reference` to a file.
+    ///
+    /// The file can only be loaded again with [`CoreDump::load_delta`], using the same
+    /// reference dump.
+    pub fn store_delta(
+        &self,
+        path: &Path,
+        reference: &CoreDump,
+        compression: DumpCompression,
+    ) -> Result<(), CoreDumpError> {
+        write_dump_file(path, &self.to_bytes(compression, Some(reference))?)
+    }
+
+    /// Serialize the dumped core into the format written by [`CoreDump::store`].
+    ///
+    /// If a `reference` is given, only the memory that differs from it is stored.
+    pub fn to_bytes(
+        &self,
+        compression: DumpCompression,
+        reference: Option<&CoreDump>,
+    ) -> Result<Vec<u8>, CoreDumpError> {
+        let (payload, flags) = match reference {
+            Some(reference) => (
+                rmp_serde::encode::to_vec_named(&self.delta_from(reference)),
+                format::FLAG_DELTA,
+            ),
+            None => (rmp_serde::encode::to_vec_named(self), 0),
+        };
+        let payload = payload.map_err(CoreDumpError::EncodingCoreDump)?;
+
+        format::encode(format::ContentKind::Core, compression, flags, payload)
+    }
+
+    /// Returns the delta of this dump to `reference`.
+    fn delta_from(&self, reference: &CoreDump) -> CoreDumpDelta {
+        let mut data: Vec<(Range<u64>, Vec<u8>)> = Vec::new();
+
+        for (range, memory) in &self.data {
+            let Some((_, reference_memory)) = reference.data.iter().find(|(r, _)| r == range)
+            else {
+                data.push((range.clone(), memory.clone()));
+                continue;
+            };
+
+            let region_start = data.len();
+            for (index, (chunk, reference_chunk)) in memory
+                .chunks(DELTA_CHUNK_SIZE)
+                .zip(reference_memory.chunks(DELTA_CHUNK_SIZE))
+                .enumerate()
+            {
+                if chunk == reference_chunk {
+                    continue;
+                }
+
+                let start = range.start + (index * DELTA_CHUNK_SIZE) as u64;
+                match data[region_start..].last_mut() {
+                    // Merge adjacent chunks of the same region into a single range. Chunks of
+                    // different regions are kept apart, even if the regions are adjacent.
+                    Some((last, last_memory)) if last.end == start => {
+                        last.end += chunk.len() as u64;
+                        last_memory.extend_from_slice(chunk);
+                    }
+                    _ => data.push((start..start + chunk.len() as u64, chunk.to_vec())),
+                }
+            }
+        }
+
+        CoreDumpDelta {
+            reference: reference.fingerprint(),
+            ranges: self.data.iter().map(|(range, _)| range.clone()).collect(),
+            dump: CoreDump {
+                data,
+                ..self.clone_without_data()
+            },
+        }
+    }
+
+    /// Reconstructs the full dump from a delta and the reference it was created from.
+    fn apply_delta(delta: CoreDumpDelta, reference: &CoreDump) -> Result<Self, CoreDumpError> {
+        if delta.reference != reference.fingerprint() {
+            return Err(CoreDumpError::ReferenceMismatch);
+        }
+
+        let mut data = delta
+            .ranges
+            .into_iter()
+            .filter_map(|range| reference.data.iter().find(|(r, _)| *r == range).cloned())
+            .collect::<Vec<_>>();
+
+        for (range, memory) in delta.dump.data.iter() {
+            match data.iter_mut().find(|(r, _)| r.contains_range(range)) {
+                Some((full_range, full_memory)) => {
+                    let offset = (range.start - full_range.start) as usize;
+                    full_memory[offset..][..memory.len()].copy_from_slice(memory);
+                }
+                None => data.push((range.clone(), memory.clone())),
+            }
+        }
+        data.sort_by_key(|(range, _)| range.start);
+
+        Ok(CoreDump { data, ..delta.dump })
+    }
+
+    fn clone_without_data(&self) -> Self {
+        CoreDump {
+            registers: self.registers.clone(),
+            data: Vec::new(),
+            instruction_set: self.instruction_set,
+            supports_native_64bit_access: self.supports_native_64bit_access,
+            core_type: self.core_type,
+            fpu_support: self.fpu_support,
+            floating_point_register_count: self.floating_point_register_count,
+        }
+    }
+
+    /// A FNV-1a hash over the dumped memory, used to identify reference dumps.
+    fn fingerprint(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325u64;
+        for (range, memory) in &self.data {
+            let bytes = range
+                .start
+                .to_le_bytes()
+                .into_iter()
+                .chain(memory.iter().copied());
+            for byte in bytes {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+            }
+        }
+        hash
+    }
+
+    /// Store the dumped core to a file as an ELF core file (`ET_CORE`).
+    ///
+    /// Unlike the file written by [`CoreDump::store`], an ELF core file can be opened
+    /// by GDB and LLDB alongside the firmware binary.
+    pub fn store_elf(&self, path: &Path) -> Result<(), CoreDumpError> {
+        let elf = self.to_elf()?;
+        std::fs::write(path, elf).map_err(|e| {
+            CoreDumpError::CoreDumpFileWrite(e, dunce::canonicalize(path).unwrap_or_default())
+        })
+    }
+
+    /// Encode the dumped core as an ELF core file (`ET_CORE`).
+    ///
+    /// Only 32-bit ARM, RISC-V and Xtensa cores are supported.
+    pub fn to_elf(&self) -> Result<Vec<u8>, CoreDumpError> {
+        let processor: &dyn Processor = match self.core_type {
+            CoreType::Armv6m
+            | CoreType::Armv7m
+            | CoreType::Armv7em
+            | CoreType::Armv8m
+            | CoreType::Armv7a
+            | CoreType::Armv7r => &ArmProcessor {
+                core_type: self.core_type,
+            },
+            CoreType::Riscv => &RiscvProcessor,
+            CoreType::Xtensa => &XtensaProcessor,
+            other => {
+                return Err(CoreDumpError::EncodingElfCoreDump(format!(
+                    "Unsupported core type: {other:?}",
+                )));
+            }
+        };
+
+        elf::write_elf_core(self, processor)
     }
 
     /// Load the dumped core from a file.
     pub fn load(path: &Path) -> Result<Self, CoreDumpError> {
-        let file_contents = std::fs::read(path).map_err(|e| {
-            CoreDumpError::CoreDumpFileRead(e, dunce::canonicalize(path).unwrap_or_default())
-        })?;
-        Self::load_raw(&file_contents)
+        Self::load_raw(&read_dump_file(path)?)
     }
 
     /// Load the dumped core from a file.
     pub fn load_raw(data: &[u8]) -> Result<Self, CoreDumpError> {
-        if let Ok(elf) = object::read::elf::ElfFile32::parse(data) {
+        Self::decode(data, None)
+    }
+
+    /// Load a dumped core from a file, that was stored with [`CoreDump::store_delta`].
+    ///
+    /// Files that contain a complete dump are loaded as well, ignoring `reference`.
+    pub fn load_delta(path: &Path, reference: &CoreDump) -> Result<Self, CoreDumpError> {
+        Self::load_delta_raw(&read_dump_file(path)?, reference)
+    }
+
+    /// Load a dumped core, that was stored with [`CoreDump::store_delta`].
+    ///
+    /// Data that contains a complete dump is loaded as well, ignoring `reference`.
+    pub fn load_delta_raw(data: &[u8], reference: &CoreDump) -> Result<Self, CoreDumpError> {
+        Self::decode(data, Some(reference))
+    }
+
+    fn decode(data: &[u8], reference: Option<&CoreDump>) -> Result<Self, CoreDumpError> {
+        if let Some(container) = format::decode(data)? {
+            if container.kind != format::ContentKind::Core {
+                return Err(CoreDumpError::InvalidHeader(
+                    "The file does not contain a core dump".to_string(),
+                ));
+            }
+
+            if container.flags & format::FLAG_DELTA == 0 {
+                return rmp_serde::from_slice(&container.payload)
+                    .map_err(CoreDumpError::DecodingCoreDump);
+            }
+
+            let reference = reference.ok_or(CoreDumpError::MissingReference)?;
+            let delta = rmp_serde::from_slice(&container.payload)
+                .map_err(CoreDumpError::DecodingCoreDump)?;
+            Self::apply_delta(delta, reference)
+        } else if let Ok(elf) = object::read::elf::ElfFile32::parse(data) {
             Self::load_elf(elf)
         } else if let Ok(elf) = object::read::elf::ElfFile64::parse(data) {
             Self::load_elf(elf)
         } else {
+            // Version 1 dumps have no header.
             rmp_serde::from_slice(data).map_err(CoreDumpError::DecodingCoreDump)
         }
     }
@@ -223,6 +520,10 @@ impl CoreDump {
         let elf_data = elf.data();
 
         let processor: Box<dyn Processor> = match elf.architecture() {
+            // ELF core files do not record the profile of an ARM core.
+            object::Architecture::Arm => Box::new(ArmProcessor {
+                core_type: CoreType::Armv7m,
+            }),
             object::Architecture::Riscv32 => Box::new(RiscvProcessor),
             object::Architecture::Xtensa => Box::new(XtensaProcessor),
             other => {
@@ -264,7 +565,7 @@ impl CoreDump {
             .expect("Failed to read notes from a PT_NOTE segment. This is a bug, please report it.")
         {
             let note = note?;
-            if note.name() != b"CORE" {
+            if note.name() != b"CORE" || note.n_type(endianness) != NT_PRSTATUS {
                 continue;
             }
 
@@ -272,7 +573,7 @@ impl CoreDump {
             // We only care about the registers, so let's cut off the rest. If we decide to use
             // the other information, we can do that later, most likely without
             // architecture-specific processing code.
-            const CORE_NOTE_HEADER_SIZE: usize = 72;
+            const CORE_NOTE_HEADER_SIZE: usize = elf::PRSTATUS_HEADER_SIZE;
             let note_length = processor.register_data_len();
 
             if note.desc().len() < CORE_NOTE_HEADER_SIZE + note_length {
@@ -354,6 +655,37 @@ impl CoreDump {
     }
 }
 
+/// Stored instead of a [`CoreDump`] by [`CoreDump::store_delta`].
+#[derive(Serialize, Deserialize)]
+struct CoreDumpDelta {
+    /// The fingerprint of the reference dump.
+    reference: u64,
+    /// All memory ranges of the complete dump.
+    ranges: Vec<Range<u64>>,
+    /// The dump, with only the memory that differs from the reference.
+    dump: CoreDump,
+}
+
+fn write_dump_file(path: &Path, data: &[u8]) -> Result<(), CoreDumpError> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(path)
+        .map_err(|e| {
+            CoreDumpError::CoreDumpFileWrite(e, dunce::canonicalize(path).unwrap_or_default())
+        })?;
+    file.write_all(data).map_err(|e| {
+        CoreDumpError::CoreDumpFileWrite(e, dunce::canonicalize(path).unwrap_or_default())
+    })
+}
+
+fn read_dump_file(path: &Path) -> Result<Vec<u8>, CoreDumpError> {
+    std::fs::read(path).map_err(|e| {
+        CoreDumpError::CoreDumpFileRead(e, dunce::canonicalize(path).unwrap_or_default())
+    })
+}
+
 impl MemoryInterface for CoreDump {
     fn supports_native_64bit_access(&mut self) -> bool {
         self.supports_native_64bit_access
@@ -462,7 +794,131 @@ pub enum CoreDumpError {
     /// Decoding the coredump .elf failed.
     #[error("Decoding the coredump .elf failed.")]
     DecodingElfCoreDump(String),
+    /// Encoding the coredump .elf failed.
+    #[error("Encoding the coredump .elf failed: {0}")]
+    EncodingElfCoreDump(String),
+    /// The header of the dump is invalid.
+    #[error("Invalid dump header: {0}")]
+    InvalidHeader(String),
+    /// The dump is compressed with a method this build of probe-rs does not support.
+    #[error("Compression {0:?} is not supported by this build of probe-rs.")]
+    UnsupportedCompression(DumpCompression),
+    /// Compressing or decompressing the dump failed.
+    #[error("Compressing or decompressing the dump failed.")]
+    Compression(#[source] std::io::Error),
+    /// The dump only contains the differences to a reference dump, which was not provided.
+    #[error("The dump is a delta dump and can only be loaded with its reference dump.")]
+    MissingReference,
+    /// The reference dump is not the one the delta dump was created from.
+    #[error("The reference dump does not match the one the delta dump was created from.")]
+    ReferenceMismatch,
+    /// The dump was written by a newer, unsupported version of the format.
+    #[error(
+        "The dump uses format version {0}, which is not supported by this version of probe-rs."
+    )]
+    UnsupportedVersion(u32),
     /// Invalid ELF file.
     #[error("Invalid ELF file.")]
     ElfCoreDumpFormat(#[from] object::read::Error),
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dump(data: Vec<(Range<u64>, Vec<u8>)>) -> CoreDump {
+        CoreDump {
+            registers: HashMap::from([(RegisterId(15), RegisterValue::U32(0x0800_0100))]),
+            data,
+            instruction_set: InstructionSet::Thumb2,
+            supports_native_64bit_access: false,
+            core_type: CoreType::Armv7em,
+            fpu_support: false,
+            floating_point_register_count: None,
+        }
+    }
+
+    #[test]
+    fn delta_round_trip() {
+        let reference = dump(vec![
+            (0x2000_0000..0x2000_1000, vec![0; 0x1000]),
+            (0x2001_0000..0x2001_0100, vec![1; 0x100]),
+        ]);
+
+        let mut ram = vec![0; 0x1000];
+        ram[0x123] = 0xaa;
+        ram[0x800..0x900].fill(0x55);
+        let current = dump(vec![
+            (0x2000_0000..0x2000_1000, ram),
+            (0x3000_0000..0x3000_0010, vec![2; 0x10]),
+        ]);
+
+        let delta = current.delta_from(&reference);
+        assert_eq!(
+            delta
+                .dump
+                .data
+                .iter()
+                .map(|(range, _)| range.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                0x2000_0100..0x2000_0200,
+                0x2000_0800..0x2000_0900,
+                0x3000_0000..0x3000_0010,
+            ]
+        );
+
+        let data = current
+            .to_bytes(DumpCompression::None, Some(&reference))
+            .unwrap();
+        let loaded = CoreDump::load_delta_raw(&data, &reference).unwrap();
+        assert_eq!(loaded.data, current.data);
+        assert_eq!(loaded.registers, current.registers);
+    }
+
+    #[test]
+    fn delta_of_adjacent_regions() {
+        let reference = dump(vec![
+            (0x2000_0000..0x2000_0100, vec![0; 0x100]),
+            (0x2000_0100..0x2000_0200, vec![0; 0x100]),
+        ]);
+        let current = dump(vec![
+            (0x2000_0000..0x2000_0100, vec![1; 0x100]),
+            (0x2000_0100..0x2000_0200, vec![2; 0x100]),
+        ]);
+
+        let data = current
+            .to_bytes(DumpCompression::None, Some(&reference))
+            .unwrap();
+        let loaded = CoreDump::load_delta_raw(&data, &reference).unwrap();
+        assert_eq!(loaded.data, current.data);
+    }
+
+    #[test]
+    fn delta_requires_matching_reference() {
+        let reference = dump(vec![(0x2000_0000..0x2000_0100, vec![0; 0x100])]);
+        let other = dump(vec![(0x2000_0000..0x2000_0100, vec![1; 0x100])]);
+
+        let data = other
+            .to_bytes(DumpCompression::None, Some(&reference))
+            .unwrap();
+
+        assert!(matches!(
+            CoreDump::load_raw(&data),
+            Err(CoreDumpError::MissingReference)
+        ));
+        assert!(matches!(
+            CoreDump::load_delta_raw(&data, &other),
+            Err(CoreDumpError::ReferenceMismatch)
+        ));
+    }
+
+    #[test]
+    fn headerless_dumps_are_loaded() {
+        let original = dump(vec![(0x2000_0000..0x2000_0004, vec![1, 2, 3, 4])]);
+        let data = rmp_serde::encode::to_vec_named(&original).unwrap();
+
+        let loaded = CoreDump::load_raw(&data).unwrap();
+        assert_eq!(loaded.data, original.data);
+    }
+}
@@ -0,0 +1,231 @@
+//! Writer for ELF core files (`ET_CORE`).
+//!
+//! The layout follows what GDB and LLDB expect from a Linux core file: the register state is
+//! stored in a `PT_NOTE` segment as `NT_PRSTATUS` (and optionally a floating point note), and
+//! every dumped memory range becomes a `PT_LOAD` segment.
+
+use object::elf::{
+    ELFCLASS32, ELFDATA2LSB, ELFMAG, ET_CORE, EV_CURRENT, NT_PRSTATUS, PF_R, PF_W, PF_X, PT_LOAD,
+    PT_NOTE,
+};
+
+use super::{CoreDump, CoreDumpError, FpNote, Processor};
+use crate::RegisterValue;
+
+const ELF_HEADER_SIZE: usize = 52;
+const PROGRAM_HEADER_SIZE: usize = 32;
+
+/// Size of the `elf_prstatus` fields in front of the general purpose registers.
+pub(super) const PRSTATUS_HEADER_SIZE: usize = 72;
+/// Offset of `pr_pid` in `elf_prstatus`.
+const PRSTATUS_PID_OFFSET: usize = 24;
+
+pub(super) fn write_elf_core(
+    dump: &CoreDump,
+    processor: &dyn Processor,
+) -> Result<Vec<u8>, CoreDumpError> {
+    let mut notes = Vec::new();
+
+    let fp_note = processor
+        .fp_note()
+        .and_then(|layout| fp_note_data(dump, &layout).map(|data| (layout, data)));
+
+    // NT_PRSTATUS: thread information, followed by the registers and `pr_fpvalid`.
+    let mut prstatus = vec![0; PRSTATUS_HEADER_SIZE];
+    prstatus[PRSTATUS_PID_OFFSET..][..4].copy_from_slice(&1u32.to_le_bytes());
+    prstatus.extend(processor.write_registers(&dump.registers));
+    prstatus.extend_from_slice(&u32::from(fp_note.is_some()).to_le_bytes());
+    write_note(&mut notes, b"CORE", NT_PRSTATUS.0, &prstatus);
+
+    if let Some((layout, data)) = fp_note {
+        write_note(&mut notes, layout.name, layout.note_type, &data);
+    }
+
+    let segment_count = 1 + dump.data.len();
+    let mut offset = ELF_HEADER_SIZE + segment_count * PROGRAM_HEADER_SIZE;
+
+    let mut out = Vec::with_capacity(
+        offset + notes.len() + dump.data.iter().map(|(_, d)| d.len()).sum::<usize>(),
+    );
+
+    // ELF header
+    out.extend_from_slice(&ELFMAG);
+    out.extend_from_slice(&[ELFCLASS32.0, ELFDATA2LSB.0, EV_CURRENT.0]);
+    out.extend_from_slice(&[0; 9]);
+    out.extend_from_slice(&ET_CORE.0.to_le_bytes());
+    out.extend_from_slice(&processor.elf_machine().to_le_bytes());
+    out.extend_from_slice(&u32::from(EV_CURRENT.0).to_le_bytes());
+    // e_entry, e_phoff, e_shoff, e_flags
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(ELF_HEADER_SIZE as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    // e_ehsize, e_phentsize, e_phnum, e_shentsize, e_shnum, e_shstrndx
+    out.extend_from_slice(&(ELF_HEADER_SIZE as u16).to_le_bytes());
+    out.extend_from_slice(&(PROGRAM_HEADER_SIZE as u16).to_le_bytes());
+    out.extend_from_slice(&(segment_count as u16).to_le_bytes());
+    out.extend_from_slice(&[0; 6]);
+
+    // Program headers
+    write_program_header(&mut out, PT_NOTE.0, offset, 0, notes.len(), 0, 4);
+    offset += notes.len();
+    for (range, data) in &dump.data {
+        let address = u32::try_from(range.start).map_err(|_| {
+            CoreDumpError::EncodingElfCoreDump(format!(
+                "Memory range {range:#x?} does not fit into a 32-bit core file"
+            ))
+        })?;
+        write_program_header(
+            &mut out,
+            PT_LOAD.0,
+            offset,
+            address,
+            data.len(),
+            PF_R.0 | PF_W.0 | PF_X.0,
+            1,
+        );
+        offset += data.len();
+    }
+
+    // Segment contents
+    out.extend_from_slice(&notes);
+    for (_, data) in &dump.data {
+        out.extend_from_slice(data);
+    }
+
+    Ok(out)
+}
+
+fn write_program_header(
+    out: &mut Vec<u8>,
+    p_type: u32,
+    offset: usize,
+    address: u32,
+    size: usize,
+    flags: u32,
+    align: u32,
+) {
+    for field in [
+        p_type,
+        offset as u32,
+        address,
+        address,
+        size as u32,
+        size as u32,
+        flags,
+        align,
+    ] {
+        out.extend_from_slice(&field.to_le_bytes());
+    }
+}
+
+fn write_note(out: &mut Vec<u8>, name: &[u8], note_type: u32, desc: &[u8]) {
+    // The name is zero terminated, name and descriptor are padded to 4 bytes.
+    let name_size = name.len() + 1;
+    out.extend_from_slice(&(name_size as u32).to_le_bytes());
+    out.extend_from_slice(&(desc.len() as u32).to_le_bytes());
+    out.extend_from_slice(&note_type.to_le_bytes());
+    out.extend_from_slice(name);
+    out.resize(out.len() + name_size.next_multiple_of(4) - name.len(), 0);
+    out.extend_from_slice(desc);
+    out.resize(out.len().next_multiple_of(4), 0);
+}
+
+/// Collects the floating point registers, followed by the floating point status register.
+///
+/// Returns `None` if the dump contains no floating point registers.
+fn fp_note_data(dump: &CoreDump, layout: &FpNote) -> Option<Vec<u8>> {
+    let registers = dump.registers();
+    let fpu_registers = registers.fpu_registers()?;
+
+    let mut data = Vec::new();
+    for register in fpu_registers {
+        match dump.registers.get(&register.id()) {
+            Some(RegisterValue::U32(value)) => data.extend_from_slice(&value.to_le_bytes()),
+            Some(RegisterValue::U64(value)) => data.extend_from_slice(&value.to_le_bytes()),
+            Some(RegisterValue::U128(value)) => data.extend_from_slice(&value.to_le_bytes()),
+            None => data.resize(data.len() + register.size_in_bytes(), 0),
+        }
+    }
+
+    if let Some(size) = layout.register_area_size {
+        data.resize(size, 0);
+    }
+
+    let status = registers
+        .fpsr()
+        .and_then(|fpsr| dump.registers.get(&fpsr.id()))
+        .and_then(|value| TryInto::<u32>::try_into(*value).ok())
+        .unwrap_or_default();
+    data.extend_from_slice(&status.to_le_bytes());
+
+    Some(data)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::{CoreDump, CoreType, InstructionSet, RegisterId, RegisterValue};
+
+    fn dump(core_type: CoreType, instruction_set: InstructionSet, registers: u16) -> CoreDump {
+        CoreDump {
+            registers: (0..registers)
+                .map(|id| (RegisterId(id), RegisterValue::U32(0x1000 + id as u32)))
+                .collect::<HashMap<_, _>>(),
+            data: vec![
+                (0x2000_0000..0x2000_0010, (0..16).collect()),
+                (0x2000_1000..0x2000_1004, vec![0xaa; 4]),
+            ],
+            instruction_set,
+            supports_native_64bit_access: false,
+            core_type,
+            fpu_support: false,
+            floating_point_register_count: None,
+        }
+    }
+
+    #[test]
+    fn cortex_m_round_trip() {
+        let original = dump(CoreType::Armv7em, InstructionSet::Thumb2, 17);
+        let elf = original.to_elf().unwrap();
+
+        let loaded = CoreDump::load_raw(&elf).unwrap();
+        assert_eq!(loaded.data, original.data);
+        for id in 0..17 {
+            assert_eq!(
+                loaded.registers.get(&RegisterId(id)),
+                original.registers.get(&RegisterId(id)),
+                "register {id}"
+            );
+        }
+    }
+
+    #[test]
+    fn riscv_round_trip() {
+        use crate::architecture::riscv::registers::RISCV_CORE_REGISTERS;
+
+        let mut original = dump(CoreType::Riscv, InstructionSet::RV32, 0);
+        for (value, register) in RISCV_CORE_REGISTERS.all_registers().enumerate() {
+            original
+                .registers
+                .insert(register.id(), RegisterValue::U32(value as u32));
+        }
+        let elf = original.to_elf().unwrap();
+
+        let loaded = CoreDump::load_raw(&elf).unwrap();
+        assert_eq!(loaded.data, original.data);
+        let pc = RISCV_CORE_REGISTERS.pc().unwrap().id();
+        assert_eq!(loaded.registers.get(&pc), original.registers.get(&pc));
+        for idx in 1..32 {
+            let id = RISCV_CORE_REGISTERS.core_register(idx).id();
+            assert_eq!(loaded.registers.get(&id), original.registers.get(&id));
+        }
+    }
+
+    #[test]
+    fn aarch64_is_rejected() {
+        let original = dump(CoreType::Armv8a, InstructionSet::A64, 4);
+        assert!(original.to_elf().is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! The container format of the dump files written by probe-rs.
+//!
+//! A dump file starts with a fixed 16 byte header, followed by the (optionally compressed)
+//! MessagePack encoded payload:
+//!
+//! | Offset | Size | Content                              |
+//! |--------|------|--------------------------------------|
+//! | 0      | 8    | Magic, `PRSDUMP\0`                   |
+//! | 8      | 2    | Format version, little endian        |
+//! | 10     | 1    | Content, see [`ContentKind`]         |
+//! | 11     | 1    | Compression, see [`DumpCompression`] |
+//! | 12     | 1    | Flags, see [`FLAG_DELTA`]            |
+//! | 13     | 3    | Reserved, must be 0                  |
+//!
+//! Files without the header are version 1 dumps, which only contain the bare MessagePack
+//! encoded [`CoreDump`](super::CoreDump).
+
+use super::CoreDumpError;
+
+const MAGIC: [u8; 8] = *b"PRSDUMP\0";
+const HEADER_SIZE: usize = 16;
+
+/// The version of the container format written by this version of probe-rs.
+pub(super) const VERSION: u16 = 2;
+
+/// The payload only contains the differences to a reference dump.
+pub(super) const FLAG_DELTA: u8 = 1 << 0;
+
+/// Compression of the payload of a stored dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DumpCompression {
+    /// The payload is stored uncompressed.
+    #[default]
+    None,
+    /// The payload is compressed with deflate. Requires the `flate2` feature.
+    Deflate,
+    /// The payload is compressed with Zstandard. Requires the `zstd` feature.
+    Zstd,
+}
+
+impl DumpCompression {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::None),
+            1 => Some(Self::Deflate),
+            2 => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Deflate => 1,
+            Self::Zstd => 2,
+        }
+    }
+
+    fn compress(self, payload: Vec<u8>) -> Result<Vec<u8>, CoreDumpError> {
+        match self {
+            Self::None => Ok(payload),
+            #[cfg(feature = "flate2")]
+            Self::Deflate => {
+                use std::io::Write;
+
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder
+                    .write_all(&payload)
+                    .and_then(|_| encoder.finish())
+                    .map_err(CoreDumpError::Compression)
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => Ok(ruzstd::encoding::compress_to_vec(
+                payload.as_slice(),
+                ruzstd::encoding::CompressionLevel::Fastest,
+            )),
+            #[allow(unreachable_patterns)]
+            other => Err(CoreDumpError::UnsupportedCompression(other)),
+        }
+    }
+
+    fn decompress(self, payload: &[u8]) -> Result<Vec<u8>, CoreDumpError> {
+        match self {
+            Self::None => Ok(payload.to_vec()),
+            #[cfg(feature = "flate2")]
+            Self::Deflate => {
+                use std::io::Read;
+
+                let mut data = Vec::new();
+                flate2::read::DeflateDecoder::new(payload)
+                    .read_to_end(&mut data)
+                    .map_err(CoreDumpError::Compression)?;
+                Ok(data)
+            }
+            #[cfg(feature = "zstd")]
+            Self::Zstd => {
+                use std::io::Read;
+
+                let mut data = Vec::new();
+                ruzstd::decoding::StreamingDecoder::new(payload)
+                    .map_err(|e| {
+                        CoreDumpError::Compression(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            e,
+                        ))
+                    })?
+                    .read_to_end(&mut data)
+                    .map_err(CoreDumpError::Compression)?;
+                Ok(data)
+            }
+            #[allow(unreachable_patterns)]
+            other => Err(CoreDumpError::UnsupportedCompression(other)),
+        }
+    }
+}
+
+/// The kind of dump stored in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ContentKind {
+    /// A single [`CoreDump`](super::CoreDump).
+    Core,
+    /// A [`SessionDump`](super::SessionDump).
+    Session,
+}
+
+impl ContentKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Core),
+            1 => Some(Self::Session),
+            _ => None,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            Self::Core => 0,
+            Self::Session => 1,
+        }
+    }
+}
+
+/// The decoded header and payload of a dump file.
+pub(super) struct Container {
+    pub kind: ContentKind,
+    pub flags: u8,
+    pub payload: Vec<u8>,
+}
+
+/// Prepends the header to the payload, compressing it on the way.
+pub(super) fn encode(
+    kind: ContentKind,
+    compression: DumpCompression,
+    flags: u8,
+    payload: Vec<u8>,
+) -> Result<Vec<u8>, CoreDumpError> {
+    let payload = compression.compress(payload)?;
+
+    let mut out = Vec::with_capacity(HEADER_SIZE + payload.len());
+    out.extend_from_slice(&MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&[kind.to_byte(), compression.to_byte(), flags, 0, 0, 0]);
+    out.extend_from_slice(&payload);
+
+    Ok(out)
+}
+
+/// Parses the header and decompresses the payload.
+///
+/// Returns `None` if the data does not start with a header, i.e. if it is a version 1 dump.
+pub(super) fn decode(data: &[u8]) -> Result<Option<Container>, CoreDumpError> {
+    let Some(header) = data.get(..HEADER_SIZE).filter(|h| h.starts_with(&MAGIC)) else {
+        return Ok(None);
+    };
+
+    let version = u16::from_le_bytes([header[8], header[9]]);
+    if version > VERSION {
+        return Err(CoreDumpError::UnsupportedVersion(version.into()));
+    }
+
+    let kind = ContentKind::from_byte(header[10]).ok_or_else(|| {
+        CoreDumpError::InvalidHeader(format!("Unknown content kind {}", header[10]))
+    })?;
+    let compression = DumpCompression::from_byte(header[11]).ok_or_else(|| {
+        CoreDumpError::InvalidHeader(format!("Unknown compression {}", header[11]))
+    })?;
+
+    Ok(Some(Container {
+        kind,
+        flags: header[12],
+        payload: compression.decompress(&data[HEADER_SIZE..])?,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip(compression: DumpCompression) {
+        let payload = (0..4096).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+
+        let encoded = encode(
+            ContentKind::Session,
+            compression,
+            FLAG_DELTA,
+            payload.clone(),
+        )
+        .unwrap();
+        let container = decode(&encoded).unwrap().unwrap();
+
+        assert_eq!(container.kind, ContentKind::Session);
+        assert_eq!(container.flags, FLAG_DELTA);
+        assert_eq!(container.payload, payload);
+    }
+
+    #[test]
+    fn uncompressed_round_trip() {
+        round_trip(DumpCompression::None);
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn deflate_round_trip() {
+        round_trip(DumpCompression::Deflate);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_round_trip() {
+        round_trip(DumpCompression::Zstd);
+    }
+
+    #[test]
+    fn headerless_data_is_version_1() {
+        assert!(decode(&[0x8a, 0xa9]).unwrap().is_none());
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let mut encoded = encode(ContentKind::Core, DumpCompression::None, 0, vec![]).unwrap();
+        encoded[8..10].copy_from_slice(&(VERSION + 1).to_le_bytes());
+
+        assert!(matches!(
+            decode(&encoded),
+            Err(CoreDumpError::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,257 @@
+//! Dumps of all cores in a [`Session`].
+
+use std::{ops::Range, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{CoreDump, CoreDumpError, DumpCompression, format, read_dump_file, write_dump_file};
+use crate::{Error, MemoryInterface, Session};
+
+/// A snapshot of every core in a session, stored in a single file.
+///
+/// Each core is captured as a separate [`CoreDump`], which contains the RAM regions the core
+/// can access. Additional (e.g. peripheral) ranges are read once and shared by all cores. The
+/// dump of a single core, including the shared ranges, can be retrieved with
+/// [`SessionDump::core`] and replayed like any other [`CoreDump`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDump {
+    /// The version of the session dump.
+    pub version: u32,
+    /// The name of the target the dump was taken from.
+    pub target: String,
+    /// The dumps of the individual cores.
+    pub cores: Vec<SessionCoreDump>,
+    /// Memory ranges that were read once for all cores, e.g. peripheral register blocks.
+    #[serde(default)]
+    pub shared: Vec<(Range<u64>, Vec<u8>)>,
+}
+
+/// The dump of a single core in a [`SessionDump`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCoreDump {
+    /// The index of the core in the session.
+    pub index: usize,
+    /// The name of the core, as defined in the target description.
+    pub name: String,
+    /// The dumped core state.
+    pub dump: CoreDump,
+}
+
+impl SessionDump {
+    /// The current version of the session dump.
+    ///
+    /// Version 1 dumps are stored without a header and read peripheral ranges per core.
+    pub const VERSION: u32 = 2;
+
+    /// Dump every core of the session.
+    ///
+    /// Running cores are halted while the dump is taken and resumed afterwards. Disabled cores
+    /// are skipped.
+    ///
+    /// # Arguments
+    /// * `session`: The session to dump.
+    /// * `peripheral_ranges`: Additional memory ranges that should be dumped once for all cores,
+    ///   e.g. peripheral register blocks. They are read through the first enabled core, ranges
+    ///   that it cannot read are skipped.
+    pub fn dump_session(
+        session: &mut Session,
+        peripheral_ranges: &[Range<u64>],
+    ) -> Result<Self, Error> {
+        session.halted_access(|session| {
+            let mut cores = Vec::new();
+            let mut shared = Vec::new();
+
+            for (index, _) in session.list_cores() {
+                let name = session.target().cores[index].name.clone();
+                let ram_ranges = session
+                    .target()
+                    .memory_map
+                    .iter()
+                    .filter_map(|region| region.as_ram_region())
+                    .filter(|region| !region.is_alias && region.accessible_by(&name))
+                    .map(|region| region.range.clone())
+                    .collect::<Vec<_>>();
+
+                let mut core = match session.core(index) {
+                    Err(Error::CoreDisabled(_)) => continue,
+                    other => other?,
+                };
+
+                let dump = CoreDump::dump_core(&mut core, ram_ranges)?;
+
+                if cores.is_empty() {
+                    for range in peripheral_ranges {
+                        let mut values = vec![0; (range.end - range.start) as usize];
+                        match core.read(range.start, &mut values) {
+                            Ok(()) => shared.push((range.clone(), values)),
+                            Err(error) => tracing::warn!(
+                                "Skipping range {range:#x?} for core {index} ({name}): {error}"
+                            ),
+                        }
+                    }
+                }
+
+                cores.push(SessionCoreDump { index, name, dump });
+            }
+
+            Ok(Self {
+                version: Self::VERSION,
+                target: session.target().name.clone(),
+                cores,
+                shared,
+            })
+        })
+    }
+
+    /// Returns the dump of the core with the given index, including the shared ranges.
+    ///
+    /// The returned dump can be used to replay the state of the core, e.g. to unwind its stack.
+    pub fn core(&self, index: usize) -> Option<CoreDump> {
+        let core = self.cores.iter().find(|core| core.index == index)?;
+
+        let mut dump = core.dump.clone();
+        dump.data.extend(self.shared.iter().cloned());
+        Some(dump)
+    }
+
+    /// Store the session dump to a file.
+    pub fn store(&self, path: &Path) -> Result<(), CoreDumpError> {
+        self.store_compressed(path, DumpCompression::None)
+    }
+
+    /// Store the session dump to a file, compressing the registers and memory ranges.
+    pub fn store_compressed(
+        &self,
+        path: &Path,
+        compression: DumpCompression,
+    ) -> Result<(), CoreDumpError> {
+        write_dump_file(path, &self.to_bytes(compression)?)
+    }
+
+    /// Serialize the session dump into the format written by [`SessionDump::store`].
+    pub fn to_bytes(&self, compression: DumpCompression) -> Result<Vec<u8>, CoreDumpError> {
+        let payload =
+            rmp_serde::encode::to_vec_named(self).map_err(CoreDumpError::EncodingCoreDump)?;
+        format::encode(format::ContentKind::Session, compression, 0, payload)
+    }
+
+    /// Load a session dump from a file.
+    pub fn load(path: &Path) -> Result<Self, CoreDumpError> {
+        Self::load_raw(&read_dump_file(path)?)
+    }
+
+    /// Load a session dump from its serialized form.
+    pub fn load_raw(data: &[u8]) -> Result<Self, CoreDumpError> {
+        let dump: Self = match format::decode(data)? {
+            Some(container) if container.kind == format::ContentKind::Session => {
+                rmp_serde::from_slice(&container.payload)
+            }
+            Some(_) => {
+                return Err(CoreDumpError::InvalidHeader(
+                    "The file does not contain a session dump".to_string(),
+                ));
+            }
+            // Version 1 dumps have no header.
+            None => rmp_serde::from_slice(data),
+        }
+        .map_err(CoreDumpError::DecodingCoreDump)?;
+
+        if dump.version > Self::VERSION {
+            return Err(CoreDumpError::UnsupportedVersion(dump.version));
+        }
+
+        Ok(dump)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{CoreType, InstructionSet, RegisterId, RegisterValue};
+
+    fn session_dump(version: u32) -> SessionDump {
+        let core = |index: usize| SessionCoreDump {
+            index,
+            name: format!("core{index}"),
+            dump: CoreDump {
+                registers: HashMap::from([(RegisterId(15), RegisterValue::U32(index as u32))]),
+                data: vec![(0x2000_0000..0x2000_0004, vec![index as u8; 4])],
+                instruction_set: InstructionSet::Thumb2,
+                supports_native_64bit_access: false,
+                core_type: CoreType::Armv8m,
+                fpu_support: false,
+                floating_point_register_count: None,
+            },
+        };
+
+        SessionDump {
+            version,
+            target: "nRF5340_xxAA".to_string(),
+            cores: vec![core(0), core(1)],
+            shared: vec![(0x5000_0000..0x5000_0004, vec![0xff; 4])],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = session_dump(SessionDump::VERSION)
+            .to_bytes(DumpCompression::None)
+            .unwrap();
+
+        let loaded = SessionDump::load_raw(&data).unwrap();
+        assert_eq!(loaded.target, "nRF5340_xxAA");
+        assert_eq!(loaded.cores.len(), 2);
+
+        let core1 = loaded.core(1).unwrap();
+        assert_eq!(
+            core1.data,
+            vec![
+                (0x2000_0000..0x2000_0004, vec![1; 4]),
+                (0x5000_0000..0x5000_0004, vec![0xff; 4]),
+            ]
+        );
+        assert_eq!(
+            core1.registers.get(&RegisterId(15)),
+            Some(&RegisterValue::U32(1))
+        );
+        assert!(loaded.core(2).is_none());
+    }
+
+    #[test]
+    fn core_dumps_are_rejected() {
+        let data = session_dump(SessionDump::VERSION).cores[0]
+            .dump
+            .to_bytes(DumpCompression::None, None)
+            .unwrap();
+
+        assert!(matches!(
+            SessionDump::load_raw(&data),
+            Err(CoreDumpError::InvalidHeader(_))
+        ));
+    }
+
+    #[test]
+    fn headerless_version_1_dumps_are_loaded() {
+        let mut dump = session_dump(1);
+        dump.shared.clear();
+        let data = rmp_serde::encode::to_vec_named(&dump).unwrap();
+
+        let loaded = SessionDump::load_raw(&data).unwrap();
+        assert_eq!(loaded.version, 1);
+        assert_eq!(loaded.cores.len(), 2);
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let data = session_dump(SessionDump::VERSION + 1)
+            .to_bytes(DumpCompression::None)
+            .unwrap();
+
+        assert!(matches!(
+            SessionDump::load_raw(&data),
+            Err(CoreDumpError::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+
+use crate::InstructionSet;
+
+/// The kind of memory a software breakpoint is placed in.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SoftwareBreakpointLocation {
+    /// The breakpoint instruction was written to RAM.
+    Ram,
+    /// The breakpoint instruction was programmed into flash, by reprogramming the containing sector.
+    Flash,
+}
+
+/// A software breakpoint, which replaces an instruction with a break instruction.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SoftwareBreakpoint {
+    /// The address of the replaced instruction.
+    pub address: u64,
+    /// The kind of memory the breakpoint is placed in.
+    pub location: SoftwareBreakpointLocation,
+    /// The bytes of the replaced instruction.
+    pub(crate) original: Vec<u8>,
+    /// The bytes of the break instruction.
+    pub(crate) instruction: &'static [u8],
+    /// Whether the break instruction is currently in memory. Breakpoints in flash are only
+    /// programmed when the core is resumed.
+    pub(crate) programmed: bool,
+}
+
+/// The software breakpoints set on a core.
+///
+/// This lives in the core state, so the breakpoints are kept while the core is not attached.
+#[derive(Debug, Default)]
+pub struct SoftwareBreakpoints {
+    breakpoints: BTreeMap<u64, SoftwareBreakpoint>,
+    /// Cleared breakpoints in flash, whose break instruction may still be programmed.
+    removed: BTreeMap<u64, SoftwareBreakpoint>,
+}
+
+impl SoftwareBreakpoints {
+    /// Returns the breakpoint at `address`, if there is one.
+    pub fn get(&self, address: u64) -> Option<&SoftwareBreakpoint> {
+        self.breakpoints.get(&address)
+    }
+
+    /// Returns an iterator over all breakpoints, ordered by address.
+    pub fn iter(&self) -> impl Iterator<Item = &SoftwareBreakpoint> {
+        self.breakpoints.values()
+    }
+
+    /// Returns `true` if no breakpoints are set.
+    pub fn is_empty(&self) -> bool {
+        self.breakpoints.is_empty()
+    }
+
+    /// Returns `true` if breakpoints in flash have to be programmed or restored.
+    pub(crate) fn has_pending_flash_changes(&self) -> bool {
+        !self.removed.is_empty()
+            || self.breakpoints.values().any(|breakpoint| {
+                breakpoint.location == SoftwareBreakpointLocation::Flash && !breakpoint.programmed
+            })
+    }
+
+    /// Returns `true` if a break instruction may be at `address`, either of a breakpoint or of
+    /// a cleared breakpoint in flash.
+    pub(crate) fn has_break_instruction(&self, address: u64) -> bool {
+        self.breakpoints.contains_key(&address) || self.removed.contains_key(&address)
+    }
+
+    pub(crate) fn insert(&mut self, breakpoint: SoftwareBreakpoint) {
+        self.breakpoints.insert(breakpoint.address, breakpoint);
+    }
+
+    pub(crate) fn remove(&mut self, address: u64) -> Option<SoftwareBreakpoint> {
+        self.breakpoints.remove(&address)
+    }
+
+    /// Takes the cleared breakpoint in flash at `address`, whose break instruction may still be
+    /// programmed.
+    pub(crate) fn take_removed(&mut self, address: u64) -> Option<SoftwareBreakpoint> {
+        self.removed.remove(&address)
+    }
+
+    /// Clears the breakpoint in flash at `address`. The original instruction is restored the next
+    /// time the flash is updated.
+    pub(crate) fn remove_from_flash(&mut self, address: u64) {
+        if let Some(breakpoint) = self.breakpoints.remove(&address) {
+            self.removed.insert(address, breakpoint);
+        }
+    }
+
+    /// Returns the changes to the flash needed so that exactly the break instructions of the
+    /// breakpoints are programmed, except for the one at `suspended`.
+    ///
+    /// The original instruction at `suspended` is always included, as the core is about to
+    /// execute it.
+    pub(crate) fn flash_patches(&self, suspended: Option<u64>) -> Vec<(u64, &[u8])> {
+        let breakpoints = self
+            .breakpoints
+            .values()
+            .filter(|breakpoint| breakpoint.location == SoftwareBreakpointLocation::Flash)
+            .filter_map(|breakpoint| {
+                let enabled = suspended != Some(breakpoint.address);
+                (!enabled || enabled != breakpoint.programmed).then(|| {
+                    let data = if enabled {
+                        breakpoint.instruction
+                    } else {
+                        breakpoint.original.as_slice()
+                    };
+                    (breakpoint.address, data)
+                })
+            });
+        let removed = self
+            .removed
+            .values()
+            .map(|breakpoint| (breakpoint.address, breakpoint.original.as_slice()));
+
+        breakpoints.chain(removed).collect()
+    }
+
+    /// Records that the flash was updated with the patches from
+    /// [`flash_patches`](Self::flash_patches).
+    pub(crate) fn flash_patched(&mut self, suspended: Option<u64>) {
+        for breakpoint in self.breakpoints.values_mut() {
+            if breakpoint.location == SoftwareBreakpointLocation::Flash {
+                breakpoint.programmed = suspended != Some(breakpoint.address);
+            }
+        }
+        self.removed.clear();
+    }
+
+    /// Takes all breakpoints in flash, including cleared ones whose break instruction may still
+    /// be programmed.
+    pub(crate) fn take_flash(&mut self) -> Vec<SoftwareBreakpoint> {
+        let mut flash = Vec::new();
+        self.breakpoints.retain(|_, breakpoint| {
+            if breakpoint.location != SoftwareBreakpointLocation::Flash {
+                return true;
+            }
+            flash.push(breakpoint.clone());
+            false
+        });
+        flash.extend(std::mem::take(&mut self.removed).into_values());
+        flash
+    }
+}
+
+/// Returns the break instruction which replaces the instruction starting with `first_byte`.
+///
+/// Where the instruction set has instructions of different lengths, the break instruction
+/// has the same length as the replaced instruction, so it never overlaps the next one.
+/// Returns `None` if software breakpoints are not supported for the instruction set.
+pub(crate) fn break_instruction(
+    instruction_set: InstructionSet,
+    first_byte: u8,
+) -> Option<&'static [u8]> {
+    // Compressed RISC-V instructions have the two lowest bits not both set.
+    let is_compressed_riscv = first_byte & 0b11 != 0b11;
+    // Narrow Xtensa instructions have an op0 field of 8 or larger.
+    let is_narrow_xtensa = first_byte & 0x0F >= 8;
+
+    match instruction_set {
+        // BKPT #0
+        InstructionSet::Thumb2 => Some(&[0x00, 0xBE]),
+        // C.EBREAK
+        InstructionSet::RV32C | InstructionSet::RV64C if is_compressed_riscv => Some(&[0x02, 0x90]),
+        // EBREAK
+        InstructionSet::RV32
+        | InstructionSet::RV32C
+        | InstructionSet::RV64
+        | InstructionSet::RV64C => Some(&[0x73, 0x00, 0x10, 0x00]),
+        // BREAK.N 0
+        InstructionSet::Xtensa if is_narrow_xtensa => Some(&[0x2D, 0xF0]),
+        // BREAK 0, 0
+        InstructionSet::Xtensa => Some(&[0x00, 0x40, 0x00]),
+        InstructionSet::A32 | InstructionSet::A64 => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn break_instruction_matches_instruction_length() {
+        assert_eq!(
+            break_instruction(InstructionSet::Thumb2, 0x70),
+            Some(&[0x00, 0xBE][..])
+        );
+
+        // c.nop and addi x0, x0, 0
+        assert_eq!(
+            break_instruction(InstructionSet::RV32C, 0x01),
+            Some(&[0x02, 0x90][..])
+        );
+        assert_eq!(
+            break_instruction(InstructionSet::RV32C, 0x13),
+            Some(&[0x73, 0x00, 0x10, 0x00][..])
+        );
+        assert_eq!(
+            break_instruction(InstructionSet::RV32, 0x13),
+            Some(&[0x73, 0x00, 0x10, 0x00][..])
+        );
+
+        // nop.n and nop
+        assert_eq!(
+            break_instruction(InstructionSet::Xtensa, 0x3D),
+            Some(&[0x2D, 0xF0][..])
+        );
+        assert_eq!(
+            break_instruction(InstructionSet::Xtensa, 0xF0),
+            Some(&[0x00, 0x40, 0x00][..])
+        );
+
+        assert_eq!(break_instruction(InstructionSet::A64, 0x1F), None);
+    }
+}
@@ -0,0 +1,30 @@
+/// The kind of memory access a watchpoint halts the core on.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum WatchpointKind {
+    /// Halt when the watched memory is read.
+    Read,
+    /// Halt when the watched memory is written.
+    Write,
+    /// Halt when the watched memory is read or written.
+    Access,
+}
+
+/// A hardware watchpoint, which halts the core when a range of memory is accessed.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Watchpoint {
+    /// The address of the watched memory.
+    pub address: u64,
+    /// The number of watched bytes.
+    ///
+    /// Most architectures only support power-of-two lengths, with the address aligned to the length.
+    pub length: u64,
+    /// The kind of access the core halts on.
+    pub kind: WatchpointKind,
+}
+
+impl Watchpoint {
+    /// Returns `true` if the length is a power of two, and the address is aligned to it.
+    pub(crate) fn is_naturally_aligned(&self) -> bool {
+        self.length.is_power_of_two() && self.address.is_multiple_of(self.length)
+    }
+}
@@ -3,8 +3,10 @@ use crate::architecture::riscv::communication_interface::RiscvError;
 use crate::architecture::xtensa::communication_interface::XtensaError;
 use crate::config::RegistryError;
 use crate::core::memory_mapped_registers::RegisterAddressOutOfBounds;
+use crate::flashing::FlashError;
 use crate::memory::{InvalidDataLengthError, MemoryNotAlignedError};
 use crate::probe::DebugProbeError;
+use probe_rs_target::InstructionSet;
 
 /// The overarching error type which contains all possible errors as variants.
 #[derive(thiserror::Error, Debug, docsplay::Display)]
@@ -81,6 +83,27 @@ pub enum Error {
 pub enum BreakpointError {
     /// No breakpoint found at address {0:#010x}
     NotFound(u64),
+    /// All {0} hardware watchpoint units are in use
+    NoWatchpointUnits(usize),
+    /// No watchpoint found at address {0:#010x}
+    WatchpointNotFound(u64),
+    /// A watchpoint of {length} bytes at address {address:#010x} is not supported: {reason}
+    UnsupportedWatchpoint {
+        /// The address of the watchpoint.
+        address: u64,
+        /// The length of the watchpoint.
+        length: u64,
+        /// Why the watchpoint is not supported.
+        reason: &'static str,
+    },
+    /// Software breakpoints are not supported for the {0:?} instruction set
+    SoftwareBreakpointsUnsupported(InstructionSet),
+    /// The break instruction could not be written to address {0:#010x}
+    NotWritable(u64),
+    /// Address {0:#010x} is in flash, where software breakpoints can only be set and cleared through the session
+    InFlash(u64),
+    /// Reprogramming the flash for a software breakpoint failed
+    FlashPatch(#[source] Box<FlashError>),
 }
 
 impl From<ArmError> for Error {
@@ -50,6 +50,11 @@ impl FlashPage {
     pub(super) fn data_mut(&mut self) -> &mut [u8] {
         &mut self.data
     }
+
+    /// Returns the address range of the page.
+    pub(super) fn address_range(&self) -> Range<u64> {
+        self.address..self.address + self.data.len() as u64
+    }
 }
 
 /// The description of a sector in flash.
@@ -69,6 +74,11 @@ impl FlashSector {
     pub fn size(&self) -> u64 {
         self.size
     }
+
+    /// Returns the address range of the sector.
+    pub(super) fn address_range(&self) -> Range<u64> {
+        self.address..self.address + self.size
+    }
 }
 
 /// A struct to hold all the information about one region
@@ -138,6 +148,126 @@ impl FlashLayout {
     pub fn data_blocks(&self) -> &[FlashDataBlockSpan] {
         &self.data_blocks
     }
+
+    /// Returns the data that is programmed into the given sector, without the fills.
+    ///
+    /// Contiguous data of neighbouring pages is merged into a single chunk.
+    pub(super) fn sector_data(&self, sector: &FlashSector) -> Vec<(u64, Vec<u8>)> {
+        let sector_range = sector.address_range();
+        let mut chunks: Vec<(u64, Vec<u8>)> = vec![];
+
+        let mut push = |page: &FlashPage, range: Range<u64>| {
+            if range.is_empty() {
+                return;
+            }
+            let data = &page.data[(range.start - page.address) as usize..]
+                [..(range.end - range.start) as usize];
+            match chunks.last_mut() {
+                Some((address, chunk)) if *address + chunk.len() as u64 == range.start => {
+                    chunk.extend_from_slice(data)
+                }
+                _ => chunks.push((range.start, data.to_vec())),
+            }
+        };
+
+        for (idx, page) in self.pages.iter().enumerate() {
+            let page_range = page.address_range();
+            let start = page_range.start.max(sector_range.start);
+            let end = page_range.end.min(sector_range.end);
+            if start >= end {
+                continue;
+            }
+
+            let mut cursor = start;
+            for fill in self.fills.iter().filter(|fill| fill.page_index == idx) {
+                let fill_start = fill.address.clamp(start, end);
+                let fill_end = (fill.address + fill.size).clamp(start, end);
+                push(page, cursor..fill_start.max(cursor));
+                cursor = cursor.max(fill_end);
+            }
+            push(page, cursor..end);
+        }
+
+        chunks
+    }
+
+    /// Removes the sectors for which `keep` returns `false`, together with their pages and
+    /// fills.
+    ///
+    /// A page that overlaps a kept sector is kept, and so is every sector that a kept page
+    /// overlaps, because a page can't be programmed without erasing all of its sectors.
+    pub(super) fn retain_sectors(&mut self, keep: impl FnMut(&FlashSector) -> bool) {
+        let mut keep_sector = self.sectors.iter().map(keep).collect::<Vec<_>>();
+        // Pages outside of any sector are never skipped.
+        let mut keep_page = self
+            .pages
+            .iter()
+            .map(|page| {
+                let range = page.address_range();
+                !self
+                    .sectors
+                    .iter()
+                    .any(|sector| sector.address_range().intersects_range(&range))
+            })
+            .collect::<Vec<_>>();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (page, kept) in self.pages.iter().zip(keep_page.iter_mut()) {
+                let range = page.address_range();
+                if !*kept
+                    && self
+                        .sectors
+                        .iter()
+                        .zip(&keep_sector)
+                        .any(|(sector, &k)| k && sector.address_range().intersects_range(&range))
+                {
+                    *kept = true;
+                    changed = true;
+                }
+            }
+            for (sector, kept) in self.sectors.iter().zip(keep_sector.iter_mut()) {
+                let range = sector.address_range();
+                if !*kept
+                    && self
+                        .pages
+                        .iter()
+                        .zip(&keep_page)
+                        .any(|(page, &k)| k && page.address_range().intersects_range(&range))
+                {
+                    *kept = true;
+                    changed = true;
+                }
+            }
+        }
+
+        let mut kept = keep_sector.iter();
+        self.sectors.retain(|_| *kept.next().unwrap());
+
+        let mut new_index = Vec::with_capacity(self.pages.len());
+        let mut count = 0;
+        for &kept in &keep_page {
+            new_index.push(count);
+            count += kept as usize;
+        }
+        self.fills.retain_mut(|fill| {
+            let kept = keep_page[fill.page_index];
+            fill.page_index = new_index[fill.page_index];
+            kept
+        });
+
+        let mut kept = keep_page.iter();
+        self.pages.retain(|_| *kept.next().unwrap());
+
+        let pages = &self.pages;
+        self.data_blocks.retain(|block| {
+            let range = block.address..block.address + block.size;
+            pages
+                .iter()
+                .any(|page| page.address_range().intersects_range(&range))
+        });
+    }
 }
 
 /// A block of data that is to be written to flash.
@@ -217,6 +347,43 @@ impl FlashBuilder {
         Ok(())
     }
 
+    /// Overwrites staged data.
+    ///
+    /// Returns `false` and leaves the data untouched if `address..address + data.len()` is not
+    /// fully covered by staged data.
+    pub(crate) fn patch_data(&mut self, address: u64, data: &[u8]) -> bool {
+        let range = address..address + data.len() as u64;
+        if !self.covers_range(&range) {
+            return false;
+        }
+
+        for (&chunk_addr, chunk) in self.data.range_mut(..range.end).rev() {
+            let chunk_end = chunk_addr + chunk.len() as u64;
+            if chunk_end <= range.start {
+                break;
+            }
+
+            let start = chunk_addr.max(range.start);
+            let end = chunk_end.min(range.end);
+            chunk[(start - chunk_addr) as usize..(end - chunk_addr) as usize]
+                .copy_from_slice(&data[(start - address) as usize..(end - address) as usize]);
+        }
+
+        true
+    }
+
+    /// Check whether every address of the range has staged data.
+    pub(crate) fn covers_range(&self, range: &Range<u64>) -> bool {
+        let mut next = range.start;
+        for (addr, data) in self.data_in_range(range) {
+            if addr != next {
+                return false;
+            }
+            next = addr + data.len() as u64;
+        }
+        next >= range.end
+    }
+
     /// Check whether there is staged data for a given address range.
     pub(crate) fn has_data_in_range(&self, range: &Range<u64>) -> bool {
         self.data_in_range(range).next().is_some()
@@ -1145,4 +1312,92 @@ mod tests {
             }
         )
     }
+
+    #[test]
+    fn patch_data_across_chunks() {
+        let mut builder = FlashBuilder::new();
+        builder.add_data(0x100, &[0; 4]).unwrap();
+        builder.add_data(0x108, &[0; 4]).unwrap();
+        builder.add_data(0x104, &[0; 4]).unwrap();
+
+        assert!(builder.patch_data(0x102, &[1, 2, 3, 4, 5, 6, 7, 8]));
+        let data = builder
+            .data_in_range(&(0x100..0x10C))
+            .flat_map(|(_, data)| data.iter().copied())
+            .collect::<Vec<_>>();
+        assert_eq!(data, [0, 0, 1, 2, 3, 4, 5, 6, 7, 8, 0, 0]);
+
+        // Partially covered ranges are not patched.
+        assert!(!builder.patch_data(0x10A, &[9; 4]));
+        assert!(!builder.covers_range(&(0x0FF..0x101)));
+    }
+
+    #[test]
+    fn sector_data_skips_fills() {
+        let (region, flash_algorithm) = assemble_demo_flash1();
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.add_data(0x0010, &[1; 0x0400]).unwrap();
+        flash_builder.add_data(0x0500, &[2; 0x10]).unwrap();
+        let flash_layout = flash_builder
+            .build_sectors_and_pages(&region, &flash_algorithm, true)
+            .unwrap();
+
+        assert_eq!(
+            flash_layout.sector_data(&flash_layout.sectors[0]),
+            [(0x0010, vec![1; 0x0400]), (0x0500, vec![2; 0x10])]
+        );
+    }
+
+    #[test]
+    fn retain_sectors_removes_pages_and_fills() {
+        let (region, flash_algorithm) = assemble_demo_flash1();
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.add_data(0x0000, &[1; 0x10]).unwrap();
+        flash_builder.add_data(0x1000, &[2; 0x10]).unwrap();
+        let mut flash_layout = flash_builder
+            .build_sectors_and_pages(&region, &flash_algorithm, true)
+            .unwrap();
+
+        flash_layout.retain_sectors(|sector| sector.address == 0x1000);
+
+        assert_eq!(flash_layout.sectors.len(), 1);
+        assert_eq!(flash_layout.sectors[0].address, 0x1000);
+        assert_eq!(flash_layout.pages.len(), 4);
+        assert_eq!(flash_layout.pages[0].address, 0x1000);
+        assert_eq!(flash_layout.fills[0].page_index, 0);
+        assert!(
+            flash_layout
+                .fills
+                .iter()
+                .all(|fill| fill.address >= 0x1000 && fill.page_index < 4)
+        );
+        assert_eq!(
+            flash_layout.data_blocks,
+            [FlashDataBlockSpan {
+                address: 0x1000,
+                size: 0x10,
+            }]
+        );
+    }
+
+    #[test]
+    fn retain_sectors_keeps_sectors_of_kept_pages() {
+        // 128 byte sectors with 1024 byte pages.
+        let (region, flash_algorithm) = assemble_demo_flash2();
+        let mut flash_builder = FlashBuilder::new();
+        flash_builder.add_data(0x0000, &[1; 0x0400]).unwrap();
+        let mut flash_layout = flash_builder
+            .build_sectors_and_pages(&region, &flash_algorithm, true)
+            .unwrap();
+        assert_eq!(flash_layout.sectors.len(), 8);
+
+        flash_layout.retain_sectors(|sector| sector.address == 0x0100);
+        assert_eq!(flash_layout.sectors.len(), 8);
+        assert_eq!(flash_layout.pages.len(), 1);
+
+        flash_layout.retain_sectors(|_| false);
+        assert!(flash_layout.sectors.is_empty());
+        assert!(flash_layout.pages.is_empty());
+        assert!(flash_layout.data_blocks.is_empty());
+    }
 }
@@ -0,0 +1,70 @@
+//! CRC32 checksums of the flash contents, computed on the target.
+//!
+//! The checksum is the standard CRC32 (reflected polynomial `0xEDB88320`, as used by zlib), so
+//! the digest computed on the target can be compared with the one of the image data.
+
+/// A position independent Thumb routine `crc32(address, length, crc) -> crc` for ARMv6-M and
+/// newer cores.
+///
+/// `crc` is the result of a previous call, or 0, so a checksum can be computed over several
+/// chunks. The routine doesn't use the stack, and must be loaded at a word aligned address.
+///
+/// ```text
+///         adds  r1, r0, r1        @ r1 = end address
+///         mov   r12, r1
+///         mvns  r2, r2
+///         ldr   r3, poly
+/// next_byte:
+///         cmp   r0, r12
+///         bhs   done
+///         ldrb  r1, [r0]
+///         adds  r0, #1
+///         eors  r2, r1
+///         movs  r1, #8
+/// next_bit:
+///         lsrs  r2, r2, #1
+///         bcc   no_xor
+///         eors  r2, r3
+/// no_xor:
+///         subs  r1, #1
+///         bne   next_bit
+///         b     next_byte
+/// done:
+///         mvns  r0, r2
+///         bx    lr
+///         .p2align 2
+/// poly:   .word 0xEDB88320
+/// ```
+pub(super) const CRC32_THUMB: [u8; 40] = [
+    0x41, 0x18, 0x8c, 0x46, 0xd2, 0x43, 0x07, 0x4b, 0x60, 0x45, 0x09, 0xd2, 0x01, 0x78, 0x40, 0x1c,
+    0x4a, 0x40, 0x08, 0x21, 0x52, 0x08, 0x00, 0xd3, 0x5a, 0x40, 0x49, 0x1e, 0xfa, 0xd1, 0xf3, 0xe7,
+    0xd0, 0x43, 0x70, 0x47, 0x20, 0x83, 0xb8, 0xed,
+];
+
+/// Computes the same checksum as [`CRC32_THUMB`] on the host.
+pub(super) fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let xor = if crc & 1 != 0 { 0xEDB8_8320 } else { 0 };
+            crc = (crc >> 1) ^ xor;
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(0, b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn crc32_can_be_chained() {
+        assert_eq!(crc32(crc32(0, b"12345"), b"6789"), crc32(0, b"123456789"));
+    }
+}
@@ -0,0 +1,176 @@
+//! Reading and programming of configuration regions, like option bytes, OTP memory and fuses.
+
+use crate::config::{ConfigField, ConfigRegion, ConfigRegionKind};
+use crate::flashing::{DownloadOptions, FlashError, FlashProgress};
+use crate::{MemoryInterface, Session};
+
+/// A change of a single field of a [`ConfigRegion`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFieldChange {
+    /// The name of the field.
+    pub name: String,
+    /// The current value of the field.
+    pub old: u64,
+    /// The new value of the field.
+    pub new: u64,
+    /// True if the change can't be undone.
+    pub irreversible: bool,
+}
+
+/// The contents of a [`ConfigRegion`].
+///
+/// Read the current contents from the target with [`ConfigRegionData::read`], change fields with
+/// [`ConfigRegionData::set`] and program the result with [`program_config_region`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigRegionData {
+    region: ConfigRegion,
+    data: Vec<u8>,
+}
+
+impl ConfigRegionData {
+    /// Creates the contents of a region from raw bytes.
+    ///
+    /// Returns `None` if the length of `data` doesn't match the size of the region.
+    pub fn new(region: ConfigRegion, data: Vec<u8>) -> Option<Self> {
+        (data.len() as u64 == region.range.end - region.range.start)
+            .then_some(Self { region, data })
+    }
+
+    /// Reads the contents of the region from the target.
+    pub fn read(session: &mut Session, region: &ConfigRegion) -> Result<Self, FlashError> {
+        let core_index = region
+            .cores
+            .first()
+            .and_then(|core| session.target().core_index_by_name(core))
+            .ok_or_else(|| FlashError::NoNvmCoreAccess(region.to_nvm_region()))?;
+
+        let mut data = vec![0; (region.range.end - region.range.start) as usize];
+        let mut core = session.core(core_index).map_err(FlashError::Core)?;
+        core.read(region.range.start, &mut data)
+            .map_err(FlashError::Core)?;
+
+        Ok(Self {
+            region: region.clone(),
+            data,
+        })
+    }
+
+    /// Returns the region the contents belong to.
+    pub fn region(&self) -> &ConfigRegion {
+        &self.region
+    }
+
+    /// Returns the raw contents of the region.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns all fields of the region with their values.
+    pub fn fields(&self) -> impl Iterator<Item = (&ConfigField, u64)> {
+        self.region
+            .fields
+            .iter()
+            .map(|field| (field, field.extract(&self.data)))
+    }
+
+    /// Returns the value of the field with the given name.
+    pub fn get(&self, name: &str) -> Result<u64, FlashError> {
+        Ok(self.field(name)?.extract(&self.data))
+    }
+
+    /// Sets the field with the given name.
+    pub fn set(&mut self, name: &str, value: u64) -> Result<(), FlashError> {
+        let field = self.field(name)?.clone();
+        if value > field.max_value() {
+            return Err(FlashError::ConfigValueOutOfRange {
+                name: field.name,
+                value,
+            });
+        }
+
+        field.insert(&mut self.data, value);
+        Ok(())
+    }
+
+    /// Returns the fields that differ between `self` and `new`.
+    pub fn diff(&self, new: &ConfigRegionData) -> Vec<ConfigFieldChange> {
+        self.region
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let old = field.extract(&self.data);
+                let new = field.extract(&new.data);
+                (old != new).then(|| ConfigFieldChange {
+                    name: field.name.clone(),
+                    old,
+                    new,
+                    irreversible: self.region.is_irreversible_change(field, old, new),
+                })
+            })
+            .collect()
+    }
+
+    fn field(&self, name: &str) -> Result<&ConfigField, FlashError> {
+        self.region
+            .field(name)
+            .ok_or_else(|| FlashError::UnknownConfigField {
+                name: name.to_string(),
+            })
+    }
+}
+
+/// Programs the contents of a configuration region, with the flash algorithm that covers the
+/// region.
+///
+/// The region is read from the target first, and only programmed if a field changes. Unless
+/// `allow_irreversible` is set, changes that can't be undone are rejected with
+/// [`FlashError::IrreversibleConfigChange`]. Returns the changed fields.
+///
+/// Many devices only apply the new configuration after a reset.
+pub fn program_config_region(
+    session: &mut Session,
+    new: &ConfigRegionData,
+    allow_irreversible: bool,
+    progress: FlashProgress<'_>,
+) -> Result<Vec<ConfigFieldChange>, FlashError> {
+    let current = ConfigRegionData::read(session, &new.region)?;
+    let changes = current.diff(new);
+    if changes.is_empty() {
+        tracing::info!("Configuration region is up to date");
+        return Ok(changes);
+    }
+
+    if new.region.kind == ConfigRegionKind::Fuses {
+        // Fuses can only be blown, so every bit that is set must stay set.
+        for field in &new.region.fields {
+            let old = field.extract(&current.data);
+            if old & !field.extract(&new.data) != 0 {
+                return Err(FlashError::ConfigBitsCannotBeCleared {
+                    name: field.name.clone(),
+                });
+            }
+        }
+    }
+
+    let irreversible = changes
+        .iter()
+        .filter(|change| change.irreversible)
+        .map(|change| change.name.clone())
+        .collect::<Vec<_>>();
+    if !irreversible.is_empty() && !allow_irreversible {
+        return Err(FlashError::IrreversibleConfigChange {
+            fields: irreversible,
+        });
+    }
+
+    let mut loader = session.target().flash_loader();
+    loader.allow_config_regions();
+    loader.add_data(new.region.range.start, &new.data)?;
+
+    let mut options = DownloadOptions::new();
+    options.progress = progress;
+    options.keep_unwritten_bytes = true;
+    loader.commit(session, options)?;
+
+    Ok(changes)
+}
@@ -9,6 +9,7 @@ use crate::session::Session;
 
 /// Extended options for flashing a binary file.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+#[serde(default)]
 #[cfg(feature = "builtin-formats")]
 pub struct BinOptions {
     /// The address in memory where the binary will be put at.
@@ -19,6 +20,7 @@ pub struct BinOptions {
 
 /// Extended options for flashing an ELF file.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+#[serde(default)]
 #[cfg(feature = "builtin-formats")]
 pub struct ElfOptions {
     /// Sections to skip flashing
@@ -94,6 +96,20 @@ fn print_instr_sets(instr_sets: &[InstructionSet]) -> String {
         .join(", ")
 }
 
+/// How [`DownloadOptions::preverify`] finds the sectors that are already up to date.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PreverifyStrategy {
+    /// Read the flash contents back over the probe.
+    #[default]
+    ReadBack,
+    /// Compute a CRC32 of every sector with a small routine running in target RAM, so only the
+    /// checksums are transferred over the probe.
+    ///
+    /// Falls back to [`PreverifyStrategy::ReadBack`] if the routine can't run on the target,
+    /// e.g. because the flash is not memory mapped.
+    Checksum,
+}
+
 /// Options for downloading a file onto a target chip.
 ///
 /// This struct should be created using the [`DownloadOptions::default()`] function, and can be configured by setting
@@ -127,8 +143,11 @@ pub struct DownloadOptions<'p> {
     /// If the chip was pre-erased with external erasers, this flag can set to true to skip erasing
     /// It may be useful for mass production.
     pub skip_erase: bool,
-    /// Before flashing, read back the flash contents to skip up-to-date regions.
+    /// Before flashing, compare the flash contents with the data to be flashed, and only erase and
+    /// program the sectors that differ. This is ignored if `do_chip_erase` is set.
     pub preverify: bool,
+    /// How the flash contents are compared with the data if `preverify` is set.
+    pub preverify_strategy: PreverifyStrategy,
     /// After flashing, read back all the flashed data to verify it has been written correctly.
     pub verify: bool,
     /// Disable double buffering when loading flash.
@@ -138,6 +157,11 @@ pub struct DownloadOptions<'p> {
     pub preferred_algos: Vec<String>,
     /// RAM chunk size relevant for loading into RAM. [None] disables the chunking.
     pub ram_chunk_size: Option<u64>,
+    /// Halt the cores running the flash algorithms instead of resetting them, and restore their
+    /// registers and the RAM used by the flash algorithms afterwards.
+    ///
+    /// This allows patching the flash without disturbing the program being debugged.
+    pub preserve_core_state: bool,
 }
 
 impl DownloadOptions<'_> {
@@ -220,4 +220,66 @@ pub enum FlashError {
         /// The operation that caused the stack overflow.
         operation: &'static str,
     },
+    /// The configuration region has no field with the given name.
+    #[error("The configuration region has no field called {name}.")]
+    UnknownConfigField {
+        /// The name of the field.
+        name: String,
+    },
+    /// The value does not fit into the configuration field.
+    #[error("The value {value:#x} does not fit into the field {name}.")]
+    ConfigValueOutOfRange {
+        /// The name of the field.
+        name: String,
+        /// The value that was given.
+        value: u64,
+    },
+    /// The change of the configuration can't be undone, and was not allowed.
+    #[error("Changing {} can't be undone, and irreversible changes were not allowed.", fields.join(", "))]
+    IrreversibleConfigChange {
+        /// The names of the fields with irreversible changes.
+        fields: Vec<String>,
+    },
+    /// The addresses are not fully covered by data.
+    #[error("The addresses {range:#010x?} are not fully covered by the image data.")]
+    MissingData {
+        /// The address range.
+        range: Range<u64>,
+    },
+    /// Injected data has to fill its placeholder exactly.
+    #[error(
+        "The injected data has {size} bytes, but the placeholder at {placeholder:#010x?} has {} bytes.",
+        placeholder.end - placeholder.start
+    )]
+    PlaceholderSizeMismatch {
+        /// The address range of the placeholder.
+        placeholder: Range<u64>,
+        /// The size of the injected data.
+        size: usize,
+    },
+    /// A checksum can't be stored inside the data it covers.
+    #[error(
+        "The checksum at {address:#010x} would be stored inside the data it covers ({range:#010x?})."
+    )]
+    ChecksumInsideRange {
+        /// The address of the checksum.
+        address: u64,
+        /// The address range covered by the checksum.
+        range: Range<u64>,
+    },
+    /// Fuses can only be set, not cleared.
+    #[error("The field {name} would clear fuses that are already set.")]
+    ConfigBitsCannotBeCleared {
+        /// The name of the field.
+        name: String,
+    },
+    /// The SFDP tables of an external flash are invalid.
+    #[error("Failed to read the SFDP tables of the external flash.")]
+    Sfdp(#[from] super::SfdpError),
+    /// probe-rs does not know the SPI controller of the external flash of the target.
+    #[error("The SPI controller for the external flash of {target} is not known.")]
+    NoSpiNorController {
+        /// The name of the target.
+        target: String,
+    },
 }
@@ -6,6 +6,7 @@ use probe_rs_target::{
 };
 use std::collections::BTreeMap;
 use std::mem::size_of_val;
+use std::ops::Range;
 
 /// A flash algorithm, which has been assembled for a specific
 /// chip.
@@ -115,6 +116,23 @@ impl FlashAlgorithm {
         })
     }
 
+    /// Returns the RAM ranges the algorithm uses: its code and data, its stack and its page buffers.
+    pub fn ram_ranges(&self) -> Vec<Range<u64>> {
+        let page_size = self.flash_properties.page_size as u64;
+
+        let mut ranges = vec![
+            self.load_address..self.load_address + (self.instructions.len() * 4) as u64,
+            self.stack_top - self.stack_size..self.stack_top,
+        ];
+        ranges.extend(
+            self.page_buffers
+                .iter()
+                .map(|&buffer| buffer..buffer + page_size),
+        );
+
+        ranges
+    }
+
     /// Iterate over all the sectors of the flash.
     pub fn iter_sectors(&self) -> impl Iterator<Item = SectorInfo> + '_ {
         let props = &self.flash_properties;
@@ -1,17 +1,24 @@
-use probe_rs_target::{RawFlashAlgorithm, TransferEncoding};
+use probe_rs_target::{MemoryRange, MemoryRegion, RawFlashAlgorithm, TransferEncoding};
 use tracing::Level;
 use zerocopy::IntoBytes;
 
-use super::{FlashAlgorithm, FlashBuilder, FlashError, FlashPage, FlashProgress};
+use super::checksum::{self, CRC32_THUMB};
+use super::{
+    FlashAlgorithm, FlashBuilder, FlashError, FlashLoader, FlashPage, FlashProgress,
+    PreverifyStrategy,
+};
 use crate::config::NvmRegion;
 use crate::error::Error;
 use crate::flashing::encoder::FlashEncoder;
 use crate::flashing::{FlashLayout, FlashSector};
 use crate::memory::MemoryInterface;
 use crate::rtt::{Rtt, ScanRegion};
-use crate::{Core, InstructionSet, RegisterValue, core::CoreRegisters, session::Session};
+use crate::{
+    Core, InstructionSet, RegisterId, RegisterValue, core::CoreRegisters, session::Session,
+};
 use crate::{CoreStatus, Target};
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::marker::PhantomData;
 use std::{
     fmt::Debug,
@@ -145,11 +152,114 @@ pub struct Flasher {
     pub(super) loaded: bool,
     pub(super) regions: Vec<LoadedRegion>,
     pub(super) read_flasher_rtt: bool,
+    pub(super) preserve_core_state: bool,
 }
 
 /// The byte used to fill the stack when checking for stack overflows.
 const STACK_FILL_BYTE: u8 = 0x56;
 
+/// The registers and the RAM contents of the cores running flash algorithms, saved to be restored
+/// after flashing.
+pub(super) struct SavedCoreState {
+    cores: Vec<SavedCore>,
+}
+
+struct SavedCore {
+    core_index: usize,
+    registers: Vec<(RegisterId, RegisterValue)>,
+    memory: Vec<(u64, Vec<u8>)>,
+}
+
+impl SavedCoreState {
+    /// Halts the cores running the flash algorithms, and saves their registers and the RAM
+    /// the algorithms will use.
+    pub(super) fn save(session: &mut Session, flashers: &[Flasher]) -> Result<Self, FlashError> {
+        let mut cores = Vec::<SavedCore>::new();
+
+        for flasher in flashers {
+            let mut core = session.core(flasher.core_index).map_err(FlashError::Core)?;
+
+            let saved = match cores
+                .iter()
+                .position(|c| c.core_index == flasher.core_index)
+            {
+                Some(saved) => saved,
+                None => {
+                    cores.push(SavedCore::save_registers(&mut core)?);
+                    cores.len() - 1
+                }
+            };
+            cores[saved].save_memory(&mut core, &flasher.flash_algorithm)?;
+        }
+
+        Ok(Self { cores })
+    }
+
+    /// Writes the saved RAM contents and registers back. The cores are left halted.
+    pub(super) fn restore(self, session: &mut Session) -> Result<(), FlashError> {
+        for saved in self.cores {
+            let mut core = session.core(saved.core_index).map_err(FlashError::Core)?;
+            saved.restore(&mut core)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl SavedCore {
+    /// Halts `core`, and saves its registers.
+    fn save_registers(core: &mut Core<'_>) -> Result<Self, FlashError> {
+        core.halt(Duration::from_millis(500))
+            .map_err(FlashError::Core)?;
+
+        let mut registers = Vec::new();
+        for register in core.registers().all_registers() {
+            // Not all registers are accessible, e.g. FPU registers with the FPU disabled.
+            match core.read_core_reg::<RegisterValue>(register.id()) {
+                Ok(value) => registers.push((register.id(), value)),
+                Err(error) => {
+                    tracing::debug!("Not saving register {}: {error}", register.name())
+                }
+            }
+        }
+
+        Ok(Self {
+            core_index: core.id(),
+            registers,
+            memory: Vec::new(),
+        })
+    }
+
+    /// Saves the RAM `algorithm` will use.
+    fn save_memory(
+        &mut self,
+        core: &mut Core<'_>,
+        algorithm: &FlashAlgorithm,
+    ) -> Result<(), FlashError> {
+        for range in algorithm.ram_ranges() {
+            let mut data = vec![0; (range.end - range.start) as usize];
+            core.read(range.start, &mut data)
+                .map_err(FlashError::Core)?;
+            self.memory.push((range.start, data));
+        }
+
+        Ok(())
+    }
+
+    fn restore(self, core: &mut Core<'_>) -> Result<(), FlashError> {
+        for (address, data) in &self.memory {
+            core.write(*address, data).map_err(FlashError::Core)?;
+        }
+        for (register, value) in self.registers {
+            if let Err(error) = core.write_core_reg(register, value) {
+                tracing::debug!("Not restoring register {register:?}: {error}");
+            }
+        }
+
+        Ok(())
+    }
+}
+
 impl Flasher {
     /// Creates a new Flasher object.
     pub fn new(
@@ -169,6 +279,7 @@ impl Flasher {
             loaded: false,
             regions: Vec::new(),
             read_flasher_rtt: false,
+            preserve_core_state: false,
         })
     }
 
@@ -190,16 +301,26 @@ impl Flasher {
     }
 
     fn load(&mut self, session: &mut Session) -> Result<(), FlashError> {
-        tracing::debug!("Initializing the flash algorithm.");
-        let algo = &self.flash_algorithm;
-
         // Attach to memory and core.
         let mut core = session.core(self.core_index).map_err(FlashError::Core)?;
 
-        // TODO: we probably want a full system reset here to make sure peripherals don't interfere.
-        tracing::debug!("Reset and halt core {}", self.core_index);
-        core.reset_and_halt(Duration::from_millis(500))
-            .map_err(FlashError::ResetAndHalt)?;
+        self.load_with_core(&mut core)
+    }
+
+    fn load_with_core(&self, core: &mut Core<'_>) -> Result<(), FlashError> {
+        tracing::debug!("Initializing the flash algorithm.");
+        let algo = &self.flash_algorithm;
+
+        if self.preserve_core_state {
+            tracing::debug!("Halt core {}", self.core_index);
+            core.halt(Duration::from_millis(500))
+                .map_err(FlashError::Core)?;
+        } else {
+            // TODO: we probably want a full system reset here to make sure peripherals don't interfere.
+            tracing::debug!("Reset and halt core {}", self.core_index);
+            core.reset_and_halt(Duration::from_millis(500))
+                .map_err(FlashError::ResetAndHalt)?;
+        }
 
         // TODO: Possible special preparation of the target such as enabling faster clocks for the flash e.g.
 
@@ -274,8 +395,18 @@ impl Flasher {
         self.ensure_loaded(session)?;
 
         // Attach to memory and core.
-        let mut core = session.core(self.core_index).map_err(FlashError::Core)?;
+        let core = session.core(self.core_index).map_err(FlashError::Core)?;
 
+        self.init_with_core(core, progress, clock)
+    }
+
+    /// Prepares the loaded flashing algorithm on `core`, for the given [`Operation`].
+    fn init_with_core<'s, 'p, O: Operation>(
+        &'s mut self,
+        mut core: Core<'s>,
+        progress: &'s mut FlashProgress<'p>,
+        clock: Option<u32>,
+    ) -> Result<(ActiveFlasher<'s, 'p, O>, &'s mut [LoadedRegion]), FlashError> {
         let instruction_set = core.instruction_set().map_err(FlashError::Core)?;
 
         tracing::debug!("Preparing Flasher for operation {}", O::NAME);
@@ -624,6 +755,133 @@ impl Flasher {
         }
     }
 
+    /// Compares the flash contents with the data to be flashed, and removes the sectors that are
+    /// already up to date, so they are neither erased nor programmed.
+    ///
+    /// Fills are ignored, the unwritten bytes of a sector are allowed to differ.
+    pub(super) fn skip_up_to_date_sectors(
+        &mut self,
+        session: &mut Session,
+        progress: &mut FlashProgress<'_>,
+        strategy: PreverifyStrategy,
+    ) -> Result<(), FlashError> {
+        progress.started_verifying();
+
+        let result = self.find_up_to_date_sectors(session, progress, strategy);
+
+        match result.is_ok() {
+            true => progress.finished_verifying(),
+            false => progress.failed_verifying(),
+        }
+
+        for (region, up_to_date) in self.regions.iter_mut().zip(result?) {
+            let mut up_to_date = up_to_date.into_iter();
+            let layout = region.data.layout_mut();
+            let sectors = layout.sectors().len();
+            layout.retain_sectors(|_| !up_to_date.next().unwrap_or(false));
+
+            tracing::debug!(
+                "{} of {} sectors in {:?} are up to date",
+                sectors - layout.sectors().len(),
+                sectors,
+                region.region.name
+            );
+        }
+
+        self.regions.retain(|region| {
+            let layout = region.flash_layout();
+            !layout.sectors().is_empty() || !layout.pages().is_empty()
+        });
+
+        Ok(())
+    }
+
+    /// Returns, for every sector of every region, whether the sector is up to date.
+    fn find_up_to_date_sectors(
+        &mut self,
+        session: &mut Session,
+        progress: &mut FlashProgress<'_>,
+        strategy: PreverifyStrategy,
+    ) -> Result<Vec<Vec<bool>>, FlashError> {
+        fn compare_sectors(
+            regions: &[LoadedRegion],
+            progress: &mut FlashProgress<'_>,
+            mut matches: impl FnMut(&[(u64, Vec<u8>)]) -> Result<bool, FlashError>,
+        ) -> Result<Vec<Vec<bool>>, FlashError> {
+            let mut result = Vec::with_capacity(regions.len());
+            for region in regions {
+                let layout = region.data.layout();
+                let mut up_to_date = Vec::with_capacity(layout.sectors().len());
+                for sector in layout.sectors() {
+                    let start = Instant::now();
+                    up_to_date.push(matches(&layout.sector_data(sector))?);
+                    progress.page_verified(sector.size(), start.elapsed());
+                }
+                result.push(up_to_date);
+            }
+            Ok(result)
+        }
+
+        fn read_back_matches(
+            chunks: &[(u64, Vec<u8>)],
+            mut read: impl FnMut(u64, &mut [u8]) -> Result<(), FlashError>,
+        ) -> Result<bool, FlashError> {
+            for (address, data) in chunks {
+                let mut read_back = vec![0; data.len()];
+                read(*address, &mut read_back)?;
+                if *data != read_back {
+                    return Ok(false);
+                }
+            }
+            Ok(true)
+        }
+
+        let algo = &self.flash_algorithm;
+        let checksum_supported =
+            algo.pc_read.is_none() && algo.flash_properties.page_size as usize >= CRC32_THUMB.len();
+
+        if strategy == PreverifyStrategy::Checksum && checksum_supported {
+            self.run_verify(session, &mut FlashProgress::empty(), |active, data| {
+                if active.instruction_set != InstructionSet::Thumb2 {
+                    tracing::debug!("Preverify by reading back flash contents");
+                    return compare_sectors(data, progress, |chunks| {
+                        read_back_matches(chunks, |address, data| active.read_flash(address, data))
+                    });
+                }
+
+                tracing::debug!("Preverify using on-target CRC32");
+                let routine = active.load_page_buffer(&CRC32_THUMB, 0)?;
+                compare_sectors(data, progress, |chunks| {
+                    let mut crc = 0;
+                    for (address, data) in chunks {
+                        crc = active.flash_crc32(routine, *address, data.len() as u64, crc)?;
+                    }
+                    let expected = chunks
+                        .iter()
+                        .fold(0, |crc, (_, data)| checksum::crc32(crc, data));
+                    Ok(crc == expected)
+                })
+            })
+        } else if self.flash_algorithm.pc_read.is_some() {
+            tracing::debug!("Preverify by reading back flash contents");
+            self.run_verify(session, &mut FlashProgress::empty(), |active, data| {
+                compare_sectors(data, progress, |chunks| {
+                    read_back_matches(chunks, |address, data| active.read_flash(address, data))
+                })
+            })
+        } else {
+            tracing::debug!("Preverify by reading back flash contents");
+            // Not using a flash algorithm function, so there's no need to go
+            // through ActiveFlasher.
+            let mut core = session.core(self.core_index).map_err(FlashError::Core)?;
+            compare_sectors(&self.regions, progress, |chunks| {
+                read_back_matches(chunks, |address, data| {
+                    core.read(address, data).map_err(FlashError::Core)
+                })
+            })
+        }
+    }
+
     /// Perform an erase of all sectors given in `flash_layout`.
     fn sector_erase(
         &mut self,
@@ -789,6 +1047,170 @@ impl Flasher {
     pub(crate) fn read_rtt_output(&mut self, read: bool) {
         self.read_flasher_rtt = read;
     }
+
+    /// Writes `data` to `address` in `region` by reprogramming the containing sector, running the
+    /// flash algorithm on `core`. The registers of the core and the RAM used by the algorithm are
+    /// restored afterwards.
+    fn patch(
+        &mut self,
+        core: &mut Core<'_>,
+        region: NvmRegion,
+        patches: &[(u64, Vec<u8>)],
+    ) -> Result<(), FlashError> {
+        let mut saved = SavedCore::save_registers(core)?;
+        saved.save_memory(core, &self.flash_algorithm)?;
+
+        let result = self.patch_sectors(core, region, patches);
+        saved.restore(core)?;
+
+        result
+    }
+
+    /// Applies the patches to the sectors containing them. Every sector is read once, and only
+    /// erased and programmed if its contents change.
+    fn patch_sectors(
+        &mut self,
+        core: &mut Core<'_>,
+        region: NvmRegion,
+        patches: &[(u64, Vec<u8>)],
+    ) -> Result<(), FlashError> {
+        let mut sectors = BTreeMap::new();
+        for (address, data) in patches {
+            let range = *address..*address + data.len() as u64;
+            let sector = self
+                .flash_algorithm
+                .sector_info(*address)
+                .filter(|sector| sector.address_range().contains_range(&range))
+                .ok_or_else(|| FlashError::NoSuitableNvm {
+                    range,
+                    description_source: core.target().source().clone(),
+                })?;
+            sectors
+                .entry(sector.base_address)
+                .or_insert_with(|| (sector, Vec::new()))
+                .1
+                .push((*address, data.as_slice()));
+        }
+
+        self.load_with_core(core)?;
+        let mut progress = FlashProgress::empty();
+
+        let mut builder = FlashBuilder::new();
+        {
+            let (mut active, _) =
+                self.init_with_core::<Verify>(core.reborrow(), &mut progress, None)?;
+            for (sector, sector_patches) in sectors.values() {
+                let mut contents = vec![0; sector.size as usize];
+                active.read_flash(sector.base_address, &mut contents)?;
+
+                let mut patched = contents.clone();
+                for (address, data) in sector_patches {
+                    let offset = (address - sector.base_address) as usize;
+                    patched[offset..][..data.len()].copy_from_slice(data);
+                }
+
+                if patched != contents {
+                    builder.add_data(sector.base_address, &patched)?;
+                }
+            }
+            active.uninit()?;
+        }
+
+        if builder.data.is_empty() {
+            tracing::debug!("Flash already contains the patches");
+            return Ok(());
+        }
+        self.add_region(region, &builder, false)?;
+
+        {
+            let (mut active, regions) =
+                self.init_with_core::<Erase>(core.reborrow(), &mut progress, None)?;
+            for sector in regions[0].flash_layout().sectors() {
+                active.erase_sector(sector)?;
+            }
+            active.uninit()?;
+        }
+
+        let encoding = self.flash_algorithm.transfer_encoding;
+        {
+            let (mut active, regions) =
+                self.init_with_core::<Program>(core.reborrow(), &mut progress, None)?;
+            for page in regions[0].data.encoder(encoding, false).pages() {
+                active
+                    .program_page(page)
+                    .map_err(|error| FlashError::PageWrite {
+                        page_address: page.address(),
+                        source: Box::new(error),
+                    })?;
+            }
+            active.uninit()?;
+        }
+
+        {
+            let (mut active, _) =
+                self.init_with_core::<Verify>(core.reborrow(), &mut progress, None)?;
+            for (address, data) in patches {
+                let mut read_back = vec![0; data.len()];
+                active.read_flash(*address, &mut read_back)?;
+                if read_back != *data {
+                    return Err(FlashError::Verify);
+                }
+            }
+            active.uninit()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the patches, given as address and data, to the flash, keeping the rest of the flash
+/// sectors.
+///
+/// Each sector is reprogrammed at most once, and not at all if it already contains the patches.
+/// Unlike [`FlashLoader::commit`](super::FlashLoader::commit), this only needs the core running
+/// the flash algorithm, whose registers and RAM are preserved.
+pub(crate) fn patch_flash(
+    core: &mut Core<'_>,
+    patches: &[(u64, Vec<u8>)],
+) -> Result<(), FlashError> {
+    let target = core.target();
+    let core_name = &target.cores[core.id()].name;
+
+    let mut regions = Vec::<(NvmRegion, Vec<(u64, Vec<u8>)>)>::new();
+    for (address, data) in patches {
+        let range = *address..*address + data.len() as u64;
+        let region = core
+            .memory_regions()
+            .find_map(|region| match region {
+                MemoryRegion::Nvm(region) if region.range.contains_range(&range) => Some(region),
+                _ => None,
+            })
+            .ok_or_else(|| FlashError::NoSuitableNvm {
+                range,
+                description_source: target.source().clone(),
+            })?;
+
+        match regions.iter_mut().find(|(known, _)| known == region) {
+            Some((_, region_patches)) => region_patches.push((*address, data.clone())),
+            None => regions.push((region.clone(), vec![(*address, data.clone())])),
+        }
+    }
+
+    let mut flashers = Vec::with_capacity(regions.len());
+    for (region, region_patches) in regions {
+        let algorithm =
+            FlashLoader::get_flash_algorithm_for_region(&region, target, core_name, &[])?;
+
+        let mut flasher = Flasher::new(target, core.id(), algorithm)?;
+        flasher.preserve_core_state = true;
+        flashers.push((flasher, region, region_patches));
+    }
+
+    for (mut flasher, region, region_patches) in flashers {
+        flasher.patch(core, region, &region_patches)?;
+    }
+
+    Ok(())
 }
 
 struct Registers {
@@ -1481,3 +1903,27 @@ impl ActiveFlasher<'_, '_, Program> {
             })
     }
 }
+
+impl ActiveFlasher<'_, '_, Verify> {
+    /// Computes the CRC32 of `length` bytes of flash at `address` with the checksum routine that
+    /// was loaded to `routine`, continuing the checksum `crc` of the previous chunk.
+    fn flash_crc32(
+        &mut self,
+        routine: u64,
+        address: u64,
+        length: u64,
+        crc: u32,
+    ) -> Result<u32, FlashError> {
+        self.call_function_and_wait(
+            &Registers {
+                pc: routine,
+                r0: Some(address),
+                r1: Some(length),
+                r2: Some(crc as u64),
+                r3: None,
+            },
+            false,
+            Duration::from_secs(30),
+        )
+    }
+}
@@ -11,7 +11,9 @@ use std::time::{Duration, Instant};
 use yaml_serde::Value;
 
 use super::builder::FlashBuilder;
-use super::{DownloadOptions, FileDownloadError, FlashError, Flasher};
+use super::flasher::SavedCoreState;
+use super::manifest::ManifestLoaderFactory;
+use super::{DownloadOptions, FileDownloadError, FlashError, Flasher, SpiNorFlash};
 use crate::Target;
 use crate::flashing::progress::ProgressOperation;
 use crate::flashing::{FlashLayout, FlashProgress};
@@ -60,8 +62,7 @@ impl<T> ImageReader for T where T: Read + Seek {}
 
 /// A list of all known image formats.
 static LOADERS: LazyLock<RwLock<Vec<&'static dyn ImageFormat>>> = LazyLock::new(|| {
-    #[allow(unused_mut)]
-    let mut image_formats: Vec<&'static dyn ImageFormat> = vec![];
+    let mut image_formats: Vec<&'static dyn ImageFormat> = vec![&ManifestLoaderFactory];
 
     #[cfg(feature = "builtin-formats")]
     {
@@ -104,11 +105,12 @@ mod builtin {
     use ihex::Record;
     use probe_rs_target::MemoryRange;
     use std::io::SeekFrom;
+    use std::ops::Range;
     use yaml_serde::Value;
 
     use object::{
-        Endianness, Object, ObjectSection, elf::FileHeader32, elf::FileHeader64, elf::PT_LOAD,
-        read::elf::ElfFile, read::elf::FileHeader, read::elf::ProgramHeader,
+        Endianness, Object, ObjectSection, ObjectSymbol, elf::FileHeader32, elf::FileHeader64,
+        elf::PT_LOAD, read::elf::ElfFile, read::elf::FileHeader, read::elf::ProgramHeader,
     };
 
     use crate::flashing::loader::{FlashLoader, ImageFormat, ImageLoader, ImageReader};
@@ -273,6 +275,54 @@ mod builtin {
         }
     }
 
+    /// Returns the addresses an ELF symbol is programmed to.
+    ///
+    /// For a symbol that is copied to RAM at startup, like a variable in `.data`, these are the
+    /// addresses of its initial value. Returns `None` if the symbol doesn't exist, or is not part
+    /// of a loadable segment.
+    pub fn elf_symbol_load_range(
+        elf_data: &[u8],
+        name: &str,
+    ) -> Result<Option<Range<u64>>, FileDownloadError> {
+        match object::FileKind::parse(elf_data)? {
+            object::FileKind::Elf32 => {
+                symbol_load_range::<FileHeader32<Endianness>>(elf_data, name)
+            }
+            object::FileKind::Elf64 => {
+                symbol_load_range::<FileHeader64<Endianness>>(elf_data, name)
+            }
+            _ => Err(FileDownloadError::Object("Unsupported file type")),
+        }
+    }
+
+    fn symbol_load_range<T: FileHeader>(
+        elf_data: &[u8],
+        name: &str,
+    ) -> Result<Option<Range<u64>>, FileDownloadError> {
+        let binary = ElfFile::<T>::parse(elf_data)?;
+        let Some(symbol) = binary.symbols().find(|symbol| symbol.name() == Ok(name)) else {
+            return Ok(None);
+        };
+
+        let address = symbol.address();
+        let endian = binary.endian();
+        for segment in binary.elf_program_headers() {
+            if segment.p_type(endian) != PT_LOAD {
+                continue;
+            }
+
+            let p_vaddr: u64 = segment.p_vaddr(endian).into();
+            let p_filesz: u64 = segment.p_filesz(endian).into();
+            if (p_vaddr..p_vaddr + p_filesz).contains(&address) {
+                let p_paddr: u64 = segment.p_paddr(endian).into();
+                let start = p_paddr + (address - p_vaddr);
+                return Ok(Some(start..start + symbol.size()));
+            }
+        }
+
+        Ok(None)
+    }
+
     fn extract_from_elf_inner<'data, T: FileHeader>(
         elf_header: &T,
         binary: ElfFile<'_, T>,
@@ -472,6 +522,26 @@ pub enum BootInfo {
     Other,
 }
 
+/// Where [`FlashLoader::inject_data`] places the injected data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InjectionLocation {
+    /// The data is added at the address, next to the other data.
+    Address(u64),
+    /// The data replaces a placeholder of the image, like an ELF symbol, which occupies exactly
+    /// this address range.
+    Placeholder(Range<u64>),
+}
+
+impl InjectionLocation {
+    /// Returns the first address of the injected data.
+    pub fn address(&self) -> u64 {
+        match self {
+            InjectionLocation::Address(address) => *address,
+            InjectionLocation::Placeholder(range) => range.start,
+        }
+    }
+}
+
 /// `FlashLoader` is a struct which manages the flashing of any chunks of data onto any sections of flash.
 ///
 /// Use [add_data()](FlashLoader::add_data) to add a chunk of data.
@@ -489,6 +559,12 @@ pub struct FlashLoader {
     vector_table_addr: Option<u64>,
 
     read_flasher_rtt: bool,
+
+    /// Whether data may be written to configuration regions.
+    config_regions: bool,
+
+    /// External flashes that are programmed from the host.
+    external_flashes: Vec<SpiNorFlash>,
 }
 
 impl FlashLoader {
@@ -500,6 +576,21 @@ impl FlashLoader {
             source,
             vector_table_addr: None,
             read_flasher_rtt: false,
+            config_regions: false,
+            external_flashes: Vec::new(),
+        }
+    }
+
+    /// Creates an empty loader for the same memory, including external flashes and configuration
+    /// regions.
+    ///
+    /// Image loaders that transform an image, e.g. by signing it, load the original image into
+    /// such a loader first.
+    pub fn with_same_memory(&self) -> Self {
+        Self {
+            config_regions: self.config_regions,
+            external_flashes: self.external_flashes.clone(),
+            ..Self::new(self.memory_map.clone(), self.source.clone())
         }
     }
 
@@ -514,6 +605,29 @@ impl FlashLoader {
         self.read_flasher_rtt = read;
     }
 
+    /// Allows data in configuration regions, which are then programmed like NVM regions.
+    ///
+    /// Configuration regions are skipped by default, so flashing an image never changes option
+    /// bytes or fuses by accident.
+    pub(crate) fn allow_config_regions(&mut self) {
+        self.config_regions = true;
+    }
+
+    /// Adds an external flash, so data can be added for its address range.
+    ///
+    /// The flash is programmed from the host, after the flashes with a flash algorithm.
+    pub fn add_external_flash(&mut self, flash: SpiNorFlash) {
+        tracing::debug!("Adding external flash at {:#010x?}", flash.range());
+        self.external_flashes.push(flash);
+    }
+
+    /// Returns the external flash that contains `address`.
+    fn external_flash_for_address(&self, address: u64) -> Option<&SpiNorFlash> {
+        self.external_flashes
+            .iter()
+            .find(|flash| flash.range().contains(&address))
+    }
+
     /// Vector table address, if available for this flash operation.
     pub fn vector_table_addr(&self) -> Option<u64> {
         self.vector_table_addr
@@ -544,9 +658,17 @@ impl FlashLoader {
     fn check_data_in_memory_map(&mut self, range: Range<u64>) -> Result<(), FlashError> {
         let mut address = range.start;
         while address < range.end {
+            if let Some(flash) = self.external_flash_for_address(address) {
+                address = flash.range().end;
+                continue;
+            }
+
             match Self::get_region_for_address(&self.memory_map, address) {
                 Some(MemoryRegion::Nvm(region)) => address = region.range.end,
                 Some(MemoryRegion::Ram(region)) => address = region.range.end,
+                Some(MemoryRegion::Config(region)) if self.config_regions => {
+                    address = region.range.end
+                }
                 _ => {
                     return Err(FlashError::NoSuitableNvm {
                         range,
@@ -572,6 +694,72 @@ impl FlashLoader {
         self.builder.add_data(address, data)
     }
 
+    /// Injects device specific data, like a serial number, at `location`.
+    ///
+    /// Data injected at an address is added like with [`FlashLoader::add_data`], and must not
+    /// overlap other data. Data injected into a placeholder of the image, like an ELF symbol,
+    /// overwrites exactly the staged data of the placeholder, and has to have its size.
+    pub fn inject_data(
+        &mut self,
+        location: InjectionLocation,
+        data: &[u8],
+    ) -> Result<(), FlashError> {
+        match location {
+            InjectionLocation::Address(address) => {
+                tracing::debug!(
+                    "Injecting {} bytes at address {:#010x}",
+                    data.len(),
+                    address
+                );
+                self.add_data(address, data)
+            }
+            InjectionLocation::Placeholder(placeholder) => {
+                tracing::debug!(
+                    "Replacing the placeholder at {:#010x?} with {} bytes",
+                    placeholder,
+                    data.len()
+                );
+                if placeholder.end - placeholder.start != data.len() as u64 {
+                    return Err(FlashError::PlaceholderSizeMismatch {
+                        placeholder,
+                        size: data.len(),
+                    });
+                }
+                if !self.builder.patch_data(placeholder.start, data) {
+                    return Err(FlashError::MissingData { range: placeholder });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Computes the CRC32 of the data in `range`, and injects it as a little endian word at
+    /// `location` with [`FlashLoader::inject_data`]. Returns the checksum.
+    ///
+    /// The range has to be fully covered by data, so this is called after all other data is
+    /// added. The checksum is the one used by zlib.
+    pub fn inject_crc32(
+        &mut self,
+        range: Range<u64>,
+        location: InjectionLocation,
+    ) -> Result<u32, FlashError> {
+        let address = location.address();
+        if address < range.end && range.start < address + 4 {
+            return Err(FlashError::ChecksumInsideRange { address, range });
+        }
+        if !self.builder.covers_range(&range) {
+            return Err(FlashError::MissingData { range });
+        }
+
+        let crc = self
+            .builder
+            .data_in_range(&range)
+            .fold(0, |crc, (_, data)| super::checksum::crc32(crc, data));
+        self.inject_data(location, &crc.to_le_bytes())?;
+
+        Ok(crc)
+    }
+
     pub(super) fn get_region_for_address(
         memory_map: &[MemoryRegion],
         address: u64,
@@ -632,6 +820,7 @@ impl FlashLoader {
         progress: &mut FlashProgress<'_>,
     ) -> Result<(), FlashError> {
         let mut algos = self.prepare_plan(session, false, &[])?;
+        let external = self.prepare_external_plan(false)?;
 
         for flasher in algos.iter_mut() {
             let mut program_size = 0;
@@ -643,6 +832,9 @@ impl FlashLoader {
             }
             progress.add_progress_bar(ProgressOperation::Verify, Some(program_size));
         }
+        for (_, layout) in &external {
+            progress.add_progress_bar(ProgressOperation::Verify, Some(layout_size(layout)));
+        }
 
         // Iterate all flash algorithms we need to use and do the flashing.
         for mut flasher in algos {
@@ -656,6 +848,12 @@ impl FlashLoader {
             }
         }
 
+        for (flash, layout) in &external {
+            if !flash.verify(session, layout, progress, true)? {
+                return Err(FlashError::Verify);
+            }
+        }
+
         self.verify_ram(session)?;
 
         Ok(())
@@ -675,6 +873,7 @@ impl FlashLoader {
             options.keep_unwritten_bytes,
             &options.preferred_algos,
         )?;
+        let external = self.prepare_external_plan(options.keep_unwritten_bytes)?;
 
         if options.dry_run {
             tracing::info!("Skipping programming, dry run!");
@@ -686,7 +885,37 @@ impl FlashLoader {
             return Ok(());
         }
 
-        self.initialize(&mut algos, session, &mut options)?;
+        if options.preverify {
+            if options.do_chip_erase {
+                tracing::warn!("Skipping preverify, the chip is erased anyway.");
+            } else {
+                Self::skip_up_to_date_sectors(&mut algos, session, &mut options)?;
+            }
+        }
+
+        if !options.preserve_core_state {
+            return self.program(algos, external, session, &mut options);
+        }
+
+        for flasher in algos.iter_mut() {
+            flasher.preserve_core_state = true;
+        }
+        let saved_state = SavedCoreState::save(session, &algos)?;
+        let result = self.program(algos, external, session, &mut options);
+        saved_state.restore(session)?;
+
+        result
+    }
+
+    /// Programs the planned flash sectors and writes the RAM contents.
+    fn program(
+        &self,
+        mut algos: Vec<Flasher>,
+        mut external: Vec<(&SpiNorFlash, FlashLayout)>,
+        session: &mut Session,
+        options: &mut DownloadOptions,
+    ) -> Result<(), FlashError> {
+        self.initialize(&mut algos, &external, session, options)?;
 
         let mut do_chip_erase = options.do_chip_erase;
         let mut did_chip_erase = false;
@@ -721,6 +950,30 @@ impl FlashLoader {
             )?;
         }
 
+        // External flashes are never erased by a chip erase.
+        for (flash, layout) in external.iter_mut() {
+            tracing::debug!("Flashing external flash {:#010x?}", flash.range());
+
+            flash.program(
+                session,
+                layout,
+                &mut options.progress,
+                options.keep_unwritten_bytes,
+                options.skip_erase,
+            )?;
+
+            if options.verify
+                && !flash.verify(
+                    session,
+                    layout,
+                    &mut options.progress,
+                    !options.keep_unwritten_bytes,
+                )?
+            {
+                return Err(FlashError::Verify);
+            }
+        }
+
         tracing::debug!("Committing RAM!");
 
         if let BootInfo::FromRam { cores_to_reset, .. } = self.boot_info() {
@@ -912,11 +1165,11 @@ impl FlashLoader {
         // chip erase once per algorithm, not once per region. Otherwise subsequent chip erases will
         // erase previous regions' flashed contents.
         tracing::debug!("Regions:");
-        for region in self
-            .memory_map
-            .iter()
-            .filter_map(MemoryRegion::as_nvm_region)
-        {
+        for region in self.memory_map.iter().filter_map(|region| match region {
+            MemoryRegion::Nvm(region) => Some(region.clone()),
+            MemoryRegion::Config(region) if self.config_regions => Some(region.to_nvm_region()),
+            _ => None,
+        }) {
             tracing::debug!(
                 "    region: {:#010X?} ({} bytes)",
                 region.range,
@@ -932,7 +1185,15 @@ impl FlashLoader {
                 continue;
             }
 
-            let region = region.clone();
+            // The data is programmed through the external flash instead.
+            if self
+                .external_flashes
+                .iter()
+                .any(|flash| flash.range().intersects_range(&region.range))
+            {
+                tracing::debug!("     -- external flash, ignoring!");
+                continue;
+            }
 
             let Some(core_name) = region.cores.first() else {
                 return Err(FlashError::NoNvmCoreAccess(region));
@@ -967,9 +1228,68 @@ impl FlashLoader {
         Ok(algos)
     }
 
+    /// Lays out the data of the external flashes, skipping the flashes without data.
+    fn prepare_external_plan(
+        &self,
+        restore_unwritten_bytes: bool,
+    ) -> Result<Vec<(&SpiNorFlash, FlashLayout)>, FlashError> {
+        let mut plan = Vec::new();
+        for flash in &self.external_flashes {
+            let region = flash.nvm_region();
+            if !self.builder.has_data_in_range(&region.range) {
+                continue;
+            }
+
+            let layout = self.builder.build_sectors_and_pages(
+                &region,
+                &flash.flash_algorithm(),
+                restore_unwritten_bytes,
+            )?;
+            plan.push((flash, layout));
+        }
+        Ok(plan)
+    }
+
+    /// Compares the flash contents with the data to be flashed, and removes the sectors that are
+    /// already up to date from the plan.
+    fn skip_up_to_date_sectors(
+        algos: &mut Vec<Flasher>,
+        session: &mut Session,
+        options: &mut DownloadOptions,
+    ) -> Result<(), FlashError> {
+        for flasher in algos.iter() {
+            let size = flasher
+                .regions
+                .iter()
+                .flat_map(|region| region.flash_layout().sectors())
+                .map(|sector| sector.size())
+                .sum();
+            options
+                .progress
+                .add_progress_bar(ProgressOperation::Verify, Some(size));
+        }
+
+        for flasher in algos.iter_mut() {
+            tracing::debug!(
+                "Preverifying ranges for algo: {}",
+                flasher.flash_algorithm.name
+            );
+            flasher.skip_up_to_date_sectors(
+                session,
+                &mut options.progress,
+                options.preverify_strategy,
+            )?;
+        }
+
+        algos.retain(|flasher| !flasher.regions.is_empty());
+
+        Ok(())
+    }
+
     fn initialize(
         &self,
         algos: &mut [Flasher],
+        external: &[(&SpiNorFlash, FlashLayout)],
         session: &mut Session,
         options: &mut DownloadOptions,
     ) -> Result<(), FlashError> {
@@ -1038,6 +1358,32 @@ impl FlashLoader {
             phases.push(phase_layout);
         }
 
+        for (_, layout) in external {
+            let size = layout_size(layout);
+            if options.keep_unwritten_bytes {
+                let fill_size = layout.fills().iter().map(|s| s.size()).sum::<u64>();
+                options
+                    .progress
+                    .add_progress_bar(ProgressOperation::Fill, Some(fill_size));
+            }
+            if !options.skip_erase {
+                let erase_size = layout.sectors().iter().map(|s| s.size()).sum::<u64>();
+                options
+                    .progress
+                    .add_progress_bar(ProgressOperation::Erase, Some(erase_size));
+            }
+            options
+                .progress
+                .add_progress_bar(ProgressOperation::Program, Some(size));
+            if options.verify {
+                options
+                    .progress
+                    .add_progress_bar(ProgressOperation::Verify, Some(size));
+            }
+
+            phases.push(layout.clone());
+        }
+
         let ram_size: u64 = self
             .memory_map
             .iter()
@@ -1066,6 +1412,11 @@ impl FlashLoader {
                 data.len()
             );
 
+            // External flashes are verified when they are programmed.
+            if self.external_flash_for_address(address).is_some() {
+                continue;
+            }
+
             let associated_region = session.target().memory_region_by_address(address).unwrap();
 
             // We verified NVM regions before, in flasher.program().
@@ -1179,3 +1530,8 @@ impl FlashLoader {
             .map(|(address, data)| (*address, data.as_slice()))
     }
 }
+
+/// The number of bytes programmed for a layout.
+fn layout_size(layout: &FlashLayout) -> u64 {
+    layout.pages().iter().map(|page| page.size() as u64).sum()
+}
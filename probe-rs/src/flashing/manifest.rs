@@ -0,0 +1,439 @@
+//! An image format that combines several images and data patches into a single download.
+//!
+//! A manifest is a YAML file:
+//!
+//! ```yaml
+//! images:
+//!   - path: bootloader.hex
+//!   - path: application.elf
+//!   - path: calibration.bin
+//!     format: bin
+//!     options:
+//!       base_address: 0x0807E000
+//! patches:
+//!   - address: 0x0807F000
+//!     type: u32
+//!     value: "{{serial}}"
+//! ```
+//!
+//! All images and patches are added to the same [`FlashLoader`], so they are erased, programmed
+//! and verified together, and overlapping data is rejected.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use yaml_serde::Value;
+
+use super::{FileDownloadError, FlashLoader, ImageFormat, ImageLoader, ImageReader};
+use crate::session::Session;
+
+/// The names of the manifest image format.
+const FORMAT_NAMES: &[&str] = &["manifest"];
+
+/// Options for loading a [`Manifest`].
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Default)]
+#[serde(default)]
+pub struct ManifestOptions {
+    /// The directory relative image paths are resolved against. Defaults to the working
+    /// directory.
+    pub base_dir: Option<PathBuf>,
+    /// The values of the `{{name}}` placeholders in the patches.
+    pub variables: HashMap<String, String>,
+}
+
+/// A list of images and data patches that are downloaded together.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// The images to load.
+    #[serde(default)]
+    pub images: Vec<ManifestImage>,
+    /// Data written after all images are loaded.
+    #[serde(default)]
+    pub patches: Vec<ManifestPatch>,
+}
+
+/// An image of a [`Manifest`].
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestImage {
+    /// The path to the image.
+    pub path: PathBuf,
+    /// The name of the image format. Defaults to the extension of `path`.
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Options for the image format, e.g. the `base_address` of a binary image.
+    #[serde(default)]
+    pub options: Option<Value>,
+}
+
+/// Data written to a fixed address by a [`Manifest`].
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ManifestPatch {
+    /// The address the data is written to.
+    pub address: u64,
+    /// How `value` is encoded.
+    #[serde(rename = "type")]
+    pub kind: PatchKind,
+    /// The value to write. `{{name}}` placeholders are replaced by the variables of the
+    /// [`ManifestOptions`].
+    pub value: String,
+    /// The number of bytes written for a string, padded with zeros. Defaults to the length of the
+    /// string.
+    #[serde(default)]
+    pub size: Option<usize>,
+}
+
+/// The encoding of a [`ManifestPatch`].
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum PatchKind {
+    /// An unsigned 8 bit integer.
+    U8,
+    /// An unsigned little endian 16 bit integer.
+    U16,
+    /// An unsigned little endian 32 bit integer.
+    U32,
+    /// An unsigned little endian 64 bit integer.
+    U64,
+    /// A UTF-8 string.
+    String,
+    /// Bytes given as a hex string, e.g. `DEADBEEF`.
+    Bytes,
+}
+
+/// Errors of the manifest image format.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum ManifestError {
+    /// Failed to parse the manifest.
+    Parse(#[from] yaml_serde::Error),
+
+    /// Failed to open the image {path:?}.
+    Open {
+        /// The path of the image.
+        path: PathBuf,
+        /// The error that occurred.
+        source: std::io::Error,
+    },
+
+    /// The format of the image {path:?} is unknown. Set it with `format`.
+    UnknownFormat {
+        /// The path of the image.
+        path: PathBuf,
+    },
+
+    /// The image {path:?} has the unsupported format `{format}`.
+    UnsupportedFormat {
+        /// The path of the image.
+        path: PathBuf,
+        /// The name of the format.
+        format: String,
+    },
+
+    /// No value is given for the variable `{0}`.
+    UnknownVariable(String),
+
+    /// `{value}` is not a valid {kind:?} value.
+    InvalidValue {
+        /// The value, with all placeholders replaced.
+        value: String,
+        /// The encoding of the value.
+        kind: PatchKind,
+    },
+}
+
+impl Manifest {
+    /// Parses a manifest from YAML.
+    pub fn from_yaml(yaml: &str) -> Result<Self, ManifestError> {
+        Ok(yaml_serde::from_str(yaml)?)
+    }
+
+    /// Serializes the manifest to YAML.
+    pub fn to_yaml(&self) -> Result<String, ManifestError> {
+        Ok(yaml_serde::to_string(self)?)
+    }
+
+    /// Adds all images and patches to the flash loader.
+    pub fn load(
+        &self,
+        flash_loader: &mut FlashLoader,
+        session: &mut Session,
+        options: &ManifestOptions,
+    ) -> Result<(), FileDownloadError> {
+        for image in &self.images {
+            let path = match &options.base_dir {
+                Some(base_dir) => base_dir.join(&image.path),
+                None => image.path.clone(),
+            };
+
+            let format = image.format_name().ok_or_else(|| {
+                into_manifest_error(ManifestError::UnknownFormat { path: path.clone() })
+            })?;
+            tracing::info!("Loading {} as {format}", path.display());
+
+            let mut file = File::open(&path).map_err(|source| {
+                into_manifest_error(ManifestError::Open {
+                    path: path.clone(),
+                    source,
+                })
+            })?;
+
+            let loader = super::image_format(&format)
+                .filter(|_| !FORMAT_NAMES.contains(&format.as_str()))
+                .ok_or_else(|| {
+                    into_manifest_error(ManifestError::UnsupportedFormat {
+                        path: path.clone(),
+                        format: format.clone(),
+                    })
+                })?
+                .create_loader(image.options.clone());
+
+            loader.load(flash_loader, session, &mut file)?;
+        }
+
+        for patch in &self.patches {
+            let data = patch
+                .encode(&options.variables)
+                .map_err(into_manifest_error)?;
+            tracing::info!("Patching {} byte(s) at {:#010x}", data.len(), patch.address);
+            flash_loader.add_data(patch.address, &data)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl ManifestImage {
+    /// Returns the name of the image format, which is `format` or the extension of `path`.
+    pub fn format_name(&self) -> Option<String> {
+        self.format.clone().or_else(|| {
+            self.path
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+        })
+    }
+}
+
+impl ManifestPatch {
+    /// Returns the bytes written by the patch.
+    pub fn encode(&self, variables: &HashMap<String, String>) -> Result<Vec<u8>, ManifestError> {
+        let value = expand_placeholders(&self.value, variables)?;
+        let invalid = || ManifestError::InvalidValue {
+            value: value.clone(),
+            kind: self.kind,
+        };
+
+        let data = match self.kind {
+            PatchKind::U8 => u8::try_from(parse_int(&value).ok_or_else(invalid)?)
+                .map_err(|_| invalid())?
+                .to_le_bytes()
+                .to_vec(),
+            PatchKind::U16 => u16::try_from(parse_int(&value).ok_or_else(invalid)?)
+                .map_err(|_| invalid())?
+                .to_le_bytes()
+                .to_vec(),
+            PatchKind::U32 => u32::try_from(parse_int(&value).ok_or_else(invalid)?)
+                .map_err(|_| invalid())?
+                .to_le_bytes()
+                .to_vec(),
+            PatchKind::U64 => parse_int(&value)
+                .ok_or_else(invalid)?
+                .to_le_bytes()
+                .to_vec(),
+            PatchKind::String => {
+                let mut data = value.as_bytes().to_vec();
+                if let Some(size) = self.size {
+                    if data.len() > size {
+                        return Err(invalid());
+                    }
+                    data.resize(size, 0);
+                }
+                data
+            }
+            PatchKind::Bytes => parse_hex_bytes(&value).ok_or_else(invalid)?,
+        };
+
+        Ok(data)
+    }
+}
+
+fn into_manifest_error(error: ManifestError) -> FileDownloadError {
+    super::into_format_error(FORMAT_NAMES[0], error)
+}
+
+/// Replaces the `{{name}}` placeholders in `template`.
+fn expand_placeholders(
+    template: &str,
+    variables: &HashMap<String, String>,
+) -> Result<String, ManifestError> {
+    let mut expanded = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+
+        let name = rest[start + 2..start + end].trim();
+        let value = variables
+            .get(name)
+            .ok_or_else(|| ManifestError::UnknownVariable(name.to_string()))?;
+
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(value);
+        rest = &rest[start + end + 2..];
+    }
+
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn parse_int(value: &str) -> Option<u64> {
+    let value = value.trim().replace('_', "");
+    if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        u64::from_str_radix(hex, 16).ok()
+    } else if let Some(binary) = value.strip_prefix("0b") {
+        u64::from_str_radix(binary, 2).ok()
+    } else {
+        value.parse().ok()
+    }
+}
+
+fn parse_hex_bytes(value: &str) -> Option<Vec<u8>> {
+    let digits = value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ':')
+        .collect::<Vec<_>>();
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let high = pair[0].to_digit(16)?;
+            let low = pair[1].to_digit(16)?;
+            Some((high * 16 + low) as u8)
+        })
+        .collect()
+}
+
+pub(super) struct ManifestLoaderFactory;
+
+impl ImageFormat for ManifestLoaderFactory {
+    fn formats(&self) -> &[&str] {
+        FORMAT_NAMES
+    }
+
+    fn create_loader(&self, options: Option<Value>) -> Box<dyn ImageLoader> {
+        let options = options
+            .and_then(|value| yaml_serde::from_value(value).ok())
+            .unwrap_or_default();
+        Box::new(ManifestLoader(options))
+    }
+}
+
+/// Loads a [`Manifest`], and adds all of its images and patches to the loader.
+pub struct ManifestLoader(pub ManifestOptions);
+
+impl ImageLoader for ManifestLoader {
+    fn load(
+        &self,
+        flash_loader: &mut FlashLoader,
+        session: &mut Session,
+        file: &mut dyn ImageReader,
+    ) -> Result<(), FileDownloadError> {
+        let mut yaml = String::new();
+        file.read_to_string(&mut yaml)?;
+
+        let manifest = Manifest::from_yaml(&yaml).map_err(into_manifest_error)?;
+        manifest.load(flash_loader, session, &self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch(kind: PatchKind, value: &str) -> ManifestPatch {
+        ManifestPatch {
+            address: 0,
+            kind,
+            value: value.to_string(),
+            size: None,
+        }
+    }
+
+    #[test]
+    fn parse_manifest() {
+        let manifest = Manifest::from_yaml(
+            r#"
+images:
+  - path: bootloader.hex
+  - path: calibration.bin
+    format: bin
+    options:
+      base_address: 0x0807E000
+patches:
+  - address: 0x0807F000
+    type: u32
+    value: "{{serial}}"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.images.len(), 2);
+        assert_eq!(manifest.images[0].format_name().as_deref(), Some("hex"));
+        assert_eq!(manifest.images[1].format.as_deref(), Some("bin"));
+        assert_eq!(manifest.patches[0].address, 0x0807_F000);
+        assert_eq!(manifest.patches[0].kind, PatchKind::U32);
+    }
+
+    #[test]
+    fn encode_patches() {
+        let variables = HashMap::from([("serial".to_string(), "0x1234".to_string())]);
+
+        assert_eq!(
+            patch(PatchKind::U32, "{{ serial }}")
+                .encode(&variables)
+                .unwrap(),
+            [0x34, 0x12, 0, 0]
+        );
+        assert_eq!(
+            patch(PatchKind::String, "SN-{{serial}}")
+                .encode(&variables)
+                .unwrap(),
+            b"SN-0x1234"
+        );
+        assert_eq!(
+            patch(PatchKind::Bytes, "de:ad:BE:EF")
+                .encode(&variables)
+                .unwrap(),
+            [0xde, 0xad, 0xbe, 0xef]
+        );
+        assert!(matches!(
+            patch(PatchKind::U8, "256").encode(&variables),
+            Err(ManifestError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            patch(PatchKind::U8, "{{mac}}").encode(&variables),
+            Err(ManifestError::UnknownVariable(name)) if name == "mac"
+        ));
+    }
+
+    #[test]
+    fn pad_strings() {
+        let mut patch = patch(PatchKind::String, "abc");
+        patch.size = Some(5);
+        assert_eq!(patch.encode(&HashMap::new()).unwrap(), b"abc\0\0");
+
+        patch.size = Some(2);
+        assert!(patch.encode(&HashMap::new()).is_err());
+    }
+}
@@ -41,8 +41,36 @@
 //!
 //! # Ok::<(), anyhow::Error>(())
 //! ```
+//!
+//! ### Changing option bytes
+//!
+//! Option bytes, OTP memory and fuses are described by configuration regions in the memory map
+//! of the target, and are never written when flashing an image.
+//!
+//! ```no_run
+//! use probe_rs::{Session, SessionConfig, flashing::{self, ConfigRegionData, FlashProgress}};
+//!
+//! let session_config = SessionConfig::default();
+//! let mut session = Session::auto_attach("nrf51822", session_config)?;
+//!
+//! let region = session
+//!     .target()
+//!     .memory_map
+//!     .iter()
+//!     .find_map(|region| region.as_config_region())
+//!     .cloned()
+//!     .unwrap();
+//!
+//! let mut data = ConfigRegionData::read(&mut session, &region)?;
+//! data.set("RDP", 0xAA)?;
+//! flashing::program_config_region(&mut session, &data, false, FlashProgress::empty())?;
+//!
+//! # Ok::<(), anyhow::Error>(())
+//! ```
 
 mod builder;
+mod checksum;
+mod config_region;
 mod download;
 mod encoder;
 mod erase;
@@ -50,17 +78,25 @@ mod error;
 mod flash_algorithm;
 mod flasher;
 mod loader;
+mod manifest;
 mod progress;
+mod sfdp;
+mod spi_nor;
 
 use builder::*;
 
 pub use builder::{FlashDataBlockSpan, FlashFill, FlashLayout, FlashPage, FlashSector};
+pub use config_region::*;
 pub use download::*;
 pub use erase::*;
 pub use error::*;
 pub use flash_algorithm::*;
+pub(crate) use flasher::patch_flash;
 pub use flasher::{
     ActiveFlasher, Erase, FlashData, Flasher, LoadedRegion, Operation, Program, Verify,
 };
 pub use loader::*;
+pub use manifest::*;
 pub use progress::*;
+pub use sfdp::*;
+pub use spi_nor::*;
@@ -194,6 +194,9 @@ pub enum ProgressOperation {
 /// If flashing works without problems, the events will arrive in the
 /// following order:
 ///
+/// * If [`DownloadOptions::preverify`](super::DownloadOptions::preverify) is set, the
+///   `AddProgressBar`, `Started`, `Progress`, and `Finished` events of the
+///   [`ProgressOperation::Verify`] operation that finds the up-to-date sectors
 /// * `FlashLayoutReady`
 /// * A number of `AddProgressBar` events
 /// * `Started`, `Progress`, and `Finished` events for each operation
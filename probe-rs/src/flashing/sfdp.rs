@@ -0,0 +1,312 @@
+//! Parsing of the Serial Flash Discoverable Parameters (SFDP, JESD216) of serial NOR flashes.
+
+use std::time::Duration;
+
+use super::FlashError;
+
+/// The SFDP signature, "SFDP" in little endian.
+const SFDP_SIGNATURE: u32 = 0x5044_4653;
+
+/// The parameter ID of the Basic Flash Parameter Table.
+const BASIC_FLASH_PARAMETER_TABLE: u16 = 0xFF00;
+
+/// Erase timeout used when the flash does not describe its erase times.
+const DEFAULT_ERASE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Page program timeout used when the flash does not describe its program time.
+const DEFAULT_PAGE_PROGRAM_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Errors of the SFDP tables.
+#[derive(Debug, thiserror::Error, docsplay::Display)]
+pub enum SfdpError {
+    /// The flash has no SFDP signature. It might not support SFDP, or it does not respond.
+    MissingSignature,
+
+    /// The SFDP tables have no Basic Flash Parameter Table.
+    MissingBasicTable,
+
+    /// The Basic Flash Parameter Table is too short, only {0} DWORDs long.
+    TableTooShort(usize),
+
+    /// The flash supports no erase command.
+    NoEraseType,
+}
+
+/// The number of address bytes a flash accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressMode {
+    /// Only 3-byte addresses.
+    ThreeByte,
+    /// 3-byte addresses by default, 4-byte addresses after entering the 4-byte address mode.
+    ThreeOrFourByte,
+    /// Only 4-byte addresses.
+    FourByte,
+}
+
+/// An erase command of a flash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EraseType {
+    /// The size of the erased block in bytes.
+    pub size: u32,
+    /// The opcode of the erase command.
+    pub opcode: u8,
+    /// The maximum time an erase takes.
+    pub timeout: Duration,
+}
+
+/// The parameters of a serial NOR flash, read from its SFDP tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SfdpParameters {
+    /// The size of the flash in bytes.
+    pub size: u64,
+    /// The size of a page in bytes, the largest block that can be programmed at once.
+    pub page_size: u32,
+    /// The maximum time a page program takes.
+    pub page_program_timeout: Duration,
+    /// The supported erase commands, ordered by size.
+    pub erase_types: Vec<EraseType>,
+    /// The number of address bytes the flash accepts.
+    pub address_mode: AddressMode,
+}
+
+impl SfdpParameters {
+    /// Reads the SFDP tables of a flash.
+    ///
+    /// `read` reads SFDP data at an offset, usually with the `RDSFDP` (`0x5A`) command.
+    pub fn read(
+        mut read: impl FnMut(u32, &mut [u8]) -> Result<(), FlashError>,
+    ) -> Result<Self, FlashError> {
+        let mut header = [0; 8];
+        read(0, &mut header)?;
+
+        if u32::from_le_bytes(header[0..4].try_into().unwrap()) != SFDP_SIGNATURE {
+            return Err(SfdpError::MissingSignature.into());
+        }
+
+        // The number of parameter headers is stored minus one.
+        let parameter_headers = header[6] as u32 + 1;
+        for index in 0..parameter_headers {
+            let mut parameter_header = [0; 8];
+            read(8 + index * 8, &mut parameter_header)?;
+
+            let id = u16::from_le_bytes([parameter_header[0], parameter_header[7]]);
+            if id != BASIC_FLASH_PARAMETER_TABLE {
+                continue;
+            }
+
+            let length = parameter_header[3] as usize * 4;
+            let pointer = u32::from_le_bytes([
+                parameter_header[4],
+                parameter_header[5],
+                parameter_header[6],
+                0,
+            ]);
+
+            let mut table = vec![0; length];
+            read(pointer, &mut table)?;
+
+            return Ok(Self::from_basic_parameter_table(&table)?);
+        }
+
+        Err(SfdpError::MissingBasicTable.into())
+    }
+
+    /// Parses the Basic Flash Parameter Table.
+    pub fn from_basic_parameter_table(table: &[u8]) -> Result<Self, SfdpError> {
+        // DWORDs are numbered from 1 in the standard.
+        let dword = |index: usize| {
+            table
+                .get((index - 1) * 4..index * 4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        };
+
+        // JESD216 defines 9 DWORDs, later revisions add more.
+        if table.len() < 9 * 4 {
+            return Err(SfdpError::TableTooShort(table.len() / 4));
+        }
+
+        let first = dword(1).unwrap();
+        let address_mode = match (first >> 17) & 0b11 {
+            0b01 => AddressMode::ThreeOrFourByte,
+            0b10 => AddressMode::FourByte,
+            _ => AddressMode::ThreeByte,
+        };
+
+        let density = dword(2).unwrap();
+        let size_bits = if density & 0x8000_0000 == 0 {
+            density as u64 + 1
+        } else {
+            1 << (density & 0x7FFF_FFFF)
+        };
+
+        let erase_times = dword(10);
+        let mut erase_types = Vec::new();
+        for (index, bits) in [dword(8).unwrap(), dword(9).unwrap()]
+            .into_iter()
+            .flat_map(|dword| [dword & 0xFFFF, dword >> 16])
+            .enumerate()
+        {
+            let size_exponent = bits & 0xFF;
+            if size_exponent == 0 {
+                continue;
+            }
+
+            let timeout = erase_times
+                .map(|times| erase_timeout(times, index))
+                .unwrap_or(DEFAULT_ERASE_TIMEOUT);
+            erase_types.push(EraseType {
+                size: 1 << size_exponent,
+                opcode: (bits >> 8) as u8,
+                timeout,
+            });
+        }
+
+        // Flashes that only describe the 4 KiB erase in the first DWORD.
+        if erase_types.is_empty() && first & 0b11 == 0b01 {
+            erase_types.push(EraseType {
+                size: 4096,
+                opcode: (first >> 8) as u8,
+                timeout: DEFAULT_ERASE_TIMEOUT,
+            });
+        }
+
+        if erase_types.is_empty() {
+            return Err(SfdpError::NoEraseType);
+        }
+        erase_types.sort_by_key(|erase_type| erase_type.size);
+
+        let (page_size, page_program_timeout) = match dword(11) {
+            Some(program) => (1 << ((program >> 4) & 0xF), page_program_timeout(program)),
+            None => (256, DEFAULT_PAGE_PROGRAM_TIMEOUT),
+        };
+
+        Ok(Self {
+            size: size_bits / 8,
+            page_size,
+            page_program_timeout,
+            erase_types,
+            address_mode,
+        })
+    }
+
+    /// Returns the smallest erase command, which determines the sector size.
+    pub fn smallest_erase_type(&self) -> EraseType {
+        self.erase_types[0]
+    }
+}
+
+/// Computes the maximum erase time of erase type `index` from the 10th DWORD.
+fn erase_timeout(times: u32, index: usize) -> Duration {
+    let multiplier = 2 * ((times & 0xF) + 1);
+    let time = (times >> (4 + index * 7)) & 0x7F;
+
+    let count = (time & 0x1F) + 1;
+    let unit = match time >> 5 {
+        0b00 => Duration::from_millis(1),
+        0b01 => Duration::from_millis(16),
+        0b10 => Duration::from_millis(128),
+        _ => Duration::from_secs(1),
+    };
+
+    // The maximum time is the typical time times the multiplier.
+    unit * count * multiplier
+}
+
+/// Computes the maximum page program time from the 11th DWORD.
+fn page_program_timeout(program: u32) -> Duration {
+    let multiplier = 2 * ((program & 0xF) + 1);
+    let count = ((program >> 8) & 0x1F) + 1;
+    let unit = if program & (1 << 13) == 0 {
+        Duration::from_micros(8)
+    } else {
+        Duration::from_micros(64)
+    };
+
+    unit * count * multiplier
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The Basic Flash Parameter Table of a 16 MiB flash, like the W25Q128.
+    fn basic_table() -> Vec<u8> {
+        [
+            0xFFF9_20E5u32, // 4 KiB erase with 0x20, 3-byte addresses
+            0x07FF_FFFF,    // 128 Mbit
+            0x6B08_EB44,
+            0xBB42_3B08,
+            0xFFFF_FFFE,
+            0xFF00_FFFF,
+            0xEB40_FFFF,
+            0x520F_200C, // 4 KiB erase with 0x20, 32 KiB erase with 0x52
+            0xFF00_D810, // 64 KiB erase with 0xD8
+            0x0045_6A23, // erase times
+            0x0000_2281, // 256 byte pages
+        ]
+        .iter()
+        .flat_map(|dword| dword.to_le_bytes())
+        .collect()
+    }
+
+    #[test]
+    fn parse_basic_table() {
+        let parameters = SfdpParameters::from_basic_parameter_table(&basic_table()).unwrap();
+
+        assert_eq!(parameters.size, 16 * 1024 * 1024);
+        assert_eq!(parameters.page_size, 256);
+        assert_eq!(parameters.address_mode, AddressMode::ThreeByte);
+
+        let erase_types = parameters
+            .erase_types
+            .iter()
+            .map(|erase_type| (erase_type.size, erase_type.opcode))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            erase_types,
+            [(0x1000, 0x20), (0x8000, 0x52), (0x10000, 0xD8)]
+        );
+
+        // 4 KiB erase: 3 * 16 ms typical, with a multiplier of 2 * (3 + 1).
+        assert_eq!(
+            parameters.erase_types[0].timeout,
+            Duration::from_millis(3 * 16 * 8)
+        );
+    }
+
+    #[test]
+    fn read_sfdp_tables() {
+        let mut sfdp = vec![0xFF; 0x80];
+        sfdp[0..8].copy_from_slice(&[b'S', b'F', b'D', b'P', 0x06, 0x01, 0x01, 0xFF]);
+        // A vendor table first, then the basic table at 0x40.
+        sfdp[8..16].copy_from_slice(&[0x84, 0x00, 0x01, 0x02, 0x30, 0x00, 0x00, 0xFF]);
+        sfdp[16..24].copy_from_slice(&[0x00, 0x06, 0x01, 0x0B, 0x40, 0x00, 0x00, 0xFF]);
+        sfdp.splice(0x40..0x40 + 44, basic_table());
+
+        let parameters = SfdpParameters::read(|offset, data| {
+            data.copy_from_slice(&sfdp[offset as usize..][..data.len()]);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(parameters.smallest_erase_type().size, 0x1000);
+
+        sfdp[0] = 0xFF;
+        let error = SfdpParameters::read(|offset, data| {
+            data.copy_from_slice(&sfdp[offset as usize..][..data.len()]);
+            Ok(())
+        })
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            FlashError::Sfdp(SfdpError::MissingSignature)
+        ));
+    }
+
+    #[test]
+    fn reject_short_table() {
+        assert!(matches!(
+            SfdpParameters::from_basic_parameter_table(&basic_table()[..32]),
+            Err(SfdpError::TableTooShort(8))
+        ));
+    }
+}
@@ -0,0 +1,554 @@
+//! Programming of external serial NOR flash through a memory-mapped SPI controller.
+//!
+//! The flash is driven from the host: a [`SpiNorController`] sends the SPI commands through the
+//! registers of a vendor specific controller, and the erase sizes and opcodes are read from the
+//! SFDP tables of the flash. No flash algorithm needs to exist for the board. The controller of a
+//! target is provided by its [`Vendor`](crate::vendor::Vendor).
+
+use std::fmt::Debug;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use probe_rs_target::{FlashProperties, MemoryRange, NvmRegion, SectorDescription};
+
+use super::sfdp::{AddressMode, EraseType, SfdpParameters};
+use super::{FlashAlgorithm, FlashError, FlashLayout, FlashProgress, FlashSector};
+use crate::memory::MemoryInterface;
+use crate::session::Session;
+use crate::{Core, Target};
+
+const WRITE_ENABLE: u8 = 0x06;
+const READ_STATUS: u8 = 0x05;
+const READ_JEDEC_ID: u8 = 0x9F;
+const READ_SFDP: u8 = 0x5A;
+const PAGE_PROGRAM: u8 = 0x02;
+const ENTER_4_BYTE_ADDRESS_MODE: u8 = 0xB7;
+
+/// The status register bit that is set while an erase or program is in progress.
+const STATUS_BUSY: u8 = 1 << 0;
+
+/// Flashes up to this size are addressed with 3 address bytes.
+const THREE_BYTE_ADDRESS_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// A SPI command in single line mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpiCommand {
+    /// The opcode of the command.
+    pub opcode: u8,
+    /// The address, if the command has an address phase.
+    pub address: Option<u32>,
+    /// The number of address bytes, 3 or 4.
+    pub address_bytes: u8,
+    /// The number of dummy cycles between the address and the data phase.
+    pub dummy_cycles: u8,
+}
+
+impl SpiCommand {
+    /// A command without address.
+    pub fn new(opcode: u8) -> Self {
+        Self {
+            opcode,
+            address: None,
+            address_bytes: 3,
+            dummy_cycles: 0,
+        }
+    }
+
+    /// A command with an address.
+    pub fn with_address(opcode: u8, address: u32, address_bytes: u8) -> Self {
+        Self {
+            opcode,
+            address: Some(address),
+            address_bytes,
+            dummy_cycles: 0,
+        }
+    }
+}
+
+/// The data phase of a SPI command.
+#[derive(Debug)]
+pub enum SpiData<'a> {
+    /// The command has no data phase.
+    None,
+    /// Data is read from the flash.
+    Read(&'a mut [u8]),
+    /// Data is written to the flash.
+    Write(&'a [u8]),
+}
+
+/// A vendor specific SPI controller, which maps an external flash into memory.
+///
+/// The controller is driven through debug memory accesses. Its clock and pins have to be set up
+/// already, e.g. by the boot ROM or a debug sequence.
+pub trait SpiNorController: Debug + Send + Sync {
+    /// The address range where the flash is mapped into memory.
+    fn memory_range(&self) -> Range<u64>;
+
+    /// Stops memory-mapped reads, so commands can be sent.
+    fn enter_command_mode(&self, memory: &mut dyn MemoryInterface) -> Result<(), crate::Error>;
+
+    /// Sends a command, and transfers its data.
+    fn command(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        command: SpiCommand,
+        data: SpiData<'_>,
+    ) -> Result<(), crate::Error>;
+
+    /// Maps the flash into memory again, reading it with the given number of address bytes.
+    fn enter_memory_mapped_mode(
+        &self,
+        memory: &mut dyn MemoryInterface,
+        size: u64,
+        address_bytes: u8,
+    ) -> Result<(), crate::Error>;
+}
+
+/// Returns the SPI controller of the external flash of `target`, if its vendor knows it.
+pub fn spi_nor_controller(target: &Target) -> Option<Arc<dyn SpiNorController>> {
+    crate::vendor::try_create_spi_nor_controller(target)
+}
+
+/// An external serial NOR flash, described by its SFDP tables.
+///
+/// The flash is added to a [`FlashLoader`](super::FlashLoader) with
+/// [`add_external_flash`](super::FlashLoader::add_external_flash), and then programmed like
+/// internal flash.
+#[derive(Debug, Clone)]
+pub struct SpiNorFlash {
+    controller: Arc<dyn SpiNorController>,
+    core_index: usize,
+    core_name: String,
+    jedec_id: [u8; 3],
+    parameters: SfdpParameters,
+}
+
+impl SpiNorFlash {
+    /// Identifies the flash connected to `controller`, and reads its SFDP tables.
+    ///
+    /// The controller is accessed through the core that the memory map assigns to the address
+    /// range of the flash, or the default core of the target. The core is halted.
+    pub fn probe(
+        session: &mut Session,
+        controller: Arc<dyn SpiNorController>,
+    ) -> Result<Self, FlashError> {
+        let target = session.target();
+        let memory_range = controller.memory_range();
+        let core_name = target
+            .memory_map
+            .iter()
+            .filter(|region| region.address_range().intersects_range(&memory_range))
+            .find_map(|region| region.cores().first())
+            .unwrap_or(&target.default_core().name)
+            .clone();
+        let Some(core_index) = target.core_index_by_name(&core_name) else {
+            return Err(FlashError::Core(crate::Error::Other(format!(
+                "The memory map refers to the unknown core {core_name}"
+            ))));
+        };
+        let mut core = session.core(core_index).map_err(FlashError::Core)?;
+        halt(&mut core)?;
+
+        controller
+            .enter_command_mode(&mut core)
+            .map_err(FlashError::Core)?;
+
+        let mut jedec_id = [0; 3];
+        controller
+            .command(
+                &mut core,
+                SpiCommand::new(READ_JEDEC_ID),
+                SpiData::Read(&mut jedec_id),
+            )
+            .map_err(FlashError::Core)?;
+
+        let parameters = SfdpParameters::read(|offset, data| {
+            let command = SpiCommand {
+                dummy_cycles: 8,
+                ..SpiCommand::with_address(READ_SFDP, offset, 3)
+            };
+            controller
+                .command(&mut core, command, SpiData::Read(data))
+                .map_err(FlashError::Core)
+        })?;
+
+        tracing::info!(
+            "Found external flash {:02x?} with {} bytes",
+            jedec_id,
+            parameters.size
+        );
+
+        let flash = Self {
+            controller,
+            core_index,
+            core_name,
+            jedec_id,
+            parameters,
+        };
+        flash.enter_memory_mapped_mode(&mut core)?;
+
+        Ok(flash)
+    }
+
+    /// The JEDEC manufacturer and device ID of the flash.
+    pub fn jedec_id(&self) -> [u8; 3] {
+        self.jedec_id
+    }
+
+    /// The parameters read from the SFDP tables.
+    pub fn parameters(&self) -> &SfdpParameters {
+        &self.parameters
+    }
+
+    /// The address range where the flash is mapped into memory.
+    pub fn range(&self) -> Range<u64> {
+        let start = self.controller.memory_range().start;
+        start..start + self.parameters.size
+    }
+
+    /// The memory region of the flash.
+    pub fn nvm_region(&self) -> NvmRegion {
+        NvmRegion {
+            name: Some("External flash".to_string()),
+            range: self.range(),
+            cores: vec![self.core_name.clone()],
+            is_alias: false,
+            access: None,
+        }
+    }
+
+    /// A flash algorithm that describes the sectors and pages of the flash.
+    ///
+    /// The algorithm has no code, the flash is programmed from the host.
+    pub fn flash_algorithm(&self) -> FlashAlgorithm {
+        let erase_type = self.parameters.smallest_erase_type();
+        let max_erase_timeout = self
+            .parameters
+            .erase_types
+            .iter()
+            .map(|erase_type| erase_type.timeout)
+            .max()
+            .unwrap_or(erase_type.timeout);
+
+        FlashAlgorithm {
+            name: format!("SFDP {:02x?}", self.jedec_id),
+            flash_properties: FlashProperties {
+                address_range: self.range(),
+                page_size: self.parameters.page_size,
+                erased_byte_value: 0xFF,
+                program_page_timeout: self.parameters.page_program_timeout.as_millis() as u32,
+                erase_sector_timeout: max_erase_timeout.as_millis() as u32,
+                sectors: vec![SectorDescription {
+                    size: erase_type.size as u64,
+                    address: 0,
+                }],
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Erases the sectors and programs the pages of `layout`.
+    pub(super) fn program(
+        &self,
+        session: &mut Session,
+        layout: &mut FlashLayout,
+        progress: &mut FlashProgress<'_>,
+        restore_unwritten_bytes: bool,
+        skip_erasing: bool,
+    ) -> Result<(), FlashError> {
+        let mut core = session.core(self.core_index).map_err(FlashError::Core)?;
+        halt(&mut core)?;
+
+        if restore_unwritten_bytes {
+            progress.started_filling();
+            for fill in layout.fills.iter() {
+                let t = Instant::now();
+                let page = &mut layout.pages[fill.page_index()];
+                let page_offset = (fill.address() - page.address()) as usize;
+                core.read(
+                    fill.address(),
+                    &mut page.data_mut()[page_offset..][..fill.size() as usize],
+                )
+                .map_err(|error| {
+                    progress.failed_filling();
+                    FlashError::Core(error)
+                })?;
+                progress.page_filled(fill.size(), t.elapsed());
+            }
+            progress.finished_filling();
+        }
+
+        self.controller
+            .enter_command_mode(&mut core)
+            .map_err(FlashError::Core)?;
+        self.enter_address_mode(&mut core)?;
+
+        if !skip_erasing {
+            progress.started_erasing();
+            if let Err(error) = self.erase_sectors(&mut core, layout.sectors(), progress) {
+                progress.failed_erasing();
+                return Err(error);
+            }
+            progress.finished_erasing();
+        }
+
+        progress.started_programming();
+        for page in layout.pages() {
+            let t = Instant::now();
+            if let Err(source) = self.program_page(&mut core, page.address(), page.data()) {
+                progress.failed_programming();
+                return Err(FlashError::PageWrite {
+                    page_address: page.address(),
+                    source: Box::new(source),
+                });
+            }
+            progress.page_programmed(page.size() as u64, t.elapsed());
+        }
+        progress.finished_programming();
+
+        self.enter_memory_mapped_mode(&mut core)
+    }
+
+    /// Compares the pages of `layout` with the flash contents.
+    pub(super) fn verify(
+        &self,
+        session: &mut Session,
+        layout: &FlashLayout,
+        progress: &mut FlashProgress<'_>,
+        ignore_fills: bool,
+    ) -> Result<bool, FlashError> {
+        let mut core = session.core(self.core_index).map_err(FlashError::Core)?;
+        halt(&mut core)?;
+
+        progress.started_verifying();
+        for (index, page) in layout.pages().iter().enumerate() {
+            let t = Instant::now();
+            let mut read_back = vec![0; page.data().len()];
+            core.read(page.address(), &mut read_back).map_err(|error| {
+                progress.failed_verifying();
+                FlashError::Core(error)
+            })?;
+
+            if ignore_fills {
+                for fill in layout
+                    .fills()
+                    .iter()
+                    .filter(|fill| fill.page_index() == index)
+                {
+                    let offset = (fill.address() - page.address()) as usize;
+                    let size = fill.size() as usize;
+                    read_back[offset..][..size].copy_from_slice(&page.data()[offset..][..size]);
+                }
+            }
+
+            if read_back != page.data() {
+                tracing::debug!(
+                    "Verification failed for page at address {:#010x}",
+                    page.address()
+                );
+                progress.failed_verifying();
+                return Ok(false);
+            }
+            progress.page_verified(page.size() as u64, t.elapsed());
+        }
+        progress.finished_verifying();
+
+        Ok(true)
+    }
+
+    fn address_bytes(&self) -> u8 {
+        if self.parameters.size > THREE_BYTE_ADDRESS_LIMIT
+            || self.parameters.address_mode == AddressMode::FourByte
+        {
+            4
+        } else {
+            3
+        }
+    }
+
+    /// Switches flashes larger than 16 MiB to 4-byte addresses.
+    fn enter_address_mode(&self, core: &mut Core<'_>) -> Result<(), FlashError> {
+        if self.address_bytes() == 4 && self.parameters.address_mode == AddressMode::ThreeOrFourByte
+        {
+            self.send(core, SpiCommand::new(WRITE_ENABLE), SpiData::None)?;
+            self.send(
+                core,
+                SpiCommand::new(ENTER_4_BYTE_ADDRESS_MODE),
+                SpiData::None,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn enter_memory_mapped_mode(&self, core: &mut Core<'_>) -> Result<(), FlashError> {
+        self.controller
+            .enter_memory_mapped_mode(core, self.parameters.size, self.address_bytes())
+            .map_err(FlashError::Core)
+    }
+
+    fn erase_sectors(
+        &self,
+        core: &mut Core<'_>,
+        sectors: &[FlashSector],
+        progress: &mut FlashProgress<'_>,
+    ) -> Result<(), FlashError> {
+        let base = self.range().start;
+        for (address, erase_type) in erase_plan(sectors, &self.parameters.erase_types, base) {
+            let t = Instant::now();
+            let offset = (address - base) as u32;
+
+            let result = self
+                .send(core, SpiCommand::new(WRITE_ENABLE), SpiData::None)
+                .and_then(|_| {
+                    self.send(
+                        core,
+                        SpiCommand::with_address(erase_type.opcode, offset, self.address_bytes()),
+                        SpiData::None,
+                    )
+                })
+                .and_then(|_| self.wait_until_ready(core, erase_type.timeout));
+
+            if let Err(source) = result {
+                return Err(FlashError::EraseFailed {
+                    sector_address: address,
+                    source: Box::new(source),
+                });
+            }
+            progress.sector_erased(erase_type.size as u64, t.elapsed());
+        }
+
+        Ok(())
+    }
+
+    fn program_page(
+        &self,
+        core: &mut Core<'_>,
+        address: u64,
+        data: &[u8],
+    ) -> Result<(), FlashError> {
+        let offset = (address - self.range().start) as u32;
+
+        self.send(core, SpiCommand::new(WRITE_ENABLE), SpiData::None)?;
+        self.send(
+            core,
+            SpiCommand::with_address(PAGE_PROGRAM, offset, self.address_bytes()),
+            SpiData::Write(data),
+        )?;
+        self.wait_until_ready(core, self.parameters.page_program_timeout)
+    }
+
+    fn wait_until_ready(&self, core: &mut Core<'_>, timeout: Duration) -> Result<(), FlashError> {
+        let start = Instant::now();
+        loop {
+            let mut status = [0];
+            self.send(
+                core,
+                SpiCommand::new(READ_STATUS),
+                SpiData::Read(&mut status),
+            )?;
+            if status[0] & STATUS_BUSY == 0 {
+                return Ok(());
+            }
+            if start.elapsed() > timeout {
+                return Err(FlashError::Core(crate::Error::Timeout));
+            }
+        }
+    }
+
+    fn send(
+        &self,
+        core: &mut Core<'_>,
+        command: SpiCommand,
+        data: SpiData<'_>,
+    ) -> Result<(), FlashError> {
+        self.controller
+            .command(core, command, data)
+            .map_err(FlashError::Core)
+    }
+}
+
+fn halt(core: &mut Core<'_>) -> Result<(), FlashError> {
+    if !core.core_halted().map_err(FlashError::Core)? {
+        core.halt(Duration::from_millis(500))
+            .map_err(FlashError::Core)?;
+    }
+    Ok(())
+}
+
+/// Covers the sectors with as few erase commands as possible.
+///
+/// The sectors have the size of the smallest erase type. Larger erase types are used where the
+/// sectors cover an aligned block of their size.
+fn erase_plan(
+    sectors: &[FlashSector],
+    erase_types: &[EraseType],
+    base: u64,
+) -> Vec<(u64, EraseType)> {
+    let mut ranges: Vec<Range<u64>> = Vec::new();
+    for sector in sectors {
+        let start = sector.address();
+        let end = start + sector.size();
+        match ranges.last_mut() {
+            Some(range) if range.end == start => range.end = end,
+            _ => ranges.push(start..end),
+        }
+    }
+
+    let mut plan = Vec::new();
+    for range in ranges {
+        let mut address = range.start;
+        while address < range.end {
+            let erase_type = erase_types
+                .iter()
+                .rev()
+                .find(|erase_type| {
+                    let size = erase_type.size as u64;
+                    (address - base).is_multiple_of(size) && address + size <= range.end
+                })
+                .unwrap_or(&erase_types[0]);
+
+            plan.push((address, *erase_type));
+            address += erase_type.size as u64;
+        }
+    }
+
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn erase_plan_uses_large_blocks() {
+        let erase_types = [0x1000, 0x8000, 0x10000].map(|size| EraseType {
+            size,
+            opcode: size as u8,
+            timeout: Duration::from_secs(1),
+        });
+
+        // 0x9000_F000..0x9002_2000, 4 KiB sectors.
+        let sectors = (0..0x13)
+            .map(|index| FlashSector {
+                address: 0x9000_F000 + index * 0x1000,
+                size: 0x1000,
+            })
+            .collect::<Vec<_>>();
+
+        let plan = erase_plan(&sectors, &erase_types, 0x9000_0000)
+            .into_iter()
+            .map(|(address, erase_type)| (address, erase_type.size))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            plan,
+            [
+                (0x9000_F000, 0x1000),
+                (0x9001_0000, 0x10000),
+                (0x9002_0000, 0x1000),
+                (0x9002_1000, 0x1000),
+            ]
+        );
+    }
+}
@@ -91,13 +91,16 @@ pub mod test;
 
 pub use crate::config::{CoreType, Endian, InstructionSet, Target};
 #[cfg(feature = "coredump")]
-pub use crate::core::dump::{CoreDump, CoreDumpError};
+pub use crate::core::dump::{
+    CoreDump, CoreDumpError, DumpCompression, SessionCoreDump, SessionDump,
+};
 pub use crate::core::registers::RegisterDataType;
 pub use crate::core::registers::UnwindRule;
 pub use crate::core::{
     Architecture, BreakpointCause, Core, CoreInformation, CoreInterface, CoreRegister,
     CoreRegisters, CoreState, CoreStatus, HaltReason, MemoryMappedRegister, RegisterId,
-    RegisterRole, RegisterValue, SpecificCoreState, VectorCatchCondition,
+    RegisterRole, RegisterValue, SoftwareBreakpoint, SoftwareBreakpointLocation,
+    SoftwareBreakpoints, SpecificCoreState, VectorCatchCondition, Watchpoint, WatchpointKind,
 };
 pub use crate::error::{BreakpointError, Error};
 pub use crate::memory::MemoryInterface;
@@ -1,13 +1,21 @@
 //! Plugin system for probe-rs.
 //!
 //! This module contains the interfaces necessary to define and register plugins.
-//! Plugins can extend the functionality of probe-rs by adding e.g. new targets, probes, image formats.
+//! Plugins can extend the functionality of probe-rs by adding e.g. new targets, probes, image formats,
+//! RTT decoders or semihosting operations.
 //!
 //! Plugins are registered by calling the [`register_plugin`] function.
+//!
+//! Plugins can also be built as shared libraries that export their [`Plugin`] with
+//! [`export_plugin!`](crate::export_plugin). With the `dynamic-plugins` feature, such libraries
+//! are loaded at runtime by [`load_plugin`].
 
 use probe_rs_target::ChipFamily;
 
-use crate::{flashing::ImageFormat, probe::ProbeFactory, vendor::Vendor};
+use crate::{
+    flashing::ImageFormat, probe::ProbeFactory, rtt::RttDecoderFactory,
+    semihosting::SemihostingHandler, vendor::Vendor,
+};
 
 /// A plugin that can extend the functionality of probe-rs.
 #[derive(Clone, Default)]
@@ -23,6 +31,12 @@ pub struct Plugin<'p> {
 
     /// A list of probe driver factories.
     pub probe_drivers: &'p [&'static dyn ProbeFactory],
+
+    /// A list of decoders for RTT channels.
+    pub rtt_decoders: &'p [&'static dyn RttDecoderFactory],
+
+    /// A list of handlers for semihosting operations that probe-rs does not implement.
+    pub semihosting_handlers: &'p [&'static dyn SemihostingHandler],
 }
 
 /// Register a plugin.
@@ -40,4 +54,226 @@ pub fn register_plugin(plugin: Plugin<'_>) {
     for probe_driver in plugin.probe_drivers {
         crate::probe::register_probe_factory(*probe_driver);
     }
+    for rtt_decoder in plugin.rtt_decoders {
+        crate::rtt::register_rtt_decoder(*rtt_decoder);
+    }
+    for semihosting_handler in plugin.semihosting_handlers {
+        crate::semihosting::register_semihosting_handler(*semihosting_handler);
+    }
+}
+
+/// The version of the interface between probe-rs and plugins in shared libraries.
+///
+/// Increased whenever the layout of [`PluginDeclaration`] changes.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// The name of the symbol that [`export_plugin!`](crate::export_plugin) defines.
+pub const PLUGIN_DECLARATION_SYMBOL: &str = "PROBE_RS_PLUGIN_DECLARATION";
+
+/// Describes the plugin contained in a shared library.
+///
+/// Plugin and host exchange Rust types, which have no stable ABI. A plugin is therefore only
+/// loaded by a host that uses the same version of probe-rs, with the same features, built by the
+/// same compiler.
+#[repr(C)]
+pub struct PluginDeclaration {
+    /// The [`PLUGIN_ABI_VERSION`] the plugin was built with. Checked before any other field is
+    /// read.
+    pub abi_version: u32,
+
+    /// The version of probe-rs the plugin was built with.
+    pub probe_rs_version: &'static str,
+
+    /// The version of the compiler the plugin was built with.
+    pub rustc_version: &'static str,
+
+    /// The comma separated features of probe-rs the plugin was built with.
+    pub features: &'static str,
+
+    /// Returns the plugin to register.
+    pub plugin: fn() -> Plugin<'static>,
+}
+
+impl PluginDeclaration {
+    /// Creates the declaration of a plugin built against this version of probe-rs.
+    pub const fn new(plugin: fn() -> Plugin<'static>) -> Self {
+        Self {
+            abi_version: PLUGIN_ABI_VERSION,
+            probe_rs_version: env!("CARGO_PKG_VERSION"),
+            rustc_version: env!("PROBE_RS_RUSTC_VERSION"),
+            features: env!("PROBE_RS_FEATURES"),
+            plugin,
+        }
+    }
+
+    #[cfg(feature = "dynamic-plugins")]
+    fn build_description(&self) -> String {
+        format!(
+            "probe-rs {} (features: {}) and {}",
+            self.probe_rs_version, self.features, self.rustc_version
+        )
+    }
+}
+
+/// Exports a plugin from a shared library, so that it can be loaded with [`load_plugin`].
+///
+/// The argument is a function that returns the [`Plugin`]. The crate has to be built as a
+/// `cdylib`.
+///
+/// ```ignore
+/// fn plugin() -> probe_rs::plugin::Plugin<'static> {
+///     probe_rs::plugin::Plugin {
+///         vendors: &[&MyVendor],
+///         ..Default::default()
+///     }
+/// }
+///
+/// probe_rs::export_plugin!(plugin);
+/// ```
+#[macro_export]
+macro_rules! export_plugin {
+    ($plugin:path) => {
+        #[unsafe(no_mangle)]
+        pub static PROBE_RS_PLUGIN_DECLARATION: $crate::plugin::PluginDeclaration =
+            $crate::plugin::PluginDeclaration::new($plugin);
+    };
+}
+
+/// An error that occurred while loading a plugin from a shared library.
+#[cfg(feature = "dynamic-plugins")]
+#[derive(thiserror::Error, Debug, docsplay::Display)]
+pub enum PluginLoadError {
+    /// The library {0} could not be loaded.
+    Load(std::path::PathBuf, #[source] libloading::Error),
+
+    /// The library {0} is not a probe-rs plugin.
+    NotAPlugin(std::path::PathBuf),
+
+    /// The plugin {path} was built for plugin interface version {found}, but version {expected} is required.
+    AbiMismatch {
+        /// The path of the plugin.
+        path: std::path::PathBuf,
+        /// The interface version of the plugin.
+        found: u32,
+        /// The interface version of the host.
+        expected: u32,
+    },
+
+    /// The plugin {path} was built with {found}, but {expected} is required.
+    BuildMismatch {
+        /// The path of the plugin.
+        path: std::path::PathBuf,
+        /// The probe-rs version, features and compiler version of the plugin.
+        found: String,
+        /// The probe-rs version, features and compiler version of the host.
+        expected: String,
+    },
+
+    /// The plugin directory {0} could not be read.
+    ReadDir(std::path::PathBuf, #[source] std::io::Error),
+}
+
+/// Loads a plugin from a shared library and registers it.
+///
+/// The library stays loaded until the process exits.
+///
+/// # Safety
+///
+/// Loading a library runs its initialization code, and the plugin is trusted to declare its
+/// [`PluginDeclaration`] correctly. Only load libraries from trusted sources.
+#[cfg(feature = "dynamic-plugins")]
+pub unsafe fn load_plugin(path: &std::path::Path) -> Result<(), PluginLoadError> {
+    // SAFETY: The caller trusts the library.
+    let library = unsafe { libloading::Library::new(path) }
+        .map_err(|e| PluginLoadError::Load(path.to_path_buf(), e))?;
+
+    // SAFETY: The symbol is defined by `export_plugin!`. The ABI version is the first field, and
+    // is checked before the rest of the declaration is used.
+    let declaration =
+        unsafe { library.get::<*const PluginDeclaration>(PLUGIN_DECLARATION_SYMBOL.as_bytes()) }
+            .map_err(|_| PluginLoadError::NotAPlugin(path.to_path_buf()))?;
+    // SAFETY: The symbol points to a static, which lives as long as the library.
+    let declaration = unsafe { &**declaration };
+
+    let abi_version = declaration.abi_version;
+    if abi_version != PLUGIN_ABI_VERSION {
+        return Err(PluginLoadError::AbiMismatch {
+            path: path.to_path_buf(),
+            found: abi_version,
+            expected: PLUGIN_ABI_VERSION,
+        });
+    }
+
+    let host = PluginDeclaration::new(Plugin::default);
+    if declaration.probe_rs_version != host.probe_rs_version
+        || declaration.features != host.features
+        || declaration.rustc_version != host.rustc_version
+    {
+        return Err(PluginLoadError::BuildMismatch {
+            path: path.to_path_buf(),
+            found: declaration.build_description(),
+            expected: host.build_description(),
+        });
+    }
+
+    register_plugin((declaration.plugin)());
+    tracing::debug!("Loaded plugin {}", path.display());
+
+    // The registered plugin references code and data of the library.
+    std::mem::forget(library);
+
+    Ok(())
+}
+
+/// Loads all shared libraries in a directory as plugins, in alphabetical order.
+///
+/// Returns the paths of the loaded plugins.
+///
+/// # Safety
+///
+/// See [`load_plugin`].
+#[cfg(feature = "dynamic-plugins")]
+pub unsafe fn load_plugins_from_dir(
+    dir: &std::path::Path,
+) -> Result<Vec<std::path::PathBuf>, PluginLoadError> {
+    let mut paths = std::fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| PluginLoadError::ReadDir(dir.to_path_buf(), e))?;
+    paths.retain(|path| {
+        path.is_file()
+            && path.extension().and_then(|ext| ext.to_str())
+                == Some(std::env::consts::DLL_EXTENSION)
+    });
+    paths.sort();
+
+    for path in &paths {
+        // SAFETY: Forwarded to the caller.
+        unsafe { load_plugin(path)? };
+    }
+
+    Ok(paths)
+}
+
+#[cfg(all(test, feature = "dynamic-plugins"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn missing_library_is_an_error() {
+        let result = unsafe { load_plugin(std::path::Path::new("does-not-exist.so")) };
+        assert!(matches!(result, Err(PluginLoadError::Load(..))));
+    }
+
+    #[test]
+    fn plugin_directory_without_libraries() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("README.txt"), "not a plugin").unwrap();
+
+        let loaded = unsafe { load_plugins_from_dir(dir.path()) }.unwrap();
+        assert!(loaded.is_empty());
+    }
 }
@@ -509,6 +509,11 @@ impl Probe {
         }
     }
 
+    /// Configure protocol speed to use in kHz, while the probe is attached to a target.
+    pub(crate) fn set_speed_attached(&mut self, speed_khz: u32) -> Result<u32, DebugProbeError> {
+        self.inner.set_speed(speed_khz)
+    }
+
     /// Get the currently used maximum speed for the debug protocol in kHz.
     ///
     /// Not all probes report which speed is used, meaning this value is not
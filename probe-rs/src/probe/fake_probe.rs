@@ -7,6 +7,7 @@ use crate::{
         ap::memory_ap::mock::MockMemoryAp,
         armv8m::Dhcsr,
         communication_interface::{DapProbe, SwdSequence},
+        core::cortex_m::{Dcrdr, Dcrsr},
         dp::{DpAddress, DpRegisterAddress},
         memory::{ADIMemoryInterface, ArmMemoryInterface},
         sequences::ArmDebugSequence,
@@ -22,9 +23,10 @@ use object::{
 };
 use std::{
     cell::RefCell,
-    collections::{BTreeSet, VecDeque},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     fmt::Debug,
     sync::Arc,
+    time::Duration,
 };
 
 /// This is a mock probe which can be used for mocking things in tests or for dry runs.
@@ -95,6 +97,8 @@ struct MockCore {
     program_binary: Option<Vec<u8>>,
     loadable_segments: Vec<LoadableSegment>,
     endianness: Endianness,
+
+    simulation: Option<Simulation>,
 }
 
 impl MockCore {
@@ -105,8 +109,113 @@ impl MockCore {
             program_binary: None,
             loadable_segments: Vec::new(),
             endianness: Endianness::Little,
+            simulation: None,
+        }
+    }
+
+    fn write_dhcsr(&mut self, word: u32) {
+        let dbg_key = (word >> 16) & 0xffff;
+
+        if dbg_key == 0xa05f {
+            // Mask out dbg key
+            self.dhcsr = Dhcsr::from(word & 0xffff);
+            println!("Write DHCSR = {word:#010x}");
+
+            let request_halt = self.dhcsr.c_halt();
+
+            self.is_halted = request_halt;
+
+            if !self.dhcsr.c_halt() && self.dhcsr.c_debugen() && self.dhcsr.c_step() {
+                tracing::debug!("MockCore: Single step requested, setting s_halt");
+                self.is_halted = true;
+            }
+
+            if let Some(simulation) = &mut self.simulation
+                && !self.is_halted
+                && self.dhcsr.c_debugen()
+            {
+                tracing::debug!("MockCore: Running a routine, setting s_halt");
+                simulation.run();
+                self.is_halted = true;
+            }
+        }
+    }
+
+    /// Waits for the simulated latency of a memory access.
+    fn access(&mut self) -> Option<&mut Simulation> {
+        let simulation = self.simulation.as_mut()?;
+        std::thread::sleep(simulation.latency);
+        Some(simulation)
+    }
+}
+
+/// The state of a simulated core, which keeps the written memory and core registers.
+///
+/// The core finishes every routine it runs immediately and returns 0, so flash algorithms
+/// succeed without changing the flash contents.
+struct Simulation {
+    latency: Duration,
+    memory: BTreeMap<u64, u8>,
+    registers: BTreeMap<u32, u32>,
+    dcrdr: u32,
+}
+
+impl Simulation {
+    fn new(latency: Duration) -> Self {
+        Self {
+            latency,
+            memory: BTreeMap::new(),
+            registers: BTreeMap::new(),
+            dcrdr: 0,
+        }
+    }
+
+    fn read(&self, address: u64, data: &mut [u8]) {
+        for (offset, byte) in data.iter_mut().enumerate() {
+            *byte = self
+                .memory
+                .get(&(address + offset as u64))
+                .copied()
+                .unwrap_or(0);
+        }
+    }
+
+    fn write(&mut self, address: u64, data: &[u8]) {
+        for (offset, byte) in data.iter().enumerate() {
+            self.memory.insert(address + offset as u64, *byte);
+        }
+    }
+
+    fn read_word(&self, address: u64) -> u32 {
+        match address {
+            Dcrdr::ADDRESS_OFFSET => self.dcrdr,
+            address => {
+                let mut bytes = [0; 4];
+                self.read(address, &mut bytes);
+                u32::from_le_bytes(bytes)
+            }
         }
     }
+
+    fn write_word(&mut self, address: u64, word: u32) {
+        match address {
+            Dcrdr::ADDRESS_OFFSET => self.dcrdr = word,
+            Dcrsr::ADDRESS_OFFSET => {
+                let register = word & 0x7F;
+                if word & (1 << 16) != 0 {
+                    self.registers.insert(register, self.dcrdr);
+                } else {
+                    self.dcrdr = self.registers.get(&register).copied().unwrap_or(0);
+                }
+            }
+            address => self.write(address, &word.to_le_bytes()),
+        }
+    }
+
+    /// Runs a routine, which returns 0 in R0.
+    fn run(&mut self) {
+        self.registers.insert(0, 0);
+    }
 }
 
 impl SwdSequence for &mut MockCore {
@@ -126,6 +235,11 @@ impl SwdSequence for &mut MockCore {
 
 impl MemoryInterface<ArmError> for &mut MockCore {
     fn read_8(&mut self, address: u64, data: &mut [u8]) -> Result<(), ArmError> {
+        if let Some(simulation) = self.access() {
+            simulation.read(address, data);
+            return Ok(());
+        }
+
         let mut curr_seg: Option<&LoadableSegment> = None;
 
         for (offset, val) in data.iter_mut().enumerate() {
@@ -154,11 +268,36 @@ impl MemoryInterface<ArmError> for &mut MockCore {
         Ok(())
     }
 
-    fn read_16(&mut self, _address: u64, _data: &mut [u16]) -> Result<(), ArmError> {
-        todo!()
+    fn read_16(&mut self, address: u64, data: &mut [u16]) -> Result<(), ArmError> {
+        let Some(simulation) = self.access() else {
+            todo!()
+        };
+
+        for (offset, value) in data.iter_mut().enumerate() {
+            let mut bytes = [0; 2];
+            simulation.read(address + offset as u64 * 2, &mut bytes);
+            *value = u16::from_le_bytes(bytes);
+        }
+        Ok(())
     }
 
     fn read_32(&mut self, address: u64, data: &mut [u32]) -> Result<(), ArmError> {
+        let is_halted = self.is_halted;
+        let dhcsr = self.dhcsr;
+        if let Some(simulation) = self.access() {
+            for (offset, value) in data.iter_mut().enumerate() {
+                let address = address + offset as u64 * 4;
+                *value = match address {
+                    Dhcsr::ADDRESS_OFFSET => {
+                        // S_REGRDY, and S_HALT while halted.
+                        u32::from(dhcsr) | (1 << 16) | (u32::from(is_halted) << 17)
+                    }
+                    address => simulation.read_word(address),
+                };
+            }
+            return Ok(());
+        }
+
         let mut curr_seg: Option<&LoadableSegment> = None;
 
         for (offset, val) in data.iter_mut().enumerate() {
@@ -221,42 +360,58 @@ impl MemoryInterface<ArmError> for &mut MockCore {
         Ok(())
     }
 
-    fn read_64(&mut self, _address: u64, _data: &mut [u64]) -> Result<(), ArmError> {
-        todo!()
+    fn read_64(&mut self, address: u64, data: &mut [u64]) -> Result<(), ArmError> {
+        let Some(simulation) = self.access() else {
+            todo!()
+        };
+
+        for (offset, value) in data.iter_mut().enumerate() {
+            let mut bytes = [0; 8];
+            simulation.read(address + offset as u64 * 8, &mut bytes);
+            *value = u64::from_le_bytes(bytes);
+        }
+        Ok(())
     }
 
-    fn write_8(&mut self, _address: u64, _data: &[u8]) -> Result<(), ArmError> {
-        todo!()
+    fn write_8(&mut self, address: u64, data: &[u8]) -> Result<(), ArmError> {
+        let Some(simulation) = self.access() else {
+            todo!()
+        };
+
+        simulation.write(address, data);
+        Ok(())
     }
 
-    fn write_16(&mut self, _address: u64, _data: &[u16]) -> Result<(), ArmError> {
-        todo!()
+    fn write_16(&mut self, address: u64, data: &[u16]) -> Result<(), ArmError> {
+        let Some(simulation) = self.access() else {
+            todo!()
+        };
+
+        for (offset, value) in data.iter().enumerate() {
+            simulation.write(address + offset as u64 * 2, &value.to_le_bytes());
+        }
+        Ok(())
     }
 
     fn write_32(&mut self, address: u64, data: &[u32]) -> Result<(), ArmError> {
+        if self.access().is_some() {
+            for (offset, word) in data.iter().enumerate() {
+                let address = address + offset as u64 * 4;
+                if address == Dhcsr::ADDRESS_OFFSET {
+                    self.write_dhcsr(*word);
+                } else if let Some(simulation) = &mut self.simulation {
+                    simulation.write_word(address, *word);
+                }
+            }
+            return Ok(());
+        }
+
         for (i, word) in data.iter().enumerate() {
             let address = address + (i as u64 * 4);
 
             match address {
                 // DHCSR
-                Dhcsr::ADDRESS_OFFSET => {
-                    let dbg_key = (*word >> 16) & 0xffff;
-
-                    if dbg_key == 0xa05f {
-                        // Mask out dbg key
-                        self.dhcsr = Dhcsr::from(*word & 0xffff);
-                        println!("Write DHCSR = {word:#010x}");
-
-                        let request_halt = self.dhcsr.c_halt();
-
-                        self.is_halted = request_halt;
-
-                        if !self.dhcsr.c_halt() && self.dhcsr.c_debugen() && self.dhcsr.c_step() {
-                            tracing::debug!("MockCore: Single step requested, setting s_halt");
-                            self.is_halted = true;
-                        }
-                    }
-                }
+                Dhcsr::ADDRESS_OFFSET => self.write_dhcsr(*word),
                 _ => println!("Write {address:#010x} = {word:#010x}"),
             }
         }
@@ -264,8 +419,15 @@ impl MemoryInterface<ArmError> for &mut MockCore {
         Ok(())
     }
 
-    fn write_64(&mut self, _address: u64, _data: &[u64]) -> Result<(), ArmError> {
-        todo!()
+    fn write_64(&mut self, address: u64, data: &[u64]) -> Result<(), ArmError> {
+        let Some(simulation) = self.access() else {
+            todo!()
+        };
+
+        for (offset, value) in data.iter().enumerate() {
+            simulation.write(address + offset as u64 * 8, &value.to_le_bytes());
+        }
+        Ok(())
     }
 
     fn flush(&mut self) -> Result<(), ArmError> {
@@ -277,6 +439,10 @@ impl MemoryInterface<ArmError> for &mut MockCore {
     }
 
     fn supports_8bit_transfers(&self) -> Result<bool, ArmError> {
+        if self.simulation.is_some() {
+            return Ok(true);
+        }
+
         todo!()
     }
 }
@@ -343,6 +509,21 @@ impl FakeProbe {
         }
     }
 
+    /// Fake probe with a simulated core, which keeps the written memory and registers, and
+    /// finishes every routine it runs immediately.
+    ///
+    /// Flash algorithms succeed on this core, so the whole flash programming path can run, with
+    /// every memory access taking `latency`. The flash contents don't change.
+    pub fn with_simulated_core(latency: Duration) -> Self {
+        let mut core = MockCore::new();
+        core.simulation = Some(Simulation::new(latency));
+
+        FakeProbe {
+            memory_ap: MockedAp::Core(core),
+            ..Self::default()
+        }
+    }
+
     /// Fake probe with a mocked core
     /// with access to an actual binary file.
     #[cfg(any(test, feature = "test"))]
@@ -755,8 +936,22 @@ impl DapAccess for FakeArmInterface {
 
 #[cfg(all(test, feature = "builtin-targets"))]
 mod test {
+    use std::time::Duration;
+
     use super::FakeProbe;
-    use crate::Permissions;
+    use crate::{Permissions, flashing::DownloadOptions};
+
+    #[test]
+    fn flash_with_simulated_core() {
+        let probe = FakeProbe::with_simulated_core(Duration::ZERO).into_probe();
+        let mut session = probe
+            .attach("nrf51822_xxAC", Permissions::default())
+            .unwrap();
+
+        let mut loader = session.target().flash_loader();
+        loader.add_data(0, &[0x55; 0x1000]).unwrap();
+        loader.commit(&mut session, DownloadOptions::new()).unwrap();
+    }
 
     #[test]
     fn create_session_with_fake_probe() {
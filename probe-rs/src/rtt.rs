@@ -45,7 +45,11 @@
 //! ```
 
 mod channel;
+mod decoder;
+mod discovery;
 pub use channel::*;
+pub use decoder::*;
+pub use discovery::*;
 #[cfg(feature = "object")]
 use object::{Object as _, ObjectSymbol as _};
 
@@ -54,9 +58,7 @@ use crate::Session;
 use crate::meta::ElfMetadata;
 use crate::{Core, MemoryInterface, config::MemoryRegion};
 use std::ops::Range;
-use std::thread;
 use std::time::Duration;
-use std::time::Instant;
 use zerocopy::{FromBytes, IntoBytes};
 
 /// Extract the RTT control block from a raw file, usually an ELF file.
@@ -102,6 +104,9 @@ pub fn find_rtt_control_block_in_file(file: &object::File) -> Option<u64> {
 ///     channels, as they were configured on the target. The timing of when this is called is really
 ///     important, or else unexpected results can be expected.
 ///
+/// [`RttDiscovery`] combines the ELF symbol, pinned addresses and a memory scan, and retries
+/// until the target has initialized RTT.
+///
 /// ## Examples of how timing between host and target effects the results
 ///
 /// 1. **Scenario: Ideal configuration**: The host RTT interface is created **AFTER** the target
@@ -569,34 +574,19 @@ fn display_list(list: &[u64]) -> String {
         .join(", ")
 }
 
-fn try_attach_to_rtt_inner(
-    mut try_attach_once: impl FnMut() -> Result<Rtt, Error>,
-    timeout: Duration,
-) -> Result<Rtt, Error> {
-    let t = Instant::now();
-    let mut attempt = 1;
-    loop {
-        tracing::debug!("Initializing RTT (attempt {attempt})...");
-
-        match try_attach_once() {
-            err @ Err(Error::NoControlBlockLocation) => return err,
-            Err(_) if t.elapsed() < timeout => {
-                attempt += 1;
-                tracing::debug!("Failed to initialize RTT. Retrying until timeout.");
-                thread::sleep(Duration::from_millis(50));
-            }
-            other => return other,
-        }
-    }
-}
-
 /// Try to attach to RTT, with the given timeout.
+///
+/// See [`RttDiscovery`] to combine the region with the ELF symbol or pinned addresses.
 pub fn try_attach_to_rtt(
     core: &mut Core<'_>,
     timeout: Duration,
     rtt_region: &ScanRegion,
 ) -> Result<Rtt, Error> {
-    try_attach_to_rtt_inner(|| Rtt::attach_region(core, rtt_region), timeout)
+    let discovery = RttDiscovery {
+        timeout,
+        ..RttDiscovery::from(rtt_region.clone())
+    };
+    discovery.attach(core).map(|(rtt, _)| rtt)
 }
 
 /// Try to attach to RTT, with the given timeout.
@@ -606,14 +596,13 @@ pub fn try_attach_to_rtt_shared(
     timeout: Duration,
     rtt_region: &ScanRegion,
 ) -> Result<Rtt, Error> {
-    try_attach_to_rtt_inner(
-        || {
-            let mut session_handle = session.lock();
-            let mut core = session_handle.core(core_id)?;
-            Rtt::attach_region(&mut core, rtt_region)
-        },
+    let discovery = RttDiscovery {
         timeout,
-    )
+        ..RttDiscovery::from(rtt_region.clone())
+    };
+    discovery
+        .attach_shared(session, core_id)
+        .map(|(rtt, _)| rtt)
 }
 
 #[cfg(test)]
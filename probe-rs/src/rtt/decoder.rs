@@ -0,0 +1,104 @@
+use crate::rtt::Error;
+use parking_lot::RwLock;
+use std::sync::LazyLock;
+
+/// Creates decoders for the data of RTT up channels.
+///
+/// Decoder factories are registered with a [`Plugin`](crate::plugin::Plugin). A front end picks
+/// a decoder either because its configuration names the decoder, see [`rtt_decoder`], or because
+/// the decoder claims the name of the channel, see [`rtt_decoder_for_channel`].
+pub trait RttDecoderFactory: Send + Sync {
+    /// The name used to select the decoder in the configuration of a channel.
+    fn name(&self) -> &str;
+
+    /// Returns `true` if channels with the given name should be decoded by this decoder, unless
+    /// the configuration selects a different one.
+    fn matches_channel(&self, channel_name: &str) -> bool {
+        let _ = channel_name;
+        false
+    }
+
+    /// Creates a decoder for a single channel.
+    fn create(&self, channel_name: &str) -> Box<dyn RttChannelDecoder>;
+}
+
+/// Turns the data of an RTT up channel into text.
+///
+/// A decoder is created for every channel, and may keep state between calls, e.g. for frames
+/// that are split over several reads.
+pub trait RttChannelDecoder: Send {
+    /// Decodes the data read from the channel. Returns the text to display, which may be empty
+    /// if more data is needed.
+    fn decode(&mut self, data: &[u8]) -> Result<String, Error>;
+}
+
+/// The RTT decoders registered by plugins.
+static DECODERS: LazyLock<RwLock<Vec<&'static dyn RttDecoderFactory>>> =
+    LazyLock::new(|| RwLock::new(vec![]));
+
+/// Registers an [`RttDecoderFactory`].
+///
+/// Decoders registered later take precedence over decoders that claim the same channel or
+/// name.
+pub(crate) fn register_rtt_decoder(factory: &'static dyn RttDecoderFactory) {
+    DECODERS.write().insert(0, factory);
+}
+
+/// Returns the registered RTT decoder with the given name.
+pub fn rtt_decoder(name: &str) -> Option<&'static dyn RttDecoderFactory> {
+    DECODERS
+        .read()
+        .iter()
+        .find(|factory| factory.name() == name)
+        .copied()
+}
+
+/// Returns the registered RTT decoder that claims channels with the given name.
+pub fn rtt_decoder_for_channel(channel_name: &str) -> Option<&'static dyn RttDecoderFactory> {
+    DECODERS
+        .read()
+        .iter()
+        .find(|factory| factory.matches_channel(channel_name))
+        .copied()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Hex;
+
+    impl RttDecoderFactory for Hex {
+        fn name(&self) -> &str {
+            "test-hex"
+        }
+
+        fn matches_channel(&self, channel_name: &str) -> bool {
+            channel_name == "test-hex-channel"
+        }
+
+        fn create(&self, _channel_name: &str) -> Box<dyn RttChannelDecoder> {
+            Box::new(Hex)
+        }
+    }
+
+    impl RttChannelDecoder for Hex {
+        fn decode(&mut self, data: &[u8]) -> Result<String, Error> {
+            Ok(data.iter().map(|byte| format!("{byte:02x}")).collect())
+        }
+    }
+
+    #[test]
+    fn registered_decoders_are_found() {
+        register_rtt_decoder(&Hex);
+
+        assert!(rtt_decoder("test-hex").is_some());
+        assert!(rtt_decoder("defmt").is_none());
+        assert!(rtt_decoder_for_channel("Terminal").is_none());
+
+        let mut decoder = rtt_decoder_for_channel("test-hex-channel")
+            .unwrap()
+            .create("test-hex-channel");
+        assert_eq!(decoder.decode(&[0xde, 0xad]).unwrap(), "dead");
+    }
+}
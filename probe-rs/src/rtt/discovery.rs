@@ -0,0 +1,272 @@
+use crate::rtt::{Error, Rtt, ScanRegion};
+use crate::{Core, MemoryInterface, Session};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The strategy that located an RTT control block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, docsplay::Display)]
+pub enum RttDiscoveryStrategy {
+    /// the `_SEGGER_RTT` symbol
+    Symbol,
+    /// a pinned address
+    Pinned,
+    /// a memory scan
+    Scan,
+}
+
+/// The location of an RTT control block, and the strategy that found it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RttLocation {
+    /// The address of the control block in target memory.
+    pub address: u64,
+    /// The strategy that found the control block.
+    pub strategy: RttDiscoveryStrategy,
+}
+
+/// Locates the RTT control block by trying several strategies in a fixed order.
+///
+/// On every attempt, the strategies are tried in this order:
+///
+/// 1. The address of the `_SEGGER_RTT` symbol, usually taken from the ELF file.
+/// 2. The pinned addresses, in the order they were given.
+/// 3. A scan of the [`ScanRegion`].
+///
+/// The first address that contains the RTT magic string is used. Because firmware may
+/// initialize RTT some time after boot, [`RttDiscovery::attach`] keeps retrying until the
+/// control block is found or the timeout expires.
+#[derive(Clone, Debug)]
+pub struct RttDiscovery {
+    /// The address of the `_SEGGER_RTT` symbol.
+    pub symbol: Option<u64>,
+
+    /// Addresses where the control block is expected, e.g. given by the user.
+    pub pinned: Vec<u64>,
+
+    /// The region to scan if the control block is not found at a known address.
+    pub scan: Option<ScanRegion>,
+
+    /// How long to keep retrying. A zero timeout makes a single attempt.
+    pub timeout: Duration,
+
+    /// How long to wait between attempts.
+    pub retry_interval: Duration,
+}
+
+impl Default for RttDiscovery {
+    fn default() -> Self {
+        Self {
+            symbol: None,
+            pinned: Vec::new(),
+            scan: None,
+            timeout: Duration::ZERO,
+            retry_interval: Duration::from_millis(50),
+        }
+    }
+}
+
+impl From<ScanRegion> for RttDiscovery {
+    /// Creates a pipeline that only uses the given region. An exact address is pinned, an empty
+    /// list of ranges disables scanning.
+    fn from(region: ScanRegion) -> Self {
+        let mut discovery = Self::default();
+        match region {
+            ScanRegion::Exact(address) => discovery.pinned.push(address),
+            ScanRegion::Ranges(ranges) if ranges.is_empty() => {}
+            region => discovery.scan = Some(region),
+        }
+        discovery
+    }
+}
+
+impl RttDiscovery {
+    /// Creates an empty pipeline. Without any strategy, discovery fails with
+    /// [`Error::NoControlBlockLocation`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the address of the `_SEGGER_RTT` symbol from an ELF file, if the file defines it.
+    #[cfg(feature = "object")]
+    pub fn with_symbol_from_elf(mut self, raw_file: &[u8]) -> Result<Self, object::Error> {
+        self.symbol = super::find_rtt_control_block_in_raw_file(raw_file)?;
+        Ok(self)
+    }
+
+    /// Returns the addresses where the control block is expected, without scanning.
+    pub fn known_addresses(&self) -> impl Iterator<Item = u64> + '_ {
+        self.symbol.into_iter().chain(self.pinned.iter().copied())
+    }
+
+    /// Returns `true` if at least one strategy is configured.
+    pub fn has_strategy(&self) -> bool {
+        self.symbol.is_some()
+            || !self.pinned.is_empty()
+            || match &self.scan {
+                Some(ScanRegion::Ranges(ranges)) => !ranges.is_empty(),
+                Some(_) => true,
+                None => false,
+            }
+    }
+
+    /// Tries every strategy once and returns the first location that contains a control block.
+    ///
+    /// The control block itself is not validated, use [`Rtt::attach_at`] for that.
+    pub fn locate(&self, core: &mut Core) -> Result<RttLocation, Error> {
+        if !self.has_strategy() {
+            return Err(Error::NoControlBlockLocation);
+        }
+
+        let candidates = self
+            .symbol
+            .map(|address| (address, RttDiscoveryStrategy::Symbol))
+            .into_iter()
+            .chain(
+                self.pinned
+                    .iter()
+                    .map(|&address| (address, RttDiscoveryStrategy::Pinned)),
+            );
+
+        let mut error = Error::ControlBlockNotFound;
+        for (address, strategy) in candidates {
+            match has_control_block_at(core, address) {
+                Ok(true) => return Ok(RttLocation { address, strategy }),
+                Ok(false) => {
+                    tracing::debug!("No RTT control block at {address:#010x} ({strategy})")
+                }
+                Err(e) => {
+                    tracing::debug!("Failed to read RTT control block at {address:#010x}: {e}");
+                    error = e;
+                }
+            }
+        }
+
+        match &self.scan {
+            Some(region) if !matches!(region, ScanRegion::Ranges(r) if r.is_empty()) => {
+                Rtt::find_control_block(core, region).map(|address| RttLocation {
+                    address,
+                    strategy: RttDiscoveryStrategy::Scan,
+                })
+            }
+            _ => Err(error),
+        }
+    }
+
+    /// Locates the control block and attaches to it, retrying until the timeout expires.
+    pub fn attach(&self, core: &mut Core) -> Result<(Rtt, RttLocation), Error> {
+        self.retry(|| self.attach_once(core))
+    }
+
+    /// Like [`RttDiscovery::attach`], but only locks the session while an attempt is made.
+    pub fn attach_shared(
+        &self,
+        session: &parking_lot::FairMutex<Session>,
+        core_id: usize,
+    ) -> Result<(Rtt, RttLocation), Error> {
+        self.retry(|| {
+            let mut session_handle = session.lock();
+            let mut core = session_handle.core(core_id)?;
+            self.attach_once(&mut core)
+        })
+    }
+
+    fn attach_once(&self, core: &mut Core) -> Result<(Rtt, RttLocation), Error> {
+        let location = self.locate(core)?;
+        let rtt = Rtt::attach_at(core, location.address)?;
+
+        tracing::debug!(
+            "Attached to RTT control block at {:#010x}, found by {}",
+            location.address,
+            location.strategy
+        );
+
+        Ok((rtt, location))
+    }
+
+    fn retry<T>(&self, mut attempt: impl FnMut() -> Result<T, Error>) -> Result<T, Error> {
+        let start = Instant::now();
+        let mut attempts = 1;
+        loop {
+            tracing::debug!("Initializing RTT (attempt {attempts})...");
+
+            match attempt() {
+                err @ Err(Error::NoControlBlockLocation) => return err,
+                Err(_) if start.elapsed() < self.timeout => {
+                    attempts += 1;
+                    tracing::debug!("Failed to initialize RTT. Retrying until timeout.");
+                    thread::sleep(self.retry_interval);
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+/// Checks whether the RTT magic string is present at the given address.
+fn has_control_block_at(core: &mut Core, address: u64) -> Result<bool, Error> {
+    let mut magic = [0; Rtt::RTT_ID.len()];
+    core.read(address, &mut magic)?;
+    Ok(magic == Rtt::RTT_ID)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exact_scan_region_is_pinned() {
+        let discovery = RttDiscovery::from(ScanRegion::Exact(0x2000_0000));
+        assert_eq!(discovery.pinned, [0x2000_0000]);
+        assert!(discovery.scan.is_none());
+
+        let discovery = RttDiscovery {
+            symbol: Some(0x2000_1000),
+            ..discovery
+        };
+        assert_eq!(
+            discovery.known_addresses().collect::<Vec<_>>(),
+            [0x2000_1000, 0x2000_0000]
+        );
+    }
+
+    #[test]
+    fn empty_ranges_disable_scanning() {
+        assert!(!RttDiscovery::from(ScanRegion::Ranges(vec![])).has_strategy());
+        assert!(RttDiscovery::from(ScanRegion::Ram).has_strategy());
+    }
+
+    #[test]
+    fn retries_until_timeout() {
+        let discovery = RttDiscovery {
+            timeout: Duration::from_millis(20),
+            retry_interval: Duration::from_millis(1),
+            ..RttDiscovery::from(ScanRegion::Ram)
+        };
+
+        let mut attempts = 0;
+        let result = discovery.retry(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(Error::ControlBlockNotFound)
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[test]
+    fn missing_location_is_not_retried() {
+        let discovery = RttDiscovery {
+            timeout: Duration::from_secs(10),
+            ..RttDiscovery::new()
+        };
+
+        let mut attempts = 0;
+        let result = discovery.retry::<()>(|| {
+            attempts += 1;
+            Err(Error::NoControlBlockLocation)
+        });
+        assert!(matches!(result, Err(Error::NoControlBlockLocation)));
+        assert_eq!(attempts, 1);
+    }
+}
@@ -2,7 +2,9 @@
 //!
 //! Specification: <https://github.com/ARM-software/abi-aa/blob/2024Q3/semihosting/semihosting.rst>
 
-use std::{num::NonZeroU32, time::SystemTime};
+use std::{num::NonZeroU32, sync::LazyLock, time::SystemTime};
+
+use parking_lot::RwLock;
 
 use crate::{Core, CoreInterface, Error, MemoryInterface, RegisterValue};
 
@@ -53,6 +55,27 @@ pub enum SemihostingCommand {
     /// The target indicated that it would like to read the value of errno.
     Errno(ErrnoRequest),
 
+    /// The target indicated that it would like to know whether a file handle is an interactive device.
+    IsTty(IsTtyRequest),
+
+    /// The target indicated that it would like a temporary file name on the host.
+    TmpNam(TmpNamRequest),
+
+    /// The target indicated that it would like to run a command on the host.
+    System(SystemRequest),
+
+    /// The target indicated that it would like to read the execution time in centiseconds.
+    Clock(ClockRequest),
+
+    /// The target indicated that it would like to read the elapsed time in ticks.
+    Elapsed(ElapsedRequest),
+
+    /// The target indicated that it would like to read the tick frequency used by [`SemihostingCommand::Elapsed`].
+    TickFreq(TickFreqRequest),
+
+    /// The target indicated that it would like to read the heap and stack layout.
+    HeapInfo(HeapInfoRequest),
+
     /// The target indicated that it would like to run a semihosting operation which we don't support yet.
     Unknown(UnknownCommandDetails),
 }
@@ -123,6 +146,64 @@ impl UnknownCommandDetails {
     pub fn write_status(&self, core: &mut dyn CoreInterface, status: i32) -> Result<(), Error> {
         write_status(core, status)
     }
+
+    /// Returns `true` if a registered [`SemihostingHandler`] implements the operation.
+    pub fn has_registered_handler(&self) -> bool {
+        semihosting_handler(self.operation).is_some()
+    }
+
+    /// Runs the operation with the [`SemihostingHandler`] registered for it.
+    ///
+    /// Returns `false` if no registered handler implements the operation.
+    pub fn run_registered_handler(&self, core: &mut dyn CoreInterface) -> Result<bool, Error> {
+        let Some(handler) = semihosting_handler(self.operation) else {
+            return Ok(false);
+        };
+
+        handler.handle(core, *self)?;
+        Ok(true)
+    }
+}
+
+/// Implements semihosting operations that probe-rs does not know about.
+///
+/// Handlers are registered with a [`Plugin`](crate::plugin::Plugin), and are asked to run
+/// operations that are decoded as [`SemihostingCommand::Unknown`]. The semihosting specification
+/// reserves the operations `0x100..=0x1FF` for such application specific extensions.
+pub trait SemihostingHandler: Send + Sync {
+    /// Returns `true` if the handler implements the operation.
+    fn handles(&self, operation: u32) -> bool;
+
+    /// Runs the operation.
+    ///
+    /// Before the handler is called, the status `-1` has already been written to the target.
+    /// Use [`UnknownCommandDetails::write_status`] to return a different value.
+    fn handle(
+        &self,
+        core: &mut dyn CoreInterface,
+        details: UnknownCommandDetails,
+    ) -> Result<(), Error>;
+}
+
+/// The semihosting handlers registered by plugins.
+static HANDLERS: LazyLock<RwLock<Vec<&'static dyn SemihostingHandler>>> =
+    LazyLock::new(|| RwLock::new(vec![]));
+
+/// Registers a [`SemihostingHandler`].
+///
+/// Handlers registered later take precedence over handlers for the same operation that were
+/// registered earlier.
+pub(crate) fn register_semihosting_handler(handler: &'static dyn SemihostingHandler) {
+    HANDLERS.write().insert(0, handler);
+}
+
+/// Returns the registered handler for a semihosting operation, if there is one.
+pub fn semihosting_handler(operation: u32) -> Option<&'static dyn SemihostingHandler> {
+    HANDLERS
+        .read()
+        .iter()
+        .find(|handler| handler.handles(operation))
+        .copied()
 }
 
 /// A request to read the command line arguments from the target
@@ -398,6 +479,163 @@ impl ErrnoRequest {
     }
 }
 
+/// A request to check whether a file handle refers to an interactive device
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct IsTtyRequest {
+    handle: u32,
+}
+
+impl IsTtyRequest {
+    /// Returns the handle of the file to check
+    pub fn file_handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Responds to the target whether the handle is an interactive device
+    pub fn write_is_tty(&self, core: &mut dyn CoreInterface, is_tty: bool) -> Result<(), Error> {
+        write_status(core, is_tty as i32)
+    }
+}
+
+/// A request to generate a temporary file name on the host
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TmpNamRequest {
+    buffer: u32,
+    identifier: u32,
+    len: u32,
+}
+
+impl TmpNamRequest {
+    /// Returns the target-chosen identifier (0-255) of the temporary file
+    pub fn identifier(&self) -> u8 {
+        self.identifier as u8
+    }
+
+    /// Writes the file name to the target buffer.
+    ///
+    /// Fails with an error status if the name (including the terminating `\0`) does not fit
+    /// into the buffer provided by the target.
+    pub fn write_name(&self, core: &mut dyn CoreInterface, name: &str) -> Result<(), Error> {
+        let mut buf = name.as_bytes().to_vec();
+        buf.push(0);
+
+        if buf.len() > self.len as usize {
+            tracing::warn!(
+                "Temporary file name {name} does not fit into the target buffer of {} bytes",
+                self.len
+            );
+            return write_status(core, -1);
+        }
+
+        core.write_8(self.buffer as u64, &buf)?;
+        write_status(core, 0)
+    }
+
+    /// Reports to the target that no file name could be generated.
+    pub fn write_failure(&self, core: &mut dyn CoreInterface) -> Result<(), Error> {
+        write_status(core, -1)
+    }
+}
+
+/// A request to execute a command on the host
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct SystemRequest {
+    command: ZeroTerminatedString,
+}
+
+impl SystemRequest {
+    /// Reads the command line from the target.
+    pub fn command(&self, core: &mut dyn CoreInterface) -> Result<String, Error> {
+        self.command.read(core)
+    }
+
+    /// Writes the return status of the command to the target
+    pub fn write_status(&self, core: &mut dyn CoreInterface, status: i32) -> Result<(), Error> {
+        write_status(core, status)
+    }
+}
+
+/// A request to read the number of centiseconds since the execution started
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ClockRequest {}
+impl ClockRequest {
+    /// Writes the execution time in centiseconds to the target
+    pub fn write_centiseconds(
+        &self,
+        core: &mut dyn CoreInterface,
+        value: u32,
+    ) -> Result<(), Error> {
+        write_status(core, (value & i32::MAX as u32) as i32)
+    }
+}
+
+/// A request to read the number of elapsed ticks since the execution started
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ElapsedRequest {
+    pointer: u32,
+}
+
+impl ElapsedRequest {
+    /// Writes the 64-bit tick count to the target
+    pub fn write_ticks(&self, core: &mut dyn CoreInterface, ticks: u64) -> Result<(), Error> {
+        // The least significant word is stored first.
+        let block: [u32; 2] = [ticks as u32, (ticks >> 32) as u32];
+        core.write_32(self.pointer as u64, &block)?;
+        write_status(core, 0)
+    }
+}
+
+/// A request to read the tick frequency
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TickFreqRequest {}
+impl TickFreqRequest {
+    /// Writes the number of ticks per second to the target
+    pub fn write_frequency(&self, core: &mut dyn CoreInterface, hz: u32) -> Result<(), Error> {
+        write_status(core, hz as i32)
+    }
+}
+
+/// The memory layout reported to the target in response to a [`HeapInfoRequest`].
+///
+/// A value of zero tells the C library to use its own default for that field.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct HeapInfo {
+    /// The lowest address of the heap
+    pub heap_base: u32,
+    /// The highest address of the heap
+    pub heap_limit: u32,
+    /// The initial stack pointer
+    pub stack_base: u32,
+    /// The lowest address of the stack
+    pub stack_limit: u32,
+}
+
+/// A request to read the heap and stack layout
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct HeapInfoRequest {
+    pointer: u32,
+}
+
+impl HeapInfoRequest {
+    /// Writes the memory layout to the block provided by the target
+    pub fn write_heap_info(
+        &self,
+        core: &mut dyn CoreInterface,
+        info: HeapInfo,
+    ) -> Result<(), Error> {
+        // The parameter points to a word which contains the address of a four-word block.
+        let [block] = param(core, self.pointer)?;
+        let data = [
+            info.heap_base,
+            info.heap_limit,
+            info.stack_base,
+            info.stack_limit,
+        ];
+        core.write_32(block as u64, &data)?;
+        Ok(())
+    }
+}
+
 fn write_status(core: &mut dyn CoreInterface, value: i32) -> Result<(), crate::Error> {
     let reg = core.registers().get_argument_register(0).unwrap();
     core.write_core_reg(reg.into(), RegisterValue::U32(value as u32))?;
@@ -488,7 +726,6 @@ impl ZeroTerminatedString {
 }
 
 /// Decodes a semihosting syscall without running the requested action.
-/// Only supports SYS_EXIT, SYS_EXIT_EXTENDED and SYS_GET_CMDLINE at the moment
 pub fn decode_semihosting_syscall(
     core: &mut dyn CoreInterface,
 ) -> Result<SemihostingCommand, Error> {
@@ -514,12 +751,19 @@ pub fn decode_semihosting_syscall(
     const SYS_WRITE0: u32 = 0x04;
     const SYS_WRITE: u32 = 0x05;
     const SYS_READ: u32 = 0x06;
+    const SYS_ISTTY: u32 = 0x09;
     const SYS_SEEK: u32 = 0x0a;
     const SYS_FLEN: u32 = 0x0c;
+    const SYS_TMPNAM: u32 = 0x0d;
     const SYS_REMOVE: u32 = 0x0e;
     const SYS_RENAME: u32 = 0x0f;
+    const SYS_CLOCK: u32 = 0x10;
     const SYS_TIME: u32 = 0x11;
+    const SYS_SYSTEM: u32 = 0x12;
     const SYS_ERRNO: u32 = 0x13;
+    const SYS_HEAPINFO: u32 = 0x16;
+    const SYS_ELAPSED: u32 = 0x30;
+    const SYS_TICKFREQ: u32 = 0x31;
 
     Ok(match (operation, parameter) {
         (SYS_EXIT, SYS_EXIT_ADP_STOPPED_APPLICATIONEXIT) => SemihostingCommand::ExitSuccess,
@@ -671,10 +915,64 @@ pub fn decode_semihosting_syscall(
             })
         }
 
+        (SYS_ISTTY, pointer) => {
+            let [handle] = param(core, pointer)?;
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::IsTty(IsTtyRequest { handle })
+        }
+
+        (SYS_TMPNAM, pointer) => {
+            let [buffer, identifier, len] = param(core, pointer)?;
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::TmpNam(TmpNamRequest {
+                buffer,
+                identifier,
+                len,
+            })
+        }
+
+        (SYS_SYSTEM, pointer) => {
+            let [command, len] = param(core, pointer)?;
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::System(SystemRequest {
+                command: ZeroTerminatedString {
+                    address: command,
+                    length: Some(len),
+                },
+            })
+        }
+
+        (SYS_CLOCK, 0) => {
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::Clock(ClockRequest {})
+        }
+
         (SYS_TIME, 0) => SemihostingCommand::Time(TimeRequest {}),
 
         (SYS_ERRNO, 0) => SemihostingCommand::Errno(ErrnoRequest {}),
 
+        (SYS_HEAPINFO, pointer) => {
+            // The data block is left untouched if the application does not answer this request,
+            // so the C library falls back to its own defaults.
+            SemihostingCommand::HeapInfo(HeapInfoRequest { pointer })
+        }
+
+        (SYS_ELAPSED, pointer) => {
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::Elapsed(ElapsedRequest { pointer })
+        }
+
+        (SYS_TICKFREQ, 0) => {
+            // signal to target: status = failure, in case the application does not answer this request
+            write_status(core, -1)?;
+            SemihostingCommand::TickFreq(TickFreqRequest {})
+        }
+
         _ => {
             // signal to target: status = failure, in case the application does not answer this request
             // It is not guaranteed that a value of -1 will be treated as an error by the target, but it is a common value to indicate an error.
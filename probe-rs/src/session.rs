@@ -1,12 +1,20 @@
 use crate::{
-    Core, CoreType, Error,
+    BreakpointError, Core, CoreType, Error, SoftwareBreakpoint, SoftwareBreakpointLocation,
     architecture::{
         arm::{
             ArmError, FullyQualifiedApAddress, SwoReader,
             communication_interface::ArmDebugInterface,
-            component::{TraceSink, get_arm_components},
+            component::{
+                Cti, ETM_TRACE_ID, Etm, HALT_GROUP_CHANNEL, ITM_TRACE_ID, Mtb, MtbBranch,
+                RESTART_GROUP_CHANNEL, TRIGGER_OUT_DEBUG_REQUEST, TRIGGER_OUT_RESTART, TraceSink,
+                get_arm_components,
+            },
             dp::DpAddress,
-            memory::CoresightComponent,
+            etm::Protocol,
+            memory::{
+                Component, CoresightComponent,
+                romtable::{PeripheralType, RomTableError},
+            },
             sequences::{ArmDebugSequence, DefaultArmSequence},
         },
         riscv::{
@@ -26,8 +34,13 @@ use crate::{
         fake_probe::FakeProbe, list::Lister,
     },
 };
+use std::collections::BTreeMap;
 use std::ops::DerefMut;
-use std::{fmt, sync::Arc, time::Duration};
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 /// The `Session` struct represents an active debug session.
 ///
@@ -53,6 +66,20 @@ pub struct Session {
     interfaces: ArchitectureInterface,
     cores: Vec<CombinedCoreState>,
     configured_trace_sink: Option<TraceSink>,
+    /// The trace source whose data is returned by [`Session::read_trace_data`].
+    configured_trace_id: u8,
+    permissions: Permissions,
+    halt_group: HaltGroup,
+}
+
+/// The cores which halt and resume together, see [`Session::set_halt_group`].
+#[derive(Debug, Default)]
+struct HaltGroup {
+    /// The cores in the group.
+    cores: Vec<usize>,
+    /// The Arm cores in the group which are halted and restarted by a cross trigger, with their
+    /// CTI.
+    ctis: Vec<(usize, CoresightComponent)>,
 }
 
 /// The `SessionConfig` struct is used to configure a new `Session` during auto-attach.
@@ -127,6 +154,16 @@ impl fmt::Debug for ArchitectureInterface {
 }
 
 impl ArchitectureInterface {
+    fn arm_interface(&mut self) -> Result<&mut dyn ArmDebugInterface, ArmError> {
+        let interface = match self {
+            ArchitectureInterface::Arm(state) => state.deref_mut(),
+            ArchitectureInterface::ArmWithRiscv { arm, .. } => arm.deref_mut(),
+            ArchitectureInterface::Jtag(..) => return Err(ArmError::NoArmTarget),
+        };
+
+        Ok(interface)
+    }
+
     fn attach<'probe, 'target: 'probe>(
         &'probe mut self,
         target: &'probe Target,
@@ -333,6 +370,9 @@ impl Session {
                 interfaces,
                 cores,
                 configured_trace_sink: None,
+                configured_trace_id: ITM_TRACE_ID,
+                permissions,
+                halt_group: HaltGroup::default(),
             };
 
             {
@@ -375,6 +415,9 @@ impl Session {
                 interfaces,
                 cores,
                 configured_trace_sink: None,
+                configured_trace_id: ITM_TRACE_ID,
+                permissions,
+                halt_group: HaltGroup::default(),
             })
         }
     }
@@ -423,7 +466,7 @@ impl Session {
         mut probe: Probe,
         target: Target,
         _attach_method: AttachMethod,
-        _permissions: Permissions,
+        permissions: Permissions,
         cores: Vec<CombinedCoreState>,
     ) -> Result<Self, Error> {
         // While we still don't support mixed architectures
@@ -516,6 +559,9 @@ impl Session {
             interfaces,
             cores,
             configured_trace_sink: None,
+            configured_trace_id: ITM_TRACE_ID,
+            permissions,
+            halt_group: HaltGroup::default(),
         };
 
         // Connect to the cores
@@ -694,9 +740,14 @@ impl Session {
             }
 
             TraceSink::TraceMemory => {
+                let trace_id = self.configured_trace_id;
                 let components = self.get_arm_components(DpAddress::Default)?;
                 let interface = self.get_arm_interface()?;
-                crate::architecture::arm::component::read_trace_memory(interface, &components)
+                crate::architecture::arm::component::read_trace_memory(
+                    interface,
+                    &components,
+                    trace_id,
+                )
             }
         }
     }
@@ -714,15 +765,41 @@ impl Session {
         Ok(SwoReader::new(interface))
     }
 
-    /// Get the Arm probe interface.
-    pub fn get_arm_interface(&mut self) -> Result<&mut dyn ArmDebugInterface, ArmError> {
-        let interface = match &mut self.interfaces {
-            ArchitectureInterface::Arm(state) => state.deref_mut(),
-            ArchitectureInterface::ArmWithRiscv { arm, .. } => arm.deref_mut(),
-            ArchitectureInterface::Jtag(..) => return Err(ArmError::NoArmTarget),
+    /// Get the currently used maximum speed for the debug protocol in kHz.
+    ///
+    /// Returns `None` if the probe cannot be reached through the debug interface.
+    pub fn speed_khz(&self) -> Option<u32> {
+        match &self.interfaces {
+            ArchitectureInterface::Arm(interface)
+            | ArchitectureInterface::ArmWithRiscv { arm: interface, .. } => {
+                interface.try_dap_probe().map(|probe| probe.speed_khz())
+            }
+            ArchitectureInterface::Jtag(probe, _) => Some(probe.speed_khz()),
+        }
+    }
+
+    /// Change the speed of the debug protocol in kHz, without detaching from the target.
+    ///
+    /// Returns the speed the probe actually uses. A lower speed is selected if the desired speed
+    /// is not supported.
+    pub fn set_speed(&mut self, speed_khz: u32) -> Result<u32, Error> {
+        let speed = match &mut self.interfaces {
+            ArchitectureInterface::Arm(interface)
+            | ArchitectureInterface::ArmWithRiscv { arm: interface, .. } => interface
+                .try_dap_probe_mut()
+                .ok_or(Error::NotImplemented(
+                    "Changing the speed of this probe while attached",
+                ))?
+                .set_speed(speed_khz)?,
+            ArchitectureInterface::Jtag(probe, _) => probe.set_speed_attached(speed_khz)?,
         };
 
-        Ok(interface)
+        Ok(speed)
+    }
+
+    /// Get the Arm probe interface.
+    pub fn get_arm_interface(&mut self) -> Result<&mut dyn ArmDebugInterface, ArmError> {
+        self.interfaces.arm_interface()
     }
 
     /// Get the RISC-V probe interface.
@@ -954,10 +1031,143 @@ impl Session {
         crate::architecture::arm::component::setup_tracing(interface, &components, &destination)?;
 
         self.configured_trace_sink.replace(destination);
+        self.configured_trace_id = ITM_TRACE_ID;
 
         Ok(())
     }
 
+    /// Configure the ETM of a core to trace all executed instructions into trace memory.
+    ///
+    /// The trace data can then be read with [`Session::read_trace_data`], and decoded with
+    /// [`etm::decode`](crate::architecture::arm::etm::decode) using the returned protocol. This
+    /// replaces any SWV tracing set up with [`Session::setup_tracing`].
+    ///
+    /// Cores sharing an access port are matched to the ETMs in the ROM table of that access port
+    /// in order.
+    #[tracing::instrument(skip(self))]
+    pub fn setup_instruction_trace(&mut self, core_index: usize) -> Result<Protocol, Error> {
+        let Some(core) = self.cores.get(core_index) else {
+            return Err(Error::CoreNotFound(core_index));
+        };
+        if !core.is_arm_core() {
+            return Err(ArmError::ArchitectureRequired(&["ARMv7", "ARMv8"]).into());
+        }
+
+        let ap = core.arm_memory_ap();
+
+        if matches!(
+            self.cores[core_index].core_type(),
+            CoreType::Armv7m | CoreType::Armv7em | CoreType::Armv8m
+        ) {
+            let mut core = self.core(core_index)?;
+            crate::architecture::arm::component::enable_tracing(&mut core)?;
+        }
+
+        let sequence_handle = match &self.target.debug_sequence {
+            DebugSequence::Arm(sequence) => sequence.clone(),
+            _ => unreachable!("Mismatch between architecture and sequence type!"),
+        };
+
+        let components = self.get_arm_components(ap.dp())?;
+        let etm = self.core_component(core_index, &components, PeripheralType::Etm)?;
+
+        let interface = self.get_arm_interface()?;
+        sequence_handle.trace_start(interface, &components, &TraceSink::TraceMemory)?;
+        crate::architecture::arm::component::setup_instruction_trace(interface, &components, &etm)?;
+        let protocol = Etm::new(interface, &etm).protocol()?;
+
+        self.configured_trace_sink.replace(TraceSink::TraceMemory);
+        self.configured_trace_id = ETM_TRACE_ID;
+
+        Ok(protocol)
+    }
+
+    /// Configure the MTB of a core to record branches into a window of its SRAM, and start
+    /// recording.
+    ///
+    /// The window starts `offset` bytes after the SRAM base address of the MTB. The size has to be
+    /// a power of two of at least 16 bytes, and the offset a multiple of the size. The firmware
+    /// must not use this part of the SRAM. The branches can be read with
+    /// [`Session::read_branch_trace`].
+    #[tracing::instrument(skip(self))]
+    pub fn setup_branch_trace(
+        &mut self,
+        core_index: usize,
+        offset: u32,
+        size: u32,
+    ) -> Result<(), Error> {
+        let Some(core) = self.cores.get(core_index) else {
+            return Err(Error::CoreNotFound(core_index));
+        };
+        if !core.is_arm_core() {
+            return Err(ArmError::ArchitectureRequired(&["ARMv6", "ARMv8"]).into());
+        }
+
+        let components = self.get_arm_components(core.arm_memory_ap().dp())?;
+        let component = self.core_component(core_index, &components, PeripheralType::Mtb)?;
+
+        let interface = self.get_arm_interface()?;
+        let mut mtb = Mtb::new(interface, &component);
+        mtb.configure(offset, size)?;
+        mtb.enable()?;
+
+        Ok(())
+    }
+
+    /// Read the branches recorded by the MTB of a core, oldest first.
+    ///
+    /// The core should be halted, as the MTB keeps overwriting its buffer while the core runs.
+    #[tracing::instrument(skip(self))]
+    pub fn read_branch_trace(&mut self, core_index: usize) -> Result<Vec<MtbBranch>, Error> {
+        let Some(core) = self.cores.get(core_index) else {
+            return Err(Error::CoreNotFound(core_index));
+        };
+        if !core.is_arm_core() {
+            return Err(ArmError::ArchitectureRequired(&["ARMv6", "ARMv8"]).into());
+        }
+
+        let components = self.get_arm_components(core.arm_memory_ap().dp())?;
+        let component = self.core_component(core_index, &components, PeripheralType::Mtb)?;
+
+        let interface = self.get_arm_interface()?;
+        Ok(Mtb::new(interface, &component).read_branches()?)
+    }
+
+    /// Find the per-core CoreSight component of the given type belonging to an Arm core.
+    ///
+    /// Cores sharing an access port are matched to the components in the ROM table of that access
+    /// port in order.
+    fn core_component(
+        &self,
+        core_index: usize,
+        components: &[CoresightComponent],
+        peripheral_type: PeripheralType,
+    ) -> Result<CoresightComponent, ArmError> {
+        let ap = self.cores[core_index].arm_memory_ap();
+        // The position of the core among the cores sharing its access port.
+        let position = self.cores[..core_index]
+            .iter()
+            .filter(|core| core.is_arm_core() && core.arm_memory_ap() == ap)
+            .count();
+
+        components
+            .iter()
+            .filter(|component| component.ap_address == ap)
+            .flat_map(|component| component.iter())
+            .filter(|component| {
+                component
+                    .component
+                    .id()
+                    .peripheral_id()
+                    .is_of_type(peripheral_type)
+            })
+            .nth(position)
+            .cloned()
+            .ok_or(ArmError::from(RomTableError::ComponentNotFound(
+                peripheral_type,
+            )))
+    }
+
     /// Configure the target to stop emitting SWV trace data.
     #[tracing::instrument(skip(self))]
     pub fn disable_swv(&mut self, core_index: usize) -> Result<(), Error> {
@@ -1002,14 +1212,17 @@ impl Session {
         }
     }
 
-    /// Clears all hardware breakpoints on all cores
+    /// Clears all hardware breakpoints and watchpoints on all cores
     pub fn clear_all_hw_breakpoints(&mut self) -> Result<(), Error> {
         self.halted_access(|session| {
             { 0..session.cores.len() }.try_for_each(|core| {
                 tracing::info!("Clearing breakpoints for core {core}");
 
                 match session.core(core) {
-                    Ok(mut core) => core.clear_all_hw_breakpoints(),
+                    Ok(mut core) => {
+                        core.clear_all_hw_breakpoints()?;
+                        core.clear_all_hw_watchpoints()
+                    }
                     Err(Error::CoreDisabled(_)) => Ok(()),
                     Err(Error::Riscv(
                         crate::architecture::riscv::communication_interface::RiscvError::Timeout,
@@ -1025,14 +1238,307 @@ impl Session {
         })
     }
 
+    /// Sets a software breakpoint at `address` on the core with index `core_index`.
+    ///
+    /// Breakpoints in RAM are written by the core. Breakpoints in flash need the
+    /// [`Permissions::allow_flash_breakpoints`] permission, and are programmed the next time the
+    /// core is resumed, together with all other changes to the breakpoints in flash. This
+    /// reprograms each affected sector once, using the flash algorithm on the core, whose
+    /// registers and the RAM used by the flash algorithm are restored afterwards.
+    ///
+    /// If another core sharing the memory already has a breakpoint at `address`, the
+    /// instruction is not patched again.
+    pub fn set_sw_breakpoint(&mut self, core_index: usize, address: u64) -> Result<(), Error> {
+        if let Some(breakpoint) = self.shared_sw_breakpoint(core_index, address) {
+            self.core(core_index)?
+                .sw_breakpoints_mut()
+                .insert(breakpoint);
+            return Ok(());
+        }
+
+        {
+            let mut core = self.core(core_index)?;
+            if core.sw_breakpoints().get(address).is_some() {
+                return Ok(());
+            }
+            if !core.is_in_flash(address) {
+                return core.set_sw_breakpoint(address);
+            }
+        }
+
+        self.permissions
+            .flash_breakpoints()
+            .map_err(|MissingPermissions(desc)| Error::MissingPermissions(desc))?;
+
+        let mut core = self.core(core_index)?;
+        // A breakpoint cleared since the core was last resumed still knows the original
+        // instruction, which can no longer be read from flash.
+        let breakpoint = match core.sw_breakpoints_mut().take_removed(address) {
+            Some(breakpoint) => breakpoint,
+            None => core.prepare_sw_breakpoint(address, SoftwareBreakpointLocation::Flash)?,
+        };
+
+        tracing::debug!(
+            "Break instruction at {:#010x} will be programmed when the core resumes",
+            address
+        );
+        core.sw_breakpoints_mut().insert(breakpoint);
+
+        Ok(())
+    }
+
+    /// Clears the software breakpoint at `address` on the core with index `core_index`.
+    ///
+    /// The original instruction is restored, unless another core sharing the memory still has a
+    /// breakpoint at `address`. In flash, it is restored the next time the core is resumed, or
+    /// when the session is dropped.
+    pub fn clear_sw_breakpoint(&mut self, core_index: usize, address: u64) -> Result<(), Error> {
+        let breakpoint = self
+            .cores
+            .get(core_index)
+            .and_then(|core| core.core_state.sw_breakpoints().get(address).cloned())
+            .ok_or(Error::BreakpointOperation(BreakpointError::NotFound(
+                address,
+            )))?;
+
+        if self.shared_sw_breakpoint(core_index, address).is_some() {
+            self.cores[core_index]
+                .core_state
+                .sw_breakpoints_mut()
+                .remove(address);
+            return Ok(());
+        }
+
+        match breakpoint.location {
+            SoftwareBreakpointLocation::Ram => self.core(core_index)?.clear_sw_breakpoint(address),
+            SoftwareBreakpointLocation::Flash => {
+                self.cores[core_index]
+                    .core_state
+                    .sw_breakpoints_mut()
+                    .remove_from_flash(address);
+                Ok(())
+            }
+        }
+    }
+
+    /// Clears all software breakpoints on all cores, restoring the original instructions.
+    ///
+    /// The breakpoints in flash are restored by reprogramming each affected sector once, if any
+    /// break instructions were programmed. This also happens when the session is dropped.
+    pub fn clear_all_sw_breakpoints(&mut self) -> Result<(), Error> {
+        for core_index in 0..self.cores.len() {
+            let flash_breakpoints = self.cores[core_index]
+                .core_state
+                .sw_breakpoints_mut()
+                .take_flash();
+
+            let addresses = self.cores[core_index]
+                .core_state
+                .sw_breakpoints()
+                .iter()
+                .map(|breakpoint| breakpoint.address)
+                .collect::<Vec<_>>();
+
+            for address in addresses {
+                match self.clear_sw_breakpoint(core_index, address) {
+                    Ok(()) | Err(Error::CoreDisabled(_)) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+
+            if flash_breakpoints.is_empty() {
+                continue;
+            }
+
+            let patches = flash_breakpoints
+                .into_iter()
+                .map(|breakpoint| (breakpoint.address, breakpoint.original))
+                .collect::<Vec<_>>();
+            match self.core(core_index) {
+                Ok(mut core) => core.patch_flash(&patches)?,
+                Err(Error::CoreDisabled(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the software breakpoint at `address` of another core, which shares the memory at
+    /// `address` with the core with index `core_index`.
+    fn shared_sw_breakpoint(&self, core_index: usize, address: u64) -> Option<SoftwareBreakpoint> {
+        let name = &self.target.cores[core_index].name;
+
+        self.cores
+            .iter()
+            .filter(|core| core.id() != core_index)
+            .filter(|core| {
+                let other_name = &self.target.cores[core.id()].name;
+                self.target.memory_map.iter().any(|region| {
+                    region.contains(address)
+                        && region.cores().contains(name)
+                        && region.cores().contains(other_name)
+                })
+            })
+            .find_map(|core| core.core_state.sw_breakpoints().get(address).cloned())
+    }
+
     /// Resume all cores
     pub fn resume_all_cores(&mut self) -> Result<(), Error> {
-        // Resume cores
-        for core_id in 0..self.cores.len() {
-            match self.core(core_id) {
-                Ok(mut core) => {
-                    if core.core_halted()? {
-                        core.run()?;
+        let cores = (0..self.cores.len()).collect::<Vec<_>>();
+        self.resume_cores(&cores)
+    }
+
+    /// Makes the given cores halt together.
+    ///
+    /// When one core of the group halts, for example on a breakpoint, the hardware halts the
+    /// other cores as well: Arm cores through their cross trigger interface (CTI), and RISC-V
+    /// harts through the halt groups of their debug module. Cores without such support are not
+    /// halted automatically.
+    ///
+    /// Halting or resuming a core of the group with [`Session::halt_cores`] and
+    /// [`Session::resume_cores`] also halts or resumes the other cores of the group. Cores of the
+    /// group should not be resumed with [`Core::run`], as a pending cross trigger may halt them
+    /// again right away.
+    ///
+    /// Disabled cores are skipped. Calling this with an empty slice removes the group.
+    pub fn set_halt_group(&mut self, cores: &[usize]) -> Result<(), Error> {
+        let mut members = vec![];
+        for &core in cores {
+            match self.core(core) {
+                Ok(_) => members.push(core),
+                Err(Error::CoreDisabled(i)) => tracing::debug!("Core {i} is disabled"),
+                Err(error) => return Err(error),
+            }
+        }
+        let cores = members.as_slice();
+
+        let previous = std::mem::take(&mut self.halt_group);
+        if !previous.ctis.is_empty() {
+            let interface = self.get_arm_interface()?;
+            for (_, component) in &previous.ctis {
+                Cti::new(interface, component).set_halt_group_member(false)?;
+            }
+        }
+
+        let ctis = self.halt_group_ctis(cores)?;
+        if !ctis.is_empty() {
+            let interface = self.get_arm_interface()?;
+            for (_, component) in &ctis {
+                Cti::new(interface, component).set_halt_group_member(true)?;
+            }
+        }
+
+        // Harts on different debug modules cannot halt each other.
+        let mut debug_modules = BTreeMap::<usize, (usize, Vec<u32>, bool)>::new();
+        for core in 0..self.cores.len() {
+            if let Some((debug_module, hart)) = self.riscv_debug_module(core) {
+                let entry = debug_modules
+                    .entry(debug_module)
+                    .or_insert((core, vec![], false));
+                if cores.contains(&core) {
+                    entry.1.push(hart);
+                }
+                entry.2 |= previous.cores.contains(&core);
+            }
+        }
+        for (core, harts, was_configured) in debug_modules.into_values() {
+            if harts.len() < 2 && !was_configured {
+                continue;
+            }
+            let harts = if harts.len() < 2 { vec![] } else { harts };
+            match self.get_riscv_interface(core)?.set_halt_group(&harts, 1) {
+                Ok(()) => {}
+                Err(RiscvError::HaltGroupsNotSupported) => {
+                    tracing::info!(
+                        "The debug module of core {core} does not support halt groups, its harts are halted one by one"
+                    );
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        self.halt_group = HaltGroup {
+            cores: cores.to_vec(),
+            ctis,
+        };
+
+        Ok(())
+    }
+
+    /// Returns the cores which halt together, see [`Session::set_halt_group`].
+    pub fn halt_group(&self) -> &[usize] {
+        &self.halt_group.cores
+    }
+
+    /// Halts the given cores together.
+    ///
+    /// Arm cores of the halt group are halted with a single cross trigger, which also halts the
+    /// other cores of the group. RISC-V harts on the same debug module are halted with a single
+    /// request, if the debug module supports selecting multiple harts. All other cores are halted
+    /// one after the other.
+    ///
+    /// Disabled cores are skipped.
+    pub fn halt_cores(&mut self, cores: &[usize], timeout: Duration) -> Result<(), Error> {
+        let cores = self.with_cti_group(cores);
+
+        let pulse = self
+            .halt_group
+            .ctis
+            .iter()
+            .any(|(core, _)| cores.contains(core));
+        if pulse {
+            let interface = self.interfaces.arm_interface()?;
+            let (_, component) = &self.halt_group.ctis[0];
+            Cti::new(interface, component).pulse_channel(HALT_GROUP_CHANNEL)?;
+        }
+
+        for hart_array in self.riscv_hart_arrays(&cores) {
+            let (hart_cores, harts): (Vec<_>, Vec<_>) = hart_array.into_iter().unzip();
+            match self
+                .get_riscv_interface(hart_cores[0])?
+                .halt_harts(&harts, timeout)
+            {
+                Ok(()) => {}
+                Err(RiscvError::HartArrayNotSupported) => {
+                    tracing::debug!("Cannot halt multiple harts at once, halting one by one");
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        // Halt the remaining cores, and update the state of the halted ones.
+        for &core in &cores {
+            let mut core = match self.core(core) {
+                Err(Error::CoreDisabled(_)) => continue,
+                other => other?,
+            };
+            if core.core_halted()? {
+                core.wait_for_core_halted(timeout)?;
+            } else {
+                core.halt(timeout)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resumes the given cores together.
+    ///
+    /// Every halted core first steps off its current instruction, like [`Core::run`] does. Arm
+    /// cores of the halt group are then restarted with a single cross trigger, which also resumes
+    /// the other cores of the group. RISC-V harts on the same debug module are resumed with a
+    /// single request, if the debug module supports selecting multiple harts. All other cores are
+    /// resumed one after the other.
+    ///
+    /// Disabled cores and cores which are not halted are skipped.
+    pub fn resume_cores(&mut self, cores: &[usize]) -> Result<(), Error> {
+        let mut halted = vec![];
+        for core in self.with_cti_group(cores) {
+            match self.core(core) {
+                Ok(mut handle) => {
+                    if handle.core_halted()? {
+                        halted.push(core);
                     }
                 }
                 Err(Error::CoreDisabled(i)) => tracing::debug!("Core {i} is disabled"),
@@ -1040,8 +1546,197 @@ impl Session {
             }
         }
 
+        let cti_cores = self
+            .halt_group
+            .ctis
+            .iter()
+            .map(|(core, _)| *core)
+            .filter(|core| halted.contains(core))
+            .collect::<Vec<_>>();
+        let hart_arrays = self.riscv_hart_arrays(&halted);
+
+        // A debug request which is still asserted would halt the cores again right away.
+        if !cti_cores.is_empty() {
+            let interface = self.interfaces.arm_interface()?;
+            for (_, component) in &self.halt_group.ctis {
+                Cti::new(interface, component)
+                    .acknowledge_trigger_out(TRIGGER_OUT_DEBUG_REQUEST)?;
+            }
+        }
+
+        let mut synchronized = vec![];
+        for &core in &halted {
+            let in_hart_array = hart_arrays
+                .iter()
+                .flatten()
+                .any(|(hart_core, _)| *hart_core == core);
+            if (cti_cores.contains(&core) || in_hart_array)
+                && self.core(core)?.prepare_synchronized_run()?
+            {
+                synchronized.push(core);
+            } else {
+                self.core(core)?.run()?;
+            }
+        }
+
+        if cti_cores.iter().any(|core| synchronized.contains(core)) {
+            let interface = self.interfaces.arm_interface()?;
+            let (_, component) = &self.halt_group.ctis[0];
+            Cti::new(interface, component).pulse_channel(RESTART_GROUP_CHANNEL)?;
+
+            // The restart request has to be deasserted once the cores left debug state, so they
+            // can be restarted again later.
+            let start = Instant::now();
+            for &core in &cti_cores {
+                while self.core(core)?.core_halted()?
+                    && start.elapsed() < Duration::from_millis(100)
+                {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            }
+            let interface = self.interfaces.arm_interface()?;
+            for (_, component) in &self.halt_group.ctis {
+                Cti::new(interface, component).acknowledge_trigger_out(TRIGGER_OUT_RESTART)?;
+            }
+        }
+
+        for hart_array in hart_arrays {
+            let (cores, harts): (Vec<_>, Vec<_>) = hart_array
+                .into_iter()
+                .filter(|(core, _)| synchronized.contains(core))
+                .unzip();
+            let Some(&core) = cores.first() else {
+                continue;
+            };
+            let mut interface = self.get_riscv_interface(core)?;
+            match interface.resume_harts(&harts) {
+                Ok(()) => {}
+                Err(RiscvError::HartArrayNotSupported) => {
+                    tracing::debug!("Cannot resume multiple harts at once, resuming one by one");
+                    for hart in harts {
+                        interface.select_hart(hart)?;
+                        interface.resume_core()?;
+                    }
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+
+        // The cores do not know that they were resumed by the hardware.
+        for core in synchronized {
+            self.core(core)?.status()?;
+        }
+
         Ok(())
     }
+
+    /// Adds all Arm cores of the halt group to `cores` if one of them is in `cores`, as they are
+    /// halted and resumed by the same cross trigger.
+    fn with_cti_group(&self, cores: &[usize]) -> Vec<usize> {
+        let mut cores = cores.to_vec();
+        if self
+            .halt_group
+            .ctis
+            .iter()
+            .any(|(core, _)| cores.contains(core))
+        {
+            for (core, _) in &self.halt_group.ctis {
+                if !cores.contains(core) {
+                    cores.push(*core);
+                }
+            }
+        }
+        cores
+    }
+
+    /// Finds the CTIs of the Arm cores in `cores`.
+    ///
+    /// Returns nothing if fewer than two cores have a CTI, as a single core cannot be halted
+    /// together with anything.
+    fn halt_group_ctis(
+        &mut self,
+        cores: &[usize],
+    ) -> Result<Vec<(usize, CoresightComponent)>, Error> {
+        let arm_cores = cores
+            .iter()
+            .copied()
+            .filter(|&core| self.cores[core].is_arm_core())
+            .collect::<Vec<_>>();
+        if arm_cores.len() < 2 {
+            return Ok(vec![]);
+        }
+
+        let mut components = None;
+        let mut ctis = vec![];
+        for core in arm_cores {
+            let ap = self.cores[core].arm_memory_ap();
+            let cti_base = self.cores[core].core_state.cti_base();
+            let interface = self.get_arm_interface()?;
+
+            let cti = if let Some(cti_base) = cti_base {
+                let mut memory = interface.memory_interface(&ap)?;
+                let component = Component::try_parse(&mut *memory, cti_base)
+                    .map_err(|error| Error::Arm(error.into()))?;
+                Some(CoresightComponent::new(component, ap))
+            } else {
+                // Look for a CTI in the ROM table of the core's access port.
+                let components = match &mut components {
+                    Some(components) => components,
+                    None => components.insert(get_arm_components(interface, ap.dp())?),
+                };
+                components
+                    .iter()
+                    .filter(|component| component.ap_address == ap)
+                    .find_map(|component| component.find_component(PeripheralType::Cti))
+                    .cloned()
+            };
+
+            match cti {
+                Some(cti) => ctis.push((core, cti)),
+                None => tracing::warn!(
+                    "Core {core} has no cross trigger interface, it cannot be halted together with other cores"
+                ),
+            }
+        }
+
+        if ctis.len() < 2 {
+            ctis.clear();
+        }
+        Ok(ctis)
+    }
+
+    /// Returns the debug module and hart ID of a RISC-V core.
+    ///
+    /// Cores with the same debug module can be halted and resumed with a single request.
+    fn riscv_debug_module(&self, core: usize) -> Option<(usize, u32)> {
+        let combined_state = self.cores.get(core)?;
+        let hart = combined_state.core_state.riscv_hart_id()?;
+        let debug_module = match &self.interfaces {
+            // Every core has its own debug module behind a memory AP.
+            ArchitectureInterface::ArmWithRiscv { .. } => core,
+            _ => combined_state.jtag_tap_index(),
+        };
+        Some((debug_module, hart))
+    }
+
+    /// Groups the RISC-V cores in `cores` by debug module, as `(core, hart)` pairs.
+    ///
+    /// Only debug modules with more than one hart in `cores` are returned.
+    fn riscv_hart_arrays(&self, cores: &[usize]) -> Vec<Vec<(usize, u32)>> {
+        let mut debug_modules = BTreeMap::<usize, Vec<(usize, u32)>>::new();
+        for &core in cores {
+            if let Some((debug_module, hart)) = self.riscv_debug_module(core) {
+                debug_modules
+                    .entry(debug_module)
+                    .or_default()
+                    .push((core, hart));
+            }
+        }
+        debug_modules
+            .into_values()
+            .filter(|harts| harts.len() > 1)
+            .collect()
+    }
 }
 
 // This test ensures that [Session] is fully [Send] + [Sync].
@@ -1054,6 +1749,22 @@ const _: fn() = || {
 impl Drop for Session {
     #[tracing::instrument(name = "session_drop", skip(self))]
     fn drop(&mut self) {
+        if !self.halt_group.cores.is_empty()
+            && let Err(err) = self.set_halt_group(&[])
+        {
+            tracing::warn!(
+                "Could not remove the halt group: {:?}",
+                anyhow::anyhow!(err)
+            );
+        }
+
+        if let Err(err) = self.clear_all_sw_breakpoints() {
+            tracing::warn!(
+                "Could not clear all software breakpoints: {:?}",
+                anyhow::anyhow!(err)
+            );
+        }
+
         if let Err(err) = self.clear_all_hw_breakpoints() {
             tracing::warn!(
                 "Could not clear all hardware breakpoints: {:?}",
@@ -1130,6 +1841,8 @@ fn get_target_from_selector(
 pub struct Permissions {
     /// When set to true, all memory of the chip may be erased or reset to factory default
     erase_all: bool,
+    /// When set to true, the flash may be reprogrammed to set software breakpoints
+    flash_breakpoints: bool,
 }
 
 impl Permissions {
@@ -1151,6 +1864,19 @@ impl Permissions {
         }
     }
 
+    /// Allow the session to reprogram the flash to set software breakpoints in code in flash.
+    ///
+    /// # Warning
+    /// Each breakpoint that is set, stepped over or cleared erases and reprograms a flash sector,
+    /// which wears out the flash.
+    #[must_use]
+    pub fn allow_flash_breakpoints(self) -> Self {
+        Self {
+            flash_breakpoints: true,
+            ..self
+        }
+    }
+
     pub(crate) fn erase_all(&self) -> Result<(), MissingPermissions> {
         if self.erase_all {
             Ok(())
@@ -1158,6 +1884,14 @@ impl Permissions {
             Err(MissingPermissions("erase_all".into()))
         }
     }
+
+    pub(crate) fn flash_breakpoints(&self) -> Result<(), MissingPermissions> {
+        if self.flash_breakpoints {
+            Ok(())
+        } else {
+            Err(MissingPermissions("flash_breakpoints".into()))
+        }
+    }
 }
 
 #[derive(Debug, Clone, thiserror::Error)]
@@ -1,5 +1,10 @@
 #![cfg(feature = "builtin-targets")]
-use probe_rs::{Permissions, flashing::DownloadOptions, integration::FakeProbe, probe::Probe};
+use probe_rs::{
+    Permissions,
+    flashing::{DownloadOptions, InjectionLocation},
+    integration::FakeProbe,
+    probe::Probe,
+};
 
 /// A chip where the flash algorithm's range is greater than the NVM range.
 #[test]
@@ -80,3 +85,113 @@ fn flash_dry_run_tle9871_nac_nad() {
         .commit(&mut session, flash_options)
         .expect("Failed to flash in dry run mode.");
 }
+
+/// All images and patches of a manifest end up in one flash loader.
+#[test]
+fn flash_dry_run_manifest() {
+    use probe_rs::flashing::{ManifestLoader, ManifestOptions};
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = temp_dir.path();
+    std::fs::write(dir.join("application.bin"), [0xAA; 16]).unwrap();
+    std::fs::write(
+        dir.join("production.yaml"),
+        r#"
+images:
+  - path: application.bin
+    options:
+      base_address: 0x08000000
+patches:
+  - address: 0x08001000
+    type: u32
+    value: "{{serial}}"
+"#,
+    )
+    .unwrap();
+
+    let probe = Probe::from_specific_probe(Box::new(FakeProbe::with_mocked_core()));
+
+    let mut session = probe
+        .attach("stm32wb55ccux", Permissions::default())
+        .expect("Failed to attach with 'fake' probe.");
+
+    let mut flasher = session.target().flash_loader();
+
+    let loader = ManifestLoader(ManifestOptions {
+        base_dir: Some(dir.to_path_buf()),
+        variables: [("serial".to_string(), "42".to_string())].into(),
+    });
+    let mut file = std::fs::File::open(dir.join("production.yaml")).unwrap();
+    flasher
+        .load_image(&mut session, &mut file, loader, None)
+        .expect("Failed to load the manifest");
+
+    assert!(flasher.has_data_for_address(0x0800_000F));
+    assert!(flasher.has_data_for_address(0x0800_1003));
+    assert!(!flasher.has_data_for_address(0x0800_0010));
+
+    let mut flash_options = DownloadOptions::new();
+
+    flash_options.dry_run = true;
+
+    flasher
+        .commit(&mut session, flash_options)
+        .expect("Failed to flash in dry run mode.");
+}
+
+/// Injected data replaces placeholders of the image, or is added next to it.
+#[test]
+fn flash_dry_run_injected_data() {
+    let probe = Probe::from_specific_probe(Box::new(FakeProbe::with_mocked_core()));
+
+    let mut session = probe
+        .attach("stm32wb55ccux", Permissions::default())
+        .expect("Failed to attach with 'fake' probe.");
+
+    let mut flasher = session.target().flash_loader();
+
+    flasher
+        .add_data(0x8000000, &[0; 16])
+        .expect("Failed to add flash");
+
+    // Replace a placeholder in the image, and append a serial number behind it.
+    flasher
+        .inject_data(
+            InjectionLocation::Placeholder(0x8000004..0x8000008),
+            &[1, 2, 3, 4],
+        )
+        .expect("Failed to replace the placeholder");
+    flasher
+        .inject_data(
+            InjectionLocation::Placeholder(0x8000008..0x800000C),
+            &[1, 2],
+        )
+        .expect_err("The data has to fill the placeholder");
+    flasher
+        .inject_data(InjectionLocation::Address(0x8000010), b"SN01")
+        .expect("Failed to append data");
+    flasher
+        .inject_data(InjectionLocation::Address(0x8000002), &[0; 4])
+        .expect_err("Data injected at an address doesn't overwrite the image");
+    flasher
+        .inject_data(
+            InjectionLocation::Placeholder(0x8000012..0x8000016),
+            &[0; 4],
+        )
+        .expect_err("A placeholder has to be part of the image");
+
+    flasher
+        .inject_crc32(0x8000000..0x8000014, InjectionLocation::Address(0x8000014))
+        .expect("Failed to inject the checksum");
+    flasher
+        .inject_crc32(0x8000000..0x8000100, InjectionLocation::Address(0x8000200))
+        .expect_err("The checksum range has to be covered by data");
+
+    let mut flash_options = DownloadOptions::new();
+
+    flash_options.dry_run = true;
+
+    flasher
+        .commit(&mut session, flash_options)
+        .expect("Failed to flash in dry run mode.");
+}